@@ -1,23 +1,57 @@
 use crate::api::{
     ApiClient, ApiConfig, ChatCompletionRequest, ChatCompletionResponse, Function, Message,
-    ResponseFormat, Tool,
+    ResponseFormat, StreamDelta, Tool,
 };
 use crate::cache::ResponseCache;
+use crate::config::Config;
+use crate::context::{self, ContextBudget};
 use crate::error::{GrokError, Result};
-use crate::tools::{ToolContext, ToolRegistry};
+use crate::session::{SavedSession, SessionMeta, SessionStore, SessionWriter};
+use crate::tools::{
+    CancelToken, CommandPolicy, OutputSink, PermissionMode, Permissions, ProcessTable, Sandbox,
+    ToolContext, ToolRegistry, ToolSettings,
+};
+use crate::usage::{ModelPrice, SessionUsage};
 use git2::Repository;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use tokio::sync::mpsc;
 use walkdir::WalkDir;
 
+/// Characters kept from each old tool result when compacting the history
+const COMPACT_TOOL_OUTPUT_CHARS: usize = 2000;
+
+/// User turns kept verbatim when older history is summarized
+const COMPACT_KEEP_TURNS: usize = 2;
+
 /// Message types for TUI communication
 #[derive(Debug, Clone)]
 pub enum TuiUpdate {
     Message(Message),
-    ToolStart { name: String, icon: String },
-    ToolResult { name: String, result: String },
-    Processing { message: String },
-    Error { message: String },
+    StreamChunk {
+        content: String,
+    },
+    ToolStart {
+        name: String,
+        icon: String,
+    },
+    ToolResult {
+        name: String,
+        result: String,
+    },
+    /// A line of output from a tool that is still running
+    ToolOutput {
+        name: String,
+        line: String,
+    },
+    Processing {
+        message: String,
+    },
+    Error {
+        message: String,
+    },
+    Usage(SessionUsage),
     Complete,
 }
 
@@ -29,11 +63,36 @@ pub struct GrokAgent {
     max_tokens: u32,
     project_root: PathBuf,
     codebase_summary: String,
-    dry_run: bool,
-    no_confirm: bool,
-    git_repo: Option<Repository>,
+    /// Permission mode and remembered approvals, shared with running tools
+    permissions: Permissions,
     response_cache: ResponseCache,
     tui_sender: Option<mpsc::UnboundedSender<TuiUpdate>>,
+    streaming: bool,
+    /// Whether the text of the last API response was already shown while streaming
+    streamed_response: bool,
+    provider: String,
+    /// Context window override; `None` uses the model's known window
+    context_window: Option<usize>,
+    max_iterations: usize,
+    max_timeout_retries: u32,
+    max_rate_limit_retries: u32,
+    cache_enabled: bool,
+    session_store: Option<SessionStore>,
+    session: Option<SessionWriter>,
+    /// Number of messages already written to the session file
+    session_saved: usize,
+    usage: SessionUsage,
+    price: Option<ModelPrice>,
+    /// Lets the user stop running tools (Ctrl-C)
+    cancel: CancelToken,
+    /// Background processes started by tools; killed when the agent is dropped
+    processes: ProcessTable,
+    /// Rules for the shell commands tools run
+    policy: Arc<CommandPolicy>,
+    /// Sandbox for shell commands, when enabled for this session
+    sandbox: Option<Sandbox>,
+    /// Backup, code host and commit settings for tools
+    tool_settings: Arc<ToolSettings>,
 }
 
 impl GrokAgent {
@@ -64,7 +123,66 @@ impl GrokAgent {
             tool_call_id: None,
         }];
 
-        let git_repo = Repository::open(&project_root).ok();
+        Self::with_history(
+            provider,
+            api_config,
+            project_root,
+            dry_run,
+            auto_approve,
+            tool_registry,
+            messages,
+            codebase_summary,
+        )
+    }
+
+    /// Rebuild an agent from a saved session, continuing to append to it
+    pub fn from_session(
+        provider: &str,
+        api_config: ApiConfig,
+        project_root: PathBuf,
+        dry_run: bool,
+        auto_approve: bool,
+        saved: SavedSession,
+        store: SessionStore,
+    ) -> std::result::Result<Self, Box<dyn std::error::Error>> {
+        let session = store.open(&saved.meta.id)?;
+        let session_saved = saved.messages.len();
+
+        let mut agent = Self::with_history(
+            provider,
+            api_config,
+            project_root,
+            dry_run,
+            auto_approve,
+            ToolRegistry::new(),
+            saved.messages,
+            saved.meta.codebase_summary,
+        )?;
+        agent.session_store = Some(store);
+        agent.session = Some(session);
+        agent.session_saved = session_saved;
+        Ok(agent)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn with_history(
+        provider: &str,
+        api_config: ApiConfig,
+        project_root: PathBuf,
+        dry_run: bool,
+        auto_approve: bool,
+        tool_registry: ToolRegistry,
+        messages: Vec<Message>,
+        codebase_summary: String,
+    ) -> std::result::Result<Self, Box<dyn std::error::Error>> {
+        let mode = if dry_run {
+            PermissionMode::Plan
+        } else if auto_approve {
+            PermissionMode::Bypass
+        } else {
+            PermissionMode::Ask
+        };
+        let permissions = Permissions::new(mode, Vec::new(), Config::approvals_path(&project_root));
 
         // Create the API client
         let api_client = crate::api::create_client(provider, api_config)?;
@@ -77,19 +195,147 @@ impl GrokAgent {
             max_tokens: 4096,
             project_root,
             codebase_summary,
-            dry_run,
-            no_confirm: auto_approve,
-            git_repo,
+            permissions,
             response_cache: ResponseCache::new(100, 300), // 100 entries, 5 minute TTL
             tui_sender: None,
+            streaming: std::env::var("GROK_STREAM").unwrap_or_else(|_| "true".to_string())
+                == "true",
+            streamed_response: false,
+            provider: provider.to_string(),
+            context_window: None,
+            max_iterations: 15,
+            max_timeout_retries: 3,
+            max_rate_limit_retries: 5, // More retries since we handle silently
+            cache_enabled: std::env::var("GROK_CACHE").unwrap_or_else(|_| "true".to_string())
+                == "true",
+            session_store: None,
+            session: None,
+            session_saved: 0,
+            usage: SessionUsage::default(),
+            price: None,
+            cancel: CancelToken::default(),
+            processes: ProcessTable::default(),
+            policy: Arc::new(CommandPolicy::default()),
+            sandbox: None,
+            tool_settings: Arc::default(),
         })
     }
 
+    /// Apply generation, limit and cache settings from the resolved configuration
+    pub fn apply_config(&mut self, config: &Config) {
+        self.temperature = config.temperature;
+        self.max_tokens = config.max_tokens;
+        self.streaming = config.stream;
+        self.context_window = config.context_tokens;
+        self.max_iterations = config.max_iterations;
+        self.max_timeout_retries = config.max_timeout_retries;
+        self.max_rate_limit_retries = config.max_rate_limit_retries;
+        self.cache_enabled = config.cache_enabled;
+        self.response_cache = ResponseCache::new(config.cache_max_entries, config.cache_ttl_secs);
+        self.price = config.price(&self.api_client.config().model);
+        self.policy = Arc::new(config.command_policy());
+        self.sandbox = config.sandbox();
+        self.tool_settings = Arc::new(config.tool_settings());
+        self.tool_registry = ToolRegistry::with_plugins(config.plugins_enabled);
+        self.permissions = Permissions::new(
+            config.permission_mode,
+            config.always_allow_tools.clone(),
+            Config::approvals_path(&self.project_root),
+        );
+    }
+
+    /// Tokens used (and their cost, if priced) by this session so far
+    pub fn usage(&self) -> SessionUsage {
+        self.usage
+    }
+
+    /// Add the usage reported by a fresh (uncached) response
+    fn record_usage(&mut self, response: &ChatCompletionResponse) {
+        if let Some(usage) = &response.usage {
+            self.usage.record(usage, self.price.as_ref());
+            if self.tui_sender.is_some() {
+                self.send_update(TuiUpdate::Usage(self.usage));
+            }
+        }
+    }
+
+    /// Persist this conversation to the given session store
+    pub fn enable_session(&mut self, store: SessionStore) {
+        self.session_store = Some(store);
+    }
+
+    /// Id of the session this conversation is saved to, if any
+    pub fn session_id(&self) -> Option<&str> {
+        self.session.as_ref().map(|s| s.id())
+    }
+
+    /// Write any messages not yet persisted to the session file
+    pub fn save_session(&mut self) -> Result<()> {
+        let Some(store) = &self.session_store else {
+            return Ok(());
+        };
+        if self.session.is_none() {
+            // Don't create a session file until there is something to resume
+            if !self.messages.iter().any(|m| m.role == "user") {
+                return Ok(());
+            }
+            self.session = Some(store.create(SessionMeta {
+                id: String::new(),
+                provider: self.provider.clone(),
+                model: self.api_client.config().model.clone(),
+                project_root: self.project_root.clone(),
+                codebase_summary: self.codebase_summary.clone(),
+                created_at: chrono::Local::now(),
+            })?);
+            self.session_saved = 0;
+        }
+
+        if let Some(session) = &self.session {
+            let start = self.session_saved.min(self.messages.len());
+            session.append(&self.messages[start..])?;
+            self.session_saved = self.messages.len();
+        }
+        Ok(())
+    }
+
     /// Set the TUI update channel
     pub fn set_tui_sender(&mut self, sender: mpsc::UnboundedSender<TuiUpdate>) {
         self.tui_sender = Some(sender);
     }
 
+    /// Token that stops the running tools and ends the current prompt
+    pub fn cancel_token(&self) -> CancelToken {
+        self.cancel.clone()
+    }
+
+    /// Background processes started by tools in this session
+    pub fn processes(&self) -> ProcessTable {
+        self.processes.clone()
+    }
+
+    /// Permission mode and remembered approvals; changing the mode applies to
+    /// the next tool call
+    pub fn permissions(&self) -> Permissions {
+        self.permissions.clone()
+    }
+
+    /// The sandbox shell commands run in, if enabled
+    pub fn sandbox(&self) -> Option<Sandbox> {
+        self.sandbox
+    }
+
+    /// Stream a tool's output to the TUI while it runs
+    fn output_sink(&self, tool_name: &str) -> Option<OutputSink> {
+        let sender = self.tui_sender.clone()?;
+        let name = tool_name.to_string();
+        Some(Arc::new(move |line: &str| {
+            let _ = sender.send(TuiUpdate::ToolOutput {
+                name: name.clone(),
+                line: line.to_string(),
+            });
+        }))
+    }
+
     /// Send update to TUI if available, otherwise print to stdout
     fn send_update(&self, update: TuiUpdate) {
         if let Some(sender) = &self.tui_sender {
@@ -102,6 +348,11 @@ impl GrokAgent {
                         println!("💬 {content}");
                     }
                 }
+                TuiUpdate::StreamChunk { content } => {
+                    print!("{content}");
+                    use std::io::{self, Write};
+                    io::stdout().flush().unwrap();
+                }
                 TuiUpdate::ToolStart { name, icon } => {
                     println!("{icon} {name}...");
                 }
@@ -116,7 +367,7 @@ impl GrokAgent {
                 TuiUpdate::Error { message } => {
                     eprintln!("❌ {message}");
                 }
-                TuiUpdate::Complete => {
+                TuiUpdate::ToolOutput { .. } | TuiUpdate::Usage(_) | TuiUpdate::Complete => {
                     // No-op for stdout
                 }
             }
@@ -223,6 +474,7 @@ impl GrokAgent {
         };
 
         let api_resp = self.api_client.chat_completion(body).await?;
+        self.record_usage(&api_resp);
 
         if let Some(choice) = api_resp.choices.first() {
             if let Some(content) = &choice.message.content {
@@ -253,24 +505,214 @@ impl GrokAgent {
             .collect()
     }
 
-    async fn call_api(&self, use_structured: bool) -> Result<ChatCompletionResponse> {
+    /// Enable or disable token streaming for subsequent API calls
+    pub fn set_streaming(&mut self, streaming: bool) {
+        self.streaming = streaming;
+    }
+
+    /// Estimated prompt tokens for the next request
+    pub fn context_tokens(&self) -> usize {
+        let mut tokens = context::estimate_messages_tokens(&self.messages);
+        if self.api_client.capabilities().tools {
+            tokens += context::estimate_tools_tokens(&self.get_api_tools());
+        }
+        tokens
+    }
+
+    fn context_budget(&self) -> ContextBudget {
+        let mut budget = ContextBudget::for_model(&self.api_client.capabilities(), self.max_tokens);
+        if let Some(window) = self.context_window {
+            budget.window = window;
+        }
+        budget
+    }
+
+    /// Compact the history when the next request would not fit the context window
+    async fn ensure_context_budget(&mut self) {
+        let budget = self.context_budget();
+        let before = self.context_tokens();
+        if budget.fits(before) {
+            return;
+        }
+
+        // Old tool output is the cheapest thing to drop; keep the current turn intact
+        let current_turn = self
+            .messages
+            .iter()
+            .rposition(|m| m.role == "user")
+            .unwrap_or(0);
+        context::truncate_tool_outputs(&mut self.messages, current_turn, COMPACT_TOOL_OUTPUT_CHARS);
+
+        for keep_turns in (1..=COMPACT_KEEP_TURNS).rev() {
+            if budget.fits(self.context_tokens()) {
+                break;
+            }
+            if let Err(e) = self.summarize_history(keep_turns).await {
+                self.send_update(TuiUpdate::Error {
+                    message: format!("Failed to summarize conversation history: {e}"),
+                });
+                break;
+            }
+        }
+
+        if !budget.fits(self.context_tokens()) {
+            // Last resort: shorten every tool result but the latest ones
+            let protect_from = self.messages.len().saturating_sub(2);
+            context::truncate_tool_outputs(
+                &mut self.messages,
+                protect_from,
+                COMPACT_TOOL_OUTPUT_CHARS / 4,
+            );
+        }
+
+        self.send_update(TuiUpdate::Processing {
+            message: format!(
+                "\n🗜️  Compacted conversation history (~{before} → ~{} tokens)\n",
+                self.context_tokens()
+            ),
+        });
+    }
+
+    /// Replace turns older than the last `keep_recent_turns` with a summary
+    ///
+    /// The system message stays pinned at the start of the history. Returns
+    /// false when no turns were old enough to summarize.
+    async fn summarize_history(&mut self, keep_recent_turns: usize) -> Result<bool> {
+        let Some(split) = context::summary_split(&self.messages, keep_recent_turns) else {
+            return Ok(false);
+        };
+
+        let mut transcript =
+            context::render_transcript(&self.messages[1..split], COMPACT_TOOL_OUTPUT_CHARS);
+        // The summary request itself has to fit; keep the most recent part
+        let max_chars = self.context_budget().limit() * 2;
+        let total_chars = transcript.chars().count();
+        if total_chars > max_chars {
+            let tail: String = transcript.chars().skip(total_chars - max_chars).collect();
+            transcript = format!("[earlier conversation omitted]\n{tail}");
+        }
+
+        let body = ChatCompletionRequest {
+            model: self.api_client.config().model.clone(),
+            messages: vec![
+                Message {
+                    role: "system".to_string(),
+                    content: Some(
+                        "You summarize conversations between a user and a coding agent."
+                            .to_string(),
+                    ),
+                    tool_calls: None,
+                    tool_call_id: None,
+                },
+                Message {
+                    role: "user".to_string(),
+                    content: Some(format!(
+                        "Summarize this conversation so the agent can continue the work. Keep the user's goals, decisions made, files read or changed, and any open tasks. Be concise.\n\n{transcript}"
+                    )),
+                    tool_calls: None,
+                    tool_call_id: None,
+                },
+            ],
+            tools: None,
+            tool_choice: "none".to_string(),
+            temperature: 0.3,
+            max_tokens: 1000,
+            response_format: None,
+        };
+
+        let response = self.api_client.chat_completion(body).await?;
+        self.record_usage(&response);
+        let summary = response
+            .choices
+            .first()
+            .and_then(|choice| choice.message.content.clone())
+            .filter(|content| !content.trim().is_empty())
+            .ok_or(GrokError::NoSummaryGenerated)?;
+
+        self.messages.splice(
+            1..split,
+            [Message {
+                role: "system".to_string(),
+                content: Some(format!("Summary of the earlier conversation:\n{summary}")),
+                tool_calls: None,
+                tool_call_id: None,
+            }],
+        );
+
+        // Indices into the session file no longer line up with the history
+        if let Some(session) = &self.session {
+            session.rewrite(&self.messages)?;
+            self.session_saved = self.messages.len();
+        }
+        Ok(true)
+    }
+
+    /// Compact the conversation on demand (the `/compact` command)
+    ///
+    /// Returns the estimated prompt tokens before and after compaction.
+    pub async fn compact(&mut self) -> Result<(usize, usize)> {
+        let before = self.context_tokens();
+        let len = self.messages.len();
+        context::truncate_tool_outputs(&mut self.messages, len, COMPACT_TOOL_OUTPUT_CHARS);
+        self.summarize_history(1).await?;
+        Ok((before, self.context_tokens()))
+    }
+
+    async fn call_api(
+        &self,
+        use_structured: bool,
+        stream_started: Arc<AtomicBool>,
+    ) -> Result<ChatCompletionResponse> {
+        // Only ask for what the model supports
+        let capabilities = self.api_client.capabilities();
         let mut body = ChatCompletionRequest {
             model: self.api_client.config().model.clone(),
             messages: self.messages.clone(),
-            tools: Some(self.get_api_tools()),
-            tool_choice: "auto".to_string(),
+            tools: None,
+            tool_choice: "none".to_string(),
             temperature: self.temperature,
-            max_tokens: self.max_tokens,
+            max_tokens: self.max_tokens.min(capabilities.max_output_tokens),
             response_format: None,
         };
 
-        if use_structured {
+        if capabilities.tools {
+            body.tools = Some(self.get_api_tools());
+            body.tool_choice = "auto".to_string();
+        }
+        if use_structured && capabilities.json_mode {
             body.response_format = Some(ResponseFormat {
                 r#type: "json_object".to_string(),
             });
         }
 
-        self.api_client.chat_completion(body).await
+        if !self.streaming {
+            return self.api_client.chat_completion(body).await;
+        }
+
+        // Forward text deltas to the TUI (or stdout) as they arrive
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let tui_sender = self.tui_sender.clone();
+        let forward_task = tokio::spawn(async move {
+            while let Some(delta) = rx.recv().await {
+                if let StreamDelta::Content(text) = delta {
+                    let first = !stream_started.swap(true, Ordering::SeqCst);
+                    if let Some(sender) = &tui_sender {
+                        let _ = sender.send(TuiUpdate::StreamChunk { content: text });
+                    } else {
+                        use std::io::{self, Write};
+                        if first {
+                            print!("\n💬 ");
+                        }
+                        print!("{text}");
+                        io::stdout().flush().unwrap();
+                    }
+                }
+            }
+        });
+
+        let result = self.api_client.chat_completion_stream(body, tx).await;
+        let _ = forward_task.await;
+        result
     }
 
     async fn make_api_call_with_progress(
@@ -283,12 +725,18 @@ impl GrokAgent {
         // Only show progress if not in TUI mode
         let (tx, mut rx) = tokio::sync::oneshot::channel();
         let tui_sender = self.tui_sender.clone();
+        let stream_started = Arc::new(AtomicBool::new(false));
+        let progress_stream_started = stream_started.clone();
         let progress_task = tokio::spawn(async move {
             let mut elapsed_shown = 0;
             loop {
                 tokio::select! {
                     _ = tokio::time::sleep(tokio::time::Duration::from_secs(15)) => {
                         elapsed_shown += 15;
+                        // Streamed text is its own progress indicator
+                        if progress_stream_started.load(Ordering::SeqCst) {
+                            continue;
+                        }
                         if let Some(sender) = &tui_sender {
                             let _ = sender.send(TuiUpdate::Processing {
                                 message: format!(" ({elapsed_shown}s)"),
@@ -305,12 +753,14 @@ impl GrokAgent {
             }
         });
 
-        let api_result = self.call_api(use_structured).await;
+        let api_result = self.call_api(use_structured, stream_started.clone()).await;
         let _ = tx.send(()); // Stop the progress task
         let _ = progress_task.await; // Clean up the task
+        self.streamed_response = stream_started.load(Ordering::SeqCst);
 
         // Cache successful response
         if let Ok(ref response) = api_result {
+            self.record_usage(response);
             if let Some(key) = cache_key {
                 if let Ok(serialized) = serde_json::to_string(response) {
                     self.response_cache.put(key.clone(), serialized);
@@ -322,6 +772,16 @@ impl GrokAgent {
     }
 
     pub async fn process_prompt(&mut self, user_message: &str, interactive: bool) {
+        self.run_prompt(user_message, interactive).await;
+
+        if let Err(e) = self.save_session() {
+            self.send_update(TuiUpdate::Error {
+                message: format!("Failed to save session: {e}"),
+            });
+        }
+    }
+
+    async fn run_prompt(&mut self, user_message: &str, interactive: bool) {
         self.messages.push(Message {
             role: "user".to_string(),
             content: Some(user_message.to_string()),
@@ -334,21 +794,27 @@ impl GrokAgent {
             self.send_update(TuiUpdate::Message(self.messages.last().unwrap().clone()));
         }
 
+        self.cancel.reset();
+
         let mut iterations = 0;
-        let max_iterations = 15;
+        let max_iterations = self.max_iterations;
         let mut timeout_retries = 0;
-        let max_timeout_retries = 3;
+        let max_timeout_retries = self.max_timeout_retries;
         let mut rate_limit_retries = 0;
-        let max_rate_limit_retries = 5; // More retries since we handle silently
-                                        // TODO: Make iteration and retry limits configurable
-                                        // TODO: Add exponential backoff with jitter for retries
+        let max_rate_limit_retries = self.max_rate_limit_retries;
+        // TODO: Add exponential backoff with jitter for retries
 
         // Track tool results for cache key generation
         let mut tool_results: Vec<String> = Vec::new();
-        let enable_cache =
-            std::env::var("GROK_CACHE").unwrap_or_else(|_| "true".to_string()) == "true";
+        let enable_cache = self.cache_enabled;
 
         loop {
+            if self.cancel.is_cancelled() {
+                self.send_update(TuiUpdate::Processing {
+                    message: "Cancelled by user.".to_string(),
+                });
+                break;
+            }
             if iterations >= max_iterations {
                 self.send_update(TuiUpdate::Processing {
                     message: "Max iterations reached. Stopping.".to_string(),
@@ -370,6 +836,8 @@ impl GrokAgent {
 
             let use_structured = iterations == max_iterations;
 
+            self.ensure_context_budget().await;
+
             // Check cache before making API call
             let cache_key = if enable_cache && !tool_results.is_empty() {
                 Some(ResponseCache::generate_key(user_message, &tool_results))
@@ -380,6 +848,7 @@ impl GrokAgent {
             // Try to get cached response
             let api_result = if let Some(ref key) = cache_key {
                 if let Some(cached_response) = self.response_cache.get(key) {
+                    self.streamed_response = false;
                     // Clear thinking indicator for cached response
                     if iterations == 1 {
                         self.send_update(TuiUpdate::Processing {
@@ -419,7 +888,7 @@ impl GrokAgent {
                         2. Visit your team billing page to check status:\n\
                            https://console.x.ai/team/[your-team-id]\n\
                         3. Try regenerating your API key after credits show as available\n\
-                        4. Use OpenAI instead: grok-code --dev (requires OPENAI_API_KEY)\n\n\
+                        4. Use OpenAI instead: grok-code --provider openai (requires OPENAI_API_KEY)\n\n\
                         For detailed troubleshooting, see: ./TROUBLESHOOTING_XAI.md".to_string();
                         self.send_update(TuiUpdate::Error {
                             message: error_text,
@@ -447,7 +916,7 @@ impl GrokAgent {
                         if timeout_retries > max_timeout_retries {
                             self.send_update(TuiUpdate::Error {
                                 message: "\n⏱️  The request is taking longer than expected. This sometimes happens with complex requests.\n\
-                                         Please try again with a simpler request or use --dev mode for faster responses.".to_string(),
+                                         Please try again with a simpler request or use --provider openai for faster responses.".to_string(),
                             });
                             self.send_update(TuiUpdate::Complete);
                             return;
@@ -504,23 +973,33 @@ impl GrokAgent {
             timeout_retries = 0;
             rate_limit_retries = 0;
 
-            if let Some(content) = &message.content {
-                if !content.is_empty() && content.trim() != "" {
-                    self.messages.push(message.clone());
+            // A reply can have both text and tool calls; it is recorded once,
+            // before any of the tools run
+            let has_text = message
+                .content
+                .as_deref()
+                .is_some_and(|content| !content.trim().is_empty());
+            if has_text || message.tool_calls.is_some() {
+                self.messages.push(message.clone());
+            }
+
+            if has_text {
+                if self.streamed_response && self.tui_sender.is_none() {
+                    // Text was already printed as it arrived
+                    println!();
+                } else {
                     self.send_update(TuiUpdate::Message(message.clone()));
-                    if !interactive {
-                        break;
-                    }
-                } else if message.tool_calls.is_none() {
-                    // Empty response with no tools - continue to let AI think more
-                    self.messages.push(message.clone());
-                    continue;
                 }
+                if !interactive && message.tool_calls.is_none() {
+                    break;
+                }
+            } else if message.content.is_some() && message.tool_calls.is_none() {
+                // Empty response with no tools - continue to let AI think more
+                self.messages.push(message.clone());
+                continue;
             }
 
             if let Some(tool_calls) = message.tool_calls.as_ref() {
-                self.messages.push(message.clone());
-
                 // Execute tools concurrently when possible
                 let num_tools = tool_calls.len();
                 if num_tools > 1 {
@@ -540,24 +1019,41 @@ impl GrokAgent {
                     let icon = match tool_name.as_str() {
                         "read_file" => "📖",
                         "write_file" => "✏️",
-                        "edit_file" => "📝",
+                        "edit_file" | "replace_in_file" => "📝",
+                        "apply_patch" => "🩹",
                         "list_files" => "📁",
                         "run_shell_command" => "🖥️",
-                        "search_codebase" => "🔍",
+                        "start_background_process" => "🚀",
+                        "read_process_output" => "📜",
+                        "send_process_input" => "⌨️",
+                        "kill_process" => "🛑",
+                        "search_codebase" | "find_files" => "🔍",
                         "run_lint" => "🔧",
                         "debug_code" => "🐛",
+                        "git_status" | "git_diff" | "git_log" | "git_blame" | "git_show" => "🌿",
                         _ => "⚙️",
                     };
 
                     let action_text = match tool_name.as_str() {
                         "read_file" => "Reading file",
                         "write_file" => "Writing file",
-                        "edit_file" => "Editing file",
+                        "edit_file" | "replace_in_file" => "Editing file",
+                        "apply_patch" => "Applying patch",
                         "list_files" => "Listing files",
                         "run_shell_command" => "Running command",
+                        "start_background_process" => "Starting background process",
+                        "read_process_output" => "Reading process output",
+                        "send_process_input" => "Sending process input",
+                        "kill_process" => "Stopping process",
                         "search_codebase" => "Searching codebase",
+                        "find_files" => "Finding files",
                         "run_lint" => "Running linter",
                         "debug_code" => "Debugging code",
+                        "git_status" => "Checking git status",
+                        "git_diff" => "Reading git diff",
+                        "git_log" => "Reading git log",
+                        "git_blame" => "Running git blame",
+                        "git_show" => "Showing git revision",
                         _ => &tool_name,
                     };
 
@@ -572,16 +1068,27 @@ impl GrokAgent {
                 // Execute tools (can be parallelized for tools that don't need git repo)
                 let mut results = Vec::new();
 
-                // Separate tools that can run in parallel (non-git tools)
+                // Separate tools that can run in parallel (everything but the
+                // git tools that write or use the agent's repository handle)
                 let mut parallel_tools = Vec::new();
                 let mut sequential_tools = Vec::new();
+                // Models without parallel tool calls expect each call to see
+                // the effects of the ones before it
+                let parallel = self.api_client.capabilities().parallel_tool_calls;
 
                 for (idx, tool_id, tool_name, tool_args) in tool_infos {
-                    // Git operations must be sequential
-                    if matches!(
-                        tool_name.as_str(),
-                        "create_commit" | "submit_pr" | "resolve_merge_conflict"
-                    ) {
+                    // Git operations must be sequential; read-only git tools
+                    // open the repository themselves and run in parallel
+                    if !parallel
+                        || matches!(
+                            tool_name.as_str(),
+                            "create_commit"
+                                | "submit_pr"
+                                | "list_pull_requests"
+                                | "get_review_comments"
+                                | "resolve_merge_conflict"
+                        )
+                    {
                         sequential_tools.push((idx, tool_id, tool_name, tool_args));
                     } else {
                         parallel_tools.push((idx, tool_id, tool_name, tool_args));
@@ -591,30 +1098,44 @@ impl GrokAgent {
                 // Execute parallel tools
                 if !parallel_tools.is_empty() {
                     let project_root = self.project_root.clone();
-                    let dry_run = self.dry_run;
-                    let no_confirm = self.no_confirm;
+                    let mode = self.permissions.mode();
+                    let dry_run = mode == PermissionMode::Plan;
+                    let no_confirm = mode == PermissionMode::Bypass;
                     let registry = self.tool_registry.clone();
                     let tui_mode = self.tui_sender.is_some();
+                    let sandbox = self.sandbox;
 
                     let mut tasks = Vec::new();
                     for (idx, tool_id, tool_name, tool_args) in parallel_tools {
                         let project_root = project_root.clone();
                         let registry = registry.clone();
+                        let cancel = self.cancel.clone();
+                        let output = self.output_sink(&tool_name);
+                        let processes = self.processes.clone();
+                        let policy = self.policy.clone();
+                        let permissions = self.permissions.clone();
+                        let settings = self.tool_settings.clone();
 
                         let task = tokio::spawn(async move {
                             let context = ToolContext {
                                 project_root,
                                 dry_run,
                                 no_confirm,
-                                git_repo: None, // Non-git tools don't need repo
+                                git_repo: None, // Read-only git tools open their own
                                 tui_mode,
+                                cancel,
+                                output,
+                                processes,
+                                policy,
+                                sandbox,
+                                tool_name: tool_name.clone(),
+                                permissions,
+                                settings,
                             };
                             let result = registry.execute_tool(&tool_name, &tool_args, &context);
                             (idx, tool_id, tool_name, result)
                         });
                         tasks.push(task);
-                        // TODO: Add timeout for individual tool execution
-                        // TODO: Implement cancellation tokens for long-running tools
                     }
 
                     // Wait for parallel tasks
@@ -636,19 +1157,54 @@ impl GrokAgent {
                     }
                 }
 
-                // Execute sequential tools
+                // Execute sequential tools one after another, each on a
+                // blocking thread so the TUI keeps showing output and can
+                // cancel it
                 for (idx, tool_id, tool_name, tool_args) in sequential_tools {
-                    let context = ToolContext {
-                        project_root: self.project_root.clone(),
-                        dry_run: self.dry_run,
-                        no_confirm: self.no_confirm,
-                        git_repo: self.git_repo.as_ref(),
-                        tui_mode: self.tui_sender.is_some(),
-                    };
-                    let result = self
-                        .tool_registry
-                        .execute_tool(&tool_name, &tool_args, &context);
-                    results.push((idx, tool_id, tool_name, result));
+                    let mode = self.permissions.mode();
+                    let project_root = self.project_root.clone();
+                    let registry = self.tool_registry.clone();
+                    let tui_mode = self.tui_sender.is_some();
+                    let cancel = self.cancel.clone();
+                    let output = self.output_sink(&tool_name);
+                    let processes = self.processes.clone();
+                    let policy = self.policy.clone();
+                    let sandbox = self.sandbox;
+                    let permissions = self.permissions.clone();
+                    let settings = self.tool_settings.clone();
+                    let id = tool_id.clone();
+                    let name = tool_name.clone();
+
+                    let task = tokio::task::spawn_blocking(move || {
+                        // Git tools that write need a repository handle
+                        let git_repo = Repository::open(&project_root).ok();
+                        let context = ToolContext {
+                            project_root,
+                            dry_run: mode == PermissionMode::Plan,
+                            no_confirm: mode == PermissionMode::Bypass,
+                            git_repo: git_repo.as_ref(),
+                            tui_mode,
+                            cancel,
+                            output,
+                            processes,
+                            policy,
+                            sandbox,
+                            tool_name: tool_name.clone(),
+                            permissions,
+                            settings,
+                        };
+                        let result = registry.execute_tool(&tool_name, &tool_args, &context);
+                        (idx, tool_id, tool_name, result)
+                    });
+                    match task.await {
+                        Ok(result) => results.push(result),
+                        Err(e) => {
+                            self.send_update(TuiUpdate::Error {
+                                message: format!("Tool execution failed: {e}"),
+                            });
+                            results.push((idx, id, name, format!("Error executing tool: {e}")));
+                        }
+                    }
                 }
 
                 // Sort results by original index to maintain order
@@ -684,7 +1240,8 @@ impl GrokAgent {
                                 )
                             }
                         }
-                        "read_file" | "search_codebase" => {
+                        "read_file" | "search_codebase" | "find_files" | "git_diff" | "git_log"
+                        | "git_blame" | "git_show" => {
                             let lines = tool_result.lines().count();
                             if lines > 5 {
                                 format!("✓ Success ({lines} lines of output)")
@@ -1,4 +1,8 @@
-use super::{ApiClient, ApiConfig, ChatCompletionRequest, ChatCompletionResponse, Choice, Message};
+use super::stream::{SseEvent, SseParser, StreamAccumulator, StreamDelta};
+use super::{
+    model_capabilities, streaming_body, ApiClient, ApiConfig, ChatCompletionRequest,
+    ChatCompletionResponse, Choice, Message, ModelCapabilities, Usage,
+};
 use crate::error::{GrokError, Result};
 use async_trait::async_trait;
 use reqwest::{
@@ -7,7 +11,9 @@ use reqwest::{
 };
 use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
+use std::collections::HashMap;
 use std::time::Duration;
+use tokio::sync::mpsc;
 
 /// Anthropic API client implementation
 pub struct AnthropicClient {
@@ -24,32 +30,86 @@ impl AnthropicClient {
         }
     }
 
+    /// Build the authentication and versioning headers
+    fn headers(&self) -> Result<HeaderMap> {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "x-api-key",
+            HeaderValue::from_str(&self.config.api_key)
+                .map_err(|_| GrokError::Config("Invalid API key format".to_string()))?,
+        );
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        headers.insert("anthropic-version", HeaderValue::from_static("2023-06-01"));
+        Ok(headers)
+    }
+
     /// Convert OpenAI-style request to Anthropic format
     fn convert_request(&self, request: ChatCompletionRequest) -> AnthropicRequest {
-        let mut messages = Vec::new();
-        let mut system_prompt = None;
+        let mut messages: Vec<AnthropicMessage> = Vec::new();
+        let mut system_prompt: Option<String> = None;
+        // Tool calls from the latest assistant turn that still need a result
+        let mut pending_tool_ids: Vec<String> = Vec::new();
 
-        // Extract system message and convert other messages
         for msg in request.messages {
             match msg.role.as_str() {
                 "system" => {
-                    system_prompt = msg.content;
+                    if let Some(content) = msg.content {
+                        system_prompt = Some(match system_prompt {
+                            Some(existing) => format!("{existing}\n\n{content}"),
+                            None => content,
+                        });
+                    }
                 }
-                "user" => {
-                    messages.push(AnthropicMessage {
-                        role: "user".to_string(),
-                        content: msg.content.unwrap_or_default(),
-                    });
+                "tool" => {
+                    let content = msg.content.unwrap_or_default();
+                    let id = msg.tool_call_id.unwrap_or_default();
+                    let block = match pending_tool_ids.iter().position(|p| *p == id) {
+                        Some(pos) => {
+                            pending_tool_ids.remove(pos);
+                            AnthropicBlock::ToolResult {
+                                tool_use_id: id,
+                                content,
+                                is_error: None,
+                            }
+                        }
+                        // A result without a matching tool_use would be rejected,
+                        // so pass it along as plain text instead
+                        None => AnthropicBlock::Text {
+                            text: format!("Tool result: {content}"),
+                        },
+                    };
+                    push_block(&mut messages, "user", block);
                 }
                 "assistant" => {
-                    messages.push(AnthropicMessage {
-                        role: "assistant".to_string(),
-                        content: msg.content.unwrap_or_default(),
-                    });
+                    close_pending_tool_calls(&mut messages, &mut pending_tool_ids);
+                    if let Some(text) = msg.content.filter(|t| !t.trim().is_empty()) {
+                        push_block(&mut messages, "assistant", AnthropicBlock::Text { text });
+                    }
+                    for call in msg.tool_calls.unwrap_or_default() {
+                        let input = serde_json::from_str::<JsonValue>(&call.function.arguments)
+                            .ok()
+                            .filter(JsonValue::is_object)
+                            .unwrap_or_else(|| JsonValue::Object(Default::default()));
+                        pending_tool_ids.push(call.id.clone());
+                        push_block(
+                            &mut messages,
+                            "assistant",
+                            AnthropicBlock::ToolUse {
+                                id: call.id,
+                                name: call.function.name,
+                                input,
+                            },
+                        );
+                    }
+                }
+                _ => {
+                    close_pending_tool_calls(&mut messages, &mut pending_tool_ids);
+                    let text = msg.content.unwrap_or_default();
+                    push_block(&mut messages, "user", AnthropicBlock::Text { text });
                 }
-                _ => {} // Skip other roles
             }
         }
+        close_pending_tool_calls(&mut messages, &mut pending_tool_ids);
 
         // Convert tools if present
         let tools = request.tools.map(|tools| {
@@ -132,6 +192,7 @@ impl AnthropicClient {
                     tool_call_id: None,
                 },
             }],
+            usage: response.usage.map(Usage::from),
         }
     }
 }
@@ -142,10 +203,15 @@ impl ApiClient for AnthropicClient {
         &self.config
     }
 
+    fn capabilities(&self) -> ModelCapabilities {
+        model_capabilities("anthropic", &self.config.model)
+    }
+
     async fn chat_completion(
         &self,
         request: ChatCompletionRequest,
     ) -> Result<ChatCompletionResponse> {
+        self.capabilities().check_request(&request)?;
         let url = format!("{}/messages", self.config.base_url);
 
         // Debug logging for API requests
@@ -162,20 +228,10 @@ impl ApiClient for AnthropicClient {
         // Convert request to Anthropic format
         let anthropic_request = self.convert_request(request);
 
-        // Build headers
-        let mut headers = HeaderMap::new();
-        headers.insert(
-            "x-api-key",
-            HeaderValue::from_str(&self.config.api_key)
-                .map_err(|_| GrokError::Config("Invalid API key format".to_string()))?,
-        );
-        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
-        headers.insert("anthropic-version", HeaderValue::from_static("2023-06-01"));
-
         let response = self
             .client
             .post(&url)
-            .headers(headers)
+            .headers(self.headers()?)
             .json(&anthropic_request)
             .timeout(Duration::from_secs(self.config.timeout_secs))
             .send()
@@ -202,6 +258,188 @@ impl ApiClient for AnthropicClient {
         // Convert response to OpenAI format
         Ok(self.convert_response(anthropic_response))
     }
+
+    async fn chat_completion_stream(
+        &self,
+        request: ChatCompletionRequest,
+        sender: mpsc::UnboundedSender<StreamDelta>,
+    ) -> Result<ChatCompletionResponse> {
+        self.capabilities().check_request(&request)?;
+        let url = format!("{}/messages", self.config.base_url);
+
+        if std::env::var("DEBUG_API").is_ok() {
+            eprintln!("DEBUG: Sending streaming Anthropic API request to {url}");
+            eprintln!("  Model: {}", request.model);
+            eprintln!("  Messages count: {}", request.messages.len());
+        }
+
+        let anthropic_request = self.convert_request(request);
+
+        let mut response = self
+            .client
+            .post(&url)
+            .headers(self.headers()?)
+            .json(&streaming_body(&anthropic_request)?)
+            .timeout(Duration::from_secs(self.config.timeout_secs))
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let text = response.text().await.unwrap_or_default();
+            if status.as_u16() == 429 {
+                return Err(GrokError::rate_limited(text, Some(60)));
+            }
+            return Err(GrokError::ApiError(format!("API error {status}: {text}")));
+        }
+
+        let mut parser = SseParser::new();
+        let mut state = AnthropicStreamState::default();
+
+        'read: while let Some(chunk) = response.chunk().await? {
+            for event in parser.feed(&chunk) {
+                if state.handle(event, &sender)? {
+                    break 'read;
+                }
+            }
+        }
+        if let Some(event) = parser.finish() {
+            state.handle(event, &sender)?;
+        }
+
+        Ok(state.accumulator.finish())
+    }
+}
+
+/// Tracks content blocks while reading an Anthropic event stream
+#[derive(Default)]
+struct AnthropicStreamState {
+    accumulator: StreamAccumulator,
+    /// Maps content block index to tool call index
+    tool_blocks: HashMap<usize, usize>,
+    /// Input tokens arrive in `message_start`, output tokens in `message_delta`
+    usage: AnthropicUsage,
+}
+
+impl AnthropicStreamState {
+    /// Handle one event; returns true once the message is complete
+    fn handle(
+        &mut self,
+        event: SseEvent,
+        sender: &mpsc::UnboundedSender<StreamDelta>,
+    ) -> Result<bool> {
+        let data: JsonValue = serde_json::from_str(&event.data)
+            .map_err(|e| GrokError::JsonError(format!("Invalid stream event: {e}")))?;
+        let event_type = event
+            .event
+            .as_deref()
+            .or_else(|| data["type"].as_str())
+            .unwrap_or_default();
+        let block_index = data["index"].as_u64().unwrap_or(0) as usize;
+
+        let delta = match event_type {
+            "content_block_start" => {
+                let block = &data["content_block"];
+                match block["type"].as_str() {
+                    Some("tool_use") => {
+                        let index = self.tool_blocks.len();
+                        self.tool_blocks.insert(block_index, index);
+                        Some(StreamDelta::ToolCall {
+                            index,
+                            id: block["id"].as_str().map(String::from),
+                            name: block["name"].as_str().map(String::from),
+                            arguments: String::new(),
+                        })
+                    }
+                    Some("text") => block["text"]
+                        .as_str()
+                        .filter(|t| !t.is_empty())
+                        .map(|t| StreamDelta::Content(t.to_string())),
+                    _ => None,
+                }
+            }
+            "content_block_delta" => {
+                let delta = &data["delta"];
+                match delta["type"].as_str() {
+                    Some("text_delta") => delta["text"]
+                        .as_str()
+                        .map(|t| StreamDelta::Content(t.to_string())),
+                    Some("input_json_delta") => {
+                        self.tool_blocks
+                            .get(&block_index)
+                            .map(|&index| StreamDelta::ToolCall {
+                                index,
+                                id: None,
+                                name: None,
+                                arguments: delta["partial_json"]
+                                    .as_str()
+                                    .unwrap_or_default()
+                                    .to_string(),
+                            })
+                    }
+                    _ => None,
+                }
+            }
+            "message_start" | "message_delta" => {
+                let usage = match event_type {
+                    "message_start" => &data["message"]["usage"],
+                    _ => &data["usage"],
+                };
+                if let Ok(update) = serde_json::from_value::<AnthropicUsage>(usage.clone()) {
+                    self.usage.merge(&update);
+                    Some(StreamDelta::Usage(self.usage.into()))
+                } else {
+                    None
+                }
+            }
+            "message_stop" => return Ok(true),
+            "error" => {
+                let message = data["error"]["message"]
+                    .as_str()
+                    .unwrap_or("Unknown streaming error")
+                    .to_string();
+                if data["error"]["type"].as_str() == Some("rate_limit_error") {
+                    return Err(GrokError::rate_limited(message, Some(60)));
+                }
+                return Err(GrokError::ApiError(format!("API error: {message}")));
+            }
+            _ => None,
+        };
+
+        if let Some(delta) = delta {
+            self.accumulator.apply(&delta);
+            let _ = sender.send(delta);
+        }
+        Ok(false)
+    }
+}
+
+/// Append a content block, merging it into the previous message when the role
+/// matches since Anthropic requires user and assistant turns to alternate
+fn push_block(messages: &mut Vec<AnthropicMessage>, role: &str, block: AnthropicBlock) {
+    match messages.last_mut() {
+        Some(last) if last.role == role => last.content.push(block),
+        _ => messages.push(AnthropicMessage {
+            role: role.to_string(),
+            content: vec![block],
+        }),
+    }
+}
+
+/// Every tool_use must be answered by a tool_result in the next user turn.
+/// Add an error result for any call whose output never made it into history.
+fn close_pending_tool_calls(messages: &mut Vec<AnthropicMessage>, pending: &mut Vec<String>) {
+    for id in pending.drain(..) {
+        push_block(
+            messages,
+            "user",
+            AnthropicBlock::ToolResult {
+                tool_use_id: id,
+                content: "Tool call was not executed.".to_string(),
+                is_error: Some(true),
+            },
+        );
+    }
 }
 
 // Anthropic-specific request/response types
@@ -218,10 +456,29 @@ struct AnthropicRequest {
     tools: Option<Vec<AnthropicTool>>,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Debug)]
 struct AnthropicMessage {
     role: String,
-    content: String,
+    content: Vec<AnthropicBlock>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum AnthropicBlock {
+    Text {
+        text: String,
+    },
+    ToolUse {
+        id: String,
+        name: String,
+        input: JsonValue,
+    },
+    ToolResult {
+        tool_use_id: String,
+        content: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        is_error: Option<bool>,
+    },
 }
 
 #[derive(Serialize)]
@@ -235,6 +492,49 @@ struct AnthropicTool {
 struct AnthropicResponse {
     content: Vec<AnthropicContent>,
     stop_reason: Option<String>,
+    usage: Option<AnthropicUsage>,
+}
+
+#[derive(Deserialize, Default, Clone, Copy)]
+struct AnthropicUsage {
+    #[serde(default)]
+    input_tokens: u32,
+    #[serde(default)]
+    output_tokens: u32,
+    #[serde(default)]
+    cache_creation_input_tokens: Option<u32>,
+    #[serde(default)]
+    cache_read_input_tokens: Option<u32>,
+}
+
+impl AnthropicUsage {
+    /// Apply a partial update from the event stream; zero means "not reported"
+    fn merge(&mut self, update: &AnthropicUsage) {
+        if update.input_tokens > 0 {
+            self.input_tokens = update.input_tokens;
+        }
+        if update.output_tokens > 0 {
+            self.output_tokens = update.output_tokens;
+        }
+        self.cache_creation_input_tokens = update
+            .cache_creation_input_tokens
+            .or(self.cache_creation_input_tokens);
+        self.cache_read_input_tokens = update
+            .cache_read_input_tokens
+            .or(self.cache_read_input_tokens);
+    }
+}
+
+impl From<AnthropicUsage> for Usage {
+    fn from(usage: AnthropicUsage) -> Self {
+        // Anthropic reports cached prompt tokens separately from input_tokens
+        Usage {
+            prompt_tokens: usage.input_tokens
+                + usage.cache_creation_input_tokens.unwrap_or(0)
+                + usage.cache_read_input_tokens.unwrap_or(0),
+            completion_tokens: usage.output_tokens,
+        }
+    }
 }
 
 #[derive(Deserialize)]
@@ -253,7 +553,7 @@ struct AnthropicContent {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::api::{Function, Tool};
+    use crate::api::{Function, FunctionCall, Tool, ToolCall};
     use mockito::{Server, ServerGuard};
     use serde_json::json;
 
@@ -282,7 +582,12 @@ mod tests {
                 "text": "Hello from Claude!"
             }],
             "model": "claude-3-opus-20240229",
-            "stop_reason": "end_turn"
+            "stop_reason": "end_turn",
+            "usage": {
+                "input_tokens": 12,
+                "cache_read_input_tokens": 100,
+                "output_tokens": 8
+            }
         });
 
         let _mock = server
@@ -318,6 +623,10 @@ mod tests {
             response.choices[0].message.content.as_ref().unwrap(),
             "Hello from Claude!"
         );
+        // Cached prompt tokens count towards the prompt
+        let usage = response.usage.unwrap();
+        assert_eq!(usage.prompt_tokens, 112);
+        assert_eq!(usage.completion_tokens, 8);
     }
 
     #[tokio::test]
@@ -485,4 +794,399 @@ mod tests {
             _ => panic!("Expected RateLimitExceeded error"),
         }
     }
+
+    #[tokio::test]
+    async fn test_chat_completion_stream_with_tool_use() {
+        let mut server = Server::new_async().await;
+        let client = create_test_client(&server).await;
+
+        let events = [
+            (
+                "message_start",
+                json!({"type": "message_start", "message": {"id": "msg_1", "content": [], "usage": {"input_tokens": 25, "output_tokens": 1}}}),
+            ),
+            (
+                "content_block_start",
+                json!({"type": "content_block_start", "index": 0, "content_block": {"type": "text", "text": ""}}),
+            ),
+            ("ping", json!({"type": "ping"})),
+            (
+                "content_block_delta",
+                json!({"type": "content_block_delta", "index": 0, "delta": {"type": "text_delta", "text": "Reading "}}),
+            ),
+            (
+                "content_block_delta",
+                json!({"type": "content_block_delta", "index": 0, "delta": {"type": "text_delta", "text": "the file."}}),
+            ),
+            (
+                "content_block_stop",
+                json!({"type": "content_block_stop", "index": 0}),
+            ),
+            (
+                "content_block_start",
+                json!({"type": "content_block_start", "index": 1, "content_block": {"type": "tool_use", "id": "toolu_1", "name": "read_file", "input": {}}}),
+            ),
+            (
+                "content_block_delta",
+                json!({"type": "content_block_delta", "index": 1, "delta": {"type": "input_json_delta", "partial_json": "{\"path\": "}}),
+            ),
+            (
+                "content_block_delta",
+                json!({"type": "content_block_delta", "index": 1, "delta": {"type": "input_json_delta", "partial_json": "\"test.txt\"}"}}),
+            ),
+            (
+                "content_block_stop",
+                json!({"type": "content_block_stop", "index": 1}),
+            ),
+            (
+                "message_delta",
+                json!({"type": "message_delta", "delta": {"stop_reason": "tool_use"}, "usage": {"output_tokens": 15}}),
+            ),
+            ("message_stop", json!({"type": "message_stop"})),
+        ];
+        let sse_body: String = events
+            .iter()
+            .map(|(name, data)| format!("event: {name}\ndata: {data}\n\n"))
+            .collect();
+
+        let _mock = server
+            .mock("POST", "/messages")
+            .match_header("x-api-key", "test_key")
+            .match_body(mockito::Matcher::PartialJson(json!({"stream": true})))
+            .with_status(200)
+            .with_header("content-type", "text/event-stream")
+            .with_body(sse_body)
+            .create_async()
+            .await;
+
+        let request = ChatCompletionRequest {
+            model: "claude-3-opus-20240229".to_string(),
+            messages: vec![Message {
+                role: "user".to_string(),
+                content: Some("Read the test.txt file".to_string()),
+                tool_calls: None,
+                tool_call_id: None,
+            }],
+            tools: None,
+            tool_choice: "auto".to_string(),
+            temperature: 0.7,
+            max_tokens: 100,
+            response_format: None,
+        };
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let response = client.chat_completion_stream(request, tx).await.unwrap();
+
+        let message = &response.choices[0].message;
+        assert_eq!(message.content.as_deref(), Some("Reading the file."));
+        let tool_calls = message.tool_calls.as_ref().unwrap();
+        assert_eq!(tool_calls[0].id, "toolu_1");
+        assert_eq!(tool_calls[0].function.name, "read_file");
+        assert_eq!(tool_calls[0].function.arguments, r#"{"path": "test.txt"}"#);
+        assert_eq!(
+            response.usage,
+            Some(Usage {
+                prompt_tokens: 25,
+                completion_tokens: 15
+            })
+        );
+
+        assert_eq!(
+            rx.recv().await,
+            Some(StreamDelta::Usage(Usage {
+                prompt_tokens: 25,
+                completion_tokens: 1
+            }))
+        );
+        assert_eq!(
+            rx.recv().await,
+            Some(StreamDelta::Content("Reading ".to_string()))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_chat_completion_stream_error_event() {
+        let mut server = Server::new_async().await;
+        let client = create_test_client(&server).await;
+
+        let sse_body = format!(
+            "event: error\ndata: {}\n\n",
+            json!({"type": "error", "error": {"type": "overloaded_error", "message": "Overloaded"}})
+        );
+
+        let _mock = server
+            .mock("POST", "/messages")
+            .with_status(200)
+            .with_header("content-type", "text/event-stream")
+            .with_body(sse_body)
+            .create_async()
+            .await;
+
+        let request = ChatCompletionRequest {
+            model: "claude-3-opus-20240229".to_string(),
+            messages: vec![],
+            tools: None,
+            tool_choice: "auto".to_string(),
+            temperature: 0.7,
+            max_tokens: 100,
+            response_format: None,
+        };
+
+        let (tx, _rx) = mpsc::unbounded_channel();
+        match client.chat_completion_stream(request, tx).await {
+            Err(GrokError::ApiError(msg)) => assert!(msg.contains("Overloaded")),
+            _ => panic!("Expected ApiError"),
+        }
+    }
+
+    fn message(role: &str, content: Option<&str>) -> Message {
+        Message {
+            role: role.to_string(),
+            content: content.map(String::from),
+            tool_calls: None,
+            tool_call_id: None,
+        }
+    }
+
+    fn tool_result(id: &str, content: &str) -> Message {
+        Message {
+            role: "tool".to_string(),
+            content: Some(content.to_string()),
+            tool_calls: None,
+            tool_call_id: Some(id.to_string()),
+        }
+    }
+
+    fn request_with(messages: Vec<Message>) -> ChatCompletionRequest {
+        ChatCompletionRequest {
+            model: "claude-3-opus-20240229".to_string(),
+            messages,
+            tools: None,
+            tool_choice: "auto".to_string(),
+            temperature: 0.7,
+            max_tokens: 100,
+            response_format: None,
+        }
+    }
+
+    fn test_client() -> AnthropicClient {
+        AnthropicClient::new(ApiConfig {
+            api_key: "test_key".to_string(),
+            base_url: "http://localhost".to_string(),
+            model: "claude-3-opus-20240229".to_string(),
+            timeout_secs: 60,
+            max_retries: 3,
+        })
+    }
+
+    #[test]
+    fn test_convert_request_tool_round_trip() {
+        let client = test_client();
+
+        // Claude asks for a tool...
+        let response: AnthropicResponse = serde_json::from_value(json!({
+            "content": [
+                {"type": "text", "text": "Let me look."},
+                {"type": "tool_use", "id": "toolu_1", "name": "read_file", "input": {"path": "a.rs"}}
+            ],
+            "stop_reason": "tool_use"
+        }))
+        .unwrap();
+        let assistant = client.convert_response(response).choices.remove(0).message;
+
+        // ...and the agent appends the result before the next request
+        let request = request_with(vec![
+            message("system", Some("Be helpful.")),
+            message("user", Some("Summarize a.rs")),
+            assistant,
+            tool_result("toolu_1", "fn main() {}"),
+        ]);
+        let converted = serde_json::to_value(client.convert_request(request)).unwrap();
+
+        assert_eq!(converted["system"], "Be helpful.");
+        assert_eq!(
+            converted["messages"],
+            json!([
+                {"role": "user", "content": [{"type": "text", "text": "Summarize a.rs"}]},
+                {"role": "assistant", "content": [
+                    {"type": "text", "text": "Let me look."},
+                    {"type": "tool_use", "id": "toolu_1", "name": "read_file", "input": {"path": "a.rs"}}
+                ]},
+                {"role": "user", "content": [
+                    {"type": "tool_result", "tool_use_id": "toolu_1", "content": "fn main() {}"}
+                ]}
+            ])
+        );
+    }
+
+    #[test]
+    fn test_convert_request_merges_consecutive_tool_results() {
+        let client = test_client();
+        let assistant = Message {
+            role: "assistant".to_string(),
+            content: None,
+            tool_calls: Some(vec![
+                ToolCall {
+                    id: "toolu_a".to_string(),
+                    r#type: "function".to_string(),
+                    function: FunctionCall {
+                        name: "list_files".to_string(),
+                        arguments: "{}".to_string(),
+                    },
+                },
+                ToolCall {
+                    id: "toolu_b".to_string(),
+                    r#type: "function".to_string(),
+                    function: FunctionCall {
+                        name: "read_file".to_string(),
+                        arguments: r#"{"path": "b.rs"}"#.to_string(),
+                    },
+                },
+            ]),
+            tool_call_id: None,
+        };
+
+        let request = request_with(vec![
+            message("user", Some("Look around")),
+            assistant,
+            tool_result("toolu_a", "a.rs\nb.rs"),
+            tool_result("toolu_b", "// b"),
+            message("user", Some("Thanks")),
+        ]);
+        let converted = client.convert_request(request);
+
+        assert_eq!(converted.messages.len(), 3);
+        assert_eq!(converted.messages[1].content.len(), 2);
+        let user_turn = &converted.messages[2];
+        assert_eq!(user_turn.role, "user");
+        assert_eq!(
+            user_turn.content,
+            vec![
+                AnthropicBlock::ToolResult {
+                    tool_use_id: "toolu_a".to_string(),
+                    content: "a.rs\nb.rs".to_string(),
+                    is_error: None,
+                },
+                AnthropicBlock::ToolResult {
+                    tool_use_id: "toolu_b".to_string(),
+                    content: "// b".to_string(),
+                    is_error: None,
+                },
+                AnthropicBlock::Text {
+                    text: "Thanks".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_convert_request_unmatched_tool_ids() {
+        let client = test_client();
+        let assistant = Message {
+            role: "assistant".to_string(),
+            content: Some("Running it".to_string()),
+            tool_calls: Some(vec![ToolCall {
+                id: "toolu_x".to_string(),
+                r#type: "function".to_string(),
+                function: FunctionCall {
+                    name: "run_shell_command".to_string(),
+                    arguments: "not json".to_string(),
+                },
+            }]),
+            tool_call_id: None,
+        };
+
+        let request = request_with(vec![
+            message("user", Some("Run tests")),
+            tool_result("orphan", "stale output"),
+            assistant,
+            message("user", Some("Never mind")),
+        ]);
+        let converted = client.convert_request(request);
+
+        // The orphaned result becomes text merged into the first user turn
+        assert_eq!(
+            converted.messages[0].content[1],
+            AnthropicBlock::Text {
+                text: "Tool result: stale output".to_string()
+            }
+        );
+        // Unparseable arguments fall back to an empty input object
+        assert_eq!(
+            converted.messages[1].content[1],
+            AnthropicBlock::ToolUse {
+                id: "toolu_x".to_string(),
+                name: "run_shell_command".to_string(),
+                input: json!({}),
+            }
+        );
+        // The unanswered call gets an error result before the next user text
+        assert_eq!(
+            converted.messages[2].content[0],
+            AnthropicBlock::ToolResult {
+                tool_use_id: "toolu_x".to_string(),
+                content: "Tool call was not executed.".to_string(),
+                is_error: Some(true),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_second_turn_sends_tool_result() {
+        let mut server = Server::new_async().await;
+        let client = create_test_client(&server).await;
+
+        let mock_response = json!({
+            "id": "msg_2",
+            "type": "message",
+            "role": "assistant",
+            "content": [{"type": "text", "text": "The file is empty."}],
+            "model": "claude-3-opus-20240229",
+            "stop_reason": "end_turn"
+        });
+
+        let _mock = server
+            .mock("POST", "/messages")
+            .match_body(mockito::Matcher::PartialJson(json!({
+                "messages": [
+                    {"role": "user"},
+                    {"role": "assistant", "content": [
+                        {"type": "tool_use", "id": "toolu_9", "name": "read_file"}
+                    ]},
+                    {"role": "user", "content": [
+                        {"type": "tool_result", "tool_use_id": "toolu_9", "content": ""}
+                    ]}
+                ]
+            })))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(mock_response.to_string())
+            .create_async()
+            .await;
+
+        let assistant = Message {
+            role: "assistant".to_string(),
+            content: None,
+            tool_calls: Some(vec![ToolCall {
+                id: "toolu_9".to_string(),
+                r#type: "function".to_string(),
+                function: FunctionCall {
+                    name: "read_file".to_string(),
+                    arguments: r#"{"path": "empty.txt"}"#.to_string(),
+                },
+            }]),
+            tool_call_id: None,
+        };
+        let request = request_with(vec![
+            message("user", Some("What's in empty.txt?")),
+            assistant,
+            tool_result("toolu_9", ""),
+        ]);
+
+        let response = client.chat_completion(request).await.unwrap();
+        assert_eq!(
+            response.choices[0].message.content.as_deref(),
+            Some("The file is empty.")
+        );
+    }
 }
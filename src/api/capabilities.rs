@@ -0,0 +1,209 @@
+//! What each provider and model supports
+//!
+//! Models differ in context size, output limit and which request options they
+//! accept. The agent consults [`ModelCapabilities`] to shape its requests
+//! (dropping tools or JSON mode, capping `max_tokens`) and to run tool calls
+//! one at a time for models that make them in sequence. The clients use it to
+//! reject requests the model would fail on anyway.
+
+use super::{provider_info, ChatCompletionRequest};
+use crate::error::{GrokError, Result};
+
+/// Features and limits of a model
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ModelCapabilities {
+    /// Context window in tokens (prompt and output combined)
+    pub context_window: usize,
+    /// Largest accepted `max_tokens`
+    pub max_output_tokens: u32,
+    /// Function / tool calling
+    pub tools: bool,
+    /// `response_format: json_object`
+    pub json_mode: bool,
+    /// Several tool calls in a single response
+    pub parallel_tool_calls: bool,
+    /// Image input
+    pub vision: bool,
+}
+
+impl ModelCapabilities {
+    /// Check that a request only uses options the model supports
+    pub fn check_request(&self, request: &ChatCompletionRequest) -> Result<()> {
+        if request.tools.as_ref().is_some_and(|t| !t.is_empty()) && !self.tools {
+            return Err(GrokError::InvalidInput(format!(
+                "Model {} does not support tool calls",
+                request.model
+            )));
+        }
+        if request.response_format.is_some() && !self.json_mode {
+            return Err(GrokError::InvalidInput(format!(
+                "Model {} does not support JSON response format",
+                request.model
+            )));
+        }
+        if request.max_tokens > self.max_output_tokens {
+            return Err(GrokError::InvalidInput(format!(
+                "max_tokens {} exceeds the output limit of {} for model {}",
+                request.max_tokens, self.max_output_tokens, request.model
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Used when neither the model nor the provider is known
+pub const DEFAULT_CAPABILITIES: ModelCapabilities = ModelCapabilities {
+    context_window: 32_000,
+    max_output_tokens: 4096,
+    tools: true,
+    json_mode: false,
+    parallel_tool_calls: false,
+    vision: false,
+};
+
+const fn caps(
+    context_window: usize,
+    max_output_tokens: u32,
+    tools: bool,
+    json_mode: bool,
+    parallel_tool_calls: bool,
+    vision: bool,
+) -> ModelCapabilities {
+    ModelCapabilities {
+        context_window,
+        max_output_tokens,
+        tools,
+        json_mode,
+        parallel_tool_calls,
+        vision,
+    }
+}
+
+/// Known model families, matched by id prefix. More specific prefixes come first.
+static MODELS: &[(&str, ModelCapabilities)] = &[
+    // xAI
+    ("grok-4", caps(256_000, 32_768, true, true, true, true)),
+    ("grok-2-vision", caps(32_768, 8192, true, true, true, true)),
+    (
+        "grok-3-mini",
+        caps(131_072, 32_768, true, true, true, false),
+    ),
+    ("grok", caps(131_072, 16_384, true, true, true, false)),
+    // OpenAI
+    ("gpt-4.1", caps(1_047_576, 32_768, true, true, true, true)),
+    ("gpt-4o", caps(128_000, 16_384, true, true, true, true)),
+    ("gpt-4-turbo", caps(128_000, 4096, true, true, true, true)),
+    ("gpt-4", caps(8192, 8192, true, false, false, false)),
+    ("gpt-3.5-turbo", caps(16_385, 4096, true, true, true, false)),
+    ("o1-mini", caps(128_000, 65_536, false, false, false, false)),
+    ("o3-mini", caps(200_000, 100_000, true, true, false, false)),
+    ("o1", caps(200_000, 100_000, true, true, false, true)),
+    ("o3", caps(200_000, 100_000, true, true, true, true)),
+    ("o4", caps(200_000, 100_000, true, true, true, true)),
+    // Anthropic has no JSON mode; structured output goes through tools
+    (
+        "claude-opus-4",
+        caps(200_000, 32_000, true, false, true, true),
+    ),
+    (
+        "claude-sonnet-4",
+        caps(200_000, 64_000, true, false, true, true),
+    ),
+    ("claude-3-7", caps(200_000, 64_000, true, false, true, true)),
+    ("claude-3-5", caps(200_000, 8192, true, false, true, true)),
+    ("claude", caps(200_000, 4096, true, false, true, true)),
+];
+
+/// Provider-wide defaults for models missing from the table
+pub(crate) const XAI_DEFAULTS: ModelCapabilities = caps(131_072, 16_384, true, true, true, false);
+pub(crate) const OPENAI_DEFAULTS: ModelCapabilities = caps(128_000, 4096, true, true, true, false);
+pub(crate) const ANTHROPIC_DEFAULTS: ModelCapabilities =
+    caps(200_000, 4096, true, false, true, false);
+// Ollama and llama.cpp default to small contexts; tools fall back to prompting
+pub(crate) const LOCAL_DEFAULTS: ModelCapabilities = caps(8192, 4096, true, true, false, false);
+
+/// Capabilities of a model served by a provider
+pub fn model_capabilities(provider: &str, model: &str) -> ModelCapabilities {
+    let model = model.to_lowercase();
+    // Local servers often prefix ids with a namespace ("library/llama3")
+    let id = model.rsplit('/').next().unwrap_or(&model);
+
+    MODELS
+        .iter()
+        .find(|(prefix, _)| id.starts_with(prefix))
+        .map(|(_, caps)| *caps)
+        .or_else(|| provider_info(provider).ok().map(|p| p.capabilities))
+        .unwrap_or(DEFAULT_CAPABILITIES)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{Function, ResponseFormat, Tool};
+
+    fn request(model: &str) -> ChatCompletionRequest {
+        ChatCompletionRequest {
+            model: model.to_string(),
+            messages: vec![],
+            tools: None,
+            tool_choice: "none".to_string(),
+            temperature: 0.7,
+            max_tokens: 1000,
+            response_format: None,
+        }
+    }
+
+    #[test]
+    fn test_model_capabilities_lookup() {
+        assert_eq!(
+            model_capabilities("xai", "grok-4-0709").context_window,
+            256_000
+        );
+        assert_eq!(
+            model_capabilities("openai", "gpt-3.5-turbo").context_window,
+            16_385
+        );
+        assert!(!model_capabilities("openai", "gpt-4").json_mode);
+        assert!(model_capabilities("openai", "gpt-4o-mini").vision);
+        assert!(!model_capabilities("openai", "o1-mini").tools);
+        assert!(!model_capabilities("anthropic", "claude-3-opus-20240229").json_mode);
+
+        // Unknown models fall back to the provider, then to the global default
+        assert_eq!(model_capabilities("local", "llama3.1:8b"), LOCAL_DEFAULTS);
+        assert_eq!(
+            model_capabilities("local", "openai/gpt-4o").context_window,
+            128_000
+        );
+        assert_eq!(model_capabilities("nope", "mystery"), DEFAULT_CAPABILITIES);
+    }
+
+    #[test]
+    fn test_check_request_rejects_unsupported_options() {
+        let caps = model_capabilities("openai", "gpt-4");
+        assert!(caps.check_request(&request("gpt-4")).is_ok());
+
+        let mut json = request("gpt-4");
+        json.response_format = Some(ResponseFormat {
+            r#type: "json_object".to_string(),
+        });
+        assert!(caps.check_request(&json).is_err());
+
+        let mut too_long = request("gpt-4");
+        too_long.max_tokens = 10_000;
+        assert!(caps.check_request(&too_long).is_err());
+
+        let mut tools = request("o1-mini");
+        tools.tools = Some(vec![Tool {
+            r#type: "function".to_string(),
+            function: Function {
+                name: "read_file".to_string(),
+                description: "Read a file".to_string(),
+                parameters: serde_json::json!({"type": "object"}),
+            },
+        }]);
+        let err = model_capabilities("openai", "o1-mini")
+            .check_request(&tools)
+            .unwrap_err();
+        assert!(err.to_string().contains("does not support tool calls"));
+    }
+}
@@ -0,0 +1,566 @@
+//! Client for self-hosted OpenAI-compatible servers (Ollama, llama.cpp, vLLM)
+//!
+//! Requests use the OpenAI wire format and the API key is optional. Many
+//! local servers or models reject the `tools` parameter; when that happens the
+//! client switches to a prompt-based protocol: tool definitions are described
+//! in a system message and the model answers with `<tool_call>` blocks, which
+//! are parsed back into regular tool calls.
+
+use super::openai::OpenAiClient;
+use super::stream::StreamDelta;
+use super::{
+    ApiClient, ApiConfig, ChatCompletionRequest, ChatCompletionResponse, FunctionCall, Message,
+    ModelCapabilities, Tool, ToolCall,
+};
+use crate::error::{GrokError, Result};
+use async_trait::async_trait;
+use reqwest::{header::AUTHORIZATION, Client};
+use serde::Deserialize;
+use serde_json::Value as JsonValue;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+const TOOL_CALL_OPEN: &str = "<tool_call>";
+const TOOL_CALL_CLOSE: &str = "</tool_call>";
+
+/// Local OpenAI-compatible API client
+pub struct LocalClient {
+    inner: OpenAiClient,
+    client: Client,
+    /// Cleared once the server rejects the native `tools` parameter
+    native_tools: AtomicBool,
+}
+
+impl LocalClient {
+    /// Create a new local client
+    pub fn new(config: ApiConfig) -> Self {
+        Self {
+            inner: OpenAiClient::for_provider(config, "local"),
+            client: Client::new(),
+            native_tools: AtomicBool::new(true),
+        }
+    }
+
+    /// Whether tool calls are sent natively or through the prompt fallback
+    pub fn uses_native_tools(&self) -> bool {
+        self.native_tools.load(Ordering::SeqCst) && self.inner.capabilities().tools
+    }
+
+    fn disable_native_tools(&self, error: &GrokError) {
+        if self.native_tools.swap(false, Ordering::SeqCst) && std::env::var("DEBUG_API").is_ok() {
+            eprintln!("DEBUG: Server rejected tools ({error}); using prompt-based tool calls");
+        }
+    }
+
+    async fn prompted_completion(
+        &self,
+        request: ChatCompletionRequest,
+    ) -> Result<ChatCompletionResponse> {
+        let mut response = self
+            .inner
+            .chat_completion(to_prompted_request(request))
+            .await?;
+        for choice in &mut response.choices {
+            extract_tool_calls(&mut choice.message);
+        }
+        Ok(response)
+    }
+}
+
+/// Whether an API error means the server can't handle the `tools` parameter
+fn is_tools_unsupported(error: &GrokError) -> bool {
+    let GrokError::ApiError(message) = error else {
+        return false;
+    };
+    let rejected = ["400", "404", "422", "500"]
+        .iter()
+        .any(|code| message.starts_with(&format!("API error {code}")));
+    rejected && message.to_lowercase().contains("tool")
+}
+
+#[async_trait]
+impl ApiClient for LocalClient {
+    fn config(&self) -> &ApiConfig {
+        self.inner.config()
+    }
+
+    fn capabilities(&self) -> ModelCapabilities {
+        // Models without native tool calling still get tools through the prompt
+        ModelCapabilities {
+            tools: true,
+            ..self.inner.capabilities()
+        }
+    }
+
+    async fn chat_completion(
+        &self,
+        request: ChatCompletionRequest,
+    ) -> Result<ChatCompletionResponse> {
+        if request.tools.is_none() {
+            return self.inner.chat_completion(request).await;
+        }
+        if !self.uses_native_tools() {
+            return self.prompted_completion(request).await;
+        }
+
+        match self.inner.chat_completion(request.clone()).await {
+            Err(e) if is_tools_unsupported(&e) => {
+                self.disable_native_tools(&e);
+                self.prompted_completion(request).await
+            }
+            result => result,
+        }
+    }
+
+    async fn chat_completion_stream(
+        &self,
+        request: ChatCompletionRequest,
+        sender: mpsc::UnboundedSender<StreamDelta>,
+    ) -> Result<ChatCompletionResponse> {
+        if request.tools.is_none() || self.uses_native_tools() {
+            match self
+                .inner
+                .chat_completion_stream(request.clone(), sender.clone())
+                .await
+            {
+                Err(e) if request.tools.is_some() && is_tools_unsupported(&e) => {
+                    self.disable_native_tools(&e);
+                }
+                result => return result,
+            }
+        }
+
+        // Tool-call markup must not reach the user, so the fallback isn't streamed
+        let response = self.prompted_completion(request).await?;
+        if let Some(content) = response
+            .choices
+            .first()
+            .and_then(|c| c.message.content.clone())
+        {
+            let _ = sender.send(StreamDelta::Content(content));
+        }
+        Ok(response)
+    }
+
+    async fn list_models(&self) -> Result<Vec<String>> {
+        #[derive(Deserialize)]
+        struct ModelList {
+            data: Vec<ModelEntry>,
+        }
+        #[derive(Deserialize)]
+        struct ModelEntry {
+            id: String,
+        }
+
+        let config = self.config();
+        let url = format!("{}/models", config.base_url);
+        let mut builder = self
+            .client
+            .get(&url)
+            .timeout(Duration::from_secs(config.timeout_secs));
+        if !config.api_key.is_empty() {
+            builder = builder.header(AUTHORIZATION, format!("Bearer {}", config.api_key));
+        }
+
+        let response = builder.send().await?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let text = response.text().await.unwrap_or_default();
+            return Err(GrokError::ApiError(format!("API error {status}: {text}")));
+        }
+
+        let models: ModelList = response.json().await?;
+        Ok(models.data.into_iter().map(|m| m.id).collect())
+    }
+}
+
+/// Describe the tools in a system message and drop the native tool fields
+fn to_prompted_request(mut request: ChatCompletionRequest) -> ChatCompletionRequest {
+    let tools = request.tools.take().unwrap_or_default();
+    request.tool_choice = "none".to_string();
+
+    // Tool call ids are needed to label results with the tool name
+    let mut names: HashMap<String, String> = HashMap::new();
+    let mut messages = Vec::with_capacity(request.messages.len() + 1);
+    let mut prompt_inserted = false;
+
+    for message in request.messages {
+        match message.role.as_str() {
+            "system" if !prompt_inserted => {
+                let content = message.content.unwrap_or_default();
+                messages.push(Message {
+                    role: "system".to_string(),
+                    content: Some(format!("{content}\n\n{}", tool_prompt(&tools))),
+                    tool_calls: None,
+                    tool_call_id: None,
+                });
+                prompt_inserted = true;
+            }
+            "assistant" if message.tool_calls.is_some() => {
+                let mut content = message.content.unwrap_or_default();
+                for call in message.tool_calls.unwrap_or_default() {
+                    let arguments: JsonValue = serde_json::from_str(&call.function.arguments)
+                        .unwrap_or_else(|_| JsonValue::Object(Default::default()));
+                    let block = serde_json::json!({
+                        "name": call.function.name,
+                        "arguments": arguments,
+                    });
+                    content.push_str(&format!("\n{TOOL_CALL_OPEN}\n{block}\n{TOOL_CALL_CLOSE}"));
+                    names.insert(call.id, call.function.name);
+                }
+                messages.push(Message {
+                    role: "assistant".to_string(),
+                    content: Some(content.trim().to_string()),
+                    tool_calls: None,
+                    tool_call_id: None,
+                });
+            }
+            "tool" => {
+                let name = message
+                    .tool_call_id
+                    .as_ref()
+                    .and_then(|id| names.get(id))
+                    .map(String::as_str)
+                    .unwrap_or("tool");
+                messages.push(Message {
+                    role: "user".to_string(),
+                    content: Some(format!(
+                        "Tool result from {name}:\n{}",
+                        message.content.unwrap_or_default()
+                    )),
+                    tool_calls: None,
+                    tool_call_id: None,
+                });
+            }
+            _ => messages.push(message),
+        }
+    }
+
+    if !prompt_inserted {
+        messages.insert(
+            0,
+            Message {
+                role: "system".to_string(),
+                content: Some(tool_prompt(&tools)),
+                tool_calls: None,
+                tool_call_id: None,
+            },
+        );
+    }
+
+    request.messages = messages;
+    request
+}
+
+/// Instructions describing the prompt-based tool-call protocol
+fn tool_prompt(tools: &[Tool]) -> String {
+    let mut prompt = format!(
+        "You can call tools. To call a tool, reply with one block per call in exactly this format:\n\
+         {TOOL_CALL_OPEN}\n{{\"name\": \"tool_name\", \"arguments\": {{\"arg\": \"value\"}}}}\n{TOOL_CALL_CLOSE}\n\
+         Tool results are sent back in a message starting with \"Tool result from\". \
+         Only answer in plain text when no tool is needed.\n\nAvailable tools:\n"
+    );
+    for tool in tools {
+        prompt.push_str(&format!(
+            "- {}: {}\n  parameters: {}\n",
+            tool.function.name, tool.function.description, tool.function.parameters
+        ));
+    }
+    prompt
+}
+
+/// Move `<tool_call>` blocks from the message text into `tool_calls`
+fn extract_tool_calls(message: &mut Message) {
+    let Some(content) = message.content.take() else {
+        return;
+    };
+
+    let mut text = String::new();
+    let mut calls = Vec::new();
+    let mut rest = content.as_str();
+
+    while let Some(start) = rest.find(TOOL_CALL_OPEN) {
+        let after_open = &rest[start + TOOL_CALL_OPEN.len()..];
+        let Some(end) = after_open.find(TOOL_CALL_CLOSE) else {
+            break;
+        };
+        text.push_str(&rest[..start]);
+        match parse_tool_call(&after_open[..end], calls.len()) {
+            Some(call) => calls.push(call),
+            // Keep malformed blocks visible rather than silently dropping them
+            None => text.push_str(&rest[start..start + TOOL_CALL_OPEN.len() + end]),
+        }
+        rest = &after_open[end + TOOL_CALL_CLOSE.len()..];
+    }
+    text.push_str(rest);
+
+    // Some models answer with a bare JSON object instead of the tags
+    if calls.is_empty() {
+        if let Some(call) = parse_tool_call(text.trim(), 0) {
+            calls.push(call);
+            text.clear();
+        }
+    }
+
+    let text = text.trim();
+    message.content = if text.is_empty() {
+        None
+    } else {
+        Some(text.to_string())
+    };
+    if !calls.is_empty() {
+        message.tool_calls = Some(calls);
+    }
+}
+
+fn parse_tool_call(block: &str, index: usize) -> Option<ToolCall> {
+    let block = block
+        .trim()
+        .trim_start_matches("```json")
+        .trim_start_matches("```")
+        .trim_end_matches("```")
+        .trim();
+    let value: JsonValue = serde_json::from_str(block).ok()?;
+    let name = value.get("name")?.as_str()?.to_string();
+    let arguments = match value.get("arguments") {
+        Some(JsonValue::String(s)) => s.clone(),
+        Some(args) => args.to_string(),
+        None => "{}".to_string(),
+    };
+
+    Some(ToolCall {
+        id: format!("call_local_{index}"),
+        r#type: "function".to_string(),
+        function: FunctionCall { name, arguments },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::Function;
+    use mockito::{Matcher, Server, ServerGuard};
+    use serde_json::json;
+
+    fn create_test_client(server: &ServerGuard) -> LocalClient {
+        LocalClient::new(ApiConfig {
+            api_key: String::new(),
+            base_url: server.url(),
+            model: "llama3.1".to_string(),
+            timeout_secs: 60,
+            max_retries: 3,
+        })
+    }
+
+    fn message(role: &str, content: &str) -> Message {
+        Message {
+            role: role.to_string(),
+            content: Some(content.to_string()),
+            tool_calls: None,
+            tool_call_id: None,
+        }
+    }
+
+    fn tool_request() -> ChatCompletionRequest {
+        ChatCompletionRequest {
+            model: "llama3.1".to_string(),
+            messages: vec![
+                message("system", "You are helpful."),
+                message("user", "Read main.rs"),
+            ],
+            tools: Some(vec![Tool {
+                r#type: "function".to_string(),
+                function: Function {
+                    name: "read_file".to_string(),
+                    description: "Read a file".to_string(),
+                    parameters: json!({"type": "object", "properties": {"path": {"type": "string"}}}),
+                },
+            }]),
+            tool_choice: "auto".to_string(),
+            temperature: 0.7,
+            max_tokens: 100,
+            response_format: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_list_models_without_api_key() {
+        let mut server = Server::new_async().await;
+        let client = create_test_client(&server);
+
+        let _mock = server
+            .mock("GET", "/models")
+            .match_header("authorization", Matcher::Missing)
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                json!({
+                    "object": "list",
+                    "data": [
+                        {"id": "llama3.1:8b", "object": "model"},
+                        {"id": "qwen2.5-coder", "object": "model"}
+                    ]
+                })
+                .to_string(),
+            )
+            .create_async()
+            .await;
+
+        let models = client.list_models().await.unwrap();
+        assert_eq!(models, vec!["llama3.1:8b", "qwen2.5-coder"]);
+    }
+
+    #[tokio::test]
+    async fn test_native_tools_pass_through() {
+        let mut server = Server::new_async().await;
+        let client = create_test_client(&server);
+
+        let _mock = server
+            .mock("POST", "/chat/completions")
+            .match_header("authorization", Matcher::Missing)
+            .match_body(Matcher::PartialJson(json!({"tool_choice": "auto"})))
+            .with_status(200)
+            .with_body(
+                json!({
+                    "choices": [{"message": {
+                        "role": "assistant",
+                        "tool_calls": [{
+                            "id": "call_1",
+                            "type": "function",
+                            "function": {"name": "read_file", "arguments": "{\"path\":\"main.rs\"}"}
+                        }]
+                    }}]
+                })
+                .to_string(),
+            )
+            .create_async()
+            .await;
+
+        let response = client.chat_completion(tool_request()).await.unwrap();
+        let calls = response.choices[0].message.tool_calls.as_ref().unwrap();
+        assert_eq!(calls[0].id, "call_1");
+        assert!(client.uses_native_tools());
+    }
+
+    #[tokio::test]
+    async fn test_falls_back_to_prompted_tools() {
+        let mut server = Server::new_async().await;
+        let client = create_test_client(&server);
+
+        let rejected = server
+            .mock("POST", "/chat/completions")
+            .match_body(Matcher::PartialJson(json!({"tool_choice": "auto"})))
+            .with_status(400)
+            .with_body(r#"{"error":"registry.ollama.ai/library/llama2 does not support tools"}"#)
+            .expect(1)
+            .create_async()
+            .await;
+        let prompted = server
+            .mock("POST", "/chat/completions")
+            .match_body(Matcher::AllOf(vec![
+                Matcher::PartialJson(json!({"tool_choice": "none"})),
+                Matcher::Regex("read_file: Read a file".to_string()),
+            ]))
+            .with_status(200)
+            .with_body(
+                json!({
+                    "choices": [{"message": {
+                        "role": "assistant",
+                        "content": "Let me look.\n<tool_call>\n{\"name\": \"read_file\", \"arguments\": {\"path\": \"main.rs\"}}\n</tool_call>"
+                    }}]
+                })
+                .to_string(),
+            )
+            .expect(2)
+            .create_async()
+            .await;
+
+        let response = client.chat_completion(tool_request()).await.unwrap();
+        let message = &response.choices[0].message;
+        assert_eq!(message.content.as_deref(), Some("Let me look."));
+        let calls = message.tool_calls.as_ref().unwrap();
+        assert_eq!(calls[0].function.name, "read_file");
+        assert_eq!(calls[0].function.arguments, r#"{"path":"main.rs"}"#);
+        assert!(!client.uses_native_tools());
+
+        // Later requests go straight to the fallback
+        client.chat_completion(tool_request()).await.unwrap();
+        rejected.assert_async().await;
+        prompted.assert_async().await;
+    }
+
+    #[test]
+    fn test_prompted_request_rewrites_history() {
+        let mut request = tool_request();
+        request.messages.push(Message {
+            role: "assistant".to_string(),
+            content: None,
+            tool_calls: Some(vec![ToolCall {
+                id: "call_7".to_string(),
+                r#type: "function".to_string(),
+                function: FunctionCall {
+                    name: "read_file".to_string(),
+                    arguments: r#"{"path":"main.rs"}"#.to_string(),
+                },
+            }]),
+            tool_call_id: None,
+        });
+        request.messages.push(Message {
+            role: "tool".to_string(),
+            content: Some("fn main() {}".to_string()),
+            tool_calls: None,
+            tool_call_id: Some("call_7".to_string()),
+        });
+
+        let prompted = to_prompted_request(request);
+        assert!(prompted.tools.is_none());
+        assert_eq!(prompted.messages.len(), 4);
+        let system = prompted.messages[0].content.as_deref().unwrap();
+        assert!(system.starts_with("You are helpful."));
+        assert!(system.contains(TOOL_CALL_OPEN));
+        let assistant = prompted.messages[2].content.as_deref().unwrap();
+        assert!(assistant.contains(r#""name":"read_file""#));
+        assert!(prompted.messages[2].tool_calls.is_none());
+        assert_eq!(prompted.messages[3].role, "user");
+        assert_eq!(
+            prompted.messages[3].content.as_deref(),
+            Some("Tool result from read_file:\nfn main() {}")
+        );
+    }
+
+    #[test]
+    fn test_extract_tool_calls_variants() {
+        let mut bare = message(
+            "assistant",
+            r#"{"name": "list_files", "arguments": {"path": "."}}"#,
+        );
+        extract_tool_calls(&mut bare);
+        assert!(bare.content.is_none());
+        assert_eq!(bare.tool_calls.unwrap()[0].function.name, "list_files");
+
+        let mut plain = message("assistant", "No tools needed.");
+        extract_tool_calls(&mut plain);
+        assert_eq!(plain.content.as_deref(), Some("No tools needed."));
+        assert!(plain.tool_calls.is_none());
+
+        let mut malformed = message("assistant", "<tool_call>not json</tool_call>");
+        extract_tool_calls(&mut malformed);
+        assert!(malformed.tool_calls.is_none());
+        assert!(malformed.content.unwrap().contains("not json"));
+    }
+
+    #[test]
+    fn test_is_tools_unsupported() {
+        assert!(is_tools_unsupported(&GrokError::ApiError(
+            "API error 400 Bad Request: model does not support tools".to_string()
+        )));
+        assert!(!is_tools_unsupported(&GrokError::ApiError(
+            "API error 401 Unauthorized: bad tool key".to_string()
+        )));
+        assert!(!is_tools_unsupported(&GrokError::ApiError(
+            "API error 400 Bad Request: context too long".to_string()
+        )));
+    }
+}
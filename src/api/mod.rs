@@ -2,11 +2,18 @@ use crate::error::{GrokError, Result};
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
+use tokio::sync::mpsc;
 
 pub mod anthropic;
+pub mod capabilities;
+pub mod local;
 pub mod openai;
+pub mod stream;
 pub mod xai;
 
+pub use capabilities::{model_capabilities, ModelCapabilities};
+pub use stream::StreamDelta;
+
 /// Message in a conversation
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Message {
@@ -50,7 +57,7 @@ pub struct Function {
 }
 
 /// Request to chat completion API
-#[derive(Serialize)]
+#[derive(Serialize, Clone)]
 pub struct ChatCompletionRequest {
     pub model: String,
     pub messages: Vec<Message>,
@@ -64,7 +71,7 @@ pub struct ChatCompletionRequest {
 }
 
 /// Response format specification
-#[derive(Serialize)]
+#[derive(Serialize, Clone)]
 pub struct ResponseFormat {
     pub r#type: String,
 }
@@ -73,6 +80,25 @@ pub struct ResponseFormat {
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct ChatCompletionResponse {
     pub choices: Vec<Choice>,
+    /// Token usage, when the provider reports it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<Usage>,
+}
+
+/// Tokens consumed by a request, normalized across providers
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub struct Usage {
+    /// Prompt tokens, including any served from the provider's prompt cache
+    #[serde(default)]
+    pub prompt_tokens: u32,
+    #[serde(default)]
+    pub completion_tokens: u32,
+}
+
+impl Usage {
+    pub fn total_tokens(&self) -> u32 {
+        self.prompt_tokens + self.completion_tokens
+    }
 }
 
 /// Choice in API response
@@ -82,6 +108,7 @@ pub struct Choice {
 }
 
 /// Configuration for an API client
+#[derive(Clone)]
 pub struct ApiConfig {
     pub api_key: String,
     pub base_url: String,
@@ -96,24 +123,159 @@ pub trait ApiClient: Send + Sync {
     /// Get the configuration
     fn config(&self) -> &ApiConfig;
 
+    /// Features and limits of the configured model, looked up by its name
+    /// unless the client knows better
+    fn capabilities(&self) -> ModelCapabilities {
+        model_capabilities("", &self.config().model)
+    }
+
     /// Call the chat completion API
     async fn chat_completion(
         &self,
         request: ChatCompletionRequest,
     ) -> Result<ChatCompletionResponse>;
+
+    /// Call the chat completion API with streaming enabled.
+    ///
+    /// Deltas are forwarded through `sender` as they arrive and the fully
+    /// assembled response is returned once the stream ends. The default
+    /// implementation falls back to a single non-streaming request.
+    async fn chat_completion_stream(
+        &self,
+        request: ChatCompletionRequest,
+        sender: mpsc::UnboundedSender<StreamDelta>,
+    ) -> Result<ChatCompletionResponse> {
+        let response = self.chat_completion(request).await?;
+        if let Some(content) = response
+            .choices
+            .first()
+            .and_then(|c| c.message.content.clone())
+        {
+            let _ = sender.send(StreamDelta::Content(content));
+        }
+        Ok(response)
+    }
+
+    /// List the models available from the provider
+    async fn list_models(&self) -> Result<Vec<String>> {
+        Err(GrokError::Config(
+            "Model listing is not supported for this provider".to_string(),
+        ))
+    }
+}
+
+/// Serialize a request with the `stream` flag set
+pub(crate) fn streaming_body<T: Serialize>(request: &T) -> Result<JsonValue> {
+    let mut body = serde_json::to_value(request)?;
+    body["stream"] = JsonValue::Bool(true);
+    Ok(body)
+}
+
+/// Streaming body for OpenAI-compatible APIs, which only report usage in the
+/// final chunk when asked to
+pub(crate) fn openai_streaming_body(request: &ChatCompletionRequest) -> Result<JsonValue> {
+    let mut body = streaming_body(request)?;
+    body["stream_options"] = serde_json::json!({ "include_usage": true });
+    Ok(body)
+}
+
+/// Static description of a supported API provider
+pub struct ProviderInfo {
+    /// Name used on the command line, in config files and in the keystore
+    pub name: &'static str,
+    pub display_name: &'static str,
+    pub default_base_url: &'static str,
+    pub default_model: &'static str,
+    pub default_timeout_secs: u64,
+    /// Environment variable holding the API key
+    pub api_key_env: &'static str,
+    /// Where users can create an API key
+    pub key_url: &'static str,
+    /// Whether requests fail without a key (self-hosted servers usually don't need one)
+    pub requires_api_key: bool,
+    /// Capabilities assumed for models missing from the model table
+    pub capabilities: ModelCapabilities,
+    build: fn(ApiConfig) -> Box<dyn ApiClient>,
+}
+
+/// All supported providers. The first entry is the default.
+pub static PROVIDERS: &[ProviderInfo] = &[
+    ProviderInfo {
+        name: "xai",
+        display_name: "xAI",
+        default_base_url: "https://api.x.ai/v1",
+        default_model: "grok-4-0709",
+        // Grok responses can take several minutes
+        default_timeout_secs: 300,
+        api_key_env: "XAI_API_KEY",
+        key_url: "https://x.ai/api",
+        requires_api_key: true,
+        capabilities: capabilities::XAI_DEFAULTS,
+        build: |config| Box::new(xai::XaiClient::new(config)),
+    },
+    ProviderInfo {
+        name: "openai",
+        display_name: "OpenAI",
+        default_base_url: "https://api.openai.com/v1",
+        default_model: "gpt-3.5-turbo",
+        default_timeout_secs: 60,
+        api_key_env: "OPENAI_API_KEY",
+        key_url: "https://platform.openai.com/api-keys",
+        requires_api_key: true,
+        capabilities: capabilities::OPENAI_DEFAULTS,
+        build: |config| Box::new(openai::OpenAiClient::new(config)),
+    },
+    ProviderInfo {
+        name: "anthropic",
+        display_name: "Anthropic Claude",
+        default_base_url: "https://api.anthropic.com/v1",
+        default_model: "claude-3-opus-20240229",
+        default_timeout_secs: 60,
+        api_key_env: "ANTHROPIC_API_KEY",
+        key_url: "https://console.anthropic.com/",
+        requires_api_key: true,
+        capabilities: capabilities::ANTHROPIC_DEFAULTS,
+        build: |config| Box::new(anthropic::AnthropicClient::new(config)),
+    },
+    ProviderInfo {
+        name: "local",
+        display_name: "Local (OpenAI-compatible)",
+        // Ollama's default; llama.cpp and vLLM usually listen on :8080 / :8000
+        default_base_url: "http://localhost:11434/v1",
+        default_model: "llama3.1",
+        default_timeout_secs: 300,
+        api_key_env: "LOCAL_API_KEY",
+        key_url: "",
+        requires_api_key: false,
+        capabilities: capabilities::LOCAL_DEFAULTS,
+        build: |config| Box::new(local::LocalClient::new(config)),
+    },
+];
+
+/// Look up a provider by name
+pub fn provider_info(name: &str) -> Result<&'static ProviderInfo> {
+    PROVIDERS.iter().find(|p| p.name == name).ok_or_else(|| {
+        GrokError::Config(format!(
+            "Unknown API provider: {name}. Available providers: {}",
+            provider_names().join(", ")
+        ))
+    })
+}
+
+/// Names of all supported providers
+pub fn provider_names() -> Vec<&'static str> {
+    PROVIDERS.iter().map(|p| p.name).collect()
 }
 
 /// Create an API client based on the provider
-// TODO: Add support for additional providers (Cohere, Mistral, local models)
-// TODO: Implement provider-specific configuration validation
-// TODO: Add provider capability detection (max tokens, features supported)
+// TODO: Add support for additional providers (Cohere, Mistral)
 pub fn create_client(provider: &str, config: ApiConfig) -> Result<Box<dyn ApiClient>> {
-    match provider {
-        "xai" => Ok(Box::new(xai::XaiClient::new(config))),
-        "openai" => Ok(Box::new(openai::OpenAiClient::new(config))),
-        "anthropic" => Ok(Box::new(anthropic::AnthropicClient::new(config))),
-        _ => Err(GrokError::Config(format!(
-            "Unknown API provider: {provider}"
-        ))),
+    let info = provider_info(provider)?;
+    if !config.base_url.starts_with("http://") && !config.base_url.starts_with("https://") {
+        return Err(GrokError::Config(format!(
+            "Invalid base URL for {}: {}",
+            info.display_name, config.base_url
+        )));
     }
+    Ok((info.build)(config))
 }
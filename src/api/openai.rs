@@ -1,24 +1,46 @@
-use super::{ApiClient, ApiConfig, ChatCompletionRequest, ChatCompletionResponse};
+use super::stream::{read_openai_stream, StreamDelta};
+use super::{
+    model_capabilities, openai_streaming_body, ApiClient, ApiConfig, ChatCompletionRequest,
+    ChatCompletionResponse, ModelCapabilities,
+};
 use crate::error::{GrokError, Result};
 use async_trait::async_trait;
 use reqwest::{
     header::{AUTHORIZATION, CONTENT_TYPE},
-    Client,
+    Client, RequestBuilder,
 };
 use std::time::Duration;
+use tokio::sync::mpsc;
 
 /// OpenAI API client implementation
 pub struct OpenAiClient {
     config: ApiConfig,
     client: Client,
+    /// Provider whose capability defaults apply to unknown models
+    provider: &'static str,
 }
 
 impl OpenAiClient {
     /// Create a new OpenAI client
     pub fn new(config: ApiConfig) -> Self {
+        Self::for_provider(config, "openai")
+    }
+
+    /// Create a client for another server speaking the OpenAI wire format
+    pub(crate) fn for_provider(config: ApiConfig, provider: &'static str) -> Self {
         Self {
             config,
             client: Client::new(),
+            provider,
+        }
+    }
+
+    /// Add the bearer token, unless no key is configured (self-hosted servers)
+    fn authorize(&self, builder: RequestBuilder) -> RequestBuilder {
+        if self.config.api_key.is_empty() {
+            builder
+        } else {
+            builder.header(AUTHORIZATION, format!("Bearer {}", self.config.api_key))
         }
     }
 }
@@ -29,10 +51,15 @@ impl ApiClient for OpenAiClient {
         &self.config
     }
 
+    fn capabilities(&self) -> ModelCapabilities {
+        model_capabilities(self.provider, &self.config.model)
+    }
+
     async fn chat_completion(
         &self,
         request: ChatCompletionRequest,
     ) -> Result<ChatCompletionResponse> {
+        self.capabilities().check_request(&request)?;
         let url = format!("{}/chat/completions", self.config.base_url);
 
         // Debug logging for API requests
@@ -48,9 +75,7 @@ impl ApiClient for OpenAiClient {
         }
 
         let response = self
-            .client
-            .post(&url)
-            .header(AUTHORIZATION, format!("Bearer {}", self.config.api_key))
+            .authorize(self.client.post(&url))
             .header(CONTENT_TYPE, "application/json")
             .json(&request)
             .timeout(Duration::from_secs(self.config.timeout_secs))
@@ -90,6 +115,40 @@ impl ApiClient for OpenAiClient {
 
         Ok(json_response)
     }
+
+    async fn chat_completion_stream(
+        &self,
+        request: ChatCompletionRequest,
+        sender: mpsc::UnboundedSender<StreamDelta>,
+    ) -> Result<ChatCompletionResponse> {
+        self.capabilities().check_request(&request)?;
+        let url = format!("{}/chat/completions", self.config.base_url);
+
+        if std::env::var("DEBUG_API").is_ok() {
+            eprintln!("DEBUG: Sending streaming API request to {url}");
+            eprintln!("  Model: {}", request.model);
+            eprintln!("  Messages count: {}", request.messages.len());
+        }
+
+        let response = self
+            .authorize(self.client.post(&url))
+            .header(CONTENT_TYPE, "application/json")
+            .json(&openai_streaming_body(&request)?)
+            .timeout(Duration::from_secs(self.config.timeout_secs))
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let text = response.text().await.unwrap_or_default();
+            if status.as_u16() == 429 {
+                return Err(GrokError::rate_limited(text, Some(60)));
+            }
+            return Err(GrokError::ApiError(format!("API error {status}: {text}")));
+        }
+
+        read_openai_stream(response, sender).await
+    }
 }
 
 #[cfg(test)]
@@ -167,6 +226,9 @@ mod tests {
             response.choices[0].message.content.as_ref().unwrap(),
             "Hello from OpenAI!"
         );
+        let usage = response.usage.unwrap();
+        assert_eq!(usage.prompt_tokens, 10);
+        assert_eq!(usage.completion_tokens, 20);
     }
 
     #[tokio::test]
@@ -372,4 +434,94 @@ mod tests {
             "Response after delay"
         );
     }
+
+    #[tokio::test]
+    async fn test_chat_completion_stream_tool_call() {
+        let mut server = Server::new_async().await;
+        let client = create_test_client(&server).await;
+
+        let sse_body = concat!(
+            "data: {\"choices\":[{\"index\":0,\"delta\":{\"role\":\"assistant\",\"content\":null,\"tool_calls\":[{\"index\":0,\"id\":\"call_abc\",\"type\":\"function\",\"function\":{\"name\":\"get_weather\",\"arguments\":\"\"}}]}}]}\n\n",
+            "data: {\"choices\":[{\"index\":0,\"delta\":{\"tool_calls\":[{\"index\":0,\"function\":{\"arguments\":\"{\\\"location\\\": \"}}]}}]}\n\n",
+            "data: {\"choices\":[{\"index\":0,\"delta\":{\"tool_calls\":[{\"index\":0,\"function\":{\"arguments\":\"\\\"Paris\\\"}\"}}]}}]}\n\n",
+            "data: {\"choices\":[{\"index\":0,\"delta\":{},\"finish_reason\":\"tool_calls\"}]}\n\n",
+            "data: {\"choices\":[],\"usage\":{\"prompt_tokens\":42,\"completion_tokens\":7,\"total_tokens\":49}}\n\n",
+            "data: [DONE]\n\n",
+        );
+
+        let _mock = server
+            .mock("POST", "/chat/completions")
+            .match_body(mockito::Matcher::PartialJson(
+                json!({"stream": true, "stream_options": {"include_usage": true}}),
+            ))
+            .with_status(200)
+            .with_header("content-type", "text/event-stream")
+            .with_body(sse_body)
+            .create_async()
+            .await;
+
+        let request = ChatCompletionRequest {
+            model: "gpt-4".to_string(),
+            messages: vec![Message {
+                role: "user".to_string(),
+                content: Some("What's the weather in Paris?".to_string()),
+                tool_calls: None,
+                tool_call_id: None,
+            }],
+            tools: None,
+            tool_choice: "auto".to_string(),
+            temperature: 0.7,
+            max_tokens: 100,
+            response_format: None,
+        };
+
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let response = client.chat_completion_stream(request, tx).await.unwrap();
+
+        let message = &response.choices[0].message;
+        assert!(message.content.is_none());
+        let tool_calls = message.tool_calls.as_ref().unwrap();
+        assert_eq!(tool_calls.len(), 1);
+        assert_eq!(tool_calls[0].id, "call_abc");
+        assert_eq!(tool_calls[0].function.name, "get_weather");
+        assert_eq!(tool_calls[0].function.arguments, r#"{"location": "Paris"}"#);
+        assert_eq!(response.usage.unwrap().total_tokens(), 49);
+
+        let mut fragments = 0;
+        while let Ok(delta) = rx.try_recv() {
+            if matches!(delta, StreamDelta::Usage(_)) {
+                continue;
+            }
+            assert!(matches!(delta, StreamDelta::ToolCall { index: 0, .. }));
+            fragments += 1;
+        }
+        assert_eq!(fragments, 3);
+    }
+
+    #[tokio::test]
+    async fn test_chat_completion_stream_error_status() {
+        let mut server = Server::new_async().await;
+        let client = create_test_client(&server).await;
+
+        let _mock = server
+            .mock("POST", "/chat/completions")
+            .with_status(429)
+            .with_body("Rate limit exceeded")
+            .create_async()
+            .await;
+
+        let request = ChatCompletionRequest {
+            model: "gpt-4".to_string(),
+            messages: vec![],
+            tools: None,
+            tool_choice: "auto".to_string(),
+            temperature: 0.7,
+            max_tokens: 100,
+            response_format: None,
+        };
+
+        let (tx, _rx) = tokio::sync::mpsc::unbounded_channel();
+        let result = client.chat_completion_stream(request, tx).await;
+        assert!(matches!(result, Err(GrokError::RateLimitExceeded { .. })));
+    }
 }
@@ -0,0 +1,349 @@
+//! Server-sent event (SSE) handling for streaming chat completions
+//!
+//! All providers stream their responses as SSE. This module contains the
+//! line-level SSE parser, an accumulator that rebuilds a complete
+//! [`ChatCompletionResponse`] from incremental deltas, and the reader for the
+//! OpenAI-compatible chunk format shared by xAI and OpenAI.
+
+use super::{ChatCompletionResponse, Choice, FunctionCall, Message, ToolCall, Usage};
+use crate::error::{GrokError, Result};
+use serde::Deserialize;
+use tokio::sync::mpsc;
+
+/// Incremental output emitted while a completion is streaming
+#[derive(Debug, Clone, PartialEq)]
+pub enum StreamDelta {
+    /// A fragment of assistant text
+    Content(String),
+    /// A fragment of a tool call. `id` and `name` are only present on the first
+    /// fragment of a call; `arguments` is a partial JSON string.
+    ToolCall {
+        index: usize,
+        id: Option<String>,
+        name: Option<String>,
+        arguments: String,
+    },
+    /// Token usage so far; later values replace earlier ones
+    Usage(Usage),
+}
+
+/// A single server-sent event
+#[derive(Debug, Clone, PartialEq)]
+pub struct SseEvent {
+    pub event: Option<String>,
+    pub data: String,
+}
+
+/// Incremental SSE parser that tolerates events split across network chunks
+#[derive(Default)]
+pub struct SseParser {
+    buffer: Vec<u8>,
+    event: Option<String>,
+    data: Vec<String>,
+}
+
+impl SseParser {
+    /// Create a new parser
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feed raw bytes and return every event completed by them
+    pub fn feed(&mut self, bytes: &[u8]) -> Vec<SseEvent> {
+        self.buffer.extend_from_slice(bytes);
+        let mut events = Vec::new();
+
+        while let Some(pos) = self.buffer.iter().position(|&b| b == b'\n') {
+            let raw: Vec<u8> = self.buffer.drain(..=pos).collect();
+            let line = String::from_utf8_lossy(&raw);
+            let line = line.trim_end_matches(['\n', '\r']);
+
+            if line.is_empty() {
+                if let Some(event) = self.take_event() {
+                    events.push(event);
+                }
+            } else if let Some(value) = line.strip_prefix("data:") {
+                self.data
+                    .push(value.strip_prefix(' ').unwrap_or(value).to_string());
+            } else if let Some(value) = line.strip_prefix("event:") {
+                self.event = Some(value.trim().to_string());
+            }
+            // Comments (":") and other fields (id, retry) are ignored
+        }
+
+        events
+    }
+
+    /// Flush any event left without a terminating blank line
+    pub fn finish(&mut self) -> Option<SseEvent> {
+        if !self.buffer.is_empty() {
+            let mut rest = std::mem::take(&mut self.buffer);
+            rest.push(b'\n');
+            let mut events = self.feed(&rest);
+            if let Some(event) = events.pop() {
+                return Some(event);
+            }
+        }
+        self.take_event()
+    }
+
+    fn take_event(&mut self) -> Option<SseEvent> {
+        if self.data.is_empty() {
+            self.event = None;
+            return None;
+        }
+        Some(SseEvent {
+            event: self.event.take(),
+            data: std::mem::take(&mut self.data).join("\n"),
+        })
+    }
+}
+
+/// Tool call being assembled from streamed fragments
+#[derive(Default)]
+struct PartialToolCall {
+    id: String,
+    name: String,
+    arguments: String,
+}
+
+/// Rebuilds a complete response from streamed deltas
+#[derive(Default)]
+pub struct StreamAccumulator {
+    content: String,
+    tool_calls: Vec<PartialToolCall>,
+    usage: Option<Usage>,
+}
+
+impl StreamAccumulator {
+    /// Create an empty accumulator
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Apply a delta to the response being assembled
+    pub fn apply(&mut self, delta: &StreamDelta) {
+        match delta {
+            StreamDelta::Content(text) => self.content.push_str(text),
+            StreamDelta::ToolCall {
+                index,
+                id,
+                name,
+                arguments,
+            } => {
+                if self.tool_calls.len() <= *index {
+                    self.tool_calls
+                        .resize_with(*index + 1, PartialToolCall::default);
+                }
+                let call = &mut self.tool_calls[*index];
+                if let Some(id) = id {
+                    call.id.clone_from(id);
+                }
+                if let Some(name) = name {
+                    call.name.push_str(name);
+                }
+                call.arguments.push_str(arguments);
+            }
+            StreamDelta::Usage(usage) => self.usage = Some(*usage),
+        }
+    }
+
+    /// Finish the stream and build the assistant message
+    pub fn finish(self) -> ChatCompletionResponse {
+        let tool_calls: Vec<ToolCall> = self
+            .tool_calls
+            .into_iter()
+            .filter(|call| !call.name.is_empty())
+            .map(|call| ToolCall {
+                id: call.id,
+                r#type: "function".to_string(),
+                function: FunctionCall {
+                    name: call.name,
+                    arguments: if call.arguments.trim().is_empty() {
+                        "{}".to_string()
+                    } else {
+                        call.arguments
+                    },
+                },
+            })
+            .collect();
+
+        ChatCompletionResponse {
+            choices: vec![Choice {
+                message: Message {
+                    role: "assistant".to_string(),
+                    content: if self.content.is_empty() {
+                        None
+                    } else {
+                        Some(self.content)
+                    },
+                    tool_calls: if tool_calls.is_empty() {
+                        None
+                    } else {
+                        Some(tool_calls)
+                    },
+                    tool_call_id: None,
+                },
+            }],
+            usage: self.usage,
+        }
+    }
+}
+
+// OpenAI-compatible chunk format (used by xAI and OpenAI)
+#[derive(Deserialize)]
+struct ChunkResponse {
+    #[serde(default)]
+    choices: Vec<ChunkChoice>,
+    /// Only present in the final chunk when `stream_options.include_usage` is set
+    usage: Option<Usage>,
+}
+
+#[derive(Deserialize)]
+struct ChunkChoice {
+    #[serde(default)]
+    delta: ChunkDelta,
+}
+
+#[derive(Deserialize, Default)]
+struct ChunkDelta {
+    content: Option<String>,
+    tool_calls: Option<Vec<ChunkToolCall>>,
+}
+
+#[derive(Deserialize)]
+struct ChunkToolCall {
+    #[serde(default)]
+    index: usize,
+    id: Option<String>,
+    function: Option<ChunkFunction>,
+}
+
+#[derive(Deserialize)]
+struct ChunkFunction {
+    name: Option<String>,
+    arguments: Option<String>,
+}
+
+/// Convert one OpenAI-compatible `data:` payload into deltas
+pub fn parse_openai_chunk(data: &str) -> Result<Vec<StreamDelta>> {
+    let chunk: ChunkResponse = serde_json::from_str(data)
+        .map_err(|e| GrokError::JsonError(format!("Invalid stream chunk: {e}")))?;
+
+    let mut deltas = Vec::new();
+    for choice in chunk.choices {
+        if let Some(content) = choice.delta.content {
+            if !content.is_empty() {
+                deltas.push(StreamDelta::Content(content));
+            }
+        }
+        for call in choice.delta.tool_calls.unwrap_or_default() {
+            let (name, arguments) = match call.function {
+                Some(f) => (f.name, f.arguments.unwrap_or_default()),
+                None => (None, String::new()),
+            };
+            deltas.push(StreamDelta::ToolCall {
+                index: call.index,
+                id: call.id,
+                name,
+                arguments,
+            });
+        }
+    }
+    if let Some(usage) = chunk.usage {
+        deltas.push(StreamDelta::Usage(usage));
+    }
+    Ok(deltas)
+}
+
+/// Read an OpenAI-compatible SSE response to completion, forwarding deltas
+pub async fn read_openai_stream(
+    mut response: reqwest::Response,
+    sender: mpsc::UnboundedSender<StreamDelta>,
+) -> Result<ChatCompletionResponse> {
+    let mut parser = SseParser::new();
+    let mut accumulator = StreamAccumulator::new();
+
+    let mut handle = |event: SseEvent| -> Result<bool> {
+        if event.data.trim() == "[DONE]" {
+            return Ok(true);
+        }
+        for delta in parse_openai_chunk(&event.data)? {
+            accumulator.apply(&delta);
+            let _ = sender.send(delta);
+        }
+        Ok(false)
+    };
+
+    'read: while let Some(chunk) = response.chunk().await? {
+        for event in parser.feed(&chunk) {
+            if handle(event)? {
+                break 'read;
+            }
+        }
+    }
+    if let Some(event) = parser.finish() {
+        handle(event)?;
+    }
+
+    Ok(accumulator.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sse_parser_split_chunks() {
+        let mut parser = SseParser::new();
+        assert!(parser.feed(b"event: ping\nda").is_empty());
+        let events = parser.feed(b"ta: {\"a\":1}\r\n\r\ndata: second\n\n");
+
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].event.as_deref(), Some("ping"));
+        assert_eq!(events[0].data, "{\"a\":1}");
+        assert_eq!(events[1].event, None);
+        assert_eq!(events[1].data, "second");
+    }
+
+    #[test]
+    fn test_sse_parser_finish_without_blank_line() {
+        let mut parser = SseParser::new();
+        assert!(parser.feed(b": keep-alive\n\ndata: [DONE]").is_empty());
+        assert_eq!(parser.finish().unwrap().data, "[DONE]");
+    }
+
+    #[test]
+    fn test_accumulator_assembles_tool_call_fragments() {
+        let mut acc = StreamAccumulator::new();
+        let deltas = [
+            r#"{"choices":[{"delta":{"content":"Let me "}}]}"#,
+            r#"{"choices":[{"delta":{"content":"check."}}]}"#,
+            r#"{"choices":[{"delta":{"tool_calls":[{"index":0,"id":"call_1","type":"function","function":{"name":"read_file","arguments":""}}]}}]}"#,
+            r#"{"choices":[{"delta":{"tool_calls":[{"index":0,"function":{"arguments":"{\"pa"}}]}}]}"#,
+            r#"{"choices":[{"delta":{"tool_calls":[{"index":0,"function":{"arguments":"th\": \"a.rs\"}"}}]}}]}"#,
+            r#"{"choices":[{"delta":{"tool_calls":[{"index":1,"id":"call_2","function":{"name":"list_files"}}]}}]}"#,
+        ];
+        for data in deltas {
+            for delta in parse_openai_chunk(data).unwrap() {
+                acc.apply(&delta);
+            }
+        }
+
+        let message = acc.finish().choices.remove(0).message;
+        assert_eq!(message.content.as_deref(), Some("Let me check."));
+        let calls = message.tool_calls.unwrap();
+        assert_eq!(calls.len(), 2);
+        assert_eq!(calls[0].id, "call_1");
+        assert_eq!(calls[0].function.name, "read_file");
+        assert_eq!(calls[0].function.arguments, r#"{"path": "a.rs"}"#);
+        assert_eq!(calls[1].function.name, "list_files");
+        assert_eq!(calls[1].function.arguments, "{}");
+    }
+
+    #[test]
+    fn test_parse_openai_chunk_invalid() {
+        assert!(parse_openai_chunk("not json").is_err());
+        assert!(parse_openai_chunk(r#"{"choices":[]}"#).unwrap().is_empty());
+    }
+}
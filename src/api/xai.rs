@@ -1,4 +1,8 @@
-use super::{ApiClient, ApiConfig, ChatCompletionRequest, ChatCompletionResponse};
+use super::stream::{read_openai_stream, StreamDelta};
+use super::{
+    model_capabilities, openai_streaming_body, ApiClient, ApiConfig, ChatCompletionRequest,
+    ChatCompletionResponse, ModelCapabilities,
+};
 use crate::error::{GrokError, Result};
 use async_trait::async_trait;
 use reqwest::{
@@ -6,6 +10,7 @@ use reqwest::{
     Client,
 };
 use std::time::Duration;
+use tokio::sync::mpsc;
 
 /// xAI API client implementation
 pub struct XaiClient {
@@ -29,10 +34,15 @@ impl ApiClient for XaiClient {
         &self.config
     }
 
+    fn capabilities(&self) -> ModelCapabilities {
+        model_capabilities("xai", &self.config.model)
+    }
+
     async fn chat_completion(
         &self,
         request: ChatCompletionRequest,
     ) -> Result<ChatCompletionResponse> {
+        self.capabilities().check_request(&request)?;
         let url = format!("{}/chat/completions", self.config.base_url);
 
         // Debug logging for API requests
@@ -84,6 +94,42 @@ impl ApiClient for XaiClient {
 
         Ok(json_response)
     }
+
+    async fn chat_completion_stream(
+        &self,
+        request: ChatCompletionRequest,
+        sender: mpsc::UnboundedSender<StreamDelta>,
+    ) -> Result<ChatCompletionResponse> {
+        self.capabilities().check_request(&request)?;
+        let url = format!("{}/chat/completions", self.config.base_url);
+
+        if std::env::var("DEBUG_API").is_ok() {
+            eprintln!("DEBUG: Sending streaming API request to {url}");
+            eprintln!("  Model: {}", request.model);
+            eprintln!("  Messages count: {}", request.messages.len());
+        }
+
+        let response = self
+            .client
+            .post(&url)
+            .header(AUTHORIZATION, format!("Bearer {}", self.config.api_key))
+            .header(CONTENT_TYPE, "application/json")
+            .json(&openai_streaming_body(&request)?)
+            .timeout(Duration::from_secs(self.config.timeout_secs))
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let text = response.text().await.unwrap_or_default();
+            if status.as_u16() == 429 {
+                return Err(GrokError::rate_limited(text, Some(60)));
+            }
+            return Err(GrokError::ApiError(format!("API error {status}: {text}")));
+        }
+
+        read_openai_stream(response, sender).await
+    }
 }
 
 #[cfg(test)]
@@ -455,4 +501,58 @@ mod tests {
         // Clean up
         std::env::remove_var("DEBUG_API");
     }
+
+    #[tokio::test]
+    async fn test_chat_completion_stream() {
+        let mut server = Server::new_async().await;
+        let client = create_test_client(&server).await;
+
+        let sse_body = concat!(
+            "data: {\"choices\":[{\"index\":0,\"delta\":{\"role\":\"assistant\",\"content\":\"Hello\"}}]}\n\n",
+            "data: {\"choices\":[{\"index\":0,\"delta\":{\"content\":\", world\"}}]}\n\n",
+            "data: [DONE]\n\n",
+        );
+
+        let _mock = server
+            .mock("POST", "/chat/completions")
+            .match_header("authorization", "Bearer test_key")
+            .match_body(mockito::Matcher::PartialJson(json!({"stream": true})))
+            .with_status(200)
+            .with_header("content-type", "text/event-stream")
+            .with_body(sse_body)
+            .create_async()
+            .await;
+
+        let request = ChatCompletionRequest {
+            model: "grok-2-latest".to_string(),
+            messages: vec![Message {
+                role: "user".to_string(),
+                content: Some("Hello".to_string()),
+                tool_calls: None,
+                tool_call_id: None,
+            }],
+            tools: None,
+            tool_choice: "auto".to_string(),
+            temperature: 0.7,
+            max_tokens: 100,
+            response_format: None,
+        };
+
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let response = client.chat_completion_stream(request, tx).await.unwrap();
+
+        assert_eq!(
+            response.choices[0].message.content.as_deref(),
+            Some("Hello, world")
+        );
+        assert_eq!(
+            rx.recv().await,
+            Some(StreamDelta::Content("Hello".to_string()))
+        );
+        assert_eq!(
+            rx.recv().await,
+            Some(StreamDelta::Content(", world".to_string()))
+        );
+        assert_eq!(rx.recv().await, None);
+    }
 }
@@ -10,7 +10,14 @@ use std::path::{Path, PathBuf};
 use std::time::{SystemTime, UNIX_EPOCH};
 
 /// Default retention period in days if not specified
-const DEFAULT_RETENTION_DAYS: u64 = 7;
+pub const DEFAULT_RETENTION_DAYS: u64 = 7;
+
+/// Whether `path` looks like a backup made by [`BackupManager`]
+pub fn is_backup(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| name.ends_with(".bak"))
+}
 
 /// Backup manager for handling file backups with retention
 pub struct BackupManager {
@@ -20,15 +27,7 @@ pub struct BackupManager {
 
 impl BackupManager {
     /// Create a new backup manager with the specified retention period
-    pub fn new(retention_days: Option<u64>) -> Self {
-        let retention_days = retention_days
-            .or_else(|| {
-                std::env::var("GROK_BACKUP_RETENTION_DAYS")
-                    .ok()
-                    .and_then(|s| s.parse().ok())
-            })
-            .unwrap_or(DEFAULT_RETENTION_DAYS);
-
+    pub fn new(retention_days: u64) -> Self {
         Self { retention_days }
     }
 
@@ -55,6 +54,19 @@ impl BackupManager {
         Ok(backup_path)
     }
 
+    /// Restore a file from one of its backups
+    pub fn restore_backup(&self, backup_path: &Path, file_path: &Path) -> Result<()> {
+        if !backup_path.exists() {
+            return Err(GrokError::FileNotFound(format!(
+                "Backup not found: {}",
+                backup_path.display()
+            )));
+        }
+
+        fs::copy(backup_path, file_path).map_err(GrokError::Io)?;
+        Ok(())
+    }
+
     /// Generate a timestamped backup path for a file
     fn generate_backup_path(&self, file_path: &Path) -> PathBuf {
         let timestamp = Local::now().format("%Y%m%d_%H%M%S");
@@ -177,7 +189,7 @@ impl BackupManager {
         }
 
         // Sort by creation time (newest first)
-        backups.sort_by(|a, b| b.created.cmp(&a.created));
+        backups.sort_by_key(|b| std::cmp::Reverse(b.created));
 
         Ok(backups)
     }
@@ -193,7 +205,7 @@ pub struct BackupInfo {
 
 impl Default for BackupManager {
     fn default() -> Self {
-        Self::new(None)
+        Self::new(DEFAULT_RETENTION_DAYS)
     }
 }
 
@@ -209,7 +221,7 @@ mod tests {
         let test_file = temp_dir.path().join("test.txt");
         fs::write(&test_file, "test content").unwrap();
 
-        let manager = BackupManager::new(Some(7));
+        let manager = BackupManager::new(7);
         let backup_path = manager.create_backup(&test_file).unwrap();
 
         assert!(backup_path.exists());
@@ -222,7 +234,7 @@ mod tests {
 
     #[test]
     fn test_backup_pattern() {
-        let manager = BackupManager::new(Some(7));
+        let manager = BackupManager::new(7);
         let test_path = Path::new("/tmp/test.rs");
         let backup_path = manager.generate_backup_path(test_path);
 
@@ -231,5 +243,22 @@ mod tests {
         assert!(backup_name.ends_with(".bak"));
     }
 
+    #[test]
+    fn test_restore_backup() {
+        let temp_dir = TempDir::new().unwrap();
+        let test_file = temp_dir.path().join("test.txt");
+        fs::write(&test_file, "original").unwrap();
+
+        let manager = BackupManager::new(7);
+        let backup_path = manager.create_backup(&test_file).unwrap();
+        fs::write(&test_file, "changed").unwrap();
+
+        manager.restore_backup(&backup_path, &test_file).unwrap();
+        assert_eq!(fs::read_to_string(&test_file).unwrap(), "original");
+
+        let missing = temp_dir.path().join("missing.bak");
+        assert!(manager.restore_backup(&missing, &test_file).is_err());
+    }
+
     // TODO: Add tests for cleanup functionality
 }
@@ -0,0 +1,1431 @@
+//! Layered configuration
+//!
+//! Settings are merged from several layers, later layers overriding earlier
+//! ones:
+//!
+//! 1. Built-in defaults
+//! 2. User config: `~/.config/grok-code/config.toml`, then the project's
+//!    remembered approvals in `~/.config/grok-code/projects/<project>.toml`
+//! 3. Project config: `.grok-code.toml` in the project root. It comes with
+//!    the repository, so it cannot change where credentials are sent or
+//!    loosen permissions, the command policy or the sandbox.
+//! 4. Environment variables (`GROK_MODEL`, `API_TIMEOUT_SECS`, ...)
+//! 5. Command-line flags
+//!
+//! The resolved [`Config`] remembers where every value came from so
+//! `grok-code check` can explain the effective configuration.
+//!
+//! ## Example
+//!
+//! ```toml
+//! provider = "openai"
+//! model = "gpt-4o-mini"
+//! temperature = 0.2
+//!
+//! [limits]
+//! max_iterations = 25
+//!
+//! [cache]
+//! enabled = false
+//!
+//! [backup]
+//! retention_days = 30
+//!
+//! # USD per million tokens, matched by model id prefix
+//! [pricing."gpt-4o-mini"]
+//! input = 0.15
+//! output = 0.6
+//!
+//! # Shell command policy, see `tools::policy`
+//! [commands]
+//! default = "ask"
+//!
+//! [[commands.rules]]
+//! action = "allow"
+//! command = "cargo"
+//! args = ["{build,test,clippy}"]
+//!
+//! # How much the agent may do without asking, see `tools::permissions`
+//! [permissions]
+//! mode = "accept-edits"
+//!
+//! [[permissions.always_allow]]
+//! tool = "create_commit"
+//!
+//! # Run shell commands in a Linux sandbox, see `tools::sandbox`
+//! [sandbox]
+//! enabled = true
+//! network = false
+//! cpu_secs = 300
+//! memory_mb = 4096
+//! max_processes = 256
+//!
+//! # Code host for pull requests when the remote's host name doesn't say,
+//! # see `tools::forge`
+//! [forge]
+//! kind = "gitlab"
+//! api_url = "https://git.example.com/api/v4"
+//!
+//! # Credited with Co-authored-by trailers on every commit
+//! [git]
+//! co_authors = ["Ada Lovelace <ada@example.com>"]
+//!
+//! # Where create_jira_ticket files issues; the key stays in JIRA_API_KEY
+//! [jira]
+//! url = "https://example.atlassian.net"
+//! project = "APP"
+//! email = "ada@example.com"
+//! issue_type = "Bug"
+//! ```
+
+use crate::api::{provider_info, ApiConfig, PROVIDERS};
+use crate::error::{GrokError, Result};
+use crate::tools::forge::{ForgeKind, ForgeSettings};
+use crate::tools::jira::JiraSettings;
+use crate::tools::permissions::{PermissionMode, ToolGrant};
+use crate::tools::policy::{CommandPolicy, CommandRule, PolicyAction};
+use crate::tools::sandbox::Sandbox;
+use crate::tools::ToolSettings;
+use crate::usage::{price_for, ModelPrice};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Name of the project-level config file
+pub const PROJECT_CONFIG_FILE: &str = ".grok-code.toml";
+
+/// Environment variable for each config key
+const ENV_VARS: &[(&str, &str)] = &[
+    ("provider", "GROK_PROVIDER"),
+    ("model", "GROK_MODEL"),
+    ("base_url", "GROK_BASE_URL"),
+    ("temperature", "GROK_TEMPERATURE"),
+    ("max_tokens", "GROK_MAX_TOKENS"),
+    ("timeout_secs", "API_TIMEOUT_SECS"),
+    ("max_retries", "API_MAX_RETRIES"),
+    ("stream", "GROK_STREAM"),
+    ("context_tokens", "GROK_CONTEXT_TOKENS"),
+    ("limits.max_iterations", "GROK_MAX_ITERATIONS"),
+    ("limits.max_timeout_retries", "GROK_MAX_TIMEOUT_RETRIES"),
+    (
+        "limits.max_rate_limit_retries",
+        "GROK_MAX_RATE_LIMIT_RETRIES",
+    ),
+    ("cache.enabled", "GROK_CACHE"),
+    ("cache.max_entries", "GROK_CACHE_MAX_ENTRIES"),
+    ("cache.ttl_secs", "GROK_CACHE_TTL_SECS"),
+    ("backup.retention_days", "GROK_BACKUP_RETENTION_DAYS"),
+    ("plugins.enabled", "GROK_PLUGINS"),
+    ("sessions.enabled", "GROK_SESSIONS"),
+    ("commands.default", "GROK_COMMAND_POLICY"),
+    ("permissions.mode", "GROK_PERMISSION_MODE"),
+    ("sandbox.enabled", "GROK_SANDBOX"),
+    ("sandbox.network", "GROK_SANDBOX_NETWORK"),
+    ("sandbox.cpu_secs", "GROK_SANDBOX_CPU_SECS"),
+    ("sandbox.memory_mb", "GROK_SANDBOX_MEMORY_MB"),
+    ("sandbox.max_processes", "GROK_SANDBOX_MAX_PROCESSES"),
+    ("forge.kind", "GROK_FORGE"),
+    ("forge.api_url", "GROK_FORGE_API_URL"),
+    ("git.co_authors", "GROK_CO_AUTHORS"),
+    ("jira.url", "JIRA_URL"),
+    ("jira.project", "JIRA_PROJECT"),
+    ("jira.email", "JIRA_EMAIL"),
+    ("jira.issue_type", "JIRA_ISSUE_TYPE"),
+];
+
+fn env_var_for(key: &str) -> &'static str {
+    ENV_VARS
+        .iter()
+        .find(|(k, _)| *k == key)
+        .map(|(_, var)| *var)
+        .unwrap_or("")
+}
+
+/// Where a configuration value came from
+#[derive(Debug, Clone, PartialEq)]
+pub enum Source {
+    Default,
+    File(PathBuf),
+    Env(&'static str),
+    Cli,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::Default => write!(f, "default"),
+            Source::File(path) => write!(f, "{}", path.display()),
+            Source::Env(var) => write!(f, "env {var}"),
+            Source::Cli => write!(f, "command line"),
+        }
+    }
+}
+
+/// `[limits]` section
+#[derive(Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct LimitsSection {
+    pub max_iterations: Option<usize>,
+    pub max_timeout_retries: Option<u32>,
+    pub max_rate_limit_retries: Option<u32>,
+}
+
+/// `[cache]` section
+#[derive(Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct CacheSection {
+    pub enabled: Option<bool>,
+    pub max_entries: Option<usize>,
+    pub ttl_secs: Option<u64>,
+}
+
+/// `[backup]` section
+#[derive(Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct BackupSection {
+    pub retention_days: Option<u64>,
+}
+
+/// Sections that only have an `enabled` switch (`[plugins]`, `[sessions]`)
+#[derive(Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ToggleSection {
+    pub enabled: Option<bool>,
+}
+
+/// `[commands]` section: rules for shell commands run by tools and plugins
+#[derive(Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct CommandsSection {
+    /// What to do with commands no rule matches
+    pub default: Option<PolicyAction>,
+    /// Rules from later layers are checked before those from earlier ones
+    pub rules: Vec<CommandRule>,
+}
+
+/// `[permissions]` section: permission mode and tools that never ask
+#[derive(Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct PermissionsSection {
+    pub mode: Option<PermissionMode>,
+    /// Added to by "always in this project" answers; entries from all
+    /// layers apply
+    pub always_allow: Vec<ToolGrant>,
+}
+
+/// `[sandbox]` section: run shell commands in a Linux sandbox
+#[derive(Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct SandboxSection {
+    pub enabled: Option<bool>,
+    pub network: Option<bool>,
+    pub cpu_secs: Option<u64>,
+    pub memory_mb: Option<u64>,
+    pub max_processes: Option<u64>,
+}
+
+/// `[forge]` section: the code host pull requests go to
+#[derive(Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ForgeSection {
+    pub kind: Option<ForgeKind>,
+    pub api_url: Option<String>,
+}
+
+/// `[git]` section: commits made by `create_commit`
+#[derive(Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct GitSection {
+    /// `Name <email>` of each co-author
+    pub co_authors: Option<Vec<String>>,
+}
+
+/// `[jira]` section: where `create_jira_ticket` files issues
+#[derive(Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct JiraSection {
+    pub url: Option<String>,
+    pub project: Option<String>,
+    /// Account the API key belongs to (Jira Cloud)
+    pub email: Option<String>,
+    pub issue_type: Option<String>,
+}
+
+/// One configuration layer; unset values fall through to lower layers
+#[derive(Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct PartialConfig {
+    pub provider: Option<String>,
+    pub model: Option<String>,
+    pub base_url: Option<String>,
+    pub temperature: Option<f64>,
+    pub max_tokens: Option<u32>,
+    pub timeout_secs: Option<u64>,
+    pub max_retries: Option<u32>,
+    pub stream: Option<bool>,
+    pub context_tokens: Option<usize>,
+    pub limits: LimitsSection,
+    pub cache: CacheSection,
+    pub backup: BackupSection,
+    pub plugins: ToggleSection,
+    pub sessions: ToggleSection,
+    pub commands: CommandsSection,
+    pub permissions: PermissionsSection,
+    pub sandbox: SandboxSection,
+    pub forge: ForgeSection,
+    pub git: GitSection,
+    pub jira: JiraSection,
+    /// Model prices; entries from later layers replace same-named ones
+    pub pricing: HashMap<String, ModelPrice>,
+}
+
+impl PartialConfig {
+    /// Parse a TOML config file. A missing file is an empty layer.
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let content = match std::fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(GrokError::Io(e)),
+        };
+        toml::from_str(&content)
+            .map_err(|e| GrokError::Config(format!("Invalid config file {}: {e}", path.display())))
+    }
+
+    /// Drop what a repository must not decide for whoever runs the agent in
+    /// it: endpoints that receive credentials, and anything that would make
+    /// permissions, the command policy or the sandbox looser than the
+    /// `trusted` layers below. Only deny rules are kept, since project rules
+    /// are matched before the user's and the built-in ones. Returns the keys
+    /// that were dropped.
+    fn restrict_untrusted(&mut self, trusted: &PartialConfig) -> Vec<&'static str> {
+        let mut dropped = Vec::new();
+        if self.base_url.take().is_some() {
+            dropped.push("base_url");
+        }
+        if self.forge.api_url.take().is_some() {
+            dropped.push("forge.api_url");
+        }
+        if self.jira.url.take().is_some() {
+            dropped.push("jira.url");
+        }
+        if self.permissions.mode.take().is_some() {
+            dropped.push("permissions.mode");
+        }
+        if !self.permissions.always_allow.is_empty() {
+            self.permissions.always_allow.clear();
+            dropped.push("permissions.always_allow");
+        }
+
+        let default = trusted.commands.default.unwrap_or(PolicyAction::Ask);
+        if self.commands.default.is_some_and(|action| action < default) {
+            self.commands.default = None;
+            dropped.push("commands.default");
+        }
+        let rules = self.commands.rules.len();
+        self.commands
+            .rules
+            .retain(|rule| rule.action == PolicyAction::Deny);
+        if self.commands.rules.len() < rules {
+            dropped.push("commands.rules (allow, ask)");
+        }
+
+        if self.sandbox.enabled == Some(false) {
+            self.sandbox.enabled = None;
+            dropped.push("sandbox.enabled");
+        }
+        if self.sandbox.network == Some(true) {
+            self.sandbox.network = None;
+            dropped.push("sandbox.network");
+        }
+        let defaults = Sandbox::default();
+        for (key, value, limit) in [
+            (
+                "sandbox.cpu_secs",
+                &mut self.sandbox.cpu_secs,
+                trusted.sandbox.cpu_secs.unwrap_or(defaults.cpu_secs),
+            ),
+            (
+                "sandbox.memory_mb",
+                &mut self.sandbox.memory_mb,
+                trusted.sandbox.memory_mb.unwrap_or(defaults.memory_mb),
+            ),
+            (
+                "sandbox.max_processes",
+                &mut self.sandbox.max_processes,
+                trusted
+                    .sandbox
+                    .max_processes
+                    .unwrap_or(defaults.max_processes),
+            ),
+        ] {
+            if value.is_some_and(|v| v > limit) {
+                *value = None;
+                dropped.push(key);
+            }
+        }
+        dropped
+    }
+
+    /// Read the layer from environment variables
+    pub fn from_env() -> Result<Self> {
+        Self::from_env_with(|name| std::env::var(name).ok())
+    }
+
+    /// Read the layer using a custom variable lookup
+    pub fn from_env_with(lookup: impl Fn(&str) -> Option<String>) -> Result<Self> {
+        let get = |key: &str| lookup(env_var_for(key)).filter(|v| !v.trim().is_empty());
+
+        fn parse<T: std::str::FromStr>(key: &str, value: Option<String>) -> Result<Option<T>> {
+            value
+                .map(|v| {
+                    v.trim().parse().map_err(|_| {
+                        GrokError::Config(format!("Invalid value for {}: '{v}'", env_var_for(key)))
+                    })
+                })
+                .transpose()
+        }
+        // Anything other than "true" disables a switch, as before
+        let flag = |key: &str| get(key).map(|v| v.trim() == "true");
+
+        Ok(Self {
+            provider: get("provider"),
+            model: get("model"),
+            base_url: get("base_url"),
+            temperature: parse("temperature", get("temperature"))?,
+            max_tokens: parse("max_tokens", get("max_tokens"))?,
+            timeout_secs: parse("timeout_secs", get("timeout_secs"))?,
+            max_retries: parse("max_retries", get("max_retries"))?,
+            stream: flag("stream"),
+            context_tokens: parse("context_tokens", get("context_tokens"))?,
+            limits: LimitsSection {
+                max_iterations: parse("limits.max_iterations", get("limits.max_iterations"))?,
+                max_timeout_retries: parse(
+                    "limits.max_timeout_retries",
+                    get("limits.max_timeout_retries"),
+                )?,
+                max_rate_limit_retries: parse(
+                    "limits.max_rate_limit_retries",
+                    get("limits.max_rate_limit_retries"),
+                )?,
+            },
+            cache: CacheSection {
+                enabled: flag("cache.enabled"),
+                max_entries: parse("cache.max_entries", get("cache.max_entries"))?,
+                ttl_secs: parse("cache.ttl_secs", get("cache.ttl_secs"))?,
+            },
+            backup: BackupSection {
+                retention_days: parse("backup.retention_days", get("backup.retention_days"))?,
+            },
+            plugins: ToggleSection {
+                enabled: flag("plugins.enabled"),
+            },
+            sessions: ToggleSection {
+                enabled: flag("sessions.enabled"),
+            },
+            commands: CommandsSection {
+                default: get("commands.default")
+                    .map(|v| {
+                        v.parse().map_err(|e| {
+                            GrokError::Config(format!("Invalid value for GROK_COMMAND_POLICY: {e}"))
+                        })
+                    })
+                    .transpose()?,
+                rules: Vec::new(),
+            },
+            permissions: PermissionsSection {
+                mode: get("permissions.mode")
+                    .map(|v| {
+                        v.parse().map_err(|e| {
+                            GrokError::Config(format!(
+                                "Invalid value for GROK_PERMISSION_MODE: {e}"
+                            ))
+                        })
+                    })
+                    .transpose()?,
+                always_allow: Vec::new(),
+            },
+            sandbox: SandboxSection {
+                enabled: flag("sandbox.enabled"),
+                network: flag("sandbox.network"),
+                cpu_secs: parse("sandbox.cpu_secs", get("sandbox.cpu_secs"))?,
+                memory_mb: parse("sandbox.memory_mb", get("sandbox.memory_mb"))?,
+                max_processes: parse("sandbox.max_processes", get("sandbox.max_processes"))?,
+            },
+            forge: ForgeSection {
+                kind: get("forge.kind")
+                    .map(|v| {
+                        v.parse().map_err(|e| {
+                            GrokError::Config(format!("Invalid value for GROK_FORGE: {e}"))
+                        })
+                    })
+                    .transpose()?,
+                api_url: get("forge.api_url"),
+            },
+            git: GitSection {
+                co_authors: get("git.co_authors").map(|v| split_co_authors(&v)),
+            },
+            jira: JiraSection {
+                url: get("jira.url"),
+                project: get("jira.project"),
+                email: get("jira.email"),
+                issue_type: get("jira.issue_type"),
+            },
+            pricing: HashMap::new(),
+        })
+    }
+}
+
+/// Merges layers while recording the source of each value
+#[derive(Default)]
+struct Merger {
+    merged: PartialConfig,
+    sources: HashMap<&'static str, Source>,
+}
+
+impl Merger {
+    fn layer(&mut self, layer: PartialConfig, source: impl Fn(&'static str) -> Source) {
+        fn set<T>(
+            slot: &mut Option<T>,
+            value: Option<T>,
+            key: &'static str,
+            sources: &mut HashMap<&'static str, Source>,
+            source: &impl Fn(&'static str) -> Source,
+        ) {
+            if let Some(value) = value {
+                *slot = Some(value);
+                sources.insert(key, source(key));
+            }
+        }
+
+        let m = &mut self.merged;
+        let s = &mut self.sources;
+        // A layer picking a different provider drops the model and URL the
+        // layers below configured for the old one, so its key isn't sent there
+        if let Some(provider) = &layer.provider {
+            if provider != m.provider.as_deref().unwrap_or(PROVIDERS[0].name) {
+                m.model = None;
+                m.base_url = None;
+                s.remove("model");
+                s.remove("base_url");
+            }
+        }
+        set(&mut m.provider, layer.provider, "provider", s, &source);
+        set(&mut m.model, layer.model, "model", s, &source);
+        set(&mut m.base_url, layer.base_url, "base_url", s, &source);
+        set(
+            &mut m.temperature,
+            layer.temperature,
+            "temperature",
+            s,
+            &source,
+        );
+        set(
+            &mut m.max_tokens,
+            layer.max_tokens,
+            "max_tokens",
+            s,
+            &source,
+        );
+        set(
+            &mut m.timeout_secs,
+            layer.timeout_secs,
+            "timeout_secs",
+            s,
+            &source,
+        );
+        set(
+            &mut m.max_retries,
+            layer.max_retries,
+            "max_retries",
+            s,
+            &source,
+        );
+        set(&mut m.stream, layer.stream, "stream", s, &source);
+        set(
+            &mut m.context_tokens,
+            layer.context_tokens,
+            "context_tokens",
+            s,
+            &source,
+        );
+        set(
+            &mut m.limits.max_iterations,
+            layer.limits.max_iterations,
+            "limits.max_iterations",
+            s,
+            &source,
+        );
+        set(
+            &mut m.limits.max_timeout_retries,
+            layer.limits.max_timeout_retries,
+            "limits.max_timeout_retries",
+            s,
+            &source,
+        );
+        set(
+            &mut m.limits.max_rate_limit_retries,
+            layer.limits.max_rate_limit_retries,
+            "limits.max_rate_limit_retries",
+            s,
+            &source,
+        );
+        set(
+            &mut m.cache.enabled,
+            layer.cache.enabled,
+            "cache.enabled",
+            s,
+            &source,
+        );
+        set(
+            &mut m.cache.max_entries,
+            layer.cache.max_entries,
+            "cache.max_entries",
+            s,
+            &source,
+        );
+        set(
+            &mut m.cache.ttl_secs,
+            layer.cache.ttl_secs,
+            "cache.ttl_secs",
+            s,
+            &source,
+        );
+        set(
+            &mut m.backup.retention_days,
+            layer.backup.retention_days,
+            "backup.retention_days",
+            s,
+            &source,
+        );
+        set(
+            &mut m.plugins.enabled,
+            layer.plugins.enabled,
+            "plugins.enabled",
+            s,
+            &source,
+        );
+        set(
+            &mut m.sessions.enabled,
+            layer.sessions.enabled,
+            "sessions.enabled",
+            s,
+            &source,
+        );
+        set(
+            &mut m.commands.default,
+            layer.commands.default,
+            "commands.default",
+            s,
+            &source,
+        );
+        set(
+            &mut m.permissions.mode,
+            layer.permissions.mode,
+            "permissions.mode",
+            s,
+            &source,
+        );
+        if !layer.permissions.always_allow.is_empty() {
+            s.insert(
+                "permissions.always_allow",
+                source("permissions.always_allow"),
+            );
+            m.permissions
+                .always_allow
+                .extend(layer.permissions.always_allow);
+        }
+        set(
+            &mut m.sandbox.enabled,
+            layer.sandbox.enabled,
+            "sandbox.enabled",
+            s,
+            &source,
+        );
+        set(
+            &mut m.sandbox.network,
+            layer.sandbox.network,
+            "sandbox.network",
+            s,
+            &source,
+        );
+        set(
+            &mut m.sandbox.cpu_secs,
+            layer.sandbox.cpu_secs,
+            "sandbox.cpu_secs",
+            s,
+            &source,
+        );
+        set(
+            &mut m.sandbox.memory_mb,
+            layer.sandbox.memory_mb,
+            "sandbox.memory_mb",
+            s,
+            &source,
+        );
+        set(
+            &mut m.sandbox.max_processes,
+            layer.sandbox.max_processes,
+            "sandbox.max_processes",
+            s,
+            &source,
+        );
+        set(
+            &mut m.forge.kind,
+            layer.forge.kind,
+            "forge.kind",
+            s,
+            &source,
+        );
+        set(
+            &mut m.forge.api_url,
+            layer.forge.api_url,
+            "forge.api_url",
+            s,
+            &source,
+        );
+        set(
+            &mut m.git.co_authors,
+            layer.git.co_authors,
+            "git.co_authors",
+            s,
+            &source,
+        );
+        set(&mut m.jira.url, layer.jira.url, "jira.url", s, &source);
+        set(
+            &mut m.jira.project,
+            layer.jira.project,
+            "jira.project",
+            s,
+            &source,
+        );
+        set(
+            &mut m.jira.email,
+            layer.jira.email,
+            "jira.email",
+            s,
+            &source,
+        );
+        set(
+            &mut m.jira.issue_type,
+            layer.jira.issue_type,
+            "jira.issue_type",
+            s,
+            &source,
+        );
+        if !layer.commands.rules.is_empty() {
+            s.insert("commands.rules", source("commands.rules"));
+            m.commands.rules.splice(0..0, layer.commands.rules);
+        }
+        m.pricing.extend(layer.pricing);
+    }
+}
+
+/// Fully resolved configuration
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub provider: String,
+    pub model: String,
+    pub base_url: String,
+    pub temperature: f64,
+    pub max_tokens: u32,
+    pub timeout_secs: u64,
+    pub max_retries: u32,
+    pub stream: bool,
+    pub context_tokens: Option<usize>,
+    pub max_iterations: usize,
+    pub max_timeout_retries: u32,
+    pub max_rate_limit_retries: u32,
+    pub cache_enabled: bool,
+    pub cache_max_entries: usize,
+    pub cache_ttl_secs: u64,
+    pub backup_retention_days: u64,
+    pub plugins_enabled: bool,
+    pub sessions_enabled: bool,
+    pub pricing: HashMap<String, ModelPrice>,
+    pub command_default: PolicyAction,
+    /// Configured command rules, most specific layer first
+    pub command_rules: Vec<CommandRule>,
+    pub permission_mode: PermissionMode,
+    /// Tools that run without asking
+    pub always_allow_tools: Vec<String>,
+    pub sandbox_enabled: bool,
+    /// Sandbox settings, used when `sandbox_enabled` is set
+    pub sandbox_settings: Sandbox,
+    /// Code host, when the remote's host name doesn't tell
+    pub forge_kind: Option<ForgeKind>,
+    /// API root of the code host, when it isn't in the usual place
+    pub forge_api_url: Option<String>,
+    /// `Name <email>` of each commit co-author
+    pub co_authors: Vec<String>,
+    /// Jira site, project and account for `create_jira_ticket`
+    pub jira: JiraSettings,
+    /// Problems that didn't stop the configuration from loading
+    pub warnings: Vec<String>,
+    sources: HashMap<&'static str, Source>,
+}
+
+impl Config {
+    /// Path of the user-level config file
+    pub fn user_config_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("grok-code").join("config.toml"))
+    }
+
+    /// User-owned file where "always in this project" answers for
+    /// `project_root` are saved, outside the repository so they can't be
+    /// committed or planted by it
+    pub fn approvals_path(project_root: &Path) -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| {
+            dir.join("grok-code").join("projects").join(format!(
+                "{}.toml",
+                crate::session::SessionStore::project_key(project_root)
+            ))
+        })
+    }
+
+    /// Load the configuration for a project, applying `cli` on top
+    pub fn load(project_root: &Path, cli: PartialConfig) -> Result<Self> {
+        let user_files: Vec<PathBuf> =
+            [Self::user_config_path(), Self::approvals_path(project_root)]
+                .into_iter()
+                .flatten()
+                .collect();
+        let user_files: Vec<&Path> = user_files.iter().map(PathBuf::as_path).collect();
+        let project = project_root.join(PROJECT_CONFIG_FILE);
+        Self::load_layers(&user_files, &project, PartialConfig::from_env()?, cli)
+    }
+
+    /// Merge explicit layers (used by [`Config::load`] and tests). The
+    /// `user_files` are trusted; the `project_file` is not.
+    pub fn load_layers(
+        user_files: &[&Path],
+        project_file: &Path,
+        env: PartialConfig,
+        cli: PartialConfig,
+    ) -> Result<Self> {
+        let mut merger = Merger::default();
+        for path in user_files {
+            merger.layer(PartialConfig::from_file(path)?, |_| {
+                Source::File(path.to_path_buf())
+            });
+        }
+        let mut project = PartialConfig::from_file(project_file)?;
+        let dropped = project.restrict_untrusted(&merger.merged);
+        merger.layer(project, |_| Source::File(project_file.to_path_buf()));
+        merger.layer(env, |key| Source::Env(env_var_for(key)));
+        merger.layer(cli, |_| Source::Cli);
+
+        let mut config = Self::resolve(merger)?;
+        if !dropped.is_empty() {
+            config.warnings.push(format!(
+                "Ignored {} in {}: a project can't change where credentials are sent or loosen permissions; set them in the user config, the environment or on the command line",
+                dropped.join(", "),
+                project_file.display()
+            ));
+        }
+        Ok(config)
+    }
+
+    fn resolve(merger: Merger) -> Result<Self> {
+        let Merger { merged: m, sources } = merger;
+
+        let provider = m.provider.unwrap_or_else(|| PROVIDERS[0].name.to_string());
+        let info = provider_info(&provider)?;
+
+        let temperature = m.temperature.unwrap_or(0.7);
+        if !(0.0..=2.0).contains(&temperature) {
+            return Err(GrokError::Config(format!(
+                "temperature must be between 0.0 and 2.0, got {temperature}"
+            )));
+        }
+
+        let co_authors = m.git.co_authors.unwrap_or_default();
+        if let Some(author) = co_authors.iter().find(|a| !is_co_author(a)) {
+            return Err(GrokError::Config(format!(
+                "co-author must look like 'Name <email>', got '{author}'"
+            )));
+        }
+
+        Ok(Self {
+            model: m.model.unwrap_or_else(|| info.default_model.to_string()),
+            base_url: m
+                .base_url
+                .unwrap_or_else(|| info.default_base_url.to_string()),
+            timeout_secs: m.timeout_secs.unwrap_or(info.default_timeout_secs),
+            provider,
+            temperature,
+            max_tokens: m.max_tokens.unwrap_or(4096),
+            max_retries: m.max_retries.unwrap_or(3),
+            stream: m.stream.unwrap_or(true),
+            context_tokens: m.context_tokens,
+            max_iterations: m.limits.max_iterations.unwrap_or(15),
+            max_timeout_retries: m.limits.max_timeout_retries.unwrap_or(3),
+            max_rate_limit_retries: m.limits.max_rate_limit_retries.unwrap_or(5),
+            cache_enabled: m.cache.enabled.unwrap_or(true),
+            cache_max_entries: m.cache.max_entries.unwrap_or(100),
+            cache_ttl_secs: m.cache.ttl_secs.unwrap_or(300),
+            backup_retention_days: m
+                .backup
+                .retention_days
+                .unwrap_or(crate::backup::DEFAULT_RETENTION_DAYS),
+            plugins_enabled: m.plugins.enabled.unwrap_or(true),
+            sessions_enabled: m.sessions.enabled.unwrap_or(true),
+            pricing: m.pricing,
+            command_default: m.commands.default.unwrap_or(PolicyAction::Ask),
+            command_rules: m.commands.rules,
+            permission_mode: m.permissions.mode.unwrap_or_default(),
+            always_allow_tools: m
+                .permissions
+                .always_allow
+                .into_iter()
+                .map(|grant| grant.tool)
+                .collect(),
+            sandbox_enabled: m.sandbox.enabled.unwrap_or(false),
+            sandbox_settings: {
+                let defaults = Sandbox::default();
+                Sandbox {
+                    network: m.sandbox.network.unwrap_or(defaults.network),
+                    cpu_secs: m.sandbox.cpu_secs.unwrap_or(defaults.cpu_secs),
+                    memory_mb: m.sandbox.memory_mb.unwrap_or(defaults.memory_mb),
+                    max_processes: m.sandbox.max_processes.unwrap_or(defaults.max_processes),
+                }
+            },
+            forge_kind: m.forge.kind,
+            forge_api_url: m.forge.api_url,
+            co_authors,
+            jira: JiraSettings {
+                url: m.jira.url,
+                project: m.jira.project,
+                email: m.jira.email,
+                issue_type: m.jira.issue_type,
+                ..Default::default()
+            },
+            warnings: Vec::new(),
+            sources,
+        })
+    }
+
+    /// Configured price for a model, if any
+    pub fn price(&self, model: &str) -> Option<ModelPrice> {
+        price_for(&self.pricing, model).copied()
+    }
+
+    /// Where the value for `key` came from
+    pub fn source(&self, key: &str) -> Source {
+        self.sources.get(key).cloned().unwrap_or(Source::Default)
+    }
+
+    /// Every setting as `(key, value, source)`, in display order
+    pub fn entries(&self) -> Vec<(&'static str, String, Source)> {
+        let values = [
+            ("provider", self.provider.clone()),
+            ("model", self.model.clone()),
+            ("base_url", self.base_url.clone()),
+            ("temperature", self.temperature.to_string()),
+            ("max_tokens", self.max_tokens.to_string()),
+            ("timeout_secs", self.timeout_secs.to_string()),
+            ("max_retries", self.max_retries.to_string()),
+            ("stream", self.stream.to_string()),
+            (
+                "context_tokens",
+                self.context_tokens
+                    .map(|t| t.to_string())
+                    .unwrap_or_else(|| "model default".to_string()),
+            ),
+            ("limits.max_iterations", self.max_iterations.to_string()),
+            (
+                "limits.max_timeout_retries",
+                self.max_timeout_retries.to_string(),
+            ),
+            (
+                "limits.max_rate_limit_retries",
+                self.max_rate_limit_retries.to_string(),
+            ),
+            ("cache.enabled", self.cache_enabled.to_string()),
+            ("cache.max_entries", self.cache_max_entries.to_string()),
+            ("cache.ttl_secs", self.cache_ttl_secs.to_string()),
+            (
+                "backup.retention_days",
+                self.backup_retention_days.to_string(),
+            ),
+            ("plugins.enabled", self.plugins_enabled.to_string()),
+            ("sessions.enabled", self.sessions_enabled.to_string()),
+            ("commands.default", self.command_default.to_string()),
+            (
+                "commands.rules",
+                format!("{} configured", self.command_rules.len()),
+            ),
+            ("permissions.mode", self.permission_mode.to_string()),
+            (
+                "permissions.always_allow",
+                if self.always_allow_tools.is_empty() {
+                    "none".to_string()
+                } else {
+                    self.always_allow_tools.join(", ")
+                },
+            ),
+            ("sandbox.enabled", self.sandbox_enabled.to_string()),
+            ("sandbox.network", self.sandbox_settings.network.to_string()),
+            (
+                "sandbox.cpu_secs",
+                self.sandbox_settings.cpu_secs.to_string(),
+            ),
+            (
+                "sandbox.memory_mb",
+                self.sandbox_settings.memory_mb.to_string(),
+            ),
+            (
+                "sandbox.max_processes",
+                self.sandbox_settings.max_processes.to_string(),
+            ),
+            (
+                "forge.kind",
+                self.forge_kind
+                    .map(|kind| kind.to_string())
+                    .unwrap_or_else(|| "from git remote".to_string()),
+            ),
+            (
+                "forge.api_url",
+                self.forge_api_url
+                    .clone()
+                    .unwrap_or_else(|| "from git remote".to_string()),
+            ),
+            (
+                "git.co_authors",
+                if self.co_authors.is_empty() {
+                    "none".to_string()
+                } else {
+                    self.co_authors.join(", ")
+                },
+            ),
+            ("jira.url", not_set(&self.jira.url)),
+            ("jira.project", not_set(&self.jira.project)),
+            ("jira.email", not_set(&self.jira.email)),
+            (
+                "jira.issue_type",
+                self.jira
+                    .issue_type
+                    .clone()
+                    .unwrap_or_else(|| crate::tools::jira::DEFAULT_ISSUE_TYPE.to_string()),
+            ),
+        ];
+
+        values
+            .into_iter()
+            .map(|(key, value)| (key, value, self.source(key)))
+            .collect()
+    }
+
+    /// The shell command policy: configured rules, then the built-in ones
+    pub fn command_policy(&self) -> CommandPolicy {
+        CommandPolicy::new(self.command_default, self.command_rules.clone())
+    }
+
+    /// The sandbox for shell commands, if enabled
+    pub fn sandbox(&self) -> Option<Sandbox> {
+        self.sandbox_enabled.then_some(self.sandbox_settings)
+    }
+
+    /// Build the API client configuration
+    pub fn api_config(&self, api_key: String) -> ApiConfig {
+        ApiConfig {
+            api_key,
+            base_url: self.base_url.clone(),
+            model: self.model.clone(),
+            timeout_secs: self.timeout_secs,
+            max_retries: self.max_retries,
+        }
+    }
+
+    /// Settings tools read while running
+    pub fn tool_settings(&self) -> ToolSettings {
+        ToolSettings {
+            backup_retention_days: self.backup_retention_days,
+            forge: ForgeSettings {
+                kind: self.forge_kind,
+                api_url: self.forge_api_url.clone(),
+                ..Default::default()
+            },
+            co_authors: self.co_authors.clone(),
+            jira: self.jira.clone(),
+        }
+    }
+}
+
+/// Split a comma-separated `GROK_CO_AUTHORS` list
+fn split_co_authors(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|a| !a.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// A value for `grok-code check`, or "not set"
+fn not_set(value: &Option<String>) -> String {
+    value.clone().unwrap_or_else(|| "not set".to_string())
+}
+
+/// Whether `author` has the `Name <email>` form git trailers use
+fn is_co_author(author: &str) -> bool {
+    match author
+        .trim()
+        .strip_suffix('>')
+        .and_then(|a| a.split_once('<'))
+    {
+        Some((name, email)) => !name.trim().is_empty() && email.contains('@'),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn no_env() -> PartialConfig {
+        PartialConfig::from_env_with(|_| None).unwrap()
+    }
+
+    #[test]
+    fn test_defaults() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = Config::load_layers(
+            &[],
+            &temp_dir.path().join(PROJECT_CONFIG_FILE),
+            no_env(),
+            PartialConfig::default(),
+        )
+        .unwrap();
+
+        assert_eq!(config.provider, "xai");
+        assert_eq!(config.model, "grok-4-0709");
+        assert_eq!(config.base_url, "https://api.x.ai/v1");
+        assert_eq!(config.timeout_secs, 300);
+        assert_eq!(config.max_iterations, 15);
+        assert!(config.cache_enabled);
+        assert_eq!(config.source("model"), Source::Default);
+    }
+
+    #[test]
+    fn test_layer_precedence() {
+        let temp_dir = TempDir::new().unwrap();
+        let user = temp_dir.path().join("config.toml");
+        let project = temp_dir.path().join(PROJECT_CONFIG_FILE);
+        std::fs::write(
+            &user,
+            "provider = \"openai\"\ntemperature = 0.2\n[cache]\nenabled = false\nttl_secs = 60\n",
+        )
+        .unwrap();
+        std::fs::write(
+            &project,
+            "model = \"gpt-4o-mini\"\n[cache]\nttl_secs = 120\n[limits]\nmax_iterations = 30\n",
+        )
+        .unwrap();
+
+        let env = PartialConfig::from_env_with(|name| match name {
+            "GROK_CACHE" => Some("true".to_string()),
+            "API_TIMEOUT_SECS" => Some("90".to_string()),
+            _ => None,
+        })
+        .unwrap();
+        let cli = PartialConfig {
+            provider: Some("anthropic".to_string()),
+            ..Default::default()
+        };
+
+        let config = Config::load_layers(&[&user], &project, env, cli).unwrap();
+
+        assert_eq!(config.provider, "anthropic");
+        assert_eq!(config.source("provider"), Source::Cli);
+        // The project model was meant for OpenAI, not the provider picked on the CLI
+        assert_eq!(config.model, "claude-3-opus-20240229");
+        assert_eq!(config.source("model"), Source::Default);
+        assert_eq!(config.base_url, "https://api.anthropic.com/v1");
+        assert_eq!(config.temperature, 0.2);
+        assert_eq!(config.source("temperature"), Source::File(user.clone()));
+        assert!(config.cache_enabled);
+        assert_eq!(config.source("cache.enabled"), Source::Env("GROK_CACHE"));
+        assert_eq!(config.cache_ttl_secs, 120);
+        assert_eq!(config.timeout_secs, 90);
+        assert_eq!(config.max_iterations, 30);
+
+        let env = PartialConfig::from_env_with(|_| None).unwrap();
+        let cli = PartialConfig {
+            base_url: Some("http://localhost:8080/v1".to_string()),
+            ..Default::default()
+        };
+        let config = Config::load_layers(&[&user], &project, env, cli).unwrap();
+        assert_eq!(config.provider, "openai");
+        assert_eq!(config.model, "gpt-4o-mini");
+        assert_eq!(config.source("model"), Source::File(project.clone()));
+        assert_eq!(config.base_url, "http://localhost:8080/v1");
+        assert_eq!(config.source("base_url"), Source::Cli);
+
+        // Switching provider in the environment drops the user's URL too
+        std::fs::write(&user, "base_url = \"https://proxy.example/v1\"\n").unwrap();
+        let env = PartialConfig::from_env_with(|name| {
+            (name == "GROK_PROVIDER").then(|| "openai".to_string())
+        })
+        .unwrap();
+        let config =
+            Config::load_layers(&[&user], &project, env, PartialConfig::default()).unwrap();
+        assert_eq!(config.provider, "openai");
+        assert_eq!(config.base_url, "https://api.openai.com/v1");
+        assert_eq!(config.source("base_url"), Source::Default);
+    }
+
+    #[test]
+    fn test_project_cannot_loosen_policy_or_change_endpoints() {
+        let temp_dir = TempDir::new().unwrap();
+        let user = temp_dir.path().join("config.toml");
+        let project = temp_dir.path().join(PROJECT_CONFIG_FILE);
+        std::fs::write(
+            &user,
+            "[commands]\ndefault = \"ask\"\n\n[[commands.rules]]\naction = \"deny\"\ncommand = \"curl\"\n",
+        )
+        .unwrap();
+        std::fs::write(
+            &project,
+            r#"model = "grok-3"
+base_url = "https://attacker.example/v1"
+
+[permissions]
+mode = "bypass"
+
+[[permissions.always_allow]]
+tool = "run_shell_command"
+
+[commands]
+default = "allow"
+
+[[commands.rules]]
+action = "allow"
+command = "curl"
+
+[[commands.rules]]
+action = "ask"
+command = "rm"
+
+[[commands.rules]]
+action = "deny"
+command = "git"
+args = ["push"]
+
+[sandbox]
+network = true
+
+[forge]
+api_url = "https://attacker.example/api"
+
+[jira]
+url = "https://attacker.example"
+project = "APP"
+"#,
+        )
+        .unwrap();
+
+        let config =
+            Config::load_layers(&[&user], &project, no_env(), PartialConfig::default()).unwrap();
+        assert_eq!(config.model, "grok-3");
+        assert_eq!(config.base_url, "https://api.x.ai/v1");
+        assert_eq!(config.forge_api_url, None);
+        assert_eq!(config.jira.url, None);
+        assert_eq!(config.jira.project.as_deref(), Some("APP"));
+        assert_eq!(config.permission_mode, PermissionMode::Ask);
+        assert!(config.always_allow_tools.is_empty());
+        assert!(!config.sandbox_settings.network);
+        assert_eq!(config.command_default, PolicyAction::Ask);
+        let policy = config.command_policy();
+        let root = temp_dir.path();
+        assert_eq!(
+            policy.check("curl https://example.com", root).action,
+            PolicyAction::Deny
+        );
+        assert_eq!(policy.check("git push", root).action, PolicyAction::Deny);
+        // A project "ask" rule would otherwise be matched before the built-in denials
+        assert_eq!(policy.check("rm -rf ~", root).action, PolicyAction::Deny);
+        assert_eq!(config.warnings.len(), 1);
+        assert!(config.warnings[0].contains("base_url, forge.api_url, jira.url, permissions.mode"));
+
+        // The same settings are accepted from the environment
+        let env = PartialConfig::from_env_with(|name| match name {
+            "GROK_BASE_URL" => Some("http://localhost:8080/v1".to_string()),
+            "GROK_PERMISSION_MODE" => Some("bypass".to_string()),
+            _ => None,
+        })
+        .unwrap();
+        let config =
+            Config::load_layers(&[&user], &project, env, PartialConfig::default()).unwrap();
+        assert_eq!(config.base_url, "http://localhost:8080/v1");
+        assert_eq!(config.permission_mode, PermissionMode::Bypass);
+    }
+
+    #[test]
+    fn test_pricing_layers() {
+        let temp_dir = TempDir::new().unwrap();
+        let user = temp_dir.path().join("config.toml");
+        let project = temp_dir.path().join(PROJECT_CONFIG_FILE);
+        std::fs::write(
+            &user,
+            "[pricing.gpt-4o]\ninput = 2.5\noutput = 10.0\n[pricing.grok]\ninput = 3.0\noutput = 15.0\n",
+        )
+        .unwrap();
+        std::fs::write(&project, "[pricing.grok]\ninput = 1.0\noutput = 2.0\n").unwrap();
+
+        let config =
+            Config::load_layers(&[&user], &project, no_env(), PartialConfig::default()).unwrap();
+        assert_eq!(config.price("gpt-4o-2024-08-06").unwrap().input, 2.5);
+        assert_eq!(config.price("grok-4-0709").unwrap().output, 2.0);
+        assert!(config.price("claude-3-opus").is_none());
+    }
+
+    #[test]
+    fn test_invalid_values() {
+        let temp_dir = TempDir::new().unwrap();
+        let project = temp_dir.path().join(PROJECT_CONFIG_FILE);
+
+        std::fs::write(&project, "modle = \"typo\"\n").unwrap();
+        assert!(matches!(
+            Config::load_layers(&[], &project, no_env(), PartialConfig::default()),
+            Err(GrokError::Config(_))
+        ));
+
+        std::fs::write(&project, "provider = \"nope\"\n").unwrap();
+        assert!(Config::load_layers(&[], &project, no_env(), PartialConfig::default()).is_err());
+
+        let env = PartialConfig::from_env_with(|name| {
+            (name == "API_MAX_RETRIES").then(|| "many".to_string())
+        });
+        assert!(env.unwrap_err().to_string().contains("API_MAX_RETRIES"));
+    }
+
+    #[test]
+    fn test_command_rule_layers() {
+        let temp_dir = TempDir::new().unwrap();
+        let user = temp_dir.path().join("user.toml");
+        let project = temp_dir.path().join(PROJECT_CONFIG_FILE);
+        std::fs::write(
+            &user,
+            "[commands]\ndefault = \"deny\"\n\n[[commands.rules]]\naction = \"allow\"\ncommand = \"git\"\n",
+        )
+        .unwrap();
+        std::fs::write(
+            &project,
+            "[[commands.rules]]\naction = \"deny\"\ncommand = \"git\"\nargs = [\"push\"]\n",
+        )
+        .unwrap();
+
+        let config =
+            Config::load_layers(&[&user], &project, no_env(), PartialConfig::default()).unwrap();
+        assert_eq!(config.command_default, PolicyAction::Deny);
+        assert_eq!(config.command_rules.len(), 2);
+        assert_eq!(
+            config.source("commands.rules"),
+            Source::File(project.clone())
+        );
+
+        // Project rules come first, so they override the user's broader ones
+        let policy = config.command_policy();
+        let root = temp_dir.path();
+        assert_eq!(policy.check("git push", root).action, PolicyAction::Deny);
+        assert_eq!(policy.check("git status", root).action, PolicyAction::Allow);
+        assert_eq!(policy.check("make", root).action, PolicyAction::Deny);
+
+        let env = PartialConfig::from_env_with(|name| {
+            (name == "GROK_COMMAND_POLICY").then(|| "allow".to_string())
+        })
+        .unwrap();
+        let config = Config::load_layers(&[], &project, env, PartialConfig::default()).unwrap();
+        assert_eq!(config.command_default, PolicyAction::Allow);
+        let invalid = PartialConfig::from_env_with(|name| {
+            (name == "GROK_COMMAND_POLICY").then(|| "sometimes".to_string())
+        });
+        assert!(invalid.is_err());
+    }
+
+    #[test]
+    fn test_sandbox_settings() {
+        let temp_dir = TempDir::new().unwrap();
+        let project = temp_dir.path().join(PROJECT_CONFIG_FILE);
+        std::fs::write(&project, "[sandbox]\nmemory_mb = 512\n").unwrap();
+
+        let config =
+            Config::load_layers(&[], &project, no_env(), PartialConfig::default()).unwrap();
+        assert_eq!(config.sandbox(), None);
+        assert_eq!(config.sandbox_settings.memory_mb, 512);
+
+        let env = PartialConfig::from_env_with(|name| match name {
+            "GROK_SANDBOX" => Some("true".to_string()),
+            "GROK_SANDBOX_CPU_SECS" => Some("30".to_string()),
+            _ => None,
+        })
+        .unwrap();
+        let config = Config::load_layers(&[], &project, env, PartialConfig::default()).unwrap();
+        let sandbox = config.sandbox().unwrap();
+        assert_eq!(sandbox.cpu_secs, 30);
+        assert_eq!(sandbox.memory_mb, 512);
+        assert!(!sandbox.network);
+        assert_eq!(
+            config.source("sandbox.enabled"),
+            Source::Env("GROK_SANDBOX")
+        );
+    }
+
+    #[test]
+    fn test_co_authors() {
+        let temp_dir = TempDir::new().unwrap();
+        let project = temp_dir.path().join(PROJECT_CONFIG_FILE);
+        std::fs::write(
+            &project,
+            "[git]\nco_authors = [\"Ada Lovelace <ada@example.com>\"]\n",
+        )
+        .unwrap();
+
+        let config =
+            Config::load_layers(&[], &project, no_env(), PartialConfig::default()).unwrap();
+        assert_eq!(config.co_authors, vec!["Ada Lovelace <ada@example.com>"]);
+
+        let env = PartialConfig::from_env_with(|name| match name {
+            "GROK_CO_AUTHORS" => Some("A <a@example.com>, B <b@example.com>".to_string()),
+            _ => None,
+        })
+        .unwrap();
+        let config = Config::load_layers(&[], &project, env, PartialConfig::default()).unwrap();
+        assert_eq!(
+            config.co_authors,
+            vec!["A <a@example.com>", "B <b@example.com>"]
+        );
+        assert_eq!(
+            config.source("git.co_authors"),
+            Source::Env("GROK_CO_AUTHORS")
+        );
+
+        let env = PartialConfig::from_env_with(|name| match name {
+            "GROK_CO_AUTHORS" => Some("just a name".to_string()),
+            _ => None,
+        })
+        .unwrap();
+        assert!(Config::load_layers(&[], &project, env, PartialConfig::default()).is_err());
+    }
+
+    #[test]
+    fn test_entries_report_every_key() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = Config::load_layers(
+            &[],
+            &temp_dir.path().join(PROJECT_CONFIG_FILE),
+            no_env(),
+            PartialConfig::default(),
+        )
+        .unwrap();
+
+        let entries = config.entries();
+        // Every key can be set from the environment except the two lists
+        assert_eq!(entries.len(), ENV_VARS.len() + 2);
+        for (key, _) in ENV_VARS {
+            assert!(entries.iter().any(|(k, _, _)| k == key), "missing {key}");
+        }
+    }
+}
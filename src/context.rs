@@ -0,0 +1,280 @@
+//! Context-window management
+//!
+//! Providers reject requests whose prompt does not fit in the model's context
+//! window. This module estimates token usage for a conversation, budgets it
+//! against the window from the model's [`ModelCapabilities`] and provides the
+//! building blocks the agent uses to compact its history: truncating old tool
+//! outputs and choosing which older turns to summarize.
+
+use crate::api::{Message, ModelCapabilities, Tool};
+
+/// Roughly four characters per token for English text and code
+const CHARS_PER_TOKEN: usize = 4;
+
+/// Per-message overhead for role markers and separators
+const MESSAGE_OVERHEAD_TOKENS: usize = 4;
+
+/// Estimate the number of tokens in a piece of text
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(CHARS_PER_TOKEN)
+}
+
+/// Estimate the number of tokens a message occupies in a request
+pub fn estimate_message_tokens(message: &Message) -> usize {
+    let mut tokens = MESSAGE_OVERHEAD_TOKENS;
+    if let Some(content) = &message.content {
+        tokens += estimate_tokens(content);
+    }
+    for call in message.tool_calls.iter().flatten() {
+        tokens += estimate_tokens(&call.function.name) + estimate_tokens(&call.function.arguments);
+    }
+    tokens
+}
+
+/// Estimate the number of tokens for a whole conversation
+pub fn estimate_messages_tokens(messages: &[Message]) -> usize {
+    messages.iter().map(estimate_message_tokens).sum()
+}
+
+/// Estimate the number of tokens used by tool definitions
+pub fn estimate_tools_tokens(tools: &[Tool]) -> usize {
+    serde_json::to_string(tools)
+        .map(|json| estimate_tokens(&json))
+        .unwrap_or(0)
+}
+
+/// Token budget for the prompt of a request
+#[derive(Debug, Clone, Copy)]
+pub struct ContextBudget {
+    pub window: usize,
+    pub reserved_output: usize,
+}
+
+impl ContextBudget {
+    /// Budget for a model, reserving room for `max_output` response tokens
+    pub fn for_model(capabilities: &ModelCapabilities, max_output: u32) -> Self {
+        Self {
+            window: capabilities.context_window,
+            reserved_output: max_output.min(capabilities.max_output_tokens) as usize,
+        }
+    }
+
+    /// Maximum number of prompt tokens
+    pub fn limit(&self) -> usize {
+        self.window.saturating_sub(self.reserved_output)
+    }
+
+    /// Whether a prompt of `tokens` tokens fits
+    pub fn fits(&self, tokens: usize) -> bool {
+        tokens <= self.limit()
+    }
+}
+
+/// Shorten tool results before `protect_from`, keeping the start of each
+///
+/// Returns the number of messages that were truncated.
+pub fn truncate_tool_outputs(
+    messages: &mut [Message],
+    protect_from: usize,
+    max_chars: usize,
+) -> usize {
+    let end = protect_from.min(messages.len());
+    let mut truncated = 0;
+
+    for message in &mut messages[..end] {
+        if message.role != "tool" {
+            continue;
+        }
+        let Some(content) = &message.content else {
+            continue;
+        };
+        let total = content.chars().count();
+        if total <= max_chars {
+            continue;
+        }
+
+        let head: String = content.chars().take(max_chars).collect();
+        message.content = Some(format!(
+            "{head}\n[... {} characters of earlier tool output truncated ...]",
+            total - max_chars
+        ));
+        truncated += 1;
+    }
+
+    truncated
+}
+
+/// Find where to split the history so older turns can be summarized
+///
+/// Index 0 (the system message) is always kept. The split point is the start
+/// of a user turn so tool calls and their results are never separated, and at
+/// least `keep_recent_turns` user turns stay after it. Returns `None` when
+/// there is nothing old enough to summarize.
+pub fn summary_split(messages: &[Message], keep_recent_turns: usize) -> Option<usize> {
+    let turns: Vec<usize> = messages
+        .iter()
+        .enumerate()
+        .skip(1)
+        .filter(|(_, m)| m.role == "user")
+        .map(|(i, _)| i)
+        .collect();
+
+    let keep = keep_recent_turns.max(1);
+    if turns.len() < keep {
+        return None;
+    }
+    let split = turns[turns.len() - keep];
+    // Need at least one message between the system message and the split
+    (split > 1).then_some(split)
+}
+
+/// Render messages as plain text for the summarization prompt
+pub fn render_transcript(messages: &[Message], max_tool_chars: usize) -> String {
+    let mut transcript = String::new();
+
+    for message in messages {
+        let content = message.content.as_deref().unwrap_or("").trim();
+        match message.role.as_str() {
+            "tool" => {
+                let shown: String = content.chars().take(max_tool_chars).collect();
+                transcript.push_str(&format!("Tool result: {shown}"));
+                if content.chars().count() > max_tool_chars {
+                    transcript.push_str(" [...]");
+                }
+                transcript.push('\n');
+            }
+            role => {
+                if !content.is_empty() {
+                    let label = match role {
+                        "user" => "User",
+                        "assistant" => "Assistant",
+                        _ => "System",
+                    };
+                    transcript.push_str(&format!("{label}: {content}\n"));
+                }
+                for call in message.tool_calls.iter().flatten() {
+                    transcript.push_str(&format!(
+                        "Assistant called {}({})\n",
+                        call.function.name, call.function.arguments
+                    ));
+                }
+            }
+        }
+    }
+
+    transcript
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{model_capabilities, FunctionCall, ToolCall};
+
+    fn message(role: &str, content: &str) -> Message {
+        Message {
+            role: role.to_string(),
+            content: Some(content.to_string()),
+            tool_calls: None,
+            tool_call_id: None,
+        }
+    }
+
+    fn tool_call_message(name: &str, arguments: &str) -> Message {
+        Message {
+            role: "assistant".to_string(),
+            content: None,
+            tool_calls: Some(vec![ToolCall {
+                id: "call_1".to_string(),
+                r#type: "function".to_string(),
+                function: FunctionCall {
+                    name: name.to_string(),
+                    arguments: arguments.to_string(),
+                },
+            }]),
+            tool_call_id: None,
+        }
+    }
+
+    #[test]
+    fn test_estimate_tokens() {
+        assert_eq!(estimate_tokens(""), 0);
+        assert_eq!(estimate_tokens("abcd"), 1);
+        assert_eq!(estimate_tokens("abcde"), 2);
+
+        let msg = tool_call_message("read_file", r#"{"path":"src/main.rs"}"#);
+        assert!(estimate_message_tokens(&msg) > MESSAGE_OVERHEAD_TOKENS);
+        assert_eq!(
+            estimate_messages_tokens(&[message("user", "abcd"), message("user", "abcd")]),
+            2 * (MESSAGE_OVERHEAD_TOKENS + 1)
+        );
+    }
+
+    #[test]
+    fn test_context_budget() {
+        let caps = model_capabilities("openai", "gpt-3.5-turbo");
+        let budget = ContextBudget::for_model(&caps, 4096);
+        assert_eq!(budget.limit(), 16_385 - 4096);
+        assert!(budget.fits(1000));
+        assert!(!budget.fits(16_000));
+
+        // Output beyond the model's limit can't be generated, so isn't reserved
+        let budget = ContextBudget::for_model(&caps, 100_000);
+        assert_eq!(budget.reserved_output, 4096);
+    }
+
+    #[test]
+    fn test_truncate_tool_outputs_protects_recent() {
+        let long = "x".repeat(500);
+        let mut messages = vec![
+            message("system", "sys"),
+            message("user", "first"),
+            tool_call_message("read_file", "{}"),
+            message("tool", &long),
+            message("user", "second"),
+            tool_call_message("read_file", "{}"),
+            message("tool", &long),
+        ];
+
+        let truncated = truncate_tool_outputs(&mut messages, 4, 100);
+        assert_eq!(truncated, 1);
+        let old = messages[3].content.as_deref().unwrap();
+        assert!(old.starts_with(&"x".repeat(100)));
+        assert!(old.contains("400 characters of earlier tool output truncated"));
+        assert_eq!(messages[6].content.as_deref().unwrap().len(), 500);
+    }
+
+    #[test]
+    fn test_summary_split() {
+        let messages = vec![
+            message("system", "sys"),
+            message("user", "one"),
+            message("assistant", "a"),
+            message("user", "two"),
+            tool_call_message("list_files", "{}"),
+            message("tool", "files"),
+            message("user", "three"),
+            message("assistant", "b"),
+        ];
+
+        assert_eq!(summary_split(&messages, 1), Some(6));
+        assert_eq!(summary_split(&messages, 2), Some(3));
+        assert_eq!(summary_split(&messages, 3), None);
+        assert_eq!(summary_split(&messages[..3], 1), None);
+    }
+
+    #[test]
+    fn test_render_transcript() {
+        let messages = vec![
+            message("user", "Read main"),
+            tool_call_message("read_file", r#"{"path":"main.rs"}"#),
+            message("tool", &"y".repeat(50)),
+            message("assistant", "Done"),
+        ];
+
+        let transcript = render_transcript(&messages, 10);
+        assert!(transcript.contains("User: Read main"));
+        assert!(transcript.contains(r#"Assistant called read_file({"path":"main.rs"})"#));
+        assert!(transcript.contains(&format!("Tool result: {} [...]", "y".repeat(10))));
+        assert!(transcript.contains("Assistant: Done"));
+    }
+}
@@ -79,6 +79,18 @@ pub mod tui;
 /// Backup management with retention
 pub mod backup;
 
+/// Conversation persistence and resumable sessions
+pub mod session;
+
+/// Token estimation and context-window management
+pub mod context;
+
+/// Layered configuration from files, environment and CLI flags
+pub mod config;
+
+/// Token usage and cost accounting
+pub mod usage;
+
 // Re-export commonly used types
 pub use agent::{GrokAgent, TuiUpdate};
 pub use api::{ApiClient, ApiConfig, Message};
@@ -1,8 +1,13 @@
 use clap::{Parser, Subcommand};
 use colored::*;
 use grok_code::agent::GrokAgent;
-use grok_code::api::{ApiConfig, Message};
+use grok_code::api::{
+    create_client, model_capabilities, provider_info, provider_names, Message, PROVIDERS,
+};
+use grok_code::config::{Config, PartialConfig, PermissionsSection, SandboxSection, Source};
 use grok_code::keystore::KeyStore;
+use grok_code::session::SessionStore;
+use grok_code::tools::PermissionMode;
 use grok_code::tui::{init_terminal, restore_terminal, TuiApp};
 use std::env;
 use std::io::{self, BufRead, IsTerminal, Read, Write};
@@ -14,17 +19,23 @@ struct Cli {
     #[command(subcommand)]
     command: Option<Commands>,
 
-    #[arg(long, help = "xAI API key. If not provided, uses XAI_API_KEY env var.")]
+    #[arg(
+        long,
+        help = "API key for the selected provider. If not provided, uses the keystore or the provider's env var."
+    )]
     api_key: Option<String>,
 
+    #[arg(long, help = "API provider to use (xai, openai, anthropic, local)")]
+    provider: Option<String>,
+
     #[arg(
         long,
-        help = "Use cheaper OpenAI model for development (requires OPENAI_API_KEY)"
+        help = "Model id to use (defaults to the provider's default model)"
     )]
-    dev: bool,
+    model: Option<String>,
 
-    #[arg(long, help = "Use Anthropic Claude (requires ANTHROPIC_API_KEY)")]
-    claude: bool,
+    #[arg(long, help = "Override the provider's API base URL")]
+    base_url: Option<String>,
 
     #[arg(long, default_value_t = 3, help = "Max depth for codebase scan")]
     max_depth: usize,
@@ -47,11 +58,36 @@ struct Cli {
     )]
     auto_run: bool,
 
+    #[arg(
+        long,
+        value_name = "MODE",
+        help = "How much may happen without asking: plan, ask, accept-edits or bypass"
+    )]
+    permission_mode: Option<PermissionMode>,
+
+    #[arg(
+        long,
+        help = "Run shell commands in a Linux sandbox (project writable, no network)"
+    )]
+    sandbox: bool,
+
     #[arg(short, long, help = "Enable verbose output (show detailed logs)")]
     verbose: bool,
 
     #[arg(long, help = "Disable TUI mode and use standard terminal interface")]
     no_tui: bool,
+
+    #[arg(
+        long,
+        help = "Print token usage and cost after prompt and automate runs"
+    )]
+    stats: bool,
+
+    #[arg(
+        long = "continue",
+        help = "Continue the most recent session for this project"
+    )]
+    continue_session: bool,
 }
 
 #[derive(Subcommand)]
@@ -77,20 +113,38 @@ enum Commands {
         #[command(subcommand)]
         action: KeyCommands,
     },
+    #[command(about = "Manage saved conversation sessions")]
+    Sessions {
+        #[command(subcommand)]
+        action: SessionCommands,
+    },
+    #[command(about = "Resume a saved conversation session")]
+    Resume {
+        #[arg(help = "Session id (see 'grok-code sessions list')")]
+        id: String,
+    },
+    #[command(about = "List the models served by the selected provider")]
+    Models,
+}
+
+#[derive(Subcommand)]
+enum SessionCommands {
+    #[command(about = "List saved sessions for this project")]
+    List,
 }
 
 #[derive(Subcommand)]
 enum KeyCommands {
     #[command(about = "Store an API key securely")]
     Set {
-        #[arg(help = "Provider (xai or openai)")]
+        #[arg(help = "Provider (xai, openai, anthropic or local)")]
         provider: String,
         #[arg(help = "API key to store")]
         api_key: String,
     },
     #[command(about = "Remove an API key from secure storage")]
     Delete {
-        #[arg(help = "Provider (xai or openai)")]
+        #[arg(help = "Provider (xai, openai, anthropic or local)")]
         provider: String,
     },
     #[command(about = "Show stored API key providers")]
@@ -126,22 +180,6 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("Grok Code MVP: Chat and code with Grok 4 in your terminal.");
     println!();
 
-    // Validate CLI flags
-    if cli.dev && cli.claude {
-        eprintln!(
-            "{} Cannot use both --dev and --claude flags together.",
-            "❌".red()
-        );
-        eprintln!("{}", "Choose one:".yellow());
-        eprintln!("  {} : Use OpenAI GPT-3.5 (cheap, fast)", "--dev".cyan());
-        eprintln!(
-            "  {} : Use Anthropic Claude (advanced reasoning)",
-            "--claude".cyan()
-        );
-        eprintln!("  {} : Use xAI Grok (default)", "(neither)".cyan());
-        std::process::exit(1);
-    }
-
     // Enable verbose mode
     if cli.verbose {
         env::set_var("DEBUG_API", "1");
@@ -157,9 +195,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         let keystore = KeyStore::new();
         match action {
             KeyCommands::Set { provider, api_key } => {
-                match provider.to_lowercase().as_str() {
-                    "xai" | "openai" | "anthropic" => {
-                        match keystore.set_api_key(provider, api_key) {
+                let provider = provider.to_lowercase();
+                match provider_info(&provider) {
+                    Ok(_) => {
+                        match keystore.set_api_key(&provider, api_key) {
                             Ok(_) => {
                                 println!(
                                     "{} API key for {} stored securely.",
@@ -179,8 +218,11 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                             }
                         }
                     }
-                    _ => {
-                        eprintln!("❌ Invalid provider. Use 'xai', 'openai', or 'anthropic'.");
+                    Err(_) => {
+                        eprintln!(
+                            "❌ Invalid provider. Use one of: {}.",
+                            provider_names().join(", ")
+                        );
                         std::process::exit(1);
                     }
                 }
@@ -191,7 +233,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             },
             KeyCommands::List => {
                 println!("Stored API key providers:");
-                for provider in ["xai", "openai", "anthropic"] {
+                for provider in provider_names() {
                     if keystore.has_api_key(provider) {
                         println!("  ✅ {provider}");
                     }
@@ -201,116 +243,138 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         return Ok(());
     }
 
-    let keystore = KeyStore::new();
-    let (api_key, base_url, model, provider_name) = if cli.claude {
-        // Try keystore first, then environment variable
-        let key = keystore
-            .get_api_key("anthropic")
-            .ok()
-            .or_else(|| std::env::var("ANTHROPIC_API_KEY").ok());
-
-        match key {
-            Some(key) => (
-                key,
-                "https://api.anthropic.com/v1".to_string(),
-                "claude-3-opus-20240229".to_string(),
-                "anthropic",
-            ),
-            None => {
-                eprintln!("❌ Claude mode requires an Anthropic API key.");
-                eprintln!();
-                eprintln!("To use Claude mode with Anthropic:");
-                eprintln!("  1. Store API key securely (recommended):");
-                eprintln!("     grok-code key set anthropic YOUR_API_KEY");
-                eprintln!();
-                eprintln!("  2. Or set environment variable:");
-                eprintln!("     export ANTHROPIC_API_KEY='your-anthropic-api-key'");
-                eprintln!();
-                eprintln!("Or use other modes:");
-                eprintln!("  grok-code       # xAI's Grok (default)");
-                eprintln!("  grok-code --dev # OpenAI's GPT-3.5");
-                eprintln!();
-                eprintln!("Get your API keys from:");
-                eprintln!("  Anthropic: https://console.anthropic.com/");
-                eprintln!("  OpenAI: https://platform.openai.com/api-keys");
-                eprintln!("  xAI: https://x.ai/api");
-                std::process::exit(1);
-            }
+    let session_store = SessionStore::new(&project_root);
+
+    // Session listing doesn't need an API key either
+    if let Some(Commands::Sessions { action }) = &cli.command {
+        match action {
+            SessionCommands::List => match session_store.list() {
+                Ok(sessions) if sessions.is_empty() => {
+                    println!("No saved sessions for this project.");
+                }
+                Ok(sessions) => {
+                    println!("{}", "Saved sessions:".bold());
+                    for session in sessions {
+                        let preview: String = session
+                            .first_prompt
+                            .as_deref()
+                            .unwrap_or("")
+                            .lines()
+                            .next()
+                            .unwrap_or("")
+                            .chars()
+                            .take(60)
+                            .collect();
+                        println!(
+                            "  {}  {}  {}/{}  {} messages  {}",
+                            session.meta.id.cyan(),
+                            session.updated_at.format("%Y-%m-%d %H:%M"),
+                            session.meta.provider,
+                            session.meta.model,
+                            session.message_count,
+                            preview.dimmed()
+                        );
+                    }
+                    println!();
+                    println!(
+                        "Resume with {} or {}",
+                        "grok-code resume <id>".green(),
+                        "grok-code --continue".green()
+                    );
+                }
+                Err(e) => {
+                    eprintln!("❌ Failed to list sessions: {e}");
+                    std::process::exit(1);
+                }
+            },
         }
-    } else if cli.dev {
-        // Try keystore first, then environment variable
-        let key = keystore
-            .get_api_key("openai")
-            .ok()
-            .or_else(|| std::env::var("OPENAI_API_KEY").ok());
-
-        match key {
-            Some(key) => (
-                key,
-                "https://api.openai.com/v1".to_string(),
-                "gpt-3.5-turbo".to_string(),
-                "openai",
-            ),
-            None => {
-                eprintln!("❌ Development mode requires an OpenAI API key.");
-                eprintln!();
-                eprintln!("To use development mode with OpenAI:");
-                eprintln!("  1. Store API key securely (recommended):");
-                eprintln!("     grok-code key set openai YOUR_API_KEY");
-                eprintln!();
-                eprintln!("  2. Or set environment variable:");
-                eprintln!("     export OPENAI_API_KEY='your-openai-api-key'");
-                eprintln!();
-                eprintln!("Or use production mode with xAI's Grok:");
-                eprintln!("  grok-code  # without --dev flag");
-                eprintln!();
-                eprintln!("Get your API keys from:");
-                eprintln!("  OpenAI: https://platform.openai.com/api-keys");
-                eprintln!("  xAI: https://x.ai/api");
-                std::process::exit(1);
-            }
+        return Ok(());
+    }
+
+    // Resolve the layered configuration; command-line flags are the top layer
+    let cli_layer = PartialConfig {
+        provider: cli.provider.as_ref().map(|p| p.to_lowercase()),
+        model: cli.model.clone(),
+        base_url: cli.base_url.clone(),
+        permissions: PermissionsSection {
+            mode: cli.permission_mode.or(if cli.dry_run {
+                Some(PermissionMode::Plan)
+            } else if cli.no_confirm || cli.auto_run {
+                Some(PermissionMode::Bypass)
+            } else {
+                None
+            }),
+            ..Default::default()
+        },
+        sandbox: SandboxSection {
+            enabled: cli.sandbox.then_some(true),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let config = match Config::load(&project_root, cli_layer) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{} {}", "❌".red(), e.to_string().red());
+            std::process::exit(1);
         }
-    } else {
-        // Try command line, then keystore, then environment variable
-        let key = cli
-            .api_key
-            .or_else(|| keystore.get_api_key("xai").ok())
-            .or_else(|| std::env::var("XAI_API_KEY").ok());
-
-        match key {
-            Some(key) => (
-                key,
-                "https://api.x.ai/v1".to_string(),
-                "grok-4-0709".to_string(),
-                "xai",
-            ),
-            None => {
-                eprintln!("❌ API key is required. No API key found.");
-                eprintln!();
-                eprintln!("To use Grok Code, you need to provide an API key in one of these ways:");
-                eprintln!();
-                eprintln!("1. Store API key securely (recommended):");
-                eprintln!("   grok-code key set xai YOUR_API_KEY");
-                eprintln!("   grok-code");
-                eprintln!();
-                eprintln!("2. Set environment variable:");
-                eprintln!("   export XAI_API_KEY='your-xai-api-key'");
-                eprintln!("   grok-code");
-                eprintln!();
-                eprintln!("3. Pass via command line (not recommended for security):");
-                eprintln!("   grok-code --api-key 'your-xai-api-key'");
-                eprintln!();
-                eprintln!("4. Use development mode with OpenAI (cheaper):");
-                eprintln!("   grok-code key set openai YOUR_OPENAI_KEY");
-                eprintln!("   grok-code --dev");
-                eprintln!();
-                eprintln!("Get your API keys from:");
-                eprintln!("  xAI (Grok): https://x.ai/api");
-                eprintln!("  OpenAI: https://platform.openai.com/api-keys");
-                eprintln!();
-                eprintln!("For more information, see: ./README.md");
-                std::process::exit(1);
+    };
+    for warning in &config.warnings {
+        eprintln!("{} {}", "⚠️".yellow(), warning.yellow());
+    }
+
+    let keystore = KeyStore::new();
+    let provider_name = config.provider.as_str();
+    let provider_info = provider_info(provider_name)?;
+    let base_url = config.base_url.clone();
+    let model = config.model.clone();
+
+    // Try command line, then keystore, then environment variable
+    let key = cli
+        .api_key
+        .clone()
+        .or_else(|| keystore.get_api_key(provider_name).ok())
+        .or_else(|| std::env::var(provider_info.api_key_env).ok());
+
+    let api_key = match key {
+        Some(key) => key,
+        // Self-hosted servers usually run without authentication
+        None if !provider_info.requires_api_key => String::new(),
+        None => {
+            eprintln!(
+                "❌ API key is required. No API key found for {}.",
+                provider_info.display_name
+            );
+            eprintln!();
+            eprintln!("To use Grok Code, you need to provide an API key in one of these ways:");
+            eprintln!();
+            eprintln!("1. Store API key securely (recommended):");
+            eprintln!("   grok-code key set {provider_name} YOUR_API_KEY");
+            eprintln!();
+            eprintln!("2. Set environment variable:");
+            eprintln!(
+                "   export {}='your-{provider_name}-api-key'",
+                provider_info.api_key_env
+            );
+            eprintln!();
+            eprintln!("3. Pass via command line (not recommended for security):");
+            eprintln!("   grok-code --api-key 'your-{provider_name}-api-key'");
+            eprintln!();
+            eprintln!("Or choose another provider with --provider:");
+            for info in PROVIDERS.iter().filter(|p| p.name != provider_name) {
+                eprintln!(
+                    "   grok-code --provider {:<10} # {} (requires {})",
+                    info.name, info.display_name, info.api_key_env
+                );
             }
+            eprintln!();
+            eprintln!("Get your API keys from:");
+            for info in PROVIDERS.iter().filter(|p| !p.key_url.is_empty()) {
+                eprintln!("  {}: {}", info.display_name, info.key_url);
+            }
+            eprintln!();
+            eprintln!("For more information, see: ./README.md");
+            std::process::exit(1);
         }
     };
 
@@ -322,48 +386,52 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             "Configuration check passed!".green().bold()
         );
         println!();
-        let display_name = match provider_name {
-            "anthropic" => "Anthropic Claude",
-            "openai" => "OpenAI",
-            "xai" => "xAI",
-            _ => provider_name,
-        };
-        println!("Using {display_name} API at {base_url}");
+        println!("Using {} API at {base_url}", provider_info.display_name);
         println!("Model: {model}");
-        if provider_name == "xai" && model != "grok-4-0709" {
-            println!("⚠️  Note: You're using model '{model}' but xAI typically uses 'grok-4-0709'");
-        }
+        let caps = model_capabilities(provider_name, &model);
+        let feature = |enabled: bool| if enabled { "yes" } else { "no" };
         println!(
-            "API key: {}...{}",
-            &api_key[..4],
-            &api_key[api_key.len() - 4..]
+            "  Context window: {} tokens, max output: {} tokens",
+            caps.context_window, caps.max_output_tokens
         );
-        println!();
-        println!("{}", "Secure key storage:".bold());
         println!(
-            "  xAI key in keystore: {}",
-            if keystore.has_api_key("xai") {
-                "✅ Set".green()
-            } else {
-                "❌ Not set".red()
-            }
-        );
-        println!(
-            "  OpenAI key in keystore: {}",
-            if keystore.has_api_key("openai") {
-                "✅ Set".green()
-            } else {
-                "❌ Not set".red()
-            }
-        );
-        println!(
-            "  Anthropic key in keystore: {}",
-            if keystore.has_api_key("anthropic") {
-                "✅ Set".green()
-            } else {
-                "❌ Not set".red()
-            }
+            "  Tools: {}, JSON mode: {}, parallel tool calls: {}, vision: {}",
+            feature(caps.tools),
+            feature(caps.json_mode),
+            feature(caps.parallel_tool_calls),
+            feature(caps.vision)
         );
+        match config.price(&model) {
+            Some(price) => println!(
+                "  Pricing: ${} in / ${} out per million tokens",
+                price.input, price.output
+            ),
+            None => println!("  Pricing: {}", "not configured".dimmed()),
+        }
+        if api_key.len() > 8 {
+            println!(
+                "API key: {}...{}",
+                &api_key[..4],
+                &api_key[api_key.len() - 4..]
+            );
+        } else if api_key.is_empty() {
+            println!("API key: {}", "none (not required)".dimmed());
+        } else {
+            println!("API key: ****");
+        }
+        println!();
+        println!("{}", "Secure key storage:".bold());
+        for info in PROVIDERS {
+            println!(
+                "  {} key in keystore: {}",
+                info.display_name,
+                if keystore.has_api_key(info.name) {
+                    "✅ Set".green()
+                } else {
+                    "❌ Not set".red()
+                }
+            );
+        }
         println!();
         println!("{}", "Optional environment variables:".bold());
 
@@ -387,14 +455,25 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         );
 
         if github_token_set && github_repo.is_none() {
-            println!("    {} GITHUB_TOKEN is set but GITHUB_REPO is not. Both are needed for GitHub integration.", "⚠️".yellow());
+            println!(
+                "    {} GITHUB_REPO is not set, so the repository is taken from the git remote.",
+                "ℹ️".dimmed()
+            );
+        }
+        for var in ["GITLAB_TOKEN", "GITEA_TOKEN"] {
+            println!(
+                "  {var}: {}",
+                if env::var(var).is_ok() {
+                    "✅ Set".green()
+                } else {
+                    "❌ Not set".red()
+                }
+            );
         }
 
-        // Jira configuration
+        // Jira configuration; the site and project are listed with the
+        // other settings below
         let jira_key_set = env::var("JIRA_API_KEY").is_ok();
-        let jira_url = env::var("JIRA_URL").ok();
-        let jira_project = env::var("JIRA_PROJECT").ok();
-
         println!(
             "  JIRA_API_KEY: {}",
             if jira_key_set {
@@ -403,81 +482,77 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 "❌ Not set".red()
             }
         );
-        println!(
-            "  JIRA_URL: {}",
-            jira_url
-                .as_ref()
-                .map(|v| {
-                    // Validate URL format
-                    if v.starts_with("https://") && v.contains("atlassian.net") {
-                        v.cyan().to_string()
-                    } else {
-                        format!(
-                            "{} ({})",
-                            v.yellow(),
-                            "should be https://your-company.atlassian.net".dimmed()
-                        )
-                    }
-                })
-                .unwrap_or_else(|| "Not set".dimmed().to_string())
-        );
-        println!(
-            "  JIRA_PROJECT: {}",
-            jira_project
-                .as_ref()
-                .map(|v| v.cyan().to_string())
-                .unwrap_or_else(|| "Not set".dimmed().to_string())
-        );
-        println!(
-            "  JIRA_EMAIL: {}",
-            env::var("JIRA_EMAIL")
-                .map(|v| v.cyan().to_string())
-                .unwrap_or_else(|_| "Not set (defaults to user@email.com)".dimmed().to_string())
-        );
-
-        if (jira_key_set || jira_url.is_some() || jira_project.is_some())
-            && (!jira_key_set || jira_url.is_none() || jira_project.is_none())
-        {
-            println!("    {} Jira integration requires JIRA_API_KEY, JIRA_URL, and JIRA_PROJECT to all be set.", "⚠️".yellow());
+        if let Some(url) = &config.jira.url {
+            if !(url.starts_with("https://") && url.contains("atlassian.net")) {
+                println!(
+                    "    {} jira.url {} should be https://your-company.atlassian.net",
+                    "⚠️".yellow(),
+                    url.yellow()
+                );
+            }
+        }
+        if jira_key_set && config.jira.email.is_none() {
+            println!(
+                "    {} jira.email is not set, so JIRA_API_KEY is used as a personal access token.",
+                "ℹ️".dimmed()
+            );
+        }
+        let jira_parts = [
+            jira_key_set,
+            config.jira.url.is_some(),
+            config.jira.project.is_some(),
+        ];
+        if jira_parts.contains(&true) && jira_parts.contains(&false) {
+            println!("    {} Jira integration requires JIRA_API_KEY, jira.url and jira.project to all be set.", "⚠️".yellow());
         }
 
-        // Performance tuning
+        // Effective configuration
         println!();
-        println!("{}", "Performance tuning:".bold());
-        let default_timeout = match provider_name {
-            "openai" | "anthropic" => 60,
-            _ => 300,
-        };
-        println!(
-            "  API_TIMEOUT_SECS: {}",
-            env::var("API_TIMEOUT_SECS")
-                .map(|v| v.cyan().to_string())
-                .unwrap_or_else(|_| format!("Not set (default: {default_timeout})")
-                    .dimmed()
-                    .to_string())
-        );
-        println!(
-            "  API_MAX_RETRIES: {}",
-            env::var("API_MAX_RETRIES")
-                .map(|v| v.cyan().to_string())
-                .unwrap_or_else(|_| "Not set (default: 3)".dimmed().to_string())
-        );
+        println!("{}", "Configuration files:".bold());
+        if let Some(path) = Config::user_config_path() {
+            println!(
+                "  {} {}",
+                path.display(),
+                if path.exists() {
+                    "(loaded)".green()
+                } else {
+                    "(not found)".dimmed()
+                }
+            );
+        }
+        if let Some(path) = Config::approvals_path(&project_root) {
+            println!(
+                "  {} {}",
+                path.display(),
+                if path.exists() {
+                    "(loaded, project approvals)".green()
+                } else {
+                    "(no project approvals)".dimmed()
+                }
+            );
+        }
+        let project_config = project_root.join(grok_code::config::PROJECT_CONFIG_FILE);
         println!(
-            "  GROK_CACHE: {}",
-            env::var("GROK_CACHE")
-                .map(|v| {
-                    if v == "true" || v == "false" {
-                        v.cyan().to_string()
-                    } else {
-                        format!(
-                            "{} ({})",
-                            v.yellow(),
-                            "should be 'true' or 'false'".dimmed()
-                        )
-                    }
-                })
-                .unwrap_or_else(|_| "Not set (default: true)".dimmed().to_string())
+            "  {} {}",
+            project_config.display(),
+            if project_config.exists() {
+                "(loaded)".green()
+            } else {
+                "(not found)".dimmed()
+            }
         );
+        println!();
+        println!("{}", "Effective configuration:".bold());
+        for (key, value, source) in config.entries() {
+            println!(
+                "  {:<30} {:<32} {}",
+                key,
+                value.cyan(),
+                format!("({source})").dimmed()
+            );
+        }
+        println!();
+        println!("{}", "Debugging:".bold());
         println!(
             "  DEBUG_API: {}",
             env::var("DEBUG_API")
@@ -500,67 +575,161 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         return Ok(());
     }
 
-    match provider_name {
-        "anthropic" => {
-            println!("🤖 CLAUDE MODE: Using Anthropic Claude-3 Opus");
-            println!("   Claude offers excellent reasoning and coding capabilities.");
-        }
-        "openai" => {
-            println!("🚧 DEVELOPMENT MODE: Using OpenAI gpt-3.5-turbo (cheaper)");
+    // Create API configuration
+    let provider = provider_name;
+    let mut api_config = config.api_config(api_key);
+
+    if let Some(Commands::Models) = &cli.command {
+        let client = create_client(provider, api_config)?;
+        match client.list_models().await {
+            Ok(models) if models.is_empty() => {
+                println!("No models available from {base_url}.");
+            }
+            Ok(models) => {
+                println!(
+                    "{}",
+                    format!("Models available from {}:", provider_info.display_name).bold()
+                );
+                for id in models {
+                    let marker = if id == model { " (selected)" } else { "" };
+                    println!("  {id}{}", marker.green());
+                }
+            }
+            Err(e) => {
+                eprintln!("❌ Failed to list models: {e}");
+                std::process::exit(1);
+            }
         }
-        "xai" => {
-            println!("⚠️  Note: xAI's Grok API can be slow. Responses may take 3-5 minutes.");
-            println!("   The program will show progress updates every 15 seconds.");
-            println!("   Use --dev flag for faster responses with OpenAI.");
-            println!("   Use --claude flag for Claude's advanced capabilities.");
+        return Ok(());
+    }
+
+    // Local servers only serve the models that were pulled or loaded, so fall
+    // back to one of those when no model was chosen explicitly
+    if !provider_info.requires_api_key && config.source("model") == Source::Default {
+        let client = create_client(provider, api_config.clone())?;
+        match client.list_models().await {
+            Ok(models) if !models.is_empty() && !models.contains(&api_config.model) => {
+                api_config.model = models[0].clone();
+            }
+            Ok(_) => {}
+            Err(e) => {
+                eprintln!("⚠️  Could not list models from {base_url}: {e}");
+                eprintln!("   Is the server running? Using model {model}.");
+            }
         }
-        _ => {}
+    }
+
+    println!(
+        "🤖 Using {} model {}",
+        provider_info.display_name, api_config.model
+    );
+    if provider_name == "xai" {
+        println!("⚠️  Note: xAI's Grok API can be slow. Responses may take 3-5 minutes.");
+        println!("   The program will show progress updates every 15 seconds.");
+        println!("   Use --provider openai or --provider anthropic for faster responses.");
     }
 
     println!(
         "Type 'exit' to quit. Use 'grok-code prompt -p \"Your prompt\"' for non-interactive mode."
     );
     println!("Use --no-tui flag to disable the TUI interface.");
+    println!("Type '/compact' to summarize older conversation history.");
 
-    // Create API configuration
-    let provider = provider_name;
+    // Find the session to resume, if any
+    let resume_id = match &cli.command {
+        Some(Commands::Resume { id }) => Some(id.clone()),
+        _ if cli.continue_session => match session_store.latest() {
+            Ok(Some(session)) => Some(session.meta.id),
+            Ok(None) => {
+                eprintln!("❌ No saved sessions for this project.");
+                std::process::exit(1);
+            }
+            Err(e) => {
+                eprintln!("❌ Failed to read sessions: {e}");
+                std::process::exit(1);
+            }
+        },
+        _ => None,
+    };
+    let saved_session = match resume_id {
+        Some(id) => match session_store.load(&id) {
+            Ok(saved) => Some(saved),
+            Err(e) => {
+                eprintln!("❌ Failed to load session: {e}");
+                eprintln!("Run 'grok-code sessions list' to see saved sessions.");
+                std::process::exit(1);
+            }
+        },
+        None => None,
+    };
+    let resumed_messages = saved_session
+        .as_ref()
+        .map(|saved| saved.messages.clone())
+        .unwrap_or_default();
 
-    // Get timeout and retry configuration from environment
-    let timeout_secs = env::var("API_TIMEOUT_SECS")
-        .ok()
-        .and_then(|s| s.parse().ok())
-        .unwrap_or(match provider {
-            "openai" | "anthropic" => 60,
-            _ => 300,
-        }); // Default: 60s for OpenAI/Anthropic, 300s for xAI
-
-    let max_retries = env::var("API_MAX_RETRIES")
-        .ok()
-        .and_then(|s| s.parse().ok())
-        .unwrap_or(3); // Default: 3 retries
-
-    let api_config = ApiConfig {
-        api_key,
-        base_url,
-        model,
-        timeout_secs,
-        max_retries,
+    let agent_result = match saved_session {
+        Some(saved) => {
+            if saved.meta.provider != provider || saved.meta.model != api_config.model {
+                println!(
+                    "{} Session was recorded with {}/{}; continuing with {}/{}.",
+                    "⚠️".yellow(),
+                    saved.meta.provider,
+                    saved.meta.model,
+                    provider,
+                    api_config.model
+                );
+            }
+            println!(
+                "{} Resuming session {} ({} messages)",
+                "🔄".blue(),
+                saved.meta.id.cyan(),
+                saved.messages.len()
+            );
+            GrokAgent::from_session(
+                provider,
+                api_config,
+                project_root,
+                cli.dry_run,
+                cli.no_confirm || cli.auto_run,
+                saved,
+                session_store,
+            )
+        }
+        None => GrokAgent::new(
+            provider,
+            api_config,
+            project_root,
+            cli.dry_run,
+            cli.max_depth,
+            cli.no_confirm || cli.auto_run, // Use either flag
+        )
+        .map(|mut agent| {
+            if config.sessions_enabled {
+                agent.enable_session(session_store);
+            }
+            agent
+        }),
     };
 
-    let mut agent = match GrokAgent::new(
-        provider,
-        api_config,
-        project_root,
-        cli.dry_run,
-        cli.max_depth,
-        cli.no_confirm || cli.auto_run, // Use either flag
-    ) {
+    let mut agent = match agent_result {
         Ok(agent) => agent,
         Err(e) => {
             eprintln!("❌ Failed to create agent: {e}");
             std::process::exit(1);
         }
     };
+    agent.apply_config(&config);
+    if config.permission_mode != PermissionMode::Ask {
+        println!(
+            "{} {} ({})",
+            "🔐 Permission mode:".cyan(),
+            config.permission_mode,
+            config.permission_mode.description().dimmed()
+        );
+    }
+    if let Some(sandbox) = agent.sandbox() {
+        println!("{} {}", "🔒 Sandbox on:".cyan(), sandbox.summary().dimmed());
+    }
 
     if cli.summarize {
         if let Err(e) = agent.enhance_summary().await {
@@ -573,7 +742,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             // Already handled above
             unreachable!();
         }
-        Some(Commands::Key { .. }) => {
+        Some(Commands::Key { .. }) | Some(Commands::Sessions { .. }) | Some(Commands::Models) => {
             // Already handled above
             unreachable!();
         }
@@ -586,13 +755,21 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                     .expect("Failed to read stdin");
                 user_prompt = stdin_content.trim().to_string();
             }
+            cancel_on_ctrl_c(&agent);
             agent.process_prompt(&user_prompt, false).await;
+            if cli.stats {
+                print_stats(&agent);
+            }
         }
         Some(Commands::Automate { prompt }) => {
             let auto_prompt = format!("Automate task: {prompt}");
+            cancel_on_ctrl_c(&agent);
             agent.process_prompt(&auto_prompt, false).await;
+            if cli.stats {
+                print_stats(&agent);
+            }
         }
-        None => {
+        Some(Commands::Resume { .. }) | None => {
             if !cli.no_tui {
                 // Run in TUI mode (default)
                 // Initialize terminal
@@ -606,6 +783,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
                 // Create TUI app
                 let mut tui_app = TuiApp::new();
+                tui_app.set_cancel_token(agent.cancel_token());
 
                 // Add initial system message
                 tui_app.add_message(&Message {
@@ -617,9 +795,44 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                     tool_call_id: None,
                 });
 
+                // Show the conversation so far when resuming
+                for message in resumed_messages
+                    .iter()
+                    .filter(|m| m.role == "user" || m.role == "assistant")
+                    .filter(|m| m.content.as_deref().is_some_and(|c| !c.trim().is_empty()))
+                {
+                    tui_app.add_message(message);
+                }
+
                 // Run TUI loop
                 loop {
                     match tui_app.run(&mut terminal).await {
+                        Ok(Some(input)) if input.trim() == "/compact" => {
+                            tui_app.set_processing(true);
+                            terminal.draw(|f| tui_app.draw(f))?;
+                            let text = match agent.compact().await {
+                                Ok((before, after)) => format!(
+                                    "🗜️ Compacted conversation history (~{before} → ~{after} tokens)"
+                                ),
+                                Err(e) => format!("❌ Failed to compact history: {e}"),
+                            };
+                            tui_app.set_processing(false);
+                            tui_app.add_message(&Message {
+                                role: "system".to_string(),
+                                content: Some(text),
+                                tool_calls: None,
+                                tool_call_id: None,
+                            });
+                        }
+                        Ok(Some(input)) if input.trim().starts_with("/mode") => {
+                            let text = switch_mode(&agent, &input);
+                            tui_app.add_message(&Message {
+                                role: "system".to_string(),
+                                content: Some(text),
+                                tool_calls: None,
+                                tool_call_id: None,
+                            });
+                        }
                         Ok(Some(input)) => {
                             // Add user message to TUI
                             tui_app.add_message(&Message {
@@ -642,11 +855,26 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                             // Set up agent to send updates
                             agent.set_tui_sender(tx);
 
-                            // Process the prompt - agent will send updates through channel
-                            agent.process_prompt(&input, true).await;
+                            // Process the prompt - agent will send updates through channel,
+                            // shown live while the screen keeps redrawing
+                            {
+                                let processing = agent.process_prompt(&input, true);
+                                tokio::pin!(processing);
+                                loop {
+                                    tokio::select! {
+                                        _ = &mut processing => break,
+                                        _ = tokio::time::sleep(std::time::Duration::from_millis(50)) => {
+                                            tui_app.tick(&mut terminal)?;
+                                        }
+                                    }
+                                }
+                            }
 
                             // Clear the TUI sender so agent doesn't send updates when not in TUI
                             agent.set_tui_sender(tokio::sync::mpsc::unbounded_channel().0);
+
+                            // Approval prompts drew over the screen; repaint all of it
+                            terminal.clear()?;
                         }
                         Ok(None) => {
                             // User quit TUI
@@ -667,6 +895,14 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 println!("Goodbye!");
             } else {
                 // Standard interactive mode
+                if let Some(last) = resumed_messages
+                    .iter()
+                    .rev()
+                    .find(|m| m.role == "assistant" && m.content.is_some())
+                {
+                    println!("💬 {}", last.content.as_deref().unwrap_or_default());
+                }
+                cancel_on_ctrl_c(&agent);
                 let stdin = io::stdin();
                 loop {
                     print!("You: ");
@@ -680,6 +916,21 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                         break;
                     }
 
+                    if user_input == "/compact" {
+                        match agent.compact().await {
+                            Ok((before, after)) => println!(
+                                "🗜️  Compacted conversation history (~{before} → ~{after} tokens)"
+                            ),
+                            Err(e) => eprintln!("❌ Failed to compact history: {e}"),
+                        }
+                        continue;
+                    }
+
+                    if user_input.starts_with("/mode") {
+                        println!("{}", switch_mode(&agent, user_input));
+                        continue;
+                    }
+
                     agent.process_prompt(user_input, true).await;
                 }
             }
@@ -688,6 +939,63 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+/// Handle `/mode [plan|ask|accept-edits|bypass]`: show or change the
+/// permission mode
+fn switch_mode(agent: &GrokAgent, input: &str) -> String {
+    let permissions = agent.permissions();
+    let arg = input.trim().trim_start_matches("/mode").trim();
+    if arg.is_empty() {
+        let mode = permissions.mode();
+        let modes: Vec<String> = PermissionMode::ALL.iter().map(|m| m.to_string()).collect();
+        return format!(
+            "🔐 Permission mode: {mode} ({}). Available: {}",
+            mode.description(),
+            modes.join(", ")
+        );
+    }
+    match arg.parse::<PermissionMode>() {
+        Ok(mode) => {
+            permissions.set_mode(mode);
+            format!("🔐 Permission mode set to {mode} ({})", mode.description())
+        }
+        Err(e) => format!("❌ {e}"),
+    }
+}
+
+/// Shell commands run in their own process group and don't see the terminal's
+/// Ctrl-C, so the first press cancels the running tools and a second one quits
+fn cancel_on_ctrl_c(agent: &GrokAgent) {
+    let cancel = agent.cancel_token();
+    let processes = agent.processes();
+    tokio::spawn(async move {
+        while tokio::signal::ctrl_c().await.is_ok() {
+            if cancel.cancel() {
+                // Exiting skips destructors, so stop background processes here
+                processes.kill_all();
+                std::process::exit(130);
+            }
+            eprintln!("\n⛔ Cancelling... press Ctrl-C again to quit");
+        }
+    });
+}
+
+/// Print the session's token usage to stderr so it doesn't mix with the answer
+fn print_stats(agent: &GrokAgent) {
+    let usage = agent.usage();
+    eprintln!();
+    if usage.requests == 0 {
+        eprintln!("📊 Usage: not reported by the provider");
+        return;
+    }
+    eprintln!("📊 Usage: {usage}");
+    if usage.cost.is_none() {
+        eprintln!(
+            "   {}",
+            "Add a [pricing.\"<model>\"] table to the config to see the cost".dimmed()
+        );
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
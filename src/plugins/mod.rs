@@ -29,7 +29,7 @@
 //! ```
 
 use crate::error::{GrokError, Result};
-use crate::tools::{Tool, ToolContext};
+use crate::tools::{PolicyAction, Tool, ToolContext};
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value as JsonValue};
 use std::collections::HashMap;
@@ -98,9 +98,31 @@ impl Tool for PluginTool {
     }
 
     fn execute(&self, args: &JsonValue, context: &ToolContext<'_>) -> String {
-        // Confirm action if needed
-        if !context.confirm_action(&format!("execute plugin '{}'", self.config.name)) {
-            return format!("Plugin '{}' execution not confirmed.", self.config.name);
+        let argv: Vec<String> = self
+            .config
+            .command
+            .split_whitespace()
+            .map(String::from)
+            .collect();
+
+        // The command policy decides whether to run, ask or refuse
+        let decision = context.policy.check_argv(&argv, &context.project_root);
+        match decision.action {
+            PolicyAction::Allow => {}
+            PolicyAction::Deny => {
+                return format!(
+                    "Error: Plugin '{}' blocked by command policy: {}",
+                    self.config.name, decision.reason
+                );
+            }
+            PolicyAction::Ask => {
+                if !context.confirm_action(&format!(
+                    "execute plugin '{}' ({})",
+                    self.config.name, decision.reason
+                )) {
+                    return format!("Plugin '{}' execution not confirmed.", self.config.name);
+                }
+            }
         }
 
         if context.dry_run {
@@ -111,18 +133,12 @@ impl Tool for PluginTool {
         }
 
         // Prepare command
-        let mut parts = self.config.command.split_whitespace();
-        let program = match parts.next() {
-            Some(p) => p,
-            None => return "Error: Plugin command is empty".to_string(),
+        let Some((program, rest)) = argv.split_first() else {
+            return "Error: Plugin command is empty".to_string();
         };
 
         let mut cmd = Command::new(program);
-
-        // Add remaining arguments
-        for arg in parts {
-            cmd.arg(arg);
-        }
+        cmd.args(rest);
 
         // Set working directory
         if let Some(ref dir) = self.config.working_dir {
@@ -0,0 +1,404 @@
+//! Conversation persistence
+//!
+//! Each conversation is stored as a JSONL file under a per-project directory.
+//! The first line holds the session metadata (provider, model, codebase
+//! summary) and every following line is one [`Message`] of the history, so
+//! new messages can be appended as the conversation grows.
+
+use crate::api::Message;
+use crate::error::{GrokError, Result};
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+/// Metadata recorded at the start of every session file
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SessionMeta {
+    pub id: String,
+    pub provider: String,
+    pub model: String,
+    pub project_root: PathBuf,
+    pub codebase_summary: String,
+    pub created_at: DateTime<Local>,
+}
+
+/// One line of a session file
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum SessionRecord {
+    Meta(SessionMeta),
+    Message(Message),
+}
+
+/// A fully loaded session
+#[derive(Debug)]
+pub struct SavedSession {
+    pub meta: SessionMeta,
+    pub messages: Vec<Message>,
+}
+
+/// Short description of a stored session for listings
+#[derive(Debug)]
+pub struct SessionSummary {
+    pub meta: SessionMeta,
+    pub message_count: usize,
+    pub updated_at: DateTime<Local>,
+    pub first_prompt: Option<String>,
+}
+
+/// Stores sessions for a single project
+pub struct SessionStore {
+    dir: PathBuf,
+}
+
+impl SessionStore {
+    /// Create a store for the given project under the default data directory
+    pub fn new(project_root: &Path) -> Self {
+        let base = std::env::var("GROK_SESSION_DIR")
+            .map(PathBuf::from)
+            .unwrap_or_else(|_| {
+                dirs::data_local_dir()
+                    .unwrap_or_else(std::env::temp_dir)
+                    .join("grok-code")
+                    .join("sessions")
+            });
+        Self::with_dir(base.join(Self::project_key(project_root)))
+    }
+
+    /// Create a store backed by an explicit directory
+    pub fn with_dir(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    /// Directory holding this project's session files
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Stable name for a project: its folder name plus a path hash. Names
+    /// the project's session directory and its approvals file.
+    pub fn project_key(project_root: &Path) -> String {
+        use sha2::{Digest, Sha256};
+
+        let canonical = project_root
+            .canonicalize()
+            .unwrap_or_else(|_| project_root.to_path_buf());
+        let hash = format!(
+            "{:x}",
+            Sha256::digest(canonical.to_string_lossy().as_bytes())
+        );
+        let name = canonical
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("project");
+        format!("{name}-{}", &hash[..12])
+    }
+
+    fn session_path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{id}.jsonl"))
+    }
+
+    /// Start a new session file and return a writer for it
+    pub fn create(&self, mut meta: SessionMeta) -> Result<SessionWriter> {
+        fs::create_dir_all(&self.dir)?;
+
+        if meta.id.is_empty() {
+            meta.id = self.generate_id();
+        }
+        let path = self.session_path(&meta.id);
+        let mut file = File::create(&path)?;
+        writeln!(
+            file,
+            "{}",
+            serde_json::to_string(&SessionRecord::Meta(meta))?
+        )?;
+
+        Ok(SessionWriter { path })
+    }
+
+    /// Open an existing session for appending
+    pub fn open(&self, id: &str) -> Result<SessionWriter> {
+        let path = self.session_path(id);
+        if !path.exists() {
+            return Err(GrokError::FileNotFound(format!("Session not found: {id}")));
+        }
+        Ok(SessionWriter { path })
+    }
+
+    /// Generate a sortable, unique session id
+    fn generate_id(&self) -> String {
+        let base = Local::now().format("%Y%m%d-%H%M%S").to_string();
+        let mut id = base.clone();
+        let mut suffix = 1;
+        while self.session_path(&id).exists() {
+            suffix += 1;
+            id = format!("{base}-{suffix}");
+        }
+        id
+    }
+
+    /// Load a session by id
+    pub fn load(&self, id: &str) -> Result<SavedSession> {
+        let path = self.session_path(id);
+        let file = File::open(&path).map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => {
+                GrokError::FileNotFound(format!("Session not found: {id}"))
+            }
+            _ => GrokError::Io(e),
+        })?;
+
+        let mut meta = None;
+        let mut messages = Vec::new();
+        for line in BufReader::new(file).lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str::<SessionRecord>(&line) {
+                Ok(SessionRecord::Meta(m)) => meta = Some(m),
+                Ok(SessionRecord::Message(m)) => messages.push(m),
+                // A partially written last line should not lose the session
+                Err(_) => continue,
+            }
+        }
+
+        let meta = meta.ok_or_else(|| {
+            GrokError::InvalidInput(format!("Session file has no metadata: {}", path.display()))
+        })?;
+        Ok(SavedSession { meta, messages })
+    }
+
+    /// List all sessions for this project, most recently updated first
+    pub fn list(&self) -> Result<Vec<SessionSummary>> {
+        if !self.dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut sessions = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("jsonl") {
+                continue;
+            }
+            let Some(id) = path.file_stem().and_then(|s| s.to_str()) else {
+                continue;
+            };
+            let Ok(saved) = self.load(id) else {
+                continue;
+            };
+
+            let updated_at = fs::metadata(&path)
+                .and_then(|m| m.modified())
+                .map(DateTime::<Local>::from)
+                .unwrap_or(saved.meta.created_at);
+            let first_prompt = saved
+                .messages
+                .iter()
+                .find(|m| m.role == "user")
+                .and_then(|m| m.content.clone());
+
+            sessions.push(SessionSummary {
+                message_count: saved.messages.len(),
+                meta: saved.meta,
+                updated_at,
+                first_prompt,
+            });
+        }
+
+        sessions.sort_by_key(|s| std::cmp::Reverse(s.updated_at));
+        Ok(sessions)
+    }
+
+    /// The most recently updated session, if any
+    pub fn latest(&self) -> Result<Option<SessionSummary>> {
+        Ok(self.list()?.into_iter().next())
+    }
+}
+
+/// Appends messages to a session file
+pub struct SessionWriter {
+    path: PathBuf,
+}
+
+impl SessionWriter {
+    /// Session id (the file stem)
+    pub fn id(&self) -> &str {
+        self.path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or_default()
+    }
+
+    /// Append messages to the session
+    pub fn append(&self, messages: &[Message]) -> Result<()> {
+        if messages.is_empty() {
+            return Ok(());
+        }
+
+        let mut file = OpenOptions::new().append(true).open(&self.path)?;
+        let mut buffer = String::new();
+        for message in messages {
+            buffer.push_str(&serde_json::to_string(&SessionRecord::Message(
+                message.clone(),
+            ))?);
+            buffer.push('\n');
+        }
+        file.write_all(buffer.as_bytes())?;
+        Ok(())
+    }
+
+    /// Replace the stored history, keeping the session metadata
+    ///
+    /// Used after the history has been compacted, since the file can no longer
+    /// be extended by appending.
+    pub fn rewrite(&self, messages: &[Message]) -> Result<()> {
+        let meta_line = BufReader::new(File::open(&self.path)?)
+            .lines()
+            .next()
+            .transpose()?
+            .unwrap_or_default();
+
+        let mut buffer = meta_line;
+        buffer.push('\n');
+        for message in messages {
+            buffer.push_str(&serde_json::to_string(&SessionRecord::Message(
+                message.clone(),
+            ))?);
+            buffer.push('\n');
+        }
+
+        // Write to a temporary file first so a crash can't lose the session
+        let tmp_path = self.path.with_extension("jsonl.tmp");
+        fs::write(&tmp_path, buffer)?;
+        fs::rename(&tmp_path, &self.path)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn meta(id: &str) -> SessionMeta {
+        SessionMeta {
+            id: id.to_string(),
+            provider: "xai".to_string(),
+            model: "grok-4-0709".to_string(),
+            project_root: PathBuf::from("/project"),
+            codebase_summary: "Project structure:\n- src/main.rs\n".to_string(),
+            created_at: Local::now(),
+        }
+    }
+
+    fn message(role: &str, content: &str) -> Message {
+        Message {
+            role: role.to_string(),
+            content: Some(content.to_string()),
+            tool_calls: None,
+            tool_call_id: None,
+        }
+    }
+
+    #[test]
+    fn test_session_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = SessionStore::with_dir(temp_dir.path().to_path_buf());
+
+        let writer = store.create(meta("")).unwrap();
+        writer
+            .append(&[message("system", "sys"), message("user", "hello")])
+            .unwrap();
+        writer.append(&[message("assistant", "hi there")]).unwrap();
+
+        let saved = store.load(writer.id()).unwrap();
+        assert_eq!(saved.meta.provider, "xai");
+        assert_eq!(saved.meta.model, "grok-4-0709");
+        assert!(saved.meta.codebase_summary.contains("src/main.rs"));
+        assert_eq!(saved.messages.len(), 3);
+        assert_eq!(saved.messages[2].content.as_deref(), Some("hi there"));
+    }
+
+    #[test]
+    fn test_session_list_and_latest() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = SessionStore::with_dir(temp_dir.path().to_path_buf());
+        assert!(store.latest().unwrap().is_none());
+
+        let first = store.create(meta("first")).unwrap();
+        first.append(&[message("user", "first prompt")]).unwrap();
+        let second = store.create(meta("second")).unwrap();
+        second.append(&[message("user", "second prompt")]).unwrap();
+
+        let sessions = store.list().unwrap();
+        assert_eq!(sessions.len(), 2);
+        assert!(sessions.iter().any(|s| s.meta.id == "first"));
+        assert_eq!(
+            sessions
+                .iter()
+                .find(|s| s.meta.id == "second")
+                .unwrap()
+                .first_prompt
+                .as_deref(),
+            Some("second prompt")
+        );
+        assert!(store.latest().unwrap().is_some());
+    }
+
+    #[test]
+    fn test_load_missing_session() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = SessionStore::with_dir(temp_dir.path().to_path_buf());
+        assert!(matches!(
+            store.load("nope"),
+            Err(GrokError::FileNotFound(_))
+        ));
+        assert!(store.open("nope").is_err());
+    }
+
+    #[test]
+    fn test_load_skips_truncated_line() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = SessionStore::with_dir(temp_dir.path().to_path_buf());
+        let writer = store.create(meta("partial")).unwrap();
+        writer.append(&[message("user", "kept")]).unwrap();
+
+        let path = temp_dir.path().join("partial.jsonl");
+        let mut file = OpenOptions::new().append(true).open(path).unwrap();
+        write!(file, "{{\"type\":\"message\",\"role\":\"assis").unwrap();
+
+        let saved = store.load("partial").unwrap();
+        assert_eq!(saved.messages.len(), 1);
+    }
+
+    #[test]
+    fn test_rewrite_replaces_history() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = SessionStore::with_dir(temp_dir.path().to_path_buf());
+        let writer = store.create(meta("compacted")).unwrap();
+        writer
+            .append(&[message("user", "one"), message("assistant", "two")])
+            .unwrap();
+
+        writer.rewrite(&[message("system", "summary")]).unwrap();
+        writer.append(&[message("user", "three")]).unwrap();
+
+        let saved = store.load("compacted").unwrap();
+        assert_eq!(saved.meta.model, "grok-4-0709");
+        assert_eq!(saved.messages.len(), 2);
+        assert_eq!(saved.messages[0].content.as_deref(), Some("summary"));
+        assert!(!temp_dir.path().join("compacted.jsonl.tmp").exists());
+    }
+
+    #[test]
+    fn test_project_key_is_stable() {
+        let a = SessionStore::project_key(Path::new("/some/project"));
+        let b = SessionStore::project_key(Path::new("/some/project"));
+        let c = SessionStore::project_key(Path::new("/other/project"));
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+        assert!(a.starts_with("project-"));
+    }
+}
@@ -0,0 +1,425 @@
+//! Markdown to Atlassian Document Format
+//!
+//! Jira Cloud's v3 API only accepts rich text as ADF, a JSON document tree.
+//! This covers the markdown an issue description usually has: headings,
+//! paragraphs, nested bullet and numbered lists, fenced code, block quotes,
+//! rules, and inline bold, italic, strikethrough, code and links.
+
+use serde_json::{json, Value as JsonValue};
+
+/// Convert `markdown` to an ADF document
+pub fn markdown_to_adf(markdown: &str) -> JsonValue {
+    let lines: Vec<&str> = markdown.lines().collect();
+    json!({"type": "doc", "version": 1, "content": blocks(&lines)})
+}
+
+fn blocks(lines: &[&str]) -> Vec<JsonValue> {
+    let mut nodes = Vec::new();
+    let mut paragraph = Vec::new();
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i];
+        let trimmed = line.trim();
+        let block = if trimmed.is_empty() {
+            i += 1;
+            None
+        } else if let Some(fence) = code_fence(trimmed) {
+            let language = trimmed[fence.len()..].trim();
+            let mut code = Vec::new();
+            i += 1;
+            while i < lines.len() && !lines[i].trim_start().starts_with(fence) {
+                code.push(lines[i]);
+                i += 1;
+            }
+            // Skip the closing fence
+            i += 1;
+            Some(code_block(language, &code.join("\n")))
+        } else if let Some((level, text)) = heading(trimmed) {
+            i += 1;
+            Some(json!({"type": "heading", "attrs": {"level": level}, "content": inline(text)}))
+        } else if is_rule(trimmed) {
+            i += 1;
+            Some(json!({"type": "rule"}))
+        } else if trimmed.starts_with('>') {
+            let mut quoted = Vec::new();
+            while let Some(rest) = lines.get(i).and_then(|l| l.trim_start().strip_prefix('>')) {
+                quoted.push(rest.strip_prefix(' ').unwrap_or(rest));
+                i += 1;
+            }
+            Some(json!({"type": "blockquote", "content": non_empty(blocks(&quoted))}))
+        } else if let Some(marker) = list_marker(line) {
+            let (list, next) = list(lines, i, &marker);
+            i = next;
+            Some(list)
+        } else {
+            paragraph.push(trimmed);
+            i += 1;
+            continue;
+        };
+        flush_paragraph(&mut paragraph, &mut nodes);
+        nodes.extend(block);
+    }
+    flush_paragraph(&mut paragraph, &mut nodes);
+    nodes
+}
+
+fn flush_paragraph(lines: &mut Vec<&str>, nodes: &mut Vec<JsonValue>) {
+    if !lines.is_empty() {
+        nodes.push(paragraph(&lines.join(" ")));
+        lines.clear();
+    }
+}
+
+fn paragraph(text: &str) -> JsonValue {
+    json!({"type": "paragraph", "content": inline(text)})
+}
+
+/// ADF containers need at least one child
+fn non_empty(nodes: Vec<JsonValue>) -> Vec<JsonValue> {
+    if nodes.is_empty() {
+        vec![paragraph("")]
+    } else {
+        nodes
+    }
+}
+
+fn code_fence(line: &str) -> Option<&'static str> {
+    ["```", "~~~"].into_iter().find(|f| line.starts_with(f))
+}
+
+fn code_block(language: &str, code: &str) -> JsonValue {
+    let mut node = json!({"type": "codeBlock"});
+    if !language.is_empty() {
+        node["attrs"] = json!({ "language": language });
+    }
+    if !code.is_empty() {
+        node["content"] = json!([{"type": "text", "text": code}]);
+    }
+    node
+}
+
+fn heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.len() - line.trim_start_matches('#').len();
+    let rest = &line[level..];
+    if !(1..=6).contains(&level) || !(rest.is_empty() || rest.starts_with([' ', '\t'])) {
+        return None;
+    }
+    Some((level, rest.trim().trim_end_matches('#').trim_end()))
+}
+
+/// `---`, `***` or `___`, possibly spaced out
+fn is_rule(line: &str) -> bool {
+    let chars: Vec<char> = line.chars().filter(|c| !c.is_whitespace()).collect();
+    chars.len() >= 3 && matches!(chars[0], '-' | '*' | '_') && chars.iter().all(|&c| c == chars[0])
+}
+
+/// A bullet (`-`, `*`, `+`) or number (`1.`, `1)`) starting a list item
+struct Marker {
+    indent: usize,
+    /// Column where the item's text starts
+    content: usize,
+    /// First number of a numbered list
+    start: Option<u64>,
+}
+
+fn list_marker(line: &str) -> Option<Marker> {
+    let indent = line.len() - line.trim_start().len();
+    let rest = &line[indent..];
+    let digits = rest.bytes().take_while(u8::is_ascii_digit).count();
+    let (start, width) =
+        if (1..10).contains(&digits) && matches!(rest.as_bytes().get(digits), Some(b'.' | b')')) {
+            (rest[..digits].parse().ok(), digits + 1)
+        } else if rest.starts_with(['-', '*', '+']) {
+            (None, 1)
+        } else {
+            return None;
+        };
+    let after = &rest[width..];
+    if !(after.is_empty() || after.starts_with([' ', '\t'])) {
+        return None;
+    }
+    let spaces = after.len() - after.trim_start().len();
+    let gap = if after.trim().is_empty() {
+        1
+    } else {
+        spaces.clamp(1, 4)
+    };
+    Some(Marker {
+        indent,
+        content: indent + width + gap,
+        start,
+    })
+}
+
+/// Does `line` start something other than a paragraph?
+fn starts_block(line: &str) -> bool {
+    let trimmed = line.trim();
+    code_fence(trimmed).is_some()
+        || heading(trimmed).is_some()
+        || is_rule(trimmed)
+        || trimmed.starts_with('>')
+        || list_marker(line).is_some()
+}
+
+/// Parse the list starting at `lines[i]`; returns it and the index after it
+fn list(lines: &[&str], mut i: usize, first: &Marker) -> (JsonValue, usize) {
+    let ordered = first.start.is_some();
+    let sibling = |line: &str| {
+        list_marker(line)
+            .filter(|m| m.indent < first.content && m.start.is_some() == ordered)
+            .map(|m| m.content)
+    };
+
+    let mut items = Vec::new();
+    while let Some(content) = lines.get(i).and_then(|l| sibling(l)) {
+        let mut body = vec![lines[i].get(content..).unwrap_or("").trim_end()];
+        i += 1;
+        while i < lines.len() {
+            let line = lines[i];
+            let indent = line.len() - line.trim_start().len();
+            if line.trim().is_empty() {
+                // Blank lines belong to the item if indented text follows
+                let next = lines[i..].iter().position(|l| !l.trim().is_empty());
+                match next.map(|n| lines[i + n]) {
+                    Some(l) if l.len() - l.trim_start().len() >= content => {
+                        body.push("");
+                        i += 1;
+                    }
+                    _ => break,
+                }
+            } else if indent >= content {
+                body.push(line.get(content..).unwrap_or(line.trim_start()));
+                i += 1;
+            } else if !starts_block(line) && body.last().is_some_and(|l| !l.is_empty()) {
+                // A paragraph continues without indentation
+                body.push(line.trim());
+                i += 1;
+            } else {
+                break;
+            }
+        }
+
+        let mut content = non_empty(blocks(&body));
+        if !matches!(content[0]["type"].as_str(), Some("paragraph" | "codeBlock")) {
+            content.insert(0, paragraph(""));
+        }
+        items.push(json!({"type": "listItem", "content": content}));
+
+        // Blank lines between items don't end the list
+        let next = lines[i..].iter().position(|l| !l.trim().is_empty());
+        match next {
+            Some(n) if n > 0 && sibling(lines[i + n]).is_some() => i += n,
+            _ => {}
+        }
+    }
+
+    let mut node = json!({
+        "type": if ordered { "orderedList" } else { "bulletList" },
+        "content": items,
+    });
+    if let Some(start) = first.start.filter(|&s| s != 1) {
+        node["attrs"] = json!({ "order": start });
+    }
+    (node, i)
+}
+
+fn inline(text: &str) -> Vec<JsonValue> {
+    let mut nodes = Vec::new();
+    spans(text, &[], &mut nodes);
+    nodes
+}
+
+/// Append `text` to `nodes` as text with `marks`, merging with the previous
+/// node when its marks are the same
+fn push_text(nodes: &mut Vec<JsonValue>, text: &str, marks: &[JsonValue]) {
+    if text.is_empty() {
+        return;
+    }
+    let marks_value = JsonValue::from(marks.to_vec());
+    if let Some(last) = nodes.last_mut() {
+        let same_marks = match last.get("marks") {
+            Some(existing) => *existing == marks_value,
+            None => marks.is_empty(),
+        };
+        if last["type"] == "text" && same_marks {
+            let merged = format!("{}{text}", last["text"].as_str().unwrap_or_default());
+            last["text"] = merged.into();
+            return;
+        }
+    }
+    let mut node = json!({"type": "text", "text": text});
+    if !marks.is_empty() {
+        node["marks"] = marks_value;
+    }
+    nodes.push(node);
+}
+
+fn with_mark(marks: &[JsonValue], mark: JsonValue) -> Vec<JsonValue> {
+    let mut marks = marks.to_vec();
+    if !marks.iter().any(|m| m["type"] == mark["type"]) {
+        marks.push(mark);
+    }
+    marks
+}
+
+/// Emphasis delimiters and the marks they stand for, longest first
+const EMPHASIS: &[(&str, &str)] = &[
+    ("**", "strong"),
+    ("__", "strong"),
+    ("~~", "strike"),
+    ("*", "em"),
+    ("_", "em"),
+];
+
+fn spans(text: &str, marks: &[JsonValue], nodes: &mut Vec<JsonValue>) {
+    let mut plain = String::new();
+    let mut rest = text;
+    let mut prev: Option<char> = None;
+
+    'scan: while let Some(c) = rest.chars().next() {
+        if c == '\\' {
+            if let Some(next) = rest[1..].chars().next().filter(char::is_ascii_punctuation) {
+                plain.push(next);
+                rest = &rest[2..];
+                prev = Some(next);
+                continue;
+            }
+        }
+
+        if c == '`' {
+            let ticks = rest.len() - rest.trim_start_matches('`').len();
+            let fence = &rest[..ticks];
+            if let Some(end) = rest[ticks..].find(fence) {
+                let code = rest[ticks..ticks + end].trim();
+                if !code.is_empty() {
+                    push_text(nodes, &std::mem::take(&mut plain), marks);
+                    // Code can only be combined with links
+                    let links: Vec<JsonValue> = marks
+                        .iter()
+                        .filter(|m| m["type"] == "link")
+                        .cloned()
+                        .collect();
+                    push_text(nodes, code, &with_mark(&links, json!({"type": "code"})));
+                    rest = &rest[ticks + end + ticks..];
+                    prev = Some('`');
+                    continue;
+                }
+            }
+        }
+
+        if c == '[' {
+            if let Some((label, href, len)) = link(rest) {
+                push_text(nodes, &std::mem::take(&mut plain), marks);
+                let link = json!({"type": "link", "attrs": {"href": href}});
+                spans(label, &with_mark(marks, link), nodes);
+                rest = &rest[len..];
+                prev = Some(')');
+                continue;
+            }
+        }
+
+        for &(delim, mark) in EMPHASIS {
+            // Underscores inside words, as in snake_case, are not emphasis
+            let intraword = delim.starts_with('_') && prev.is_some_and(char::is_alphanumeric);
+            if !rest.starts_with(delim) || intraword {
+                continue;
+            }
+            if let Some(end) = closing(&rest[delim.len()..], delim) {
+                push_text(nodes, &std::mem::take(&mut plain), marks);
+                let inner = &rest[delim.len()..delim.len() + end];
+                spans(inner, &with_mark(marks, json!({ "type": mark })), nodes);
+                rest = &rest[2 * delim.len() + end..];
+                prev = delim.chars().next();
+                continue 'scan;
+            }
+        }
+
+        plain.push(c);
+        rest = &rest[c.len_utf8()..];
+        prev = Some(c);
+    }
+    push_text(nodes, &plain, marks);
+}
+
+/// Offset of the delimiter closing emphasis that opened just before `text`
+fn closing(text: &str, delim: &str) -> Option<usize> {
+    if text.starts_with(char::is_whitespace) {
+        return None;
+    }
+    let first = delim.chars().next()?;
+    let mut from = 0;
+    while let Some(found) = text[from..].find(delim) {
+        let at = from + found;
+        let run = text[at..].len() - text[at..].trim_start_matches(first).len();
+        let after = text[at + run..].chars().next();
+        let fits = at > 0
+            && !text[..at].ends_with(char::is_whitespace)
+            // A single `*` doesn't close on the first half of `**`
+            && (delim.len() > 1 || run == 1)
+            && !(first == '_' && after.is_some_and(char::is_alphanumeric));
+        if fits {
+            return Some(at);
+        }
+        from = at + run;
+    }
+    None
+}
+
+/// `[label](href)` at the start of `text`: the label, the target and the
+/// length of the whole link
+fn link(text: &str) -> Option<(&str, &str, usize)> {
+    let close = text.find("](")?;
+    let label = &text[1..close];
+    let end = close + 2 + text[close + 2..].find(')')?;
+    let href = text[close + 2..end].trim();
+    if label.contains(['[', ']']) || href.is_empty() || href.contains(char::is_whitespace) {
+        return None;
+    }
+    Some((label, href, end + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_markdown_to_adf() {
+        let doc = markdown_to_adf(
+            "## Steps\n\nRun `cargo test` with **all\nfeatures** in snake_case_dir, see [docs](https://example.com).\n\n1. First\n   - nested *item*\n\n2. Second\n\n```rust\nfn main() {}\n```\n\n> quoted\n\n---",
+        );
+        assert_eq!(
+            doc,
+            serde_json::json!({"type": "doc", "version": 1, "content": [
+                {"type": "heading", "attrs": {"level": 2}, "content": [{"type": "text", "text": "Steps"}]},
+                {"type": "paragraph", "content": [
+                    {"type": "text", "text": "Run "},
+                    {"type": "text", "text": "cargo test", "marks": [{"type": "code"}]},
+                    {"type": "text", "text": " with "},
+                    {"type": "text", "text": "all features", "marks": [{"type": "strong"}]},
+                    {"type": "text", "text": " in snake_case_dir, see "},
+                    {"type": "text", "text": "docs", "marks": [{"type": "link", "attrs": {"href": "https://example.com"}}]},
+                    {"type": "text", "text": "."}
+                ]},
+                {"type": "orderedList", "content": [
+                    {"type": "listItem", "content": [
+                        {"type": "paragraph", "content": [{"type": "text", "text": "First"}]},
+                        {"type": "bulletList", "content": [
+                            {"type": "listItem", "content": [{"type": "paragraph", "content": [
+                                {"type": "text", "text": "nested "},
+                                {"type": "text", "text": "item", "marks": [{"type": "em"}]}
+                            ]}]}
+                        ]}
+                    ]},
+                    {"type": "listItem", "content": [
+                        {"type": "paragraph", "content": [{"type": "text", "text": "Second"}]}
+                    ]}
+                ]},
+                {"type": "codeBlock", "attrs": {"language": "rust"}, "content": [{"type": "text", "text": "fn main() {}"}]},
+                {"type": "blockquote", "content": [
+                    {"type": "paragraph", "content": [{"type": "text", "text": "quoted"}]}
+                ]},
+                {"type": "rule"}
+            ]})
+        );
+    }
+}
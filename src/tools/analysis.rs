@@ -305,6 +305,7 @@ impl Tool for RunLint {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::tools::{CancelToken, ProcessTable};
     use std::path::PathBuf;
 
     fn create_test_context() -> ToolContext<'static> {
@@ -314,6 +315,14 @@ mod tests {
             no_confirm: true,
             git_repo: None,
             tui_mode: false,
+            cancel: CancelToken::default(),
+            output: None,
+            processes: ProcessTable::default(),
+            policy: Default::default(),
+            sandbox: None,
+            tool_name: String::new(),
+            permissions: Default::default(),
+            settings: Default::default(),
         }
     }
 
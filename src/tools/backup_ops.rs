@@ -38,7 +38,7 @@ impl Tool for ListBackups {
             Err(e) => return format!("Error: {e}"),
         };
 
-        let backup_manager = BackupManager::new(None);
+        let backup_manager = BackupManager::new(context.settings.backup_retention_days);
         match backup_manager.list_backups(&path) {
             Ok(backups) => {
                 if backups.is_empty() {
@@ -97,7 +97,7 @@ impl Tool for CleanBackups {
 
             // Clean backups for all files in the project
             let mut total_removed = 0;
-            let backup_manager = BackupManager::new(None);
+            let backup_manager = BackupManager::new(context.settings.backup_retention_days);
 
             for entry in walkdir::WalkDir::new(&context.project_root)
                 .into_iter()
@@ -131,7 +131,7 @@ impl Tool for CleanBackups {
                 return format!("Dry-run: Would clean old backups for {}", path.display());
             }
 
-            let backup_manager = BackupManager::new(None);
+            let backup_manager = BackupManager::new(context.settings.backup_retention_days);
             match backup_manager.cleanup_old_backups(&path) {
                 Ok(removed) => {
                     if removed.is_empty() {
@@ -1,7 +1,6 @@
+use super::jira::{JiraClient, NewIssue, DEFAULT_ISSUE_TYPE};
 use super::{Tool, ToolContext};
-use base64::{engine::general_purpose, Engine as _};
-use serde_json::{json, Value as JsonValue};
-use std::env;
+use serde_json::{json, Map, Value as JsonValue};
 use urlencoding::encode;
 
 /// Tool for web search
@@ -47,7 +46,7 @@ impl Tool for CreateJiraTicket {
     }
 
     fn description(&self) -> &'static str {
-        "Create a Jira ticket. Requires JIRA_API_KEY, JIRA_URL, JIRA_PROJECT env vars."
+        "Create a Jira issue and return its key and URL. The description may use markdown. Requires the [jira] url and project settings and the JIRA_API_KEY env var."
     }
 
     fn parameters(&self) -> JsonValue {
@@ -55,56 +54,77 @@ impl Tool for CreateJiraTicket {
             "type": "object",
             "properties": {
                 "summary": {"type": "string", "description": "Ticket summary."},
-                "description": {"type": "string", "description": "Ticket description."}
+                "description": {"type": "string", "description": "Ticket description in markdown."},
+                "issue_type": {"type": "string", "description": "Issue type, such as Bug, Task or Story. Defaults to the configured issue type, or Task."},
+                "labels": {"type": "array", "items": {"type": "string"}, "description": "Labels to add."},
+                "components": {"type": "array", "items": {"type": "string"}, "description": "Component names."},
+                "fields": {"type": "object", "description": "Other fields by id, e.g. {\"priority\": {\"name\": \"High\"}, \"customfield_10010\": 5}."}
             },
             "required": ["summary"]
         })
     }
 
     fn execute(&self, args: &JsonValue, context: &ToolContext<'_>) -> String {
-        let summary = args["summary"].as_str().unwrap_or("");
+        let summary = match args["summary"].as_str().map(str::trim) {
+            Some(s) if !s.is_empty() => s,
+            _ => return "Error: 'summary' parameter is required".to_string(),
+        };
         let description = args["description"].as_str().unwrap_or("");
-
-        let api_key = match env::var("JIRA_API_KEY") {
-            Ok(k) => k,
-            Err(_) => return "JIRA_API_KEY required.".to_string(),
+        let issue_type = match args["issue_type"].as_str().map(str::trim) {
+            Some(t) if !t.is_empty() => t.to_string(),
+            _ => context
+                .settings
+                .jira
+                .issue_type
+                .clone()
+                .filter(|t| !t.trim().is_empty())
+                .unwrap_or_else(|| DEFAULT_ISSUE_TYPE.to_string()),
         };
-
-        let jira_url = match env::var("JIRA_URL") {
-            Ok(u) => u,
-            Err(_) => return "JIRA_URL required (e.g., https://your.atlassian.net)".to_string(),
+        let strings = |key: &str| -> Vec<String> {
+            args[key]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|v| v.as_str())
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty())
+                .collect()
+        };
+        let labels = strings("labels");
+        let components = strings("components");
+        let fields = match &args["fields"] {
+            JsonValue::Null => Map::new(),
+            JsonValue::Object(fields) => fields.clone(),
+            _ => return "Error: 'fields' must be an object of field ids to values".to_string(),
         };
 
-        let project = match env::var("JIRA_PROJECT") {
-            Ok(p) => p,
-            Err(_) => return "JIRA_PROJECT required.".to_string(),
+        let client = match JiraClient::from_settings(&context.settings.jira) {
+            Ok(client) => client,
+            Err(e) => return format!("Error: {e}"),
         };
 
-        if !context.confirm_action("create Jira ticket") {
+        let action = format!(
+            "create Jira {issue_type} '{summary}' in project {}",
+            client.project()
+        );
+        if !context.confirm_action(&action) {
             return "Jira ticket creation not confirmed.".to_string();
         }
 
         if context.dry_run {
-            return "Dry-run: Would create Jira ticket.".to_string();
+            return format!("Dry-run: Would {action}.");
         }
 
-        let url = format!("{jira_url}/rest/api/3/issue");
-        let _ticket_body = json!({
-            "fields": {
-                "project": {"key": project},
-                "summary": summary,
-                "description": description,
-                "issuetype": {"name": "Task"}
-            }
-        });
-
-        let jira_email = env::var("JIRA_EMAIL").unwrap_or_else(|_| "user@email.com".to_string());
-        let _auth = general_purpose::STANDARD.encode(format!("{jira_email}:{api_key}"));
-
-        // TODO: Implement actual HTTP request to Jira API
-        // TODO: Add proper error handling for API failures
-        // TODO: Parse response and return ticket ID/URL
-        // TODO: Support custom issue types and fields
-        format!("Would create Jira ticket at {url} with summary: {summary}")
+        match client.create_issue(&NewIssue {
+            summary,
+            description,
+            issue_type: &issue_type,
+            labels: &labels,
+            components: &components,
+            fields: &fields,
+        }) {
+            Ok(issue) => format!("Created Jira issue {}: {}", issue.key, issue.url),
+            Err(e) => format!("Error creating Jira issue: {e}"),
+        }
     }
 }
@@ -1,9 +1,18 @@
-use super::utils::sanitize_path;
-use super::{Tool, ToolContext};
+use super::utils::{sanitize_path, unified_diff};
+use super::{ProposedEdit, Tool, ToolContext};
 use crate::backup::BackupManager;
+use ignore::WalkBuilder;
 use ropey::Rope;
 use serde_json::{json, Value as JsonValue};
-use std::fs::{self, File};
+use std::fs;
+use std::path::Path;
+
+/// Appended to results when the user rejected some hunks of a change
+pub(super) const PARTIAL_NOTE: &str =
+    "Only the hunks accepted by the user were applied; the rest were rejected.";
+
+/// Largest output `read_file` returns in one call
+pub const MAX_READ_BYTES: usize = 100_000;
 
 /// Tool for reading file contents
 pub struct ReadFile;
@@ -14,14 +23,17 @@ impl Tool for ReadFile {
     }
 
     fn description(&self) -> &'static str {
-        "Read the contents of a file at the given path."
+        "Read the contents of a file at the given path. Use 'offset' and 'limit' to read part of a large file, and 'line_numbers' to prefix each line with its 1-indexed number (as used by edit_file). Output is capped at about 100 KB; the result says how to read the rest. Binary files are described instead of shown."
     }
 
     fn parameters(&self) -> JsonValue {
         json!({
             "type": "object",
             "properties": {
-                "path": {"type": "string", "description": "The path to the file."}
+                "path": {"type": "string", "description": "The path to the file."},
+                "offset": {"type": "number", "description": "First line to read, 1-indexed (default: 1)."},
+                "limit": {"type": "number", "description": "Maximum number of lines to read (default: to the end of the file)."},
+                "line_numbers": {"type": "boolean", "description": "Prefix each line with its line number (default: false)."}
             },
             "required": ["path"]
         })
@@ -33,35 +45,134 @@ impl Tool for ReadFile {
             None => return "Error: 'path' parameter is required".to_string(),
         };
 
+        let offset = match args["offset"].as_u64() {
+            Some(0) => return "Error: 'offset' is 1-indexed and must be at least 1".to_string(),
+            Some(n) => usize::try_from(n).unwrap_or(usize::MAX),
+            None => 1,
+        };
+        let limit = args["limit"]
+            .as_u64()
+            .map(|n| usize::try_from(n).unwrap_or(usize::MAX));
+        let line_numbers = args["line_numbers"].as_bool().unwrap_or(false);
+
         let path = match sanitize_path(path_str, &context.project_root) {
             Ok(p) => p,
             Err(e) => return format!("Error: {e}"),
         };
-        match fs::read_to_string(&path) {
-            Ok(content) => {
-                if std::env::var("DEBUG_API").is_ok() {
-                    use colored::*;
-                    eprintln!(
-                        "{}: Read file {} ({} bytes, {} lines)",
-                        "DEBUG".blue().bold(),
-                        path.display().to_string().cyan(),
-                        content.len().to_string().yellow(),
-                        content.lines().count().to_string().yellow()
-                    );
+        let bytes = match fs::read(&path) {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                return match e.kind() {
+                    std::io::ErrorKind::NotFound => {
+                        format!("Error: File not found: {}", path.display())
+                    }
+                    std::io::ErrorKind::PermissionDenied => {
+                        format!("Error: Permission denied reading file: {}", path.display())
+                    }
+                    _ => format!("Error reading file: {e}"),
                 }
-                content
             }
-            Err(e) => match e.kind() {
-                std::io::ErrorKind::NotFound => {
-                    format!("Error: File not found: {}", path.display())
-                }
-                std::io::ErrorKind::PermissionDenied => {
-                    format!("Error: Permission denied reading file: {}", path.display())
+        };
+
+        // NUL bytes near the start are the usual sign of a binary file
+        if bytes[..bytes.len().min(8192)].contains(&0) {
+            return format!(
+                "Binary file not shown: {} ({} bytes)",
+                path.display(),
+                bytes.len()
+            );
+        }
+        let content = match String::from_utf8(bytes) {
+            Ok(content) => content,
+            Err(e) => {
+                return format!(
+                    "File is not valid UTF-8 text and was not shown: {} ({} bytes, first invalid byte at offset {})",
+                    path.display(),
+                    e.as_bytes().len(),
+                    e.utf8_error().valid_up_to()
+                )
+            }
+        };
+
+        if std::env::var("DEBUG_API").is_ok() {
+            use colored::*;
+            eprintln!(
+                "{}: Read file {} ({} bytes, {} lines)",
+                "DEBUG".blue().bold(),
+                path.display().to_string().cyan(),
+                content.len().to_string().yellow(),
+                content.lines().count().to_string().yellow()
+            );
+        }
+
+        if offset == 1 && limit.is_none() && !line_numbers && content.len() <= MAX_READ_BYTES {
+            return content;
+        }
+        read_lines(&content, offset, limit, line_numbers)
+    }
+}
+
+/// Lines `offset..offset + limit` of `content`, cut off at [`MAX_READ_BYTES`]
+/// with a notice saying where to continue
+fn read_lines(content: &str, offset: usize, limit: Option<usize>, line_numbers: bool) -> String {
+    let lines: Vec<&str> = content.split_inclusive('\n').collect();
+    let total = lines.len();
+    if total == 0 {
+        return String::new();
+    }
+    if offset > total {
+        return format!("Error: offset {offset} is past the end of the file ({total} lines)");
+    }
+    let end = limit.map_or(total, |limit| {
+        offset.saturating_sub(1).saturating_add(limit).min(total)
+    });
+
+    let mut output = String::new();
+    let mut last = offset - 1;
+    let mut truncated = false;
+    for (i, line) in lines[offset - 1..end].iter().enumerate() {
+        let line = if line_numbers {
+            format!("{:>6}\t{line}", offset + i)
+        } else {
+            line.to_string()
+        };
+        if output.len() + line.len() > MAX_READ_BYTES {
+            truncated = true;
+            // A single huge line (minified code) is still shown in part
+            if output.is_empty() {
+                let mut cut = MAX_READ_BYTES;
+                while !line.is_char_boundary(cut) {
+                    cut -= 1;
                 }
-                _ => format!("Error reading file: {e}"),
-            },
+                output.push_str(&line[..cut]);
+                last = offset + i;
+            }
+            break;
         }
+        output.push_str(&line);
+        last = offset + i;
+    }
+
+    if !truncated && last == total {
+        return output;
+    }
+    if !output.ends_with('\n') {
+        output.push('\n');
     }
+    let reason = if truncated {
+        format!("Output truncated at {MAX_READ_BYTES} bytes. ")
+    } else {
+        String::new()
+    };
+    let next = if last < total {
+        format!(" Use offset={} to read more.", last + 1)
+    } else {
+        String::new()
+    };
+    output.push_str(&format!(
+        "[{reason}Showing lines {offset}-{last} of {total}.{next}]"
+    ));
+    output
 }
 
 /// Tool for writing file contents
@@ -104,7 +215,7 @@ impl Tool for WriteFile {
         };
 
         // Create backup if file exists
-        let backup_manager = BackupManager::new(None);
+        let backup_manager = BackupManager::new(context.settings.backup_retention_days);
         let backup_result = if path.exists() {
             match backup_manager.create_backup(&path) {
                 Ok(backup_path) => Some(backup_path),
@@ -114,9 +225,31 @@ impl Tool for WriteFile {
             None
         };
 
-        if !context.confirm_action(&format!("write to {}", path.display())) {
+        // Text files are reviewed as a diff; anything else is confirmed as a whole
+        let action = format!("write to {}", path.display());
+        let existing = if path.exists() {
+            fs::read_to_string(&path).ok()
+        } else {
+            Some(String::new())
+        };
+        let reviewed = match &existing {
+            Some(old) => context
+                .review_changes(
+                    &action,
+                    &[ProposedEdit {
+                        path: path_str,
+                        old,
+                        new: content,
+                    }],
+                )
+                .and_then(|mut contents| contents.pop()),
+            None => context.confirm_edit(&action).then(|| content.to_string()),
+        };
+        let Some(reviewed) = reviewed else {
             return "Write operation not confirmed.".to_string();
-        }
+        };
+        let partial = reviewed != content;
+        let content = reviewed.as_str();
 
         if context.dry_run {
             match backup_result {
@@ -140,12 +273,17 @@ impl Tool for WriteFile {
                             content.len().to_string().yellow()
                         );
                     }
-                    match backup_result {
+                    let written = match backup_result {
                         Some(backup_path) => format!(
                             "File written successfully (backed up to {}).",
                             backup_path.display()
                         ),
                         None => "File written successfully.".to_string(),
+                    };
+                    if partial {
+                        format!("{written} {PARTIAL_NOTE}")
+                    } else {
+                        written
                     }
                 }
                 Err(e) => match e.kind() {
@@ -169,7 +307,7 @@ impl Tool for EditFile {
     }
 
     fn description(&self) -> &'static str {
-        "Edit specific lines in a file. Creates timestamped backup with retention policy."
+        "Edit specific lines in a file. Prefer replace_in_file unless the exact line numbers are known. Creates timestamped backup with retention policy."
     }
 
     fn parameters(&self) -> JsonValue {
@@ -219,19 +357,60 @@ impl Tool for EditFile {
             Err(e) => return format!("Error: {e}"),
         };
 
-        let backup_manager = BackupManager::new(None);
-        let backup_path = if path.exists() {
-            match backup_manager.create_backup(&path) {
-                Ok(backup_path) => backup_path,
-                Err(e) => return format!("Error creating backup: {e}"),
+        let file_content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) => {
+                return match e.kind() {
+                    std::io::ErrorKind::NotFound => {
+                        format!("Error: File not found: {}", path.display())
+                    }
+                    std::io::ErrorKind::PermissionDenied => {
+                        format!("Error: Permission denied reading file: {}", path.display())
+                    }
+                    _ => format!("Error reading file: {e}"),
+                }
             }
+        };
+
+        let mut rope = Rope::from_str(&file_content);
+
+        // Check if line numbers are valid
+        let total_lines = rope.len_lines();
+        if start_line > total_lines {
+            return format!(
+                "Error: start_line {start_line} exceeds total lines in file ({total_lines})"
+            );
+        }
+
+        let start_char = if start_line > 1 {
+            rope.line_to_char(start_line - 1)
         } else {
-            return format!("Error: File not found: {}", path.display());
+            0
+        };
+        let end_char = rope.line_to_char(end_line.min(total_lines));
+        rope.remove(start_char..end_char);
+        rope.insert(start_char, new_content);
+        let edited = rope.to_string();
+
+        let backup_manager = BackupManager::new(context.settings.backup_retention_days);
+        let backup_path = match backup_manager.create_backup(&path) {
+            Ok(backup_path) => backup_path,
+            Err(e) => return format!("Error creating backup: {e}"),
         };
 
-        if !context.confirm_action(&format!("edit {}", path.display())) {
+        let reviewed = context
+            .review_changes(
+                &format!("edit {}", path.display()),
+                &[ProposedEdit {
+                    path: path_str,
+                    old: &file_content,
+                    new: &edited,
+                }],
+            )
+            .and_then(|mut contents| contents.pop());
+        let Some(reviewed) = reviewed else {
             return "Edit operation not confirmed.".to_string();
-        }
+        };
 
         if context.dry_run {
             return format!(
@@ -244,56 +423,263 @@ impl Tool for EditFile {
             );
         }
 
-        match fs::read_to_string(&path) {
-            Ok(file_content) => {
-                let mut rope = Rope::from_str(&file_content);
+        match fs::write(&path, &reviewed) {
+            Ok(_) if reviewed != edited => format!(
+                "File edited successfully (backed up to {}). {PARTIAL_NOTE}",
+                backup_path.display()
+            ),
+            Ok(_) => format!(
+                "File edited successfully (backed up to {}).",
+                backup_path.display()
+            ),
+            Err(e) => match e.kind() {
+                std::io::ErrorKind::PermissionDenied => format!(
+                    "Error: Permission denied writing to file: {}",
+                    path.display()
+                ),
+                _ => format!("Error writing file: {e}"),
+            },
+        }
+    }
+}
+
+/// Tool for replacing text in a file by matching it, not by line numbers
+pub struct ReplaceInFile;
 
-                // Check if line numbers are valid
-                let total_lines = rope.len_lines();
-                if start_line > total_lines {
-                    return format!(
-                        "Error: start_line {start_line} exceeds total lines in file ({total_lines})"
-                    );
-                }
+impl Tool for ReplaceInFile {
+    fn name(&self) -> &'static str {
+        "replace_in_file"
+    }
 
-                let start_char = if start_line > 1 {
-                    rope.line_to_char(start_line - 1)
-                } else {
-                    0
-                };
-                let end_char = rope.line_to_char(end_line.min(total_lines));
-                rope.remove(start_char..end_char);
-                rope.insert(start_char, new_content);
-
-                match File::create(&path) {
-                    Ok(mut file) => match rope.write_to(&mut file) {
-                        Ok(_) => format!(
-                            "File edited successfully (backed up to {}).",
-                            backup_path.display()
-                        ),
-                        Err(e) => format!("Error writing to file: {e}"),
-                    },
-                    Err(e) => match e.kind() {
-                        std::io::ErrorKind::PermissionDenied => {
-                            format!("Error: Permission denied creating file: {}", path.display())
-                        }
-                        _ => format!("Error creating file: {e}"),
-                    },
+    fn description(&self) -> &'static str {
+        "Replace text in a file. 'old_string' must match exactly one place in the file (include surrounding lines to make it unique) unless 'replace_all' is set. Differences in indentation and trailing whitespace are tolerated if there is no exact match. Returns a unified diff. Prefer this over edit_file. Creates timestamped backup with retention policy."
+    }
+
+    fn parameters(&self) -> JsonValue {
+        json!({
+            "type": "object",
+            "properties": {
+                "path": {"type": "string", "description": "The path to the file."},
+                "old_string": {"type": "string", "description": "The exact text to replace."},
+                "new_string": {"type": "string", "description": "The replacement text."},
+                "replace_all": {"type": "boolean", "description": "Replace every occurrence instead of requiring a unique match (default: false)."}
+            },
+            "required": ["path", "old_string", "new_string"]
+        })
+    }
+
+    fn execute(&self, args: &JsonValue, context: &ToolContext<'_>) -> String {
+        let path_str = match args["path"].as_str() {
+            Some(p) => p,
+            None => return "Error: 'path' parameter is required".to_string(),
+        };
+
+        let old_string = match args["old_string"].as_str() {
+            Some(s) if !s.is_empty() => s,
+            Some(_) => return "Error: 'old_string' must not be empty".to_string(),
+            None => return "Error: 'old_string' parameter is required".to_string(),
+        };
+
+        let new_string = match args["new_string"].as_str() {
+            Some(s) => s,
+            None => return "Error: 'new_string' parameter is required".to_string(),
+        };
+
+        let replace_all = args["replace_all"].as_bool().unwrap_or(false);
+
+        let path = match sanitize_path(path_str, &context.project_root) {
+            Ok(p) => p,
+            Err(e) => return format!("Error: {e}"),
+        };
+
+        let file_content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) => {
+                return match e.kind() {
+                    std::io::ErrorKind::NotFound => {
+                        format!("Error: File not found: {}", path.display())
+                    }
+                    std::io::ErrorKind::PermissionDenied => {
+                        format!("Error: Permission denied reading file: {}", path.display())
+                    }
+                    _ => format!("Error reading file: {e}"),
                 }
             }
+        };
+
+        let replacement = match replace_text(&file_content, old_string, new_string, replace_all) {
+            Ok(r) => r,
+            Err(e) => return format!("Error: {e}"),
+        };
+        if replacement.content == file_content {
+            return "Error: old_string and new_string are identical; nothing to change".to_string();
+        }
+
+        let diff = unified_diff(path_str, &file_content, &replacement.content);
+        let summary = format!(
+            "{} replacement{}{}",
+            replacement.count,
+            if replacement.count == 1 { "" } else { "s" },
+            if replacement.fuzzy {
+                " (matched ignoring whitespace differences)"
+            } else {
+                ""
+            }
+        );
+
+        let backup_manager = BackupManager::new(context.settings.backup_retention_days);
+        let backup_path = match backup_manager.create_backup(&path) {
+            Ok(backup_path) => backup_path,
+            Err(e) => return format!("Error creating backup: {e}"),
+        };
+
+        let reviewed = context
+            .review_changes(
+                &format!("edit {}", path.display()),
+                &[ProposedEdit {
+                    path: path_str,
+                    old: &file_content,
+                    new: &replacement.content,
+                }],
+            )
+            .and_then(|mut contents| contents.pop());
+        let Some(new_content) = reviewed else {
+            return "Edit operation not confirmed.".to_string();
+        };
+        let (diff, note) = if new_content == replacement.content {
+            (diff, String::new())
+        } else {
+            (
+                unified_diff(path_str, &file_content, &new_content),
+                format!(" {PARTIAL_NOTE}"),
+            )
+        };
+
+        if context.dry_run {
+            return format!(
+                "Dry-run: Would make {summary} in {} (backed up to {}):\n{diff}",
+                path.display(),
+                backup_path.display()
+            );
+        }
+
+        match fs::write(&path, &new_content) {
+            Ok(_) => format!(
+                "File edited successfully: {summary} (backed up to {}).{note}\n{diff}",
+                backup_path.display()
+            ),
             Err(e) => match e.kind() {
-                std::io::ErrorKind::NotFound => {
-                    format!("Error: File not found: {}", path.display())
-                }
-                std::io::ErrorKind::PermissionDenied => {
-                    format!("Error: Permission denied reading file: {}", path.display())
-                }
-                _ => format!("Error reading file: {e}"),
+                std::io::ErrorKind::PermissionDenied => format!(
+                    "Error: Permission denied writing to file: {}",
+                    path.display()
+                ),
+                _ => format!("Error writing file: {e}"),
             },
         }
     }
 }
 
+/// Result of applying a search/replace to file content
+pub(super) struct Replacement {
+    pub content: String,
+    pub count: usize,
+    /// Matched by the whitespace-tolerant fallback
+    pub fuzzy: bool,
+}
+
+/// Replace `old` with `new`, requiring a unique match unless `replace_all`
+pub(super) fn replace_text(
+    content: &str,
+    old: &str,
+    new: &str,
+    replace_all: bool,
+) -> Result<Replacement, String> {
+    let exact = content.matches(old).count();
+    if exact > 0 {
+        if exact > 1 && !replace_all {
+            return Err(format!(
+                "old_string matches {exact} places; include more surrounding context to make it unique, or set replace_all"
+            ));
+        }
+        return Ok(Replacement {
+            content: content.replace(old, new),
+            count: exact,
+            fuzzy: false,
+        });
+    }
+
+    // Fall back to comparing whole lines with surrounding whitespace ignored
+    let old_lines: Vec<&str> = old.trim_matches('\n').lines().collect();
+    let lines: Vec<&str> = content.split_inclusive('\n').collect();
+    if old_lines.iter().all(|l| l.trim().is_empty()) || old_lines.len() > lines.len() {
+        return Err("old_string not found in file".to_string());
+    }
+
+    let matches: Vec<usize> = (0..=lines.len() - old_lines.len())
+        .filter(|&start| {
+            old_lines
+                .iter()
+                .zip(&lines[start..])
+                .all(|(o, l)| o.trim() == l.trim())
+        })
+        .collect();
+
+    match matches.len() {
+        0 => return Err("old_string not found in file (even ignoring whitespace)".to_string()),
+        n if n > 1 && !replace_all => {
+            return Err(format!(
+                "old_string matches {n} places when ignoring whitespace; include more surrounding context to make it unique, or set replace_all"
+            ))
+        }
+        _ => {}
+    }
+
+    let mut result = String::with_capacity(content.len());
+    let mut line = 0;
+    let mut count = 0;
+    for &start in &matches {
+        // Overlapping matches can only happen for repetitive text; skip them
+        if start < line {
+            continue;
+        }
+        result.extend(lines[line..start].iter().copied());
+        let matched = &lines[start..start + old_lines.len()];
+        result.push_str(&reindent(new, old_lines[0], matched[0]));
+        // Keep the line ending of the last replaced line
+        if matched[matched.len() - 1].ends_with('\n') && !new.ends_with('\n') {
+            result.push('\n');
+        }
+        line = start + old_lines.len();
+        count += 1;
+    }
+    result.extend(lines[line..].iter().copied());
+
+    Ok(Replacement {
+        content: result,
+        count,
+        fuzzy: true,
+    })
+}
+
+/// Shift `text` from the indentation of `old_first` to that of `file_first`
+fn reindent(text: &str, old_first: &str, file_first: &str) -> String {
+    let indent = |s: &str| s[..s.len() - s.trim_start().len()].to_string();
+    let (from, to) = (indent(old_first), indent(file_first));
+    if from == to {
+        return text.to_string();
+    }
+
+    text.split_inclusive('\n')
+        .map(|line| match line.strip_prefix(from.as_str()) {
+            Some(rest) if !line.trim().is_empty() => format!("{to}{rest}"),
+            _ => line.to_string(),
+        })
+        .collect()
+}
+
+/// Entries shown by a recursive `list_files` before it stops
+const MAX_TREE_ENTRIES: usize = 500;
+
 /// Tool for listing directory contents
 pub struct ListFiles;
 
@@ -303,14 +689,16 @@ impl Tool for ListFiles {
     }
 
     fn description(&self) -> &'static str {
-        "List contents of a directory."
+        "List contents of a directory. With 'recursive', show the whole tree (respecting .gitignore and skipping hidden files) down to 'max_depth' levels."
     }
 
     fn parameters(&self) -> JsonValue {
         json!({
             "type": "object",
             "properties": {
-                "path": {"type": "string", "description": "The directory path (defaults to '.')."}
+                "path": {"type": "string", "description": "The directory path (defaults to '.')."},
+                "recursive": {"type": "boolean", "description": "List subdirectories as an indented tree (default: false)."},
+                "max_depth": {"type": "number", "description": "Levels to descend when recursive (default: 3)."}
             },
             "required": []
         })
@@ -323,6 +711,14 @@ impl Tool for ListFiles {
             Err(e) => return format!("Error: {e}"),
         };
 
+        if args["recursive"].as_bool().unwrap_or(false) {
+            if !path.is_dir() {
+                return format!("Error: Directory not found: {}", path.display());
+            }
+            let max_depth = args["max_depth"].as_u64().map_or(3, |n| n.max(1) as usize);
+            return list_tree(&path, max_depth);
+        }
+
         match fs::read_dir(&path) {
             Ok(entries) => {
                 let mut files = Vec::new();
@@ -345,3 +741,30 @@ impl Tool for ListFiles {
         }
     }
 }
+
+/// Indented tree of `root`, directories marked with a trailing `/`
+fn list_tree(root: &Path, max_depth: usize) -> String {
+    let walker = WalkBuilder::new(root)
+        .require_git(false)
+        .max_depth(Some(max_depth))
+        .sort_by_file_name(|a, b| a.cmp(b))
+        .build();
+
+    let mut lines = Vec::new();
+    for entry in walker.flatten().filter(|e| e.depth() > 0) {
+        if lines.len() == MAX_TREE_ENTRIES {
+            lines.push(format!(
+                "[Listing stopped after {MAX_TREE_ENTRIES} entries. Use a smaller max_depth, a subdirectory, or find_files.]"
+            ));
+            break;
+        }
+        let is_dir = entry.file_type().is_some_and(|t| t.is_dir());
+        lines.push(format!(
+            "{}{}{}",
+            "  ".repeat(entry.depth() - 1),
+            entry.file_name().to_string_lossy(),
+            if is_dir { "/" } else { "" }
+        ));
+    }
+    lines.join("\n")
+}
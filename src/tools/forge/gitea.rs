@@ -0,0 +1,237 @@
+//! Gitea (and Forgejo) pull requests through the REST API (v1)
+
+use super::{string_at, Api, Forge, ForgeKind, NewPullRequest, PullRequest, ReviewComment};
+use crate::error::{GrokError, Result};
+use reqwest::Method;
+use serde_json::{json, Value as JsonValue};
+
+/// Client for one Gitea repository
+pub struct Gitea {
+    api: Api,
+    token: String,
+    repo: String,
+}
+
+impl Gitea {
+    pub fn new(api_url: &str, token: &str, repo: &str) -> Result<Self> {
+        let api = Api::new(
+            ForgeKind::Gitea,
+            api_url,
+            &[("authorization", format!("token {token}"))],
+        )?;
+        Ok(Self {
+            api,
+            token: token.to_string(),
+            repo: repo.to_string(),
+        })
+    }
+}
+
+impl Forge for Gitea {
+    fn kind(&self) -> ForgeKind {
+        ForgeKind::Gitea
+    }
+
+    fn repo(&self) -> &str {
+        &self.repo
+    }
+
+    fn token(&self) -> &str {
+        &self.token
+    }
+
+    fn create_pull_request(&self, pr: &NewPullRequest<'_>) -> Result<PullRequest> {
+        // Gitea marks work in progress by the title
+        let title = if pr.draft {
+            format!("WIP: {}", pr.title)
+        } else {
+            pr.title.to_string()
+        };
+        let response = self.api.send(
+            Method::POST,
+            &format!("/repos/{}/pulls", self.repo),
+            Some(json!({
+                "title": title,
+                "body": pr.body,
+                "head": pr.head,
+                "base": pr.base,
+            })),
+        )?;
+        pull_request(&response)
+    }
+
+    fn request_reviewers(&self, number: u64, reviewers: &[String]) -> Result<()> {
+        let (teams, users): (Vec<&String>, Vec<&String>) =
+            reviewers.iter().partition(|r| r.contains('/'));
+        let teams: Vec<&str> = teams.iter().filter_map(|t| t.rsplit('/').next()).collect();
+        self.api.send(
+            Method::POST,
+            &format!("/repos/{}/pulls/{number}/requested_reviewers", self.repo),
+            Some(json!({"reviewers": users, "team_reviewers": teams})),
+        )?;
+        Ok(())
+    }
+
+    fn add_labels(&self, number: u64, labels: &[String]) -> Result<()> {
+        // Labels are added by id
+        let existing = self
+            .api
+            .get(&format!("/repos/{}/labels?limit=50", self.repo))?;
+        let ids = labels
+            .iter()
+            .map(|name| {
+                existing
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .find(|label| label["name"].as_str() == Some(name.as_str()))
+                    .and_then(|label| label["id"].as_u64())
+                    .ok_or_else(|| GrokError::InvalidInput(format!("Gitea: no label named {name}")))
+            })
+            .collect::<Result<Vec<u64>>>()?;
+        self.api.send(
+            Method::POST,
+            &format!("/repos/{}/issues/{number}/labels", self.repo),
+            Some(json!({ "labels": ids })),
+        )?;
+        Ok(())
+    }
+
+    fn list_pull_requests(&self) -> Result<Vec<PullRequest>> {
+        let response = self.api.get(&format!(
+            "/repos/{}/pulls?state=open&sort=newest&limit=50",
+            self.repo
+        ))?;
+        response
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(pull_request)
+            .collect()
+    }
+
+    fn review_comments(&self, number: u64) -> Result<Vec<ReviewComment>> {
+        let reviews = self
+            .api
+            .get(&format!("/repos/{}/pulls/{number}/reviews", self.repo))?;
+        let mut found = Vec::new();
+        for review in reviews.as_array().into_iter().flatten() {
+            if !string_at(review, "/body").trim().is_empty() {
+                found.push(ReviewComment {
+                    author: string_at(review, "/user/login"),
+                    body: string_at(review, "/body"),
+                    path: None,
+                    line: None,
+                });
+            }
+            if review["comments_count"].as_u64().unwrap_or(0) == 0 {
+                continue;
+            }
+            let Some(id) = review["id"].as_u64() else {
+                continue;
+            };
+            let comments = self.api.get(&format!(
+                "/repos/{}/pulls/{number}/reviews/{id}/comments",
+                self.repo
+            ))?;
+            found.extend(
+                comments
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .map(|comment| ReviewComment {
+                        author: string_at(comment, "/user/login"),
+                        body: string_at(comment, "/body"),
+                        path: comment["path"].as_str().map(str::to_string),
+                        line: comment["position"].as_u64().filter(|&line| line > 0),
+                    }),
+            );
+        }
+        Ok(found)
+    }
+}
+
+fn pull_request(value: &JsonValue) -> Result<PullRequest> {
+    let number = value["number"]
+        .as_u64()
+        .ok_or_else(|| GrokError::api("Gitea response has no pull request number"))?;
+    let title = string_at(value, "/title");
+    Ok(PullRequest {
+        number,
+        draft: value["draft"].as_bool().unwrap_or(false)
+            || title.starts_with("WIP:")
+            || title.starts_with("[WIP]"),
+        title,
+        url: string_at(value, "/html_url"),
+        head: string_at(value, "/head/ref"),
+        base: string_at(value, "/base/ref"),
+        author: string_at(value, "/user/login"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::{Matcher, Server};
+
+    #[test]
+    fn test_pull_requests() {
+        let mut server = Server::new();
+        let created = server
+            .mock("POST", "/repos/team/app/pulls")
+            .match_header("authorization", "token secret")
+            .match_body(Matcher::PartialJson(json!({
+                "title": "WIP: Add feature",
+                "head": "feature",
+                "base": "main"
+            })))
+            .with_status(201)
+            .with_body(r#"{"number": 5, "title": "WIP: Add feature", "html_url": "https://gitea.example.com/team/app/pulls/5", "head": {"ref": "feature"}, "base": {"ref": "main"}, "user": {"login": "me"}}"#)
+            .create();
+        server
+            .mock("GET", "/repos/team/app/labels")
+            .match_query(Matcher::Any)
+            .with_body(r#"[{"id": 1, "name": "bug"}, {"id": 2, "name": "docs"}]"#)
+            .create();
+        let labelled = server
+            .mock("POST", "/repos/team/app/issues/5/labels")
+            .match_body(Matcher::Json(json!({"labels": [2]})))
+            .with_body("[]")
+            .create();
+        server
+            .mock("GET", "/repos/team/app/pulls/5/reviews")
+            .with_body(r#"[{"id": 9, "body": "Needs tests", "comments_count": 1, "user": {"login": "bob"}}]"#)
+            .create();
+        server
+            .mock("GET", "/repos/team/app/pulls/5/reviews/9/comments")
+            .with_body(r#"[{"body": "Off by one", "path": "src/lib.rs", "position": 30, "user": {"login": "bob"}}]"#)
+            .create();
+
+        let client = Gitea::new(&server.url(), "secret", "team/app").unwrap();
+        let pr = client
+            .create_pull_request(&NewPullRequest {
+                title: "Add feature",
+                body: "",
+                head: "feature",
+                base: "main",
+                draft: true,
+            })
+            .unwrap();
+        assert_eq!(pr.number, 5);
+        assert!(pr.draft);
+        created.assert();
+
+        client.add_labels(5, &["docs".to_string()]).unwrap();
+        labelled.assert();
+        let error = client.add_labels(5, &["nope".to_string()]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Invalid input: Gitea: no label named nope"
+        );
+
+        let comments = client.review_comments(5).unwrap();
+        assert_eq!(comments.len(), 2);
+        assert_eq!(comments[0].body, "Needs tests");
+        assert_eq!(comments[1].line, Some(30));
+    }
+}
@@ -0,0 +1,248 @@
+//! GitHub pull requests through the REST API
+
+use super::{string_at, Api, Forge, ForgeKind, NewPullRequest, PullRequest, ReviewComment};
+use crate::error::{GrokError, Result};
+use reqwest::Method;
+use serde_json::{json, Value as JsonValue};
+
+/// API root for github.com
+pub const DEFAULT_API_URL: &str = "https://api.github.com";
+
+/// Client for one GitHub repository
+pub struct GitHub {
+    api: Api,
+    token: String,
+    repo: String,
+}
+
+impl GitHub {
+    pub fn new(api_url: &str, token: &str, repo: &str) -> Result<Self> {
+        let api = Api::new(
+            ForgeKind::GitHub,
+            api_url,
+            &[
+                ("authorization", format!("Bearer {token}")),
+                ("accept", "application/vnd.github+json".to_string()),
+                ("x-github-api-version", "2022-11-28".to_string()),
+            ],
+        )?;
+        Ok(Self {
+            api,
+            token: token.to_string(),
+            repo: repo.to_string(),
+        })
+    }
+}
+
+impl Forge for GitHub {
+    fn kind(&self) -> ForgeKind {
+        ForgeKind::GitHub
+    }
+
+    fn repo(&self) -> &str {
+        &self.repo
+    }
+
+    fn token(&self) -> &str {
+        &self.token
+    }
+
+    fn create_pull_request(&self, pr: &NewPullRequest<'_>) -> Result<PullRequest> {
+        let response = self.api.send(
+            Method::POST,
+            &format!("/repos/{}/pulls", self.repo),
+            Some(json!({
+                "title": pr.title,
+                "body": pr.body,
+                "head": pr.head,
+                "base": pr.base,
+                "draft": pr.draft,
+            })),
+        )?;
+        pull_request(&response)
+    }
+
+    fn request_reviewers(&self, number: u64, reviewers: &[String]) -> Result<()> {
+        let (teams, users): (Vec<&String>, Vec<&String>) =
+            reviewers.iter().partition(|r| r.contains('/'));
+        let teams: Vec<&str> = teams.iter().filter_map(|t| t.rsplit('/').next()).collect();
+        self.api.send(
+            Method::POST,
+            &format!("/repos/{}/pulls/{number}/requested_reviewers", self.repo),
+            Some(json!({"reviewers": users, "team_reviewers": teams})),
+        )?;
+        Ok(())
+    }
+
+    fn add_labels(&self, number: u64, labels: &[String]) -> Result<()> {
+        self.api.send(
+            Method::POST,
+            &format!("/repos/{}/issues/{number}/labels", self.repo),
+            Some(json!({ "labels": labels })),
+        )?;
+        Ok(())
+    }
+
+    fn list_pull_requests(&self) -> Result<Vec<PullRequest>> {
+        let response = self.api.get(&format!(
+            "/repos/{}/pulls?state=open&per_page=100",
+            self.repo
+        ))?;
+        response
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(pull_request)
+            .collect()
+    }
+
+    fn review_comments(&self, number: u64) -> Result<Vec<ReviewComment>> {
+        let reviews = self.api.get(&format!(
+            "/repos/{}/pulls/{number}/reviews?per_page=100",
+            self.repo
+        ))?;
+        let comments = self.api.get(&format!(
+            "/repos/{}/pulls/{number}/comments?per_page=100",
+            self.repo
+        ))?;
+
+        let reviews = reviews
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter(|review| !string_at(review, "/body").trim().is_empty())
+            .map(|review| ReviewComment {
+                author: string_at(review, "/user/login"),
+                body: string_at(review, "/body"),
+                path: None,
+                line: None,
+            });
+        let comments = comments
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(|comment| ReviewComment {
+                author: string_at(comment, "/user/login"),
+                body: string_at(comment, "/body"),
+                path: comment["path"].as_str().map(str::to_string),
+                line: comment["line"]
+                    .as_u64()
+                    .or_else(|| comment["original_line"].as_u64()),
+            });
+        Ok(reviews.chain(comments).collect())
+    }
+}
+
+fn pull_request(value: &JsonValue) -> Result<PullRequest> {
+    let number = value["number"]
+        .as_u64()
+        .ok_or_else(|| GrokError::api("GitHub response has no pull request number"))?;
+    Ok(PullRequest {
+        number,
+        title: string_at(value, "/title"),
+        url: string_at(value, "/html_url"),
+        head: string_at(value, "/head/ref"),
+        base: string_at(value, "/base/ref"),
+        author: string_at(value, "/user/login"),
+        draft: value["draft"].as_bool().unwrap_or(false),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::{Matcher, Server};
+
+    #[test]
+    fn test_create_pull_request() {
+        let mut server = Server::new();
+        let created = server
+            .mock("POST", "/repos/octo/app/pulls")
+            .match_header("authorization", "Bearer secret")
+            .match_body(Matcher::PartialJson(json!({
+                "title": "Add feature",
+                "head": "feature",
+                "base": "main",
+                "draft": true
+            })))
+            .with_status(201)
+            .with_body(r#"{"number": 42, "html_url": "https://github.com/octo/app/pull/42", "draft": true}"#)
+            .create();
+        let rejected = server
+            .mock("POST", "/repos/octo/app/issues/42/labels")
+            .with_status(422)
+            .with_body(r#"{"message": "Validation Failed", "errors": [{"resource": "Label", "code": "invalid", "field": "name"}]}"#)
+            .create();
+
+        let client = GitHub::new(&server.url(), "secret", "octo/app").unwrap();
+        let pr = client
+            .create_pull_request(&NewPullRequest {
+                title: "Add feature",
+                body: "",
+                head: "feature",
+                base: "main",
+                draft: true,
+            })
+            .unwrap();
+        assert_eq!(pr.number, 42);
+        assert_eq!(pr.url, "https://github.com/octo/app/pull/42");
+        assert!(pr.draft);
+
+        let error = client.add_labels(42, &["???".to_string()]).unwrap_err();
+        assert!(matches!(error, GrokError::InvalidInput(_)));
+        assert_eq!(
+            error.to_string(),
+            "Invalid input: GitHub: Validation Failed: name invalid"
+        );
+
+        created.assert();
+        rejected.assert();
+    }
+
+    #[test]
+    fn test_list_and_review_comments() {
+        let mut server = Server::new();
+        server
+            .mock("GET", "/repos/octo/app/pulls")
+            .match_query(Matcher::UrlEncoded("state".into(), "open".into()))
+            .with_body(r#"[{"number": 7, "title": "Fix", "html_url": "u", "head": {"ref": "fix"}, "base": {"ref": "main"}, "user": {"login": "alice"}, "draft": false}]"#)
+            .create();
+        server
+            .mock("GET", "/repos/octo/app/pulls/7/reviews")
+            .match_query(Matcher::Any)
+            .with_body(r#"[{"user": {"login": "bob"}, "body": "Looks good", "state": "APPROVED"}, {"user": {"login": "carol"}, "body": "", "state": "COMMENTED"}]"#)
+            .create();
+        server
+            .mock("GET", "/repos/octo/app/pulls/7/comments")
+            .match_query(Matcher::Any)
+            .with_body(r#"[{"user": {"login": "carol"}, "body": "Typo", "path": "src/lib.rs", "line": 12}]"#)
+            .create();
+
+        let client = GitHub::new(&server.url(), "secret", "octo/app").unwrap();
+        let prs = client.list_pull_requests().unwrap();
+        assert_eq!(prs.len(), 1);
+        assert_eq!(
+            (prs[0].head.as_str(), prs[0].author.as_str()),
+            ("fix", "alice")
+        );
+
+        let comments = client.review_comments(7).unwrap();
+        assert_eq!(
+            comments,
+            [
+                ReviewComment {
+                    author: "bob".to_string(),
+                    body: "Looks good".to_string(),
+                    path: None,
+                    line: None,
+                },
+                ReviewComment {
+                    author: "carol".to_string(),
+                    body: "Typo".to_string(),
+                    path: Some("src/lib.rs".to_string()),
+                    line: Some(12),
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,258 @@
+//! GitLab merge requests through the REST API (v4)
+
+use super::{string_at, Api, Forge, ForgeKind, NewPullRequest, PullRequest, ReviewComment};
+use crate::error::{GrokError, Result};
+use reqwest::Method;
+use serde_json::{json, Value as JsonValue};
+use urlencoding::encode;
+
+/// Client for one GitLab project
+pub struct GitLab {
+    api: Api,
+    token: String,
+    project: String,
+}
+
+impl GitLab {
+    pub fn new(api_url: &str, token: &str, project: &str) -> Result<Self> {
+        let api = Api::new(
+            ForgeKind::GitLab,
+            api_url,
+            &[("private-token", token.to_string())],
+        )?;
+        Ok(Self {
+            api,
+            token: token.to_string(),
+            project: project.to_string(),
+        })
+    }
+
+    /// API path of the project's merge requests, or of merge request `iid`
+    fn merge_requests(&self, iid: Option<u64>) -> String {
+        let base = format!("/projects/{}/merge_requests", encode(&self.project));
+        match iid {
+            Some(iid) => format!("{base}/{iid}"),
+            None => base,
+        }
+    }
+
+    fn user_id(&self, username: &str) -> Result<u64> {
+        let users = self
+            .api
+            .get(&format!("/users?username={}", encode(username)))?;
+        users[0]["id"]
+            .as_u64()
+            .ok_or_else(|| GrokError::InvalidInput(format!("GitLab: no user named {username}")))
+    }
+}
+
+impl Forge for GitLab {
+    fn kind(&self) -> ForgeKind {
+        ForgeKind::GitLab
+    }
+
+    fn repo(&self) -> &str {
+        &self.project
+    }
+
+    fn token(&self) -> &str {
+        &self.token
+    }
+
+    fn create_pull_request(&self, pr: &NewPullRequest<'_>) -> Result<PullRequest> {
+        // GitLab marks drafts by their title
+        let title = if pr.draft {
+            format!("Draft: {}", pr.title)
+        } else {
+            pr.title.to_string()
+        };
+        let response = self.api.send(
+            Method::POST,
+            &self.merge_requests(None),
+            Some(json!({
+                "title": title,
+                "description": pr.body,
+                "source_branch": pr.head,
+                "target_branch": pr.base,
+            })),
+        )?;
+        merge_request(&response)
+    }
+
+    fn request_reviewers(&self, number: u64, reviewers: &[String]) -> Result<()> {
+        if let Some(group) = reviewers.iter().find(|r| r.contains('/')) {
+            return Err(GrokError::InvalidInput(format!(
+                "GitLab can't request reviews from a group ({group}); name the users"
+            )));
+        }
+        let ids = reviewers
+            .iter()
+            .map(|name| self.user_id(name.trim_start_matches('@')))
+            .collect::<Result<Vec<u64>>>()?;
+        self.api.send(
+            Method::PUT,
+            &self.merge_requests(Some(number)),
+            Some(json!({ "reviewer_ids": ids })),
+        )?;
+        Ok(())
+    }
+
+    fn add_labels(&self, number: u64, labels: &[String]) -> Result<()> {
+        self.api.send(
+            Method::PUT,
+            &self.merge_requests(Some(number)),
+            Some(json!({ "add_labels": labels.join(",") })),
+        )?;
+        Ok(())
+    }
+
+    fn list_pull_requests(&self) -> Result<Vec<PullRequest>> {
+        let response = self.api.get(&format!(
+            "{}?state=opened&per_page=100",
+            self.merge_requests(None)
+        ))?;
+        response
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(merge_request)
+            .collect()
+    }
+
+    fn review_comments(&self, number: u64) -> Result<Vec<ReviewComment>> {
+        let notes = self.api.get(&format!(
+            "{}/notes?sort=asc&order_by=created_at&per_page=100",
+            self.merge_requests(Some(number))
+        ))?;
+        Ok(notes
+            .as_array()
+            .into_iter()
+            .flatten()
+            // System notes record events such as pushes, not reviews
+            .filter(|note| !note["system"].as_bool().unwrap_or(false))
+            .map(|note| ReviewComment {
+                author: string_at(note, "/author/username"),
+                body: string_at(note, "/body"),
+                path: note
+                    .pointer("/position/new_path")
+                    .and_then(JsonValue::as_str)
+                    .map(str::to_string),
+                line: note.pointer("/position/new_line").and_then(JsonValue::as_u64),
+            })
+            .collect())
+    }
+}
+
+fn merge_request(value: &JsonValue) -> Result<PullRequest> {
+    let iid = value["iid"]
+        .as_u64()
+        .ok_or_else(|| GrokError::api("GitLab response has no merge request iid"))?;
+    Ok(PullRequest {
+        number: iid,
+        title: string_at(value, "/title"),
+        url: string_at(value, "/web_url"),
+        head: string_at(value, "/source_branch"),
+        base: string_at(value, "/target_branch"),
+        author: string_at(value, "/author/username"),
+        draft: value["draft"]
+            .as_bool()
+            .or_else(|| value["work_in_progress"].as_bool())
+            .unwrap_or(false),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::{Matcher, Server};
+
+    #[test]
+    fn test_merge_requests() {
+        let mut server = Server::new();
+        let created = server
+            .mock("POST", "/projects/group%2Fapp/merge_requests")
+            .match_header("private-token", "secret")
+            .match_body(Matcher::PartialJson(json!({
+                "title": "Draft: Add feature",
+                "source_branch": "feature",
+                "target_branch": "main"
+            })))
+            .with_status(201)
+            .with_body(r#"{"iid": 3, "title": "Draft: Add feature", "web_url": "https://gitlab.example.com/group/app/-/merge_requests/3", "source_branch": "feature", "target_branch": "main", "author": {"username": "me"}, "draft": true}"#)
+            .create();
+        server
+            .mock("GET", "/users")
+            .match_query(Matcher::UrlEncoded("username".into(), "alice".into()))
+            .with_body(r#"[{"id": 11, "username": "alice"}]"#)
+            .create();
+        server
+            .mock("GET", "/users")
+            .match_query(Matcher::UrlEncoded("username".into(), "nobody".into()))
+            .with_body("[]")
+            .create();
+        let reviewers = server
+            .mock("PUT", "/projects/group%2Fapp/merge_requests/3")
+            .match_body(Matcher::Json(json!({"reviewer_ids": [11]})))
+            .with_body("{}")
+            .create();
+        server
+            .mock("GET", "/projects/group%2Fapp/merge_requests/3/notes")
+            .match_query(Matcher::Any)
+            .with_body(r#"[
+                {"body": "added 1 commit", "system": true, "author": {"username": "me"}},
+                {"body": "Rename this", "system": false, "author": {"username": "alice"}, "position": {"new_path": "src/main.rs", "new_line": 4}}
+            ]"#)
+            .create();
+        server
+            .mock("POST", "/projects/group%2Fapp/merge_requests")
+            .match_body(Matcher::PartialJson(json!({"source_branch": "taken"})))
+            .with_status(409)
+            .with_body(r#"{"message": ["Another open merge request already exists for this source branch: !2"]}"#)
+            .create();
+
+        let client = GitLab::new(&server.url(), "secret", "group/app").unwrap();
+        let mr = client
+            .create_pull_request(&NewPullRequest {
+                title: "Add feature",
+                body: "Details",
+                head: "feature",
+                base: "main",
+                draft: true,
+            })
+            .unwrap();
+        assert_eq!(mr.number, 3);
+        assert!(mr.draft);
+        created.assert();
+
+        client
+            .request_reviewers(3, &["@alice".to_string()])
+            .unwrap();
+        reviewers.assert();
+        let error = client
+            .request_reviewers(3, &["nobody".to_string()])
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Invalid input: GitLab: no user named nobody"
+        );
+
+        let comments = client.review_comments(3).unwrap();
+        assert_eq!(comments.len(), 1);
+        assert_eq!(comments[0].path.as_deref(), Some("src/main.rs"));
+        assert_eq!(comments[0].line, Some(4));
+
+        let error = client
+            .create_pull_request(&NewPullRequest {
+                title: "Again",
+                body: "",
+                head: "taken",
+                base: "main",
+                draft: false,
+            })
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Invalid input: GitLab: Another open merge request already exists for this source branch: !2"
+        );
+    }
+}
@@ -0,0 +1,574 @@
+//! Code hosts: pull requests on GitHub, merge requests on GitLab, pull
+//! requests on Gitea
+//!
+//! The host is inferred from the URL of the repository's remote: `github.com`
+//! is GitHub, hosts with `gitlab` in their name are GitLab and hosts with
+//! `gitea` or `codeberg` in their name are Gitea. Other hosts, such as a
+//! self-managed GitLab on `git.example.com`, need `kind` set in the `[forge]`
+//! config section. The API lives at the usual path on the same host unless
+//! `api_url` says otherwise.
+//!
+//! Tokens come from `GITHUB_TOKEN`, `GITLAB_TOKEN` or `GITEA_TOKEN`. Without a
+//! usable remote, `GITHUB_REPO` names the GitHub repository.
+
+use super::http;
+use crate::error::{GrokError, Result};
+use git2::Repository;
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::{Method, StatusCode};
+use serde::Deserialize;
+use serde_json::Value as JsonValue;
+use std::env;
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+pub mod gitea;
+pub mod github;
+pub mod gitlab;
+
+pub use gitea::Gitea;
+pub use github::GitHub;
+pub use gitlab::GitLab;
+
+/// Where the hosts look for a pull request template, in order
+const PR_TEMPLATES: &[&str] = &[
+    ".github/pull_request_template.md",
+    ".github/PULL_REQUEST_TEMPLATE.md",
+    ".gitlab/merge_request_templates/Default.md",
+    ".gitea/pull_request_template.md",
+    "docs/pull_request_template.md",
+    "docs/PULL_REQUEST_TEMPLATE.md",
+    "pull_request_template.md",
+    "PULL_REQUEST_TEMPLATE.md",
+];
+
+/// Kind of code host
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ForgeKind {
+    GitHub,
+    GitLab,
+    Gitea,
+}
+
+impl ForgeKind {
+    pub const ALL: [ForgeKind; 3] = [ForgeKind::GitHub, ForgeKind::GitLab, ForgeKind::Gitea];
+
+    /// Name for messages
+    pub fn display_name(self) -> &'static str {
+        match self {
+            ForgeKind::GitHub => "GitHub",
+            ForgeKind::GitLab => "GitLab",
+            ForgeKind::Gitea => "Gitea",
+        }
+    }
+
+    /// What the host calls a pull request
+    pub fn noun(self) -> &'static str {
+        match self {
+            ForgeKind::GitLab => "merge request",
+            ForgeKind::GitHub | ForgeKind::Gitea => "pull request",
+        }
+    }
+
+    /// How the host writes a reference to pull request `number`
+    pub fn reference(self, number: u64) -> String {
+        match self {
+            ForgeKind::GitLab => format!("!{number}"),
+            ForgeKind::GitHub | ForgeKind::Gitea => format!("#{number}"),
+        }
+    }
+
+    /// Environment variable holding the API token
+    pub fn token_var(self) -> &'static str {
+        match self {
+            ForgeKind::GitHub => "GITHUB_TOKEN",
+            ForgeKind::GitLab => "GITLAB_TOKEN",
+            ForgeKind::Gitea => "GITEA_TOKEN",
+        }
+    }
+
+    /// User name and password for pushing over HTTPS with `token`
+    pub fn push_credentials(self, token: &str) -> (String, String) {
+        match self {
+            ForgeKind::GitHub => ("x-access-token".to_string(), token.to_string()),
+            ForgeKind::GitLab => ("oauth2".to_string(), token.to_string()),
+            ForgeKind::Gitea => (token.to_string(), "x-oauth-basic".to_string()),
+        }
+    }
+
+    /// Guess the kind from a host name
+    pub fn detect(host: &str) -> Option<Self> {
+        let host = host.to_lowercase();
+        if host == "github.com" || host.ends_with(".github.com") {
+            Some(ForgeKind::GitHub)
+        } else if host.contains("gitlab") {
+            Some(ForgeKind::GitLab)
+        } else if host.contains("gitea") || host.contains("codeberg") {
+            Some(ForgeKind::Gitea)
+        } else {
+            None
+        }
+    }
+
+    /// API root for a host whose web pages are at `web_url`
+    pub fn api_url(self, web_url: &str) -> String {
+        match self {
+            ForgeKind::GitHub if web_url == "https://github.com" => {
+                github::DEFAULT_API_URL.to_string()
+            }
+            // GitHub Enterprise Server
+            ForgeKind::GitHub => format!("{web_url}/api/v3"),
+            ForgeKind::GitLab => format!("{web_url}/api/v4"),
+            ForgeKind::Gitea => format!("{web_url}/api/v1"),
+        }
+    }
+}
+
+impl fmt::Display for ForgeKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ForgeKind::GitHub => "github",
+            ForgeKind::GitLab => "gitlab",
+            ForgeKind::Gitea => "gitea",
+        })
+    }
+}
+
+impl FromStr for ForgeKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        ForgeKind::ALL
+            .into_iter()
+            .find(|kind| kind.to_string() == s.trim().to_lowercase())
+            .ok_or_else(|| {
+                format!(
+                    "unknown code host '{}' (expected github, gitlab or gitea)",
+                    s.trim()
+                )
+            })
+    }
+}
+
+/// A pull request to open
+pub struct NewPullRequest<'a> {
+    pub title: &'a str,
+    pub body: &'a str,
+    pub head: &'a str,
+    pub base: &'a str,
+    pub draft: bool,
+}
+
+/// A pull or merge request on the host
+#[derive(Debug, Clone, PartialEq)]
+pub struct PullRequest {
+    pub number: u64,
+    pub title: String,
+    pub url: String,
+    pub head: String,
+    pub base: String,
+    pub author: String,
+    pub draft: bool,
+}
+
+/// A review, or a comment on a line of a pull request
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReviewComment {
+    pub author: String,
+    pub body: String,
+    /// File and line the comment is attached to, if any
+    pub path: Option<String>,
+    pub line: Option<u64>,
+}
+
+/// A code host's API for one repository
+pub trait Forge {
+    fn kind(&self) -> ForgeKind;
+
+    /// Repository path on the host, such as `owner/repo`
+    fn repo(&self) -> &str;
+
+    /// Token used for the API, also good for pushing over HTTPS
+    fn token(&self) -> &str;
+
+    fn create_pull_request(&self, pr: &NewPullRequest<'_>) -> Result<PullRequest>;
+
+    /// Ask users, or teams given as `org/team`, to review a pull request
+    fn request_reviewers(&self, number: u64, reviewers: &[String]) -> Result<()>;
+
+    fn add_labels(&self, number: u64, labels: &[String]) -> Result<()>;
+
+    /// Open pull requests, newest first
+    fn list_pull_requests(&self) -> Result<Vec<PullRequest>>;
+
+    /// Reviews and line comments on a pull request, oldest first
+    fn review_comments(&self, number: u64) -> Result<Vec<ReviewComment>>;
+}
+
+/// Where a git remote URL points
+#[derive(Debug, Clone, PartialEq)]
+pub struct Remote {
+    pub host: String,
+    /// Root of the host's web pages, such as `https://gitlab.example.com`
+    pub web_url: String,
+    /// Repository path, such as `owner/repo` or `group/subgroup/repo`
+    pub path: String,
+}
+
+/// Parse an `https://`, `ssh://` or `user@host:path` remote URL. Local paths
+/// give `None`.
+pub fn parse_remote_url(url: &str) -> Option<Remote> {
+    let url = url.trim();
+    let (web_scheme, authority, path, web_port) = match url.split_once("://") {
+        Some((scheme, rest)) => {
+            let web_scheme = match scheme {
+                "http" => "http",
+                "https" | "ssh" | "git" | "git+ssh" | "ssh+git" => "https",
+                _ => return None,
+            };
+            let (authority, path) = rest.split_once('/')?;
+            // Only web URLs say which port the web pages are on
+            (web_scheme, authority, path, scheme.starts_with("http"))
+        }
+        None => {
+            let (authority, path) = url.split_once(':')?;
+            if authority.is_empty() || authority.contains(['/', '\\']) {
+                return None;
+            }
+            ("https", authority, path, false)
+        }
+    };
+
+    let host_port = authority.rsplit('@').next()?;
+    let host = host_port.split(':').next()?;
+    let path = path.trim_matches('/');
+    let path = path.strip_suffix(".git").unwrap_or(path).trim_matches('/');
+    if host.is_empty() || !path.contains('/') {
+        return None;
+    }
+    Some(Remote {
+        host: host.to_string(),
+        web_url: format!("{web_scheme}://{}", if web_port { host_port } else { host }),
+        path: path.to_string(),
+    })
+}
+
+/// URL of the remote the current branch tracks, else of `origin`, else of
+/// the first remote
+fn remote_url(repo: &Repository) -> Option<String> {
+    let upstream = repo
+        .head()
+        .ok()
+        .and_then(|head| head.name().map(str::to_string))
+        .and_then(|head| repo.branch_upstream_remote(&head).ok())
+        .and_then(|name| name.as_str().map(str::to_string));
+    let names = repo.remotes().ok()?;
+    let name = upstream
+        .or_else(|| {
+            names
+                .iter()
+                .flatten()
+                .find(|n| *n == "origin")
+                .map(str::to_string)
+        })
+        .or_else(|| names.iter().flatten().next().map(str::to_string))?;
+    repo.find_remote(&name).ok()?.url().map(str::to_string)
+}
+
+/// The remote the code host is worked out from, if it is on a code host
+pub fn repo_remote(repo: &Repository) -> Option<Remote> {
+    remote_url(repo).and_then(|url| parse_remote_url(&url))
+}
+
+/// How to reach the code host, from the `[forge]` config section
+///
+/// Unset fields are worked out from the repository's remote, or read from
+/// the host's token variable, `GITHUB_REPO` and `GITHUB_API_URL`.
+#[derive(Clone, Debug, Default)]
+pub struct ForgeSettings {
+    pub kind: Option<ForgeKind>,
+    pub api_url: Option<String>,
+    pub token: Option<String>,
+    /// `owner/repo` on GitHub
+    pub github_repo: Option<String>,
+}
+
+/// The code host for `repo`, from its remote and the `[forge]` settings
+pub fn from_repo(repo: Option<&Repository>, settings: &ForgeSettings) -> Result<Box<dyn Forge>> {
+    let var = |name: &str| env::var(name).ok().filter(|v| !v.trim().is_empty());
+    let remote = repo.and_then(repo_remote);
+
+    let kind = match (settings.kind, &remote) {
+        (Some(kind), _) => kind,
+        (None, Some(remote)) => ForgeKind::detect(&remote.host).ok_or_else(|| {
+            GrokError::config(format!(
+                "Can't tell which code host {} is. Set kind = \"github\", \"gitlab\" or \"gitea\" in the [forge] config section.",
+                remote.host
+            ))
+        })?,
+        // Without a remote there is only GITHUB_REPO to go by
+        (None, None) => ForgeKind::GitHub,
+    };
+
+    let token = settings
+        .token
+        .clone()
+        .or_else(|| var(kind.token_var()))
+        .ok_or_else(|| GrokError::config(format!("{} env var required.", kind.token_var())))?;
+    let github_repo = settings
+        .github_repo
+        .clone()
+        .or_else(|| var("GITHUB_REPO"))
+        .filter(|_| kind == ForgeKind::GitHub);
+    let path = github_repo
+        .or_else(|| remote.as_ref().map(|r| r.path.clone()))
+        .ok_or_else(|| match kind {
+            ForgeKind::GitHub => {
+                GrokError::config("GITHUB_REPO env var required (e.g., owner/repo).")
+            }
+            _ => GrokError::config("The repository has no remote on a code host."),
+        })?;
+    let github_api_url = var("GITHUB_API_URL").filter(|_| kind == ForgeKind::GitHub);
+    let api_url = settings
+        .api_url
+        .clone()
+        .or(github_api_url)
+        .or_else(|| remote.as_ref().map(|r| kind.api_url(&r.web_url)))
+        .unwrap_or_else(|| github::DEFAULT_API_URL.to_string());
+
+    Ok(match kind {
+        ForgeKind::GitHub => Box::new(GitHub::new(&api_url, &token, &path)?),
+        ForgeKind::GitLab => Box::new(GitLab::new(&api_url, &token, &path)?),
+        ForgeKind::Gitea => Box::new(Gitea::new(&api_url, &token, &path)?),
+    })
+}
+
+/// JSON requests to a host's API
+struct Api {
+    kind: ForgeKind,
+    base_url: String,
+    /// Authentication and any other headers the host wants
+    headers: HeaderMap,
+    http: reqwest::Client,
+}
+
+impl Api {
+    fn new(kind: ForgeKind, base_url: &str, headers: &[(&'static str, String)]) -> Result<Self> {
+        let mut map = HeaderMap::new();
+        for (name, value) in headers {
+            let value = value.parse().map_err(|_| {
+                GrokError::config(format!("{} contains invalid characters", kind.token_var()))
+            })?;
+            map.insert(*name, value);
+        }
+        Ok(Self {
+            kind,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            headers: map,
+            http: http::client()?,
+        })
+    }
+
+    fn get(&self, path: &str) -> Result<JsonValue> {
+        self.send(Method::GET, path, None)
+    }
+
+    fn send(&self, method: Method, path: &str, body: Option<JsonValue>) -> Result<JsonValue> {
+        let mut request = self
+            .http
+            .request(method, format!("{}{path}", self.base_url))
+            .headers(self.headers.clone());
+        if let Some(body) = body {
+            request = request.json(&body);
+        }
+        let kind = self.kind;
+
+        http::block_on(async move {
+            let response = request.send().await?;
+            let status = response.status();
+            let retry_after = response
+                .headers()
+                .get(RETRY_AFTER)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.parse().ok());
+            let text = response.text().await?;
+            if !status.is_success() {
+                return Err(api_error(kind, status, &text, retry_after));
+            }
+            if text.trim().is_empty() {
+                return Ok(JsonValue::Null);
+            }
+            Ok(serde_json::from_str(&text)?)
+        })
+    }
+}
+
+/// Turn a failed response into an error, keeping the host's explanation
+fn api_error(
+    kind: ForgeKind,
+    status: StatusCode,
+    body: &str,
+    retry_after: Option<u64>,
+) -> GrokError {
+    let parsed: JsonValue = serde_json::from_str(body).unwrap_or_default();
+    let text = |value: &JsonValue| match value {
+        JsonValue::String(s) => s.clone(),
+        other => other.to_string(),
+    };
+    let mut message = match &parsed["message"] {
+        JsonValue::String(message) => message.clone(),
+        JsonValue::Array(messages) => messages.iter().map(text).collect::<Vec<_>>().join("; "),
+        // GitLab reports invalid fields as {"field": ["problem", ...]}
+        JsonValue::Object(fields) => fields
+            .iter()
+            .map(|(field, problems)| match problems {
+                JsonValue::Array(problems) => {
+                    format!(
+                        "{field} {}",
+                        problems.iter().map(text).collect::<Vec<_>>().join(", ")
+                    )
+                }
+                problem => format!("{field} {}", text(problem)),
+            })
+            .collect::<Vec<_>>()
+            .join("; "),
+        _ => parsed["error"]
+            .as_str()
+            .map(str::to_string)
+            .unwrap_or_else(|| body.trim().to_string()),
+    };
+    // GitHub explains validation failures in `errors`
+    let details: Vec<String> = parsed["errors"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|e| {
+            e["message"].as_str().map(str::to_string).or_else(|| {
+                e["code"]
+                    .as_str()
+                    .map(|code| format!("{} {code}", e["field"].as_str().unwrap_or("field")))
+            })
+        })
+        .collect();
+    if !details.is_empty() {
+        message = format!("{message}: {}", details.join("; "));
+    }
+
+    let name = kind.display_name();
+    match status {
+        StatusCode::UNAUTHORIZED => {
+            GrokError::PermissionDenied(format!("{name} rejected {}: {message}", kind.token_var()))
+        }
+        StatusCode::FORBIDDEN if message.to_lowercase().contains("rate limit") => {
+            GrokError::rate_limited(format!("{name}: {message}"), retry_after)
+        }
+        StatusCode::FORBIDDEN => GrokError::PermissionDenied(format!(
+            "{name} refused the request (check the token's scopes): {message}"
+        )),
+        StatusCode::NOT_FOUND => GrokError::api(format!(
+            "{name} returned 404 (check the repository and that the token can see it): {message}"
+        )),
+        StatusCode::BAD_REQUEST | StatusCode::CONFLICT | StatusCode::UNPROCESSABLE_ENTITY => {
+            GrokError::InvalidInput(format!("{name}: {message}"))
+        }
+        StatusCode::TOO_MANY_REQUESTS => {
+            GrokError::rate_limited(format!("{name}: {message}"), retry_after)
+        }
+        _ => GrokError::api(format!("{name} returned {status}: {message}")),
+    }
+}
+
+/// String at `pointer` in `value`, or empty
+fn string_at(value: &JsonValue, pointer: &str) -> String {
+    value
+        .pointer(pointer)
+        .and_then(JsonValue::as_str)
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// The repository's pull request template, if it has one
+pub fn pull_request_template(project_root: &Path) -> Option<String> {
+    PR_TEMPLATES
+        .iter()
+        .filter_map(|path| std::fs::read_to_string(project_root.join(path)).ok())
+        .find(|template| !template.trim().is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_remote_url() {
+        let remote = |url: &str| parse_remote_url(url).map(|r| (r.host, r.web_url, r.path));
+        let expected = |host: &str, web_url: &str, path: &str| {
+            Some((host.to_string(), web_url.to_string(), path.to_string()))
+        };
+
+        assert_eq!(
+            remote("git@github.com:octo/app.git"),
+            expected("github.com", "https://github.com", "octo/app")
+        );
+        assert_eq!(
+            remote("https://gitlab.example.com/group/sub/app.git"),
+            expected(
+                "gitlab.example.com",
+                "https://gitlab.example.com",
+                "group/sub/app"
+            )
+        );
+        assert_eq!(
+            remote("ssh://git@git.example.com:2222/team/app"),
+            expected("git.example.com", "https://git.example.com", "team/app")
+        );
+        assert_eq!(
+            remote("http://token@localhost:3000/team/app/"),
+            expected("localhost", "http://localhost:3000", "team/app")
+        );
+        assert_eq!(remote("/srv/git/app.git"), None);
+        assert_eq!(remote("../app"), None);
+        assert_eq!(remote("file:///srv/git/app.git"), None);
+
+        assert_eq!(ForgeKind::detect("github.com"), Some(ForgeKind::GitHub));
+        assert_eq!(
+            ForgeKind::detect("gitlab.example.com"),
+            Some(ForgeKind::GitLab)
+        );
+        assert_eq!(ForgeKind::detect("codeberg.org"), Some(ForgeKind::Gitea));
+        assert_eq!(ForgeKind::detect("git.example.com"), None);
+        assert_eq!(
+            ForgeKind::GitLab.api_url("https://git.example.com"),
+            "https://git.example.com/api/v4"
+        );
+        assert_eq!("GitLab".parse(), Ok(ForgeKind::GitLab));
+    }
+
+    #[test]
+    fn test_from_repo() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let repo = Repository::init(temp_dir.path()).unwrap();
+        repo.remote("origin", "git@gitlab.example.com:group/sub/app.git")
+            .unwrap();
+        let mut settings = ForgeSettings {
+            token: Some("secret".to_string()),
+            ..Default::default()
+        };
+
+        let forge = from_repo(Some(&repo), &settings).unwrap();
+        assert_eq!(forge.kind(), ForgeKind::GitLab);
+        assert_eq!(forge.repo(), "group/sub/app");
+
+        repo.remote_set_url("origin", "https://git.example.com/team/app")
+            .unwrap();
+        let error = from_repo(Some(&repo), &settings).err().unwrap();
+        assert!(error.to_string().contains("[forge]"), "{error}");
+
+        settings.kind = Some(ForgeKind::Gitea);
+        let forge = from_repo(Some(&repo), &settings).unwrap();
+        assert_eq!(forge.kind(), ForgeKind::Gitea);
+        assert_eq!(forge.repo(), "team/app");
+    }
+}
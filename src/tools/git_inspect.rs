@@ -0,0 +1,639 @@
+//! Read-only git tools: status, diff, log, blame and show
+//!
+//! These tools only read the repository, so they never ask for confirmation
+//! and run in parallel with other tools. Each one opens the repository
+//! containing the project root itself instead of borrowing the agent's
+//! handle, which only the sequential git tools get.
+
+use super::git_ops::repo_pathspec;
+use super::{Tool, ToolContext};
+use chrono::{DateTime, FixedOffset};
+use git2::{
+    BlameOptions, BranchType, Commit, Diff, DiffFormat, DiffOptions, DiffStatsFormat, Oid,
+    Repository, Sort, Status, StatusOptions,
+};
+use serde_json::{json, Value as JsonValue};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Most lines of output a git tool returns
+pub const MAX_OUTPUT_LINES: usize = 500;
+
+/// Commits listed by `git_log` when no limit is given
+const DEFAULT_LOG_LIMIT: usize = 20;
+
+/// The repository containing the project root
+fn open_repo(context: &ToolContext<'_>) -> Result<Repository, String> {
+    Repository::discover(&context.project_root).map_err(|_| {
+        format!(
+            "Error: {} is not in a git repository",
+            context.project_root.display()
+        )
+    })
+}
+
+/// The commit `rev` names, such as `HEAD~2`, a branch, a tag or an id
+fn find_commit<'r>(repo: &'r Repository, rev: &str) -> Result<Commit<'r>, String> {
+    if repo.is_empty().unwrap_or(false) {
+        return Err("Error: the repository has no commits yet".to_string());
+    }
+    repo.revparse_single(rev)
+        .and_then(|object| object.peel_to_commit())
+        .map_err(|_| format!("Error: unknown revision '{rev}'"))
+}
+
+/// Workdir-relative pathspecs for the `paths` argument
+fn pathspecs(
+    repo: &Repository,
+    context: &ToolContext<'_>,
+    paths: &JsonValue,
+) -> Result<Vec<String>, String> {
+    paths
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|p| p.as_str())
+        .map(|p| repo_pathspec(repo, &context.project_root, p).map_err(|e| format!("Error: {e}")))
+        .collect()
+}
+
+/// A commit time in the committer's time zone
+fn format_time(time: git2::Time, format: &str) -> String {
+    let offset = FixedOffset::east_opt(time.offset_minutes() * 60)
+        .unwrap_or_else(|| FixedOffset::east_opt(0).unwrap());
+    DateTime::from_timestamp(time.seconds(), 0)
+        .map(|t| t.with_timezone(&offset).format(format).to_string())
+        .unwrap_or_default()
+}
+
+fn short_id(id: Oid) -> String {
+    id.to_string()[..7].to_string()
+}
+
+/// Cut `lines` off after `max_lines`, saying how many were left out
+fn truncate(mut lines: Vec<String>, max_lines: usize) -> Vec<String> {
+    if lines.len() > max_lines {
+        let more = lines.len() - max_lines;
+        lines.truncate(max_lines);
+        lines.push(format!("... ({more} more lines)"));
+    }
+    lines
+}
+
+/// `diff` as a patch, leaving out files `include` rejects, cut off after
+/// `max_lines` lines
+pub(super) fn render_patch(
+    diff: &Diff<'_>,
+    include: impl Fn(&Path) -> bool,
+    max_lines: usize,
+) -> Result<String, git2::Error> {
+    let mut lines = Vec::new();
+    let mut current = String::new();
+    diff.print(DiffFormat::Patch, |delta, _hunk, line| {
+        let included = delta
+            .new_file()
+            .path()
+            .or_else(|| delta.old_file().path())
+            .is_none_or(&include);
+        if included {
+            if matches!(line.origin(), '+' | '-' | ' ') {
+                current.push(line.origin());
+            }
+            current.push_str(&String::from_utf8_lossy(line.content()));
+            if current.ends_with('\n') {
+                lines.push(std::mem::take(&mut current).trim_end().to_string());
+            }
+        }
+        true
+    })?;
+    Ok(truncate(lines, max_lines).join("\n"))
+}
+
+/// Summary line and patch for `diff`
+fn render_diff(diff: &Diff<'_>) -> Result<String, git2::Error> {
+    let stats = diff.stats()?.to_buf(DiffStatsFormat::SHORT, 80)?;
+    let stats = String::from_utf8_lossy(&stats).trim().to_string();
+    Ok(format!(
+        "{stats}\n\n{}",
+        render_patch(diff, |_| true, MAX_OUTPUT_LINES)?
+    ))
+}
+
+/// Tool for showing the working tree status
+pub struct GitStatus;
+
+impl Tool for GitStatus {
+    fn name(&self) -> &'static str {
+        "git_status"
+    }
+
+    fn description(&self) -> &'static str {
+        "Show the current branch, how far it is ahead of or behind its upstream, and changed, staged and untracked files in git status --short format. Read-only."
+    }
+
+    fn parameters(&self) -> JsonValue {
+        json!({
+            "type": "object",
+            "properties": {},
+            "required": []
+        })
+    }
+
+    fn execute(&self, _args: &JsonValue, context: &ToolContext<'_>) -> String {
+        let repo = match open_repo(context) {
+            Ok(repo) => repo,
+            Err(e) => return e,
+        };
+
+        let mut options = StatusOptions::new();
+        options
+            .include_untracked(true)
+            .recurse_untracked_dirs(true)
+            .renames_head_to_index(true);
+        let statuses = match repo.statuses(Some(&mut options)) {
+            Ok(statuses) => statuses,
+            Err(e) => return format!("Error: {e}"),
+        };
+
+        let mut lines = vec![branch_line(&repo)];
+        for entry in statuses.iter() {
+            let (index, worktree) = status_codes(entry.status());
+            let renamed = entry.head_to_index().and_then(|delta| {
+                let old = delta.old_file().path()?;
+                let new = delta.new_file().path()?;
+                (old != new).then(|| format!("{} -> {}", old.display(), new.display()))
+            });
+            let path = renamed.unwrap_or_else(|| entry.path().unwrap_or_default().to_string());
+            lines.push(format!("{index}{worktree} {path}"));
+        }
+        if lines.len() == 1 {
+            lines.push("Working tree clean.".to_string());
+        }
+        truncate(lines, MAX_OUTPUT_LINES).join("\n")
+    }
+}
+
+/// The branch HEAD is on and how it compares with its upstream
+fn branch_line(repo: &Repository) -> String {
+    let head = match repo.head() {
+        Ok(head) => head,
+        Err(_) => {
+            // A new repository: HEAD names a branch with no commits
+            let branch = repo
+                .find_reference("HEAD")
+                .ok()
+                .and_then(|r| r.symbolic_target().map(str::to_string))
+                .unwrap_or_default();
+            let branch = branch.trim_start_matches("refs/heads/");
+            return format!("On branch {branch} (no commits yet)");
+        }
+    };
+    if !head.is_branch() {
+        return match head.target() {
+            Some(id) => format!("HEAD detached at {}", short_id(id)),
+            None => "HEAD detached".to_string(),
+        };
+    }
+
+    let name = head.shorthand().unwrap_or("HEAD");
+    let mut line = format!("On branch {name}");
+    if let Ok(upstream) = repo
+        .find_branch(name, BranchType::Local)
+        .and_then(|branch| branch.upstream())
+    {
+        if let (Some(local), Some(remote)) = (head.target(), upstream.get().target()) {
+            if let Ok((ahead, behind)) = repo.graph_ahead_behind(local, remote) {
+                let upstream_name = upstream.name().ok().flatten().unwrap_or("upstream");
+                line.push_str(&format!(
+                    ", tracking {upstream_name} (ahead {ahead}, behind {behind})"
+                ));
+            }
+        }
+    }
+    line
+}
+
+/// `git status --short` codes for the index and the working tree
+fn status_codes(status: Status) -> (char, char) {
+    if status.is_conflicted() {
+        return ('U', 'U');
+    }
+    if status == Status::WT_NEW {
+        return ('?', '?');
+    }
+
+    let index = if status.is_index_new() {
+        'A'
+    } else if status.is_index_modified() {
+        'M'
+    } else if status.is_index_deleted() {
+        'D'
+    } else if status.is_index_renamed() {
+        'R'
+    } else if status.is_index_typechange() {
+        'T'
+    } else {
+        ' '
+    };
+    let worktree = if status.is_wt_modified() {
+        'M'
+    } else if status.is_wt_deleted() {
+        'D'
+    } else if status.is_wt_renamed() {
+        'R'
+    } else if status.is_wt_typechange() {
+        'T'
+    } else {
+        ' '
+    };
+    (index, worktree)
+}
+
+/// Tool for showing changes as a patch
+pub struct GitDiff;
+
+impl Tool for GitDiff {
+    fn name(&self) -> &'static str {
+        "git_diff"
+    }
+
+    fn description(&self) -> &'static str {
+        "Show changes as a unified diff: unstaged changes by default, staged changes with 'staged', or the changes from revision 'from' to revision 'to' (or to the working tree). Read-only."
+    }
+
+    fn parameters(&self) -> JsonValue {
+        json!({
+            "type": "object",
+            "properties": {
+                "staged": {"type": "boolean", "description": "Show changes staged for the next commit", "default": false},
+                "from": {"type": "string", "description": "Revision to compare from, such as HEAD~3, a branch or a commit id"},
+                "to": {"type": "string", "description": "Revision to compare to (requires 'from')"},
+                "paths": {"type": "array", "items": {"type": "string"}, "description": "Only show changes to these files or directories"}
+            },
+            "required": []
+        })
+    }
+
+    fn execute(&self, args: &JsonValue, context: &ToolContext<'_>) -> String {
+        let repo = match open_repo(context) {
+            Ok(repo) => repo,
+            Err(e) => return e,
+        };
+        let staged = args["staged"].as_bool().unwrap_or(false);
+        let tree = |rev: &str| {
+            find_commit(&repo, rev).and_then(|c| c.tree().map_err(|e| format!("Error: {e}")))
+        };
+
+        let mut options = DiffOptions::new();
+        match pathspecs(&repo, context, &args["paths"]) {
+            Ok(pathspecs) => {
+                for pathspec in pathspecs {
+                    options.pathspec(pathspec);
+                }
+            }
+            Err(e) => return e,
+        }
+
+        let diff = match (args["from"].as_str(), args["to"].as_str()) {
+            (Some(from), to) => {
+                let from = match tree(from) {
+                    Ok(tree) => tree,
+                    Err(e) => return e,
+                };
+                match to {
+                    Some(to) => match tree(to) {
+                        Ok(to) => {
+                            repo.diff_tree_to_tree(Some(&from), Some(&to), Some(&mut options))
+                        }
+                        Err(e) => return e,
+                    },
+                    None if staged => {
+                        repo.diff_tree_to_index(Some(&from), None, Some(&mut options))
+                    }
+                    None => repo.diff_tree_to_workdir_with_index(Some(&from), Some(&mut options)),
+                }
+            }
+            (None, Some(_)) => return "Error: 'to' requires 'from'".to_string(),
+            (None, None) if staged => {
+                // Before the first commit everything in the index is staged
+                let head = repo.head().ok().and_then(|h| h.peel_to_tree().ok());
+                repo.diff_tree_to_index(head.as_ref(), None, Some(&mut options))
+            }
+            (None, None) => repo.diff_index_to_workdir(None, Some(&mut options)),
+        };
+
+        match diff {
+            Ok(diff) if diff.deltas().len() == 0 => "No changes.".to_string(),
+            Ok(diff) => render_diff(&diff).unwrap_or_else(|e| format!("Error: {e}")),
+            Err(e) => format!("Error: {e}"),
+        }
+    }
+}
+
+/// Tool for listing commits
+pub struct GitLog;
+
+impl Tool for GitLog {
+    fn name(&self) -> &'static str {
+        "git_log"
+    }
+
+    fn description(&self) -> &'static str {
+        "List commits, newest first, as 'id date author summary'. Can start from any revision and only list commits that changed a path. Read-only."
+    }
+
+    fn parameters(&self) -> JsonValue {
+        json!({
+            "type": "object",
+            "properties": {
+                "rev": {"type": "string", "description": "Revision to start from", "default": "HEAD"},
+                "path": {"type": "string", "description": "Only list commits that changed this file or directory"},
+                "limit": {"type": "integer", "description": "Most commits to list", "default": DEFAULT_LOG_LIMIT}
+            },
+            "required": []
+        })
+    }
+
+    fn execute(&self, args: &JsonValue, context: &ToolContext<'_>) -> String {
+        let repo = match open_repo(context) {
+            Ok(repo) => repo,
+            Err(e) => return e,
+        };
+        let rev = args["rev"].as_str().unwrap_or("HEAD");
+        let limit = args["limit"]
+            .as_u64()
+            .map_or(DEFAULT_LOG_LIMIT, |l| l as usize)
+            .clamp(1, MAX_OUTPUT_LINES);
+        let start = match find_commit(&repo, rev) {
+            Ok(commit) => commit,
+            Err(e) => return e,
+        };
+        let pathspec = match args["path"].as_str() {
+            Some(path) => match repo_pathspec(&repo, &context.project_root, path) {
+                Ok(pathspec) => Some(pathspec),
+                Err(e) => return format!("Error: {e}"),
+            },
+            None => None,
+        };
+
+        let result = (|| -> Result<Vec<String>, git2::Error> {
+            let mut revwalk = repo.revwalk()?;
+            revwalk.set_sorting(Sort::TIME)?;
+            revwalk.push(start.id())?;
+
+            let mut lines = Vec::new();
+            for id in revwalk {
+                let commit = repo.find_commit(id?)?;
+                if let Some(pathspec) = &pathspec {
+                    if !changes_path(&repo, &commit, pathspec)? {
+                        continue;
+                    }
+                }
+                lines.push(format!(
+                    "{} {} {} {}",
+                    short_id(commit.id()),
+                    format_time(commit.author().when(), "%Y-%m-%d"),
+                    commit.author().name().unwrap_or_default(),
+                    commit.summary().unwrap_or_default()
+                ));
+                if lines.len() == limit {
+                    break;
+                }
+            }
+            Ok(lines)
+        })();
+
+        match result {
+            Ok(lines) if lines.is_empty() => "No commits found.".to_string(),
+            Ok(lines) => lines.join("\n"),
+            Err(e) => format!("Error: {e}"),
+        }
+    }
+}
+
+/// Whether `commit` changed anything under `pathspec`, compared with its
+/// first parent
+fn changes_path(
+    repo: &Repository,
+    commit: &Commit<'_>,
+    pathspec: &str,
+) -> Result<bool, git2::Error> {
+    let parent = match commit.parent(0) {
+        Ok(parent) => Some(parent.tree()?),
+        Err(_) => None,
+    };
+    let mut options = DiffOptions::new();
+    options.pathspec(pathspec);
+    let diff =
+        repo.diff_tree_to_tree(parent.as_ref(), Some(&commit.tree()?), Some(&mut options))?;
+    Ok(diff.deltas().len() > 0)
+}
+
+/// Tool for showing who last changed each line of a file
+pub struct GitBlame;
+
+impl Tool for GitBlame {
+    fn name(&self) -> &'static str {
+        "git_blame"
+    }
+
+    fn description(&self) -> &'static str {
+        "Show the commit, author and date that last changed each line of a file, optionally for a range of lines. Uncommitted lines are marked. Read-only."
+    }
+
+    fn parameters(&self) -> JsonValue {
+        json!({
+            "type": "object",
+            "properties": {
+                "path": {"type": "string", "description": "The file to blame"},
+                "start_line": {"type": "integer", "description": "First line to show (1-based)"},
+                "end_line": {"type": "integer", "description": "Last line to show (inclusive)"}
+            },
+            "required": ["path"]
+        })
+    }
+
+    fn execute(&self, args: &JsonValue, context: &ToolContext<'_>) -> String {
+        let Some(path) = args["path"].as_str() else {
+            return "Error: 'path' parameter is required".to_string();
+        };
+        let repo = match open_repo(context) {
+            Ok(repo) => repo,
+            Err(e) => return e,
+        };
+        let pathspec = match repo_pathspec(&repo, &context.project_root, path) {
+            Ok(pathspec) => pathspec,
+            Err(e) => return format!("Error: {e}"),
+        };
+
+        let committed = match repo.blame_file(Path::new(&pathspec), Some(&mut BlameOptions::new()))
+        {
+            Ok(blame) => blame,
+            Err(_) => return format!("Error: '{path}' has no committed history"),
+        };
+        // Blame the working tree copy, so uncommitted edits show up
+        let Some(workdir) = repo.workdir() else {
+            return "Error: the repository has no working directory".to_string();
+        };
+        let content = match std::fs::read(workdir.join(&pathspec)) {
+            Ok(content) => content,
+            Err(e) => return format!("Error reading {path}: {e}"),
+        };
+        let blame = match committed.blame_buffer(&content) {
+            Ok(blame) => blame,
+            Err(e) => return format!("Error: {e}"),
+        };
+
+        let content = String::from_utf8_lossy(&content);
+        let total = content.lines().count();
+        let start = args["start_line"].as_u64().unwrap_or(1).max(1) as usize;
+        let end = args["end_line"]
+            .as_u64()
+            .map_or(total, |end| (end as usize).min(total));
+        if start > end {
+            return format!("Error: no lines in range {start}-{end} ({path} has {total} lines)");
+        }
+
+        let width = end.to_string().len();
+        let mut authors: HashMap<Oid, String> = HashMap::new();
+        let lines: Vec<String> = content
+            .lines()
+            .enumerate()
+            .skip(start - 1)
+            .take(end - start + 1)
+            .map(|(i, line)| {
+                let number = i + 1;
+                let origin = match blame.get_line(number) {
+                    Some(hunk) if !hunk.final_commit_id().is_zero() => {
+                        let id = hunk.final_commit_id();
+                        authors
+                            .entry(id)
+                            .or_insert_with(|| {
+                                let signature = hunk.final_signature();
+                                format!(
+                                    "{} ({} {})",
+                                    short_id(id),
+                                    signature.name().unwrap_or_default(),
+                                    format_time(signature.when(), "%Y-%m-%d")
+                                )
+                            })
+                            .clone()
+                    }
+                    _ => "0000000 (Not committed yet)".to_string(),
+                };
+                format!("{origin} {number:>width$}: {line}")
+            })
+            .collect();
+        truncate(lines, MAX_OUTPUT_LINES).join("\n")
+    }
+}
+
+/// Tool for showing a commit or a file at a revision
+pub struct GitShow;
+
+impl Tool for GitShow {
+    fn name(&self) -> &'static str {
+        "git_show"
+    }
+
+    fn description(&self) -> &'static str {
+        "Show a commit's author, date, message and patch, or with 'path', the file's content at that revision. Read-only."
+    }
+
+    fn parameters(&self) -> JsonValue {
+        json!({
+            "type": "object",
+            "properties": {
+                "rev": {"type": "string", "description": "The commit to show", "default": "HEAD"},
+                "path": {"type": "string", "description": "Show this file as it was at the revision instead"}
+            },
+            "required": []
+        })
+    }
+
+    fn execute(&self, args: &JsonValue, context: &ToolContext<'_>) -> String {
+        let repo = match open_repo(context) {
+            Ok(repo) => repo,
+            Err(e) => return e,
+        };
+        let rev = args["rev"].as_str().unwrap_or("HEAD");
+        let commit = match find_commit(&repo, rev) {
+            Ok(commit) => commit,
+            Err(e) => return e,
+        };
+
+        let result = match args["path"].as_str() {
+            Some(path) => match repo_pathspec(&repo, &context.project_root, path) {
+                Ok(pathspec) => show_file(&repo, &commit, &pathspec).map(|content| {
+                    content.unwrap_or_else(|| format!("Error: '{path}' is not in {rev}"))
+                }),
+                Err(e) => return format!("Error: {e}"),
+            },
+            None => show_commit(&repo, &commit),
+        };
+        result.unwrap_or_else(|e| format!("Error: {e}"))
+    }
+}
+
+/// Header, message and patch of `commit`, like `git show`
+fn show_commit(repo: &Repository, commit: &Commit<'_>) -> Result<String, git2::Error> {
+    let author = commit.author();
+    let mut output = format!(
+        "commit {}\nAuthor: {} <{}>\nDate:   {}\n\n",
+        commit.id(),
+        author.name().unwrap_or_default(),
+        author.email().unwrap_or_default(),
+        format_time(author.when(), "%Y-%m-%d %H:%M:%S %z")
+    );
+    for line in commit.message().unwrap_or_default().trim_end().lines() {
+        output.push_str(&format!("    {line}\n"));
+    }
+
+    // Merges are shown against their first parent
+    let parent = match commit.parent(0) {
+        Ok(parent) => Some(parent.tree()?),
+        Err(_) => None,
+    };
+    let diff = repo.diff_tree_to_tree(parent.as_ref(), Some(&commit.tree()?), None)?;
+    if diff.deltas().len() > 0 {
+        output.push('\n');
+        output.push_str(&render_diff(&diff)?);
+    }
+    Ok(output.trim_end().to_string())
+}
+
+/// Content of the file at `pathspec` in `commit`, or `None` if it isn't
+/// there
+fn show_file(
+    repo: &Repository,
+    commit: &Commit<'_>,
+    pathspec: &str,
+) -> Result<Option<String>, git2::Error> {
+    let entry = match commit.tree()?.get_path(Path::new(pathspec)) {
+        Ok(entry) => entry,
+        Err(_) => return Ok(None),
+    };
+    let object = entry.to_object(repo)?;
+    if let Some(tree) = object.as_tree() {
+        let names: Vec<String> = tree
+            .iter()
+            .map(|entry| {
+                let name = entry.name().unwrap_or_default();
+                match entry.kind() {
+                    Some(git2::ObjectType::Tree) => format!("{name}/"),
+                    _ => name.to_string(),
+                }
+            })
+            .collect();
+        return Ok(Some(truncate(names, MAX_OUTPUT_LINES).join("\n")));
+    }
+    let blob = object.peel_to_blob()?;
+    if blob.is_binary() {
+        return Ok(Some(format!("Binary file, {} bytes", blob.size())));
+    }
+    let lines = String::from_utf8_lossy(blob.content())
+        .lines()
+        .map(str::to_string)
+        .collect();
+    Ok(Some(truncate(lines, MAX_OUTPUT_LINES).join("\n")))
+}
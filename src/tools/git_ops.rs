@@ -1,11 +1,19 @@
+use super::forge::{self, parse_remote_url, pull_request_template, Forge, NewPullRequest};
+use super::git_inspect::render_patch;
 use super::utils::{sanitize_commit_message, sanitize_path};
 use super::{Tool, ToolContext};
-use crate::backup::BackupManager;
-use git2::{IndexAddOption, Signature};
+use crate::backup::{is_backup, BackupManager};
+use git2::{
+    BranchType, Cred, CredentialType, DiffOptions, ErrorCode, Index, IndexAddOption, PushOptions,
+    RemoteCallbacks, Repository,
+};
 use regex::Regex;
 use serde_json::{json, Value as JsonValue};
-use std::env;
 use std::fs;
+use std::path::Path;
+
+/// Most lines of staged diff shown by a dry run
+const MAX_PREVIEW_LINES: usize = 200;
 
 /// Tool for creating git commits
 pub struct CreateCommit;
@@ -16,81 +24,267 @@ impl Tool for CreateCommit {
     }
 
     fn description(&self) -> &'static str {
-        "Create a git commit with staged changes."
+        "Create a git commit. Commits the changes under 'paths' (including deletions), or what is already staged, or every change if nothing is staged. Backup files are never committed. The author comes from git config user.name and user.email."
     }
 
     fn parameters(&self) -> JsonValue {
         json!({
             "type": "object",
             "properties": {
-                "message": {"type": "string", "description": "The commit message."}
+                "message": {"type": "string", "description": "The commit message. Optional when amending, to keep the old one."},
+                "paths": {"type": "array", "items": {"type": "string"}, "description": "Files or directories to stage and commit."},
+                "amend": {"type": "boolean", "description": "Replace the last commit instead of adding one.", "default": false}
             },
-            "required": ["message"]
+            "required": []
         })
     }
 
     fn execute(&self, args: &JsonValue, context: &ToolContext<'_>) -> String {
+        let amend = args["amend"].as_bool().unwrap_or(false);
         let message = match args["message"].as_str() {
-            Some(msg) => match sanitize_commit_message(msg) {
-                Ok(m) => m,
+            Some(msg) if !(amend && msg.trim().is_empty()) => match sanitize_commit_message(msg) {
+                Ok(m) => Some(m),
                 Err(e) => return format!("Error: {e}"),
             },
-            None => return "Error: 'message' parameter is required".to_string(),
+            _ if amend => None,
+            _ => return "Error: 'message' parameter is required".to_string(),
         };
 
-        if let Some(repo) = &context.git_repo {
-            if !context.confirm_action("create git commit") {
-                return "Commit not confirmed.".to_string();
-            }
+        let Some(repo) = context.git_repo else {
+            return "No git repo found.".to_string();
+        };
 
-            if context.dry_run {
-                return "Dry-run: Would create commit.".to_string();
+        let pathspecs = match args["paths"].as_array() {
+            Some(paths) => {
+                let mut pathspecs = Vec::new();
+                for path in paths.iter().filter_map(|p| p.as_str()) {
+                    match repo_pathspec(repo, &context.project_root, path) {
+                        Ok(pathspec) => pathspecs.push(pathspec),
+                        Err(e) => return format!("Error: {e}"),
+                    }
+                }
+                pathspecs
             }
+            None => Vec::new(),
+        };
 
-            let mut index = match repo.index() {
-                Ok(i) => i,
+        let head = match repo.head() {
+            Ok(head) => match head.peel_to_commit() {
+                Ok(commit) => Some(commit),
                 Err(e) => return format!("Error: {e}"),
-            };
+            },
+            // A new repository has no commits yet
+            Err(e) if matches!(e.code(), ErrorCode::UnbornBranch | ErrorCode::NotFound) => None,
+            Err(e) => return format!("Error: {e}"),
+        };
+        if amend && head.is_none() {
+            return "Error: there is no commit to amend".to_string();
+        }
+        let head_tree = match head.as_ref().map(|commit| commit.tree()).transpose() {
+            Ok(tree) => tree,
+            Err(e) => return format!("Error: {e}"),
+        };
 
-            if let Err(e) = index.add_all(["."].iter(), IndexAddOption::DEFAULT, None) {
-                return format!("Error staging: {e}");
+        let signature = match repo.signature() {
+            Ok(sig) => sig,
+            Err(_) => {
+                return "Error: set user.name and user.email in git config to commit (git config user.name \"Your Name\"; git config user.email you@example.com)".to_string()
             }
+        };
+        // Amending without a message keeps the old one
+        let message = message
+            .or_else(|| {
+                head.as_ref()
+                    .and_then(|c| c.message())
+                    .map(|m| m.trim_end().to_string())
+            })
+            .map(|m| with_co_authors(&m, &context.settings.co_authors));
+
+        // Without paths, commit what is staged, or everything if nothing is
+        let stage_all = pathspecs.is_empty()
+            && match repo.diff_tree_to_index(head_tree.as_ref(), None, None) {
+                Ok(staged) => staged.deltas().len() == 0,
+                Err(e) => return format!("Error: {e}"),
+            };
+        let pathspecs = if stage_all {
+            vec![".".to_string()]
+        } else {
+            pathspecs
+        };
 
-            if let Err(e) = index.write() {
-                return format!("Error writing index: {e}");
-            }
+        let action = if amend {
+            "amend the last git commit"
+        } else {
+            "create git commit"
+        };
+        if !context.confirm_action(action) {
+            return "Commit not confirmed.".to_string();
+        }
 
-            let tree_id = match index.write_tree() {
-                Ok(id) => id,
-                Err(e) => return format!("Error writing tree: {e}"),
+        if context.dry_run {
+            return match preview(repo, head_tree.as_ref(), &pathspecs) {
+                Ok(diff) if diff.is_empty() => {
+                    format!("Dry-run: Would {action}, but there are no changes to commit.")
+                }
+                Ok(diff) => format!("Dry-run: Would {action} with these changes:\n{diff}"),
+                Err(e) => format!("Error: {e}"),
             };
+        }
 
-            let tree = match repo.find_tree(tree_id) {
-                Ok(t) => t,
-                Err(e) => return format!("Error finding tree: {e}"),
-            };
+        let mut index = match repo.index() {
+            Ok(i) => i,
+            Err(e) => return format!("Error: {e}"),
+        };
 
-            let parent = match repo.head() {
-                Ok(head) => match head.peel_to_commit() {
-                    Ok(c) => c,
-                    Err(e) => return format!("Error: {e}"),
-                },
-                Err(_) => return "No parent commit.".to_string(),
-            };
+        if let Err(e) = stage(&mut index, &pathspecs) {
+            return format!("Error staging: {e}");
+        }
 
-            let sig = match Signature::now("Grok Code", "grok@code.com") {
-                Ok(s) => s,
-                Err(e) => return format!("Error creating git signature: {e}"),
-            };
+        if let Err(e) = index.write() {
+            return format!("Error writing index: {e}");
+        }
+
+        let tree_id = match index.write_tree() {
+            Ok(id) => id,
+            Err(e) => return format!("Error writing tree: {e}"),
+        };
+
+        let tree = match repo.find_tree(tree_id) {
+            Ok(t) => t,
+            Err(e) => return format!("Error finding tree: {e}"),
+        };
+
+        if !amend && head_tree.as_ref().map(|t| t.id()) == Some(tree_id) {
+            return "Nothing to commit.".to_string();
+        }
 
-            match repo.commit(Some("HEAD"), &sig, &sig, &message, &tree, &[&parent]) {
-                Ok(_) => "Commit successful.".to_string(),
-                Err(e) => format!("Error committing: {e}"),
+        let result = match (&head, amend) {
+            (Some(head), true) => head.amend(
+                Some("HEAD"),
+                None,
+                Some(&signature),
+                None,
+                message.as_deref(),
+                Some(&tree),
+            ),
+            _ => {
+                let parents: Vec<&git2::Commit> = head.iter().collect();
+                repo.commit(
+                    Some("HEAD"),
+                    &signature,
+                    &signature,
+                    message.as_deref().unwrap_or_default(),
+                    &tree,
+                    &parents,
+                )
             }
-        } else {
-            "No git repo found.".to_string()
+        };
+
+        match result.and_then(|oid| repo.find_commit(oid)) {
+            Ok(commit) => {
+                let id = commit.id().to_string();
+                let note = if amend {
+                    " (amended)"
+                } else if head.is_none() {
+                    " (initial commit)"
+                } else {
+                    ""
+                };
+                format!(
+                    "Commit successful{note}: {} {}",
+                    &id[..7],
+                    commit.summary().unwrap_or_default()
+                )
+            }
+            Err(e) => format!("Error committing: {e}"),
+        }
+    }
+}
+
+/// `path` relative to the repository's working directory, as a pathspec
+pub(super) fn repo_pathspec(
+    repo: &Repository,
+    project_root: &Path,
+    path: &str,
+) -> Result<String, String> {
+    let workdir = repo
+        .workdir()
+        .ok_or("the repository has no working directory")?;
+    let canonical = |p: &Path| p.canonicalize().unwrap_or_else(|_| p.to_path_buf());
+    let full = sanitize_path(path, &canonical(project_root))?;
+    let relative = full
+        .strip_prefix(canonical(workdir))
+        .map_err(|_| format!("'{path}' is outside the repository"))?;
+    let pathspec = relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/");
+    Ok(if pathspec.is_empty() {
+        ".".to_string()
+    } else {
+        pathspec
+    })
+}
+
+/// Stage every change under `pathspecs`, like `git add -A`, leaving out
+/// backup files
+fn stage(index: &mut Index, pathspecs: &[String]) -> Result<(), git2::Error> {
+    let mut skip_backups = |path: &Path, _: &[u8]| if is_backup(path) { 1 } else { 0 };
+    index.add_all(
+        pathspecs,
+        IndexAddOption::DEFAULT,
+        Some(&mut skip_backups as &mut git2::IndexMatchedPath),
+    )?;
+    // Stage deletions too
+    index.update_all(pathspecs, None)
+}
+
+/// The diff a commit of `pathspecs` would make, without touching the index
+fn preview(
+    repo: &Repository,
+    head_tree: Option<&git2::Tree>,
+    pathspecs: &[String],
+) -> Result<String, git2::Error> {
+    let diff = if pathspecs.is_empty() {
+        repo.diff_tree_to_index(head_tree, None, None)?
+    } else {
+        let mut options = DiffOptions::new();
+        options
+            .include_untracked(true)
+            .recurse_untracked_dirs(true)
+            .show_untracked_content(true);
+        for pathspec in pathspecs {
+            options.pathspec(pathspec);
         }
+        repo.diff_tree_to_workdir_with_index(head_tree, Some(&mut options))?
+    };
+
+    render_patch(&diff, |path| !is_backup(path), MAX_PREVIEW_LINES)
+}
+
+/// Append a `Co-authored-by` trailer for each co-author the message doesn't
+/// already credit
+fn with_co_authors(message: &str, co_authors: &[String]) -> String {
+    let trailers: Vec<String> = co_authors
+        .iter()
+        .map(|author| format!("Co-authored-by: {author}"))
+        .filter(|trailer| !message.lines().any(|line| line.trim() == trailer))
+        .collect();
+    if trailers.is_empty() {
+        return message.to_string();
     }
+
+    // Join an existing trailer block rather than starting a second one
+    let last_paragraph = message.rsplit("\n\n").next().unwrap_or_default();
+    let trailer_line = Regex::new(r"^[A-Za-z][A-Za-z0-9-]*: ").unwrap();
+    let separator =
+        if message.contains("\n\n") && last_paragraph.lines().all(|l| trailer_line.is_match(l)) {
+            "\n"
+        } else {
+            "\n\n"
+        };
+    format!("{message}{separator}{}", trailers.join("\n"))
 }
 
 /// Tool for submitting pull requests
@@ -102,7 +296,7 @@ impl Tool for SubmitPR {
     }
 
     fn description(&self) -> &'static str {
-        "Submit a pull request to GitHub. Requires GITHUB_TOKEN, GITHUB_REPO (owner/repo) env vars."
+        "Open a pull request (a merge request on GitLab) on the repository's code host, pushing the head branch first if the remote is behind. Leave body empty to use the repository's pull request template. Needs GITHUB_TOKEN, GITLAB_TOKEN or GITEA_TOKEN."
     }
 
     fn parameters(&self) -> JsonValue {
@@ -110,11 +304,14 @@ impl Tool for SubmitPR {
             "type": "object",
             "properties": {
                 "title": {"type": "string", "description": "PR title."},
-                "body": {"type": "string", "description": "PR body."},
+                "body": {"type": "string", "description": "PR body. Defaults to the repository's pull request template."},
                 "base": {"type": "string", "description": "Base branch.", "default": "main"},
-                "head": {"type": "string", "description": "Head branch."}
+                "head": {"type": "string", "description": "Head branch. Defaults to the current branch."},
+                "draft": {"type": "boolean", "description": "Open as a draft.", "default": false},
+                "reviewers": {"type": "array", "items": {"type": "string"}, "description": "Users, or teams as org/team, to request reviews from."},
+                "labels": {"type": "array", "items": {"type": "string"}, "description": "Labels to add."}
             },
-            "required": ["title", "head"]
+            "required": ["title"]
         })
     }
 
@@ -130,50 +327,302 @@ impl Tool for SubmitPR {
             None => return "Error: 'title' parameter is required".to_string(),
         };
 
-        let body = args["body"].as_str().unwrap_or("").trim();
         let base = args["base"].as_str().unwrap_or("main").trim();
-        let head = match args["head"].as_str() {
-            Some(h) => {
-                let h = h.trim();
-                if h.is_empty() {
-                    return "Error: 'head' branch cannot be empty".to_string();
+        let head = match args["head"].as_str().map(str::trim) {
+            Some("") => return "Error: 'head' branch cannot be empty".to_string(),
+            Some(h) => h.to_string(),
+            None => match context.git_repo.and_then(current_branch) {
+                Some(branch) => branch,
+                None => {
+                    return "Error: 'head' parameter is required outside a git branch".to_string()
                 }
-                h
-            }
-            None => return "Error: 'head' parameter is required".to_string(),
+            },
+        };
+        let draft = args["draft"].as_bool().unwrap_or(false);
+        let strings = |key: &str| -> Vec<String> {
+            args[key]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|v| v.as_str())
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty())
+                .collect()
         };
+        let reviewers = strings("reviewers");
+        let labels = strings("labels");
 
-        let _token = match env::var("GITHUB_TOKEN") {
-            Ok(t) => t,
-            Err(_) => return "GITHUB_TOKEN env var required.".to_string(),
+        let body = match args["body"].as_str().map(str::trim) {
+            Some(body) if !body.is_empty() => body.to_string(),
+            _ => pull_request_template(&context.project_root).unwrap_or_default(),
         };
 
-        let repo_str = match env::var("GITHUB_REPO") {
-            Ok(r) => r,
-            Err(_) => return "GITHUB_REPO env var required (e.g., owner/repo).".to_string(),
+        let forge = match forge::from_repo(context.git_repo, &context.settings.forge) {
+            Ok(forge) => forge,
+            Err(e) => return format!("Error: {e}"),
         };
+        let kind = forge.kind();
+        let action = format!(
+            "submit {} '{title}' ({head} → {base}) to {} on {}",
+            kind.noun(),
+            forge.repo(),
+            kind.display_name()
+        );
 
-        if !context.confirm_action("submit PR to GitHub") {
+        if !context.confirm_action(&action) {
             return "PR submission not confirmed.".to_string();
         }
 
         if context.dry_run {
-            return "Dry-run: Would submit PR.".to_string();
+            return format!("Dry-run: Would {action}.");
+        }
+
+        let mut result = String::new();
+        if let Some(repo) = context.git_repo {
+            match push_branch(repo, &head, forge.as_ref()) {
+                Ok(Some(remote)) => {
+                    result.push_str(&format!("Pushed branch '{head}' to {remote}.\n"))
+                }
+                Ok(None) => {}
+                Err(e) => return format!("Error pushing branch '{head}': {e}"),
+            }
         }
 
-        let url = format!("https://api.github.com/repos/{repo_str}/pulls");
-        let _pr_body = json!({
-            "title": title,
-            "body": body,
-            "base": base,
-            "head": head
+        let pr = match forge.create_pull_request(&NewPullRequest {
+            title,
+            body: &body,
+            head: &head,
+            base,
+            draft,
+        }) {
+            Ok(pr) => pr,
+            Err(e) => return format!("{result}Error creating {}: {e}", kind.noun()),
+        };
+        result.push_str(&format!(
+            "Created {}{} {}: {}",
+            if draft { "draft " } else { "" },
+            kind.noun(),
+            kind.reference(pr.number),
+            pr.url
+        ));
+
+        if !reviewers.is_empty() {
+            match forge.request_reviewers(pr.number, &reviewers) {
+                Ok(()) => result.push_str(&format!(
+                    "\nRequested reviews from: {}",
+                    reviewers.join(", ")
+                )),
+                Err(e) => result.push_str(&format!("\nWarning: could not request reviewers: {e}")),
+            }
+        }
+        if !labels.is_empty() {
+            match forge.add_labels(pr.number, &labels) {
+                Ok(()) => result.push_str(&format!("\nLabels: {}", labels.join(", "))),
+                Err(e) => result.push_str(&format!("\nWarning: could not add labels: {e}")),
+            }
+        }
+        result
+    }
+}
+
+/// Name of the checked-out branch
+fn current_branch(repo: &Repository) -> Option<String> {
+    let head = repo.head().ok()?;
+    if !head.is_branch() {
+        return None;
+    }
+    head.shorthand().map(str::to_string)
+}
+
+/// Push `branch` to its remote (`origin` unless it tracks another one) when
+/// the remote doesn't have its latest commit. Returns the remote's name if
+/// anything was pushed.
+///
+/// The code host's token is only offered to a remote on the same host as the
+/// one the code host was worked out from; other remotes get git's credential
+/// helper.
+fn push_branch(
+    repo: &Repository,
+    branch: &str,
+    forge: &dyn Forge,
+) -> Result<Option<String>, git2::Error> {
+    let Ok(local) = repo.find_branch(branch, BranchType::Local) else {
+        // Nothing to push; the branch may only exist on the remote
+        return Ok(None);
+    };
+    let Some(oid) = local.get().target() else {
+        return Ok(None);
+    };
+
+    let refname = format!("refs/heads/{branch}");
+    let remote_name = repo
+        .branch_upstream_remote(&refname)
+        .ok()
+        .and_then(|buf| buf.as_str().map(str::to_string))
+        .unwrap_or_else(|| "origin".to_string());
+    let tracking = format!("refs/remotes/{remote_name}/{branch}");
+    if repo.refname_to_id(&tracking).ok() == Some(oid) {
+        return Ok(None);
+    }
+
+    let mut remote = repo.find_remote(&remote_name)?;
+    let config = repo.config()?;
+    let forge_host = forge::repo_remote(repo).map(|r| r.host);
+    let (user, password) = forge.kind().push_credentials(forge.token());
+    let mut attempts = 0;
+    let mut rejected = None;
+    {
+        let mut callbacks = RemoteCallbacks::new();
+        callbacks.credentials(|url, username, allowed| {
+            // libgit2 keeps asking while credentials fail
+            attempts += 1;
+            if attempts > 3 {
+                return Err(git2::Error::from_str("authentication failed"));
+            }
+            if allowed.contains(CredentialType::USER_PASS_PLAINTEXT) {
+                if same_host(url, forge_host.as_deref()) {
+                    Cred::userpass_plaintext(&user, &password)
+                } else {
+                    Cred::credential_helper(&config, url, username)
+                }
+            } else if allowed.contains(CredentialType::SSH_KEY) {
+                Cred::ssh_key_from_agent(username.unwrap_or("git"))
+            } else {
+                Cred::default()
+            }
+        });
+        callbacks.push_update_reference(|_refname, status| {
+            rejected = status.map(str::to_string);
+            Ok(())
         });
 
-        // TODO: Implement actual GitHub API request
-        // TODO: Handle authentication properly
-        // TODO: Support draft PRs and PR templates
-        // TODO: Add support for reviewers and labels
-        format!("Would submit PR to {url} with title: {title}")
+        let mut options = PushOptions::new();
+        options.remote_callbacks(callbacks);
+        remote.push(&[format!("{refname}:{refname}")], Some(&mut options))?;
+    }
+    if let Some(reason) = rejected {
+        return Err(git2::Error::from_str(&format!("push rejected: {reason}")));
+    }
+
+    // Make later pushes and pulls of the branch use this remote
+    let mut local = local;
+    let _ = local.set_upstream(Some(&format!("{remote_name}/{branch}")));
+    Ok(Some(remote_name))
+}
+
+/// Whether remote `url` is on `host`
+fn same_host(url: &str, host: Option<&str>) -> bool {
+    match (parse_remote_url(url), host) {
+        (Some(remote), Some(host)) => remote.host.eq_ignore_ascii_case(host),
+        _ => false,
+    }
+}
+
+/// Tool for listing open pull requests
+pub struct ListPullRequests;
+
+impl Tool for ListPullRequests {
+    fn name(&self) -> &'static str {
+        "list_pull_requests"
+    }
+
+    fn description(&self) -> &'static str {
+        "List the open pull requests (merge requests on GitLab) on the repository's code host."
+    }
+
+    fn parameters(&self) -> JsonValue {
+        json!({
+            "type": "object",
+            "properties": {}
+        })
+    }
+
+    fn execute(&self, _args: &JsonValue, context: &ToolContext<'_>) -> String {
+        let forge = match forge::from_repo(context.git_repo, &context.settings.forge) {
+            Ok(forge) => forge,
+            Err(e) => return format!("Error: {e}"),
+        };
+        let kind = forge.kind();
+        let prs = match forge.list_pull_requests() {
+            Ok(prs) => prs,
+            Err(e) => return format!("Error listing {}s: {e}", kind.noun()),
+        };
+        if prs.is_empty() {
+            return format!("No open {}s in {}.", kind.noun(), forge.repo());
+        }
+
+        let mut result = format!("Open {}s in {}:\n", kind.noun(), forge.repo());
+        for pr in prs {
+            result.push_str(&format!(
+                "{} {} ({} → {}) by {}{}\n  {}\n",
+                kind.reference(pr.number),
+                pr.title,
+                pr.head,
+                pr.base,
+                pr.author,
+                if pr.draft { " [draft]" } else { "" },
+                pr.url
+            ));
+        }
+        result
+    }
+}
+
+/// Tool for reading review comments on a pull request
+pub struct GetReviewComments;
+
+impl Tool for GetReviewComments {
+    fn name(&self) -> &'static str {
+        "get_review_comments"
+    }
+
+    fn description(&self) -> &'static str {
+        "Read the reviews and line comments on a pull request (merge request on GitLab)."
+    }
+
+    fn parameters(&self) -> JsonValue {
+        json!({
+            "type": "object",
+            "properties": {
+                "number": {"type": "integer", "description": "Pull request number (merge request IID on GitLab)."}
+            },
+            "required": ["number"]
+        })
+    }
+
+    fn execute(&self, args: &JsonValue, context: &ToolContext<'_>) -> String {
+        let number = match args["number"].as_u64() {
+            Some(n) => n,
+            None => return "Error: 'number' parameter is required".to_string(),
+        };
+        let forge = match forge::from_repo(context.git_repo, &context.settings.forge) {
+            Ok(forge) => forge,
+            Err(e) => return format!("Error: {e}"),
+        };
+        let kind = forge.kind();
+        let reference = format!("{} {}", kind.noun(), kind.reference(number));
+        let comments = match forge.review_comments(number) {
+            Ok(comments) => comments,
+            Err(e) => return format!("Error reading comments on {reference}: {e}"),
+        };
+        if comments.is_empty() {
+            return format!("No review comments on {reference}.");
+        }
+
+        let mut result = format!("Review comments on {reference}:\n");
+        for comment in comments {
+            let location = match (&comment.path, comment.line) {
+                (Some(path), Some(line)) => format!(" on {path}:{line}"),
+                (Some(path), None) => format!(" on {path}"),
+                _ => String::new(),
+            };
+            result.push_str(&format!(
+                "\n{}{location}:\n{}\n",
+                comment.author,
+                comment.body.trim()
+            ));
+        }
+        result
     }
 }
 
@@ -301,7 +750,7 @@ impl Tool for ResolveMergeConflict {
                 }
 
                 // Create backup
-                let backup_manager = BackupManager::new(None);
+                let backup_manager = BackupManager::new(context.settings.backup_retention_days);
                 let backup_path = match backup_manager.create_backup(&path) {
                     Ok(path) => path,
                     Err(e) => return format!("Error creating backup: {e}"),
@@ -438,6 +887,7 @@ fn auto_resolve_conflict(conflict: &MergeConflict) -> (String, String) {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::tools::{CancelToken, ProcessTable};
     use std::path::PathBuf;
 
     fn create_test_context() -> ToolContext<'static> {
@@ -447,9 +897,28 @@ mod tests {
             no_confirm: true,
             git_repo: None,
             tui_mode: false,
+            cancel: CancelToken::default(),
+            output: None,
+            processes: ProcessTable::default(),
+            policy: Default::default(),
+            sandbox: None,
+            tool_name: String::new(),
+            permissions: Default::default(),
+            settings: Default::default(),
         }
     }
 
+    #[test]
+    fn test_same_host() {
+        let host = Some("github.com");
+        assert!(same_host("https://github.com/octo/app.git", host));
+        assert!(same_host("git@GitHub.com:octo/app.git", host));
+        assert!(!same_host("https://gitlab.example.com/octo/app.git", host));
+        assert!(!same_host("https://github.com.evil.example/octo/app", host));
+        assert!(!same_host("/srv/git/app.git", host));
+        assert!(!same_host("https://github.com/octo/app.git", None));
+    }
+
     #[test]
     fn test_auto_resolve_empty_conflict() {
         let conflict = MergeConflict {
@@ -0,0 +1,36 @@
+//! HTTP requests from tools
+//!
+//! Tools run synchronously, often on a Tokio worker thread where blocking on
+//! another runtime would panic, so each request runs on a thread of its own
+//! with a small single-threaded runtime.
+
+use crate::error::{GrokError, Result};
+use std::future::Future;
+use std::time::Duration;
+
+/// How long a single request to an external service may take
+pub const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// HTTP client for talking to external services
+pub fn client() -> Result<reqwest::Client> {
+    Ok(reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .user_agent(concat!("grok-code/", env!("CARGO_PKG_VERSION")))
+        .build()?)
+}
+
+/// Run `future` to completion from synchronous code
+pub fn block_on<F, T>(future: F) -> Result<T>
+where
+    F: Future<Output = Result<T>> + Send + 'static,
+    T: Send + 'static,
+{
+    std::thread::spawn(move || {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?
+            .block_on(future)
+    })
+    .join()
+    .map_err(|_| GrokError::tool_execution("HTTP request thread panicked"))?
+}
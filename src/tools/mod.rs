@@ -5,11 +5,15 @@
 //!
 //! ## Tool Categories
 //!
-//! - **File Operations** ([`file_ops`]): Read, write, edit files
-//! - **Shell Commands** ([`shell`]): Execute system commands
+//! - **File Operations** ([`file_ops`], [`patch`]): Read, write, edit and patch files
+//! - **Shell Commands** ([`shell`], [`process`]): Execute system commands and manage background processes
+//! - **Command Policy** ([`policy`]): Decide which shell commands may run
+//! - **Permissions** ([`permissions`]): Permission modes and remembered approvals
 //! - **Search** ([`search`]): Search through codebases
 //! - **Analysis** ([`analysis`]): Code analysis, debugging, linting
-//! - **Git Operations** ([`git_ops`]): Git commits, PRs, merge conflict resolution
+//! - **Git Operations** ([`git_ops`], [`forge`]): Git commits, pull requests on GitHub, GitLab
+//!   and Gitea, merge conflict resolution
+//! - **Git Inspection** ([`git_inspect`]): Read-only status, diff, log, blame and show
 //! - **External Services** ([`external`]): Web search, Jira integration
 //!
 //! ## Creating Custom Tools
@@ -48,15 +52,19 @@
 //! }
 //! ```
 
+use crate::tui::diff::{parse_unified_diff, DiffHunk};
 use once_cell::sync::Lazy;
 use serde_json::Value as JsonValue;
 use std::path::PathBuf;
-use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 
 // Global mutex for stdin access during confirmations
 static STDIN_MUTEX: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
 
 // Re-export all tool modules
+/// Markdown to Atlassian Document Format for Jira
+pub mod adf;
 /// Tools for code analysis, debugging, and linting
 pub mod analysis;
 /// Backup management tools
@@ -65,8 +73,26 @@ pub mod backup_ops;
 pub mod external;
 /// Tools for file system operations
 pub mod file_ops;
+/// Code hosts: GitHub, GitLab and Gitea
+pub mod forge;
+/// Read-only git tools (status, diff, log, blame, show)
+pub mod git_inspect;
 /// Tools for git operations (commits, PRs, merge conflicts)
 pub mod git_ops;
+/// HTTP requests from synchronous tools
+pub mod http;
+/// Jira REST API client
+pub mod jira;
+/// Atomic multi-file patch application
+pub mod patch;
+/// Permission modes and "always allow" approvals
+pub mod permissions;
+/// Allow / ask / deny rules for shell commands
+pub mod policy;
+/// Tools for long-running background processes
+pub mod process;
+/// Sandbox for shell commands
+pub mod sandbox;
 /// Tools for searching through code
 pub mod search;
 /// Tools for executing shell commands
@@ -78,9 +104,21 @@ pub mod utils;
 pub use analysis::{AnalyzeLog, DebugCode, RunLint};
 pub use backup_ops::{CleanBackups, ListBackups};
 pub use external::{CreateJiraTicket, WebSearch};
-pub use file_ops::{EditFile, ListFiles, ReadFile, WriteFile};
-pub use git_ops::{CreateCommit, ResolveMergeConflict, SubmitPR};
-pub use search::SearchCodebase;
+pub use file_ops::{EditFile, ListFiles, ReadFile, ReplaceInFile, WriteFile};
+pub use forge::ForgeSettings;
+pub use git_inspect::{GitBlame, GitDiff, GitLog, GitShow, GitStatus};
+pub use git_ops::{
+    CreateCommit, GetReviewComments, ListPullRequests, ResolveMergeConflict, SubmitPR,
+};
+pub use jira::JiraSettings;
+pub use patch::ApplyPatch;
+pub use permissions::{Approval, PermissionMode, Permissions};
+pub use policy::{CommandPolicy, CommandRule, Decision, PolicyAction};
+pub use process::{
+    KillProcess, ProcessTable, ReadProcessOutput, SendProcessInput, StartBackgroundProcess,
+};
+pub use sandbox::Sandbox;
+pub use search::{FindFiles, SearchCodebase};
 pub use shell::RunShellCommand;
 
 /// Trait that all tools must implement
@@ -105,6 +143,81 @@ pub struct ToolContext<'a> {
     pub no_confirm: bool,
     pub git_repo: Option<&'a git2::Repository>,
     pub tui_mode: bool,
+    /// Set when the user asks to stop the running tools
+    pub cancel: CancelToken,
+    /// Receives output from long-running tools as it is produced
+    pub output: Option<OutputSink>,
+    /// Background processes started in this session
+    pub processes: ProcessTable,
+    /// Rules deciding which shell commands may run
+    pub policy: Arc<CommandPolicy>,
+    /// Run shell commands inside the sandbox when set
+    pub sandbox: Option<Sandbox>,
+    /// Name of the tool this context was made for; "always allow" answers
+    /// are remembered for it
+    pub tool_name: String,
+    /// Permission mode and approvals remembered by the session
+    pub permissions: Permissions,
+    /// Backup, code host and commit settings from the configuration
+    pub settings: Arc<ToolSettings>,
+}
+
+/// Settings from the resolved configuration that tools read while running
+#[derive(Clone, Debug)]
+pub struct ToolSettings {
+    /// Days to keep backups (0 = keep forever)
+    pub backup_retention_days: u64,
+    /// The code host pull requests go to
+    pub forge: ForgeSettings,
+    /// `Name <email>` entries credited with a trailer on every commit
+    pub co_authors: Vec<String>,
+    /// Where `create_jira_ticket` files issues
+    pub jira: JiraSettings,
+}
+
+impl Default for ToolSettings {
+    fn default() -> Self {
+        Self {
+            backup_retention_days: crate::backup::DEFAULT_RETENTION_DAYS,
+            forge: ForgeSettings::default(),
+            co_authors: Vec::new(),
+            jira: JiraSettings::default(),
+        }
+    }
+}
+
+/// Called with each line of output a tool produces while it runs
+pub type OutputSink = Arc<dyn Fn(&str) + Send + Sync>;
+
+/// Shared flag that lets the user stop running tools
+///
+/// Clones share the same flag. Tools that can run for a long time check
+/// [`is_cancelled`](CancelToken::is_cancelled) and stop early.
+#[derive(Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    /// Ask running tools to stop. Returns whether this was already requested.
+    pub fn cancel(&self) -> bool {
+        self.0.swap(true, Ordering::SeqCst)
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+
+    /// Clear a previous request so new tools can run
+    pub fn reset(&self) {
+        self.0.store(false, Ordering::SeqCst);
+    }
+}
+
+/// A change to one file, proposed by a tool before anything is written
+pub struct ProposedEdit<'a> {
+    /// Path as shown to the user
+    pub path: &'a str,
+    pub old: &'a str,
+    pub new: &'a str,
 }
 
 impl ToolContext<'_> {
@@ -120,32 +233,156 @@ impl ToolContext<'_> {
     }
 
     /// Confirm an action with the user
+    ///
+    /// Skipped when the user already allowed this tool for the session or
+    /// the project.
     pub fn confirm_action(&self, action: &str) -> bool {
+        if self.no_confirm || self.dry_run || self.permissions.tool_allowed(&self.tool_name) {
+            return true;
+        }
+
+        let approval = self.ask(action, &format!("every {} call", self.tool_name));
+        if let Err(e) = self.permissions.allow_tool(&self.tool_name, approval) {
+            self.warn(&e);
+        }
+        approval.approved()
+    }
+
+    /// Confirm a change to files that can't be shown as a diff. Accepted
+    /// without asking in `accept-edits` mode.
+    pub fn confirm_edit(&self, action: &str) -> bool {
+        self.permissions.mode() == PermissionMode::AcceptEdits || self.confirm_action(action)
+    }
+
+    /// Ask whether to go ahead, offering to remember the answer. `always`
+    /// says what an "always" answer allows from then on.
+    fn ask(&self, action: &str, always: &str) -> Approval {
         use std::io::{self, IsTerminal, Write};
 
-        if self.no_confirm || self.dry_run || self.tui_mode {
-            return true;
+        if self.tui_mode {
+            return crate::tui::prompt::ask(action, always);
         }
 
         // Check if stdin is piped/non-interactive
         if !io::stdin().is_terminal() {
             println!(); // New line for clarity
             println!("⚠️  Non-interactive mode: auto-confirming {action} ");
-            return true;
+            return Approval::Once;
         }
 
         // Lock stdin access to ensure only one confirmation prompt at a time
         let _lock = STDIN_MUTEX.lock().unwrap();
 
         println!(); // New line for clarity
-        print!("❓ Confirm {action}? (y/n): ");
+        println!("   \"Always\" allows {always}");
+        print!(
+            "❓ Confirm {action}? [y]es / [a]lways this session / always in this [p]roject / [n]o: "
+        );
         let _ = io::stdout().flush();
         let mut input = String::new();
         match io::stdin().read_line(&mut input) {
-            Ok(_) => input.trim().to_lowercase() == "y",
-            Err(_) => false,
+            Ok(_) => match input.trim().to_lowercase().as_str() {
+                "y" | "yes" => Approval::Once,
+                "a" | "always" => Approval::Session,
+                "p" | "project" => Approval::Project,
+                _ => Approval::Denied,
+            },
+            Err(_) => Approval::Denied,
+        }
+    }
+
+    /// Tell the user about a problem that doesn't stop the tool
+    fn warn(&self, message: &str) {
+        match &self.output {
+            Some(output) => output(&format!("⚠️  {message}")),
+            None => eprintln!("⚠️  {message}"),
         }
     }
+
+    /// Check a shell command against the command policy, asking the user
+    /// when the policy says so. Returns the message to give back instead of
+    /// running the command.
+    pub fn approve_command(&self, command: &str, action: &str) -> Result<(), String> {
+        let decision = self.policy.check(command, &self.project_root);
+        match decision.action {
+            PolicyAction::Allow => Ok(()),
+            PolicyAction::Deny => Err(format!(
+                "Error: Command blocked by policy: {}",
+                decision.reason
+            )),
+            PolicyAction::Ask
+                if self.no_confirm
+                    || self.dry_run
+                    || self
+                        .permissions
+                        .command_allowed(command, &self.project_root) =>
+            {
+                Ok(())
+            }
+            PolicyAction::Ask => {
+                let rules: Vec<String> = CommandRule::allowing(command)
+                    .iter()
+                    .map(CommandRule::describe)
+                    .collect();
+                let approval = self.ask(
+                    &format!("{action} ({})", decision.reason),
+                    &format!("this exact command: {}", rules.join("; ")),
+                );
+                if let Err(e) = self.permissions.allow_command(command, approval) {
+                    self.warn(&e);
+                }
+                if approval.approved() {
+                    Ok(())
+                } else {
+                    Err("Command not confirmed.".to_string())
+                }
+            }
+        }
+    }
+
+    /// Ask the user to approve changes to files
+    ///
+    /// In TUI mode every hunk is shown in a diff view and can be accepted or
+    /// rejected on its own; otherwise this is a plain [`confirm_action`]. Returns
+    /// the content to write for each file, or `None` if nothing was accepted.
+    /// Everything is accepted in `accept-edits` mode.
+    ///
+    /// [`confirm_action`]: ToolContext::confirm_action
+    pub fn review_changes(&self, action: &str, edits: &[ProposedEdit<'_>]) -> Option<Vec<String>> {
+        let proposed = || edits.iter().map(|e| e.new.to_string()).collect();
+
+        if self.permissions.mode() == PermissionMode::AcceptEdits {
+            return Some(proposed());
+        }
+        if self.no_confirm
+            || self.dry_run
+            || !self.tui_mode
+            || self.permissions.tool_allowed(&self.tool_name)
+        {
+            return self.confirm_action(action).then(proposed);
+        }
+
+        let diffs: Vec<Vec<DiffHunk>> = edits
+            .iter()
+            .map(|e| parse_unified_diff(&utils::unified_diff(e.path, e.old, e.new)))
+            .collect();
+        let accepted = crate::tui::prompt::review_diff(action, diffs.concat())?;
+        if !accepted.contains(&true) {
+            return None;
+        }
+
+        let mut offset = 0;
+        let contents = edits
+            .iter()
+            .zip(&diffs)
+            .map(|(edit, hunks)| {
+                let selected = &accepted[offset..offset + hunks.len()];
+                offset += hunks.len();
+                utils::select_hunks(edit.old, edit.new, selected)
+            })
+            .collect();
+        Some(contents)
+    }
 }
 
 /// Registry for all available tools
@@ -165,29 +402,48 @@ impl Default for ToolRegistry {
 }
 
 impl ToolRegistry {
-    /// Create a new tool registry with all available tools
+    /// Create a new tool registry with the built-in tools
     pub fn new() -> Self {
+        Self::with_plugins(false)
+    }
+
+    /// Create a tool registry with the built-in tools, and the plugin tools
+    /// too when `plugins` is set
+    pub fn with_plugins(plugins: bool) -> Self {
         let mut tools: Vec<Box<dyn Tool + Send + Sync>> = vec![
             Box::new(ReadFile),
             Box::new(WriteFile),
             Box::new(EditFile),
+            Box::new(ReplaceInFile),
+            Box::new(ApplyPatch),
             Box::new(ListFiles),
             Box::new(RunShellCommand),
+            Box::new(StartBackgroundProcess),
+            Box::new(ReadProcessOutput),
+            Box::new(SendProcessInput),
+            Box::new(KillProcess),
             Box::new(SearchCodebase),
+            Box::new(FindFiles),
             Box::new(DebugCode),
             Box::new(AnalyzeLog),
             Box::new(RunLint),
+            Box::new(GitStatus),
+            Box::new(GitDiff),
+            Box::new(GitLog),
+            Box::new(GitBlame),
+            Box::new(GitShow),
             Box::new(ResolveMergeConflict),
             Box::new(CreateCommit),
             Box::new(SubmitPR),
+            Box::new(ListPullRequests),
+            Box::new(GetReviewComments),
             Box::new(WebSearch),
             Box::new(CreateJiraTicket),
             Box::new(ListBackups),
             Box::new(CleanBackups),
         ];
 
-        // Load plugins if enabled
-        if std::env::var("GROK_PLUGINS").unwrap_or_else(|_| "true".to_string()) == "true" {
+        if plugins {
             match Self::load_plugins() {
                 Ok(plugin_tools) => {
                     let count = plugin_tools.len();
@@ -0,0 +1,414 @@
+//! Atomic multi-file patch application
+//!
+//! Every change in a patch is checked against the files on disk before
+//! anything is written. Changed files are backed up first, and if a write
+//! fails the backups are restored so the tree is never left half-patched.
+
+use super::file_ops::{replace_text, PARTIAL_NOTE};
+use super::utils::{sanitize_path, unified_diff};
+use super::{ProposedEdit, Tool, ToolContext};
+use crate::backup::BackupManager;
+use crate::tui::diff::{parse_unified_diff_checked, DiffHunk, DiffLineType};
+use serde_json::{json, Value as JsonValue};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Tool for applying a patch to several files at once, all or nothing
+pub struct ApplyPatch;
+
+impl Tool for ApplyPatch {
+    fn name(&self) -> &'static str {
+        "apply_patch"
+    }
+
+    fn description(&self) -> &'static str {
+        "Apply changes to several files at once, all or nothing. Pass either 'patch' (a unified diff; use /dev/null as the old or new path to create or delete a file) or 'edits' (a list of search/replace edits). Every change is validated before any file is written, and all files are restored from backup if a write fails."
+    }
+
+    fn parameters(&self) -> JsonValue {
+        json!({
+            "type": "object",
+            "properties": {
+                "patch": {"type": "string", "description": "A unified diff covering one or more files."},
+                "edits": {
+                    "type": "array",
+                    "description": "Search/replace edits, applied in order.",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "path": {"type": "string", "description": "The path to the file."},
+                            "old_string": {"type": "string", "description": "The exact text to replace."},
+                            "new_string": {"type": "string", "description": "The replacement text."},
+                            "replace_all": {"type": "boolean", "description": "Replace every occurrence (default: false)."}
+                        },
+                        "required": ["path", "old_string", "new_string"]
+                    }
+                }
+            }
+        })
+    }
+
+    fn execute(&self, args: &JsonValue, context: &ToolContext<'_>) -> String {
+        let changes = match (args["patch"].as_str(), args["edits"].as_array()) {
+            (Some(patch), None) => plan_patch(patch, &context.project_root),
+            (None, Some(edits)) => plan_edits(edits, &context.project_root),
+            (Some(_), Some(_)) => {
+                return "Error: pass either 'patch' or 'edits', not both".to_string()
+            }
+            (None, None) => return "Error: 'patch' or 'edits' parameter is required".to_string(),
+        };
+        let changes: Vec<FileChange> = match changes {
+            Ok(changes) => changes.into_iter().filter(|c| c.old != c.new).collect(),
+            Err(e) => return format!("Error: {e}. No files were changed."),
+        };
+        if changes.is_empty() {
+            return "Error: the patch makes no changes".to_string();
+        }
+
+        let edits: Vec<ProposedEdit> = changes
+            .iter()
+            .map(|c| ProposedEdit {
+                path: &c.display,
+                old: c.old.as_deref().unwrap_or(""),
+                new: c.new.as_deref().unwrap_or(""),
+            })
+            .collect();
+        let Some(reviewed) =
+            context.review_changes(&format!("apply patch to {}", describe(&changes)), &edits)
+        else {
+            return "Patch not confirmed.".to_string();
+        };
+
+        // Keep only what the user accepted
+        let mut partial = false;
+        let changes: Vec<FileChange> = changes
+            .into_iter()
+            .zip(reviewed)
+            .filter_map(|(mut change, content)| {
+                if content == change.new.as_deref().unwrap_or("") {
+                    return Some(change);
+                }
+                partial = true;
+                if content == change.old.as_deref().unwrap_or("") {
+                    return None;
+                }
+                change.new = Some(content);
+                Some(change)
+            })
+            .collect();
+        let note = if partial {
+            format!(" {PARTIAL_NOTE}")
+        } else {
+            String::new()
+        };
+
+        let summary = describe(&changes);
+        let diffs: String = changes
+            .iter()
+            .map(|c| {
+                unified_diff(
+                    &c.display,
+                    c.old.as_deref().unwrap_or(""),
+                    c.new.as_deref().unwrap_or(""),
+                )
+            })
+            .collect();
+
+        if context.dry_run {
+            return format!("Dry-run: Would apply patch to {summary}:\n{diffs}");
+        }
+
+        // Back up everything before the first write
+        let backup_manager = BackupManager::new(context.settings.backup_retention_days);
+        let mut backups = Vec::with_capacity(changes.len());
+        for change in &changes {
+            if change.old.is_none() {
+                backups.push(None);
+                continue;
+            }
+            match backup_manager.create_backup(&change.path) {
+                Ok(backup_path) => backups.push(Some(backup_path)),
+                Err(e) => {
+                    return format!(
+                        "Error creating backup of {}: {e}. No files were changed.",
+                        change.display
+                    )
+                }
+            }
+        }
+
+        for (i, change) in changes.iter().enumerate() {
+            if let Err(e) = write_change(change) {
+                let failed = rollback(&backup_manager, &changes[..=i], &backups);
+                let outcome = if failed.is_empty() {
+                    "All changes were rolled back.".to_string()
+                } else {
+                    format!("Rollback failed for: {}", failed.join(", "))
+                };
+                return format!("Error writing {}: {e}. {outcome}", change.display);
+            }
+        }
+
+        let backed_up: Vec<String> = backups
+            .iter()
+            .flatten()
+            .map(|p| p.display().to_string())
+            .collect();
+        if backed_up.is_empty() {
+            format!("Patch applied to {summary}.{note}\n{diffs}")
+        } else {
+            format!(
+                "Patch applied to {summary} (backed up to {}).{note}\n{diffs}",
+                backed_up.join(", ")
+            )
+        }
+    }
+}
+
+/// "2 files (a.rs, b.rs)"
+fn describe(changes: &[FileChange]) -> String {
+    let files: Vec<&str> = changes.iter().map(|c| c.display.as_str()).collect();
+    format!(
+        "{} file{} ({})",
+        files.len(),
+        if files.len() == 1 { "" } else { "s" },
+        files.join(", ")
+    )
+}
+
+/// New content for one file; `None` on either side means the file is
+/// created or deleted
+struct FileChange {
+    display: String,
+    path: PathBuf,
+    old: Option<String>,
+    new: Option<String>,
+}
+
+fn read_existing(path: &Path, display: &str) -> Result<String, String> {
+    fs::read_to_string(path).map_err(|e| match e.kind() {
+        std::io::ErrorKind::NotFound => format!("file not found: {display}"),
+        _ => format!("cannot read {display}: {e}"),
+    })
+}
+
+fn plan_patch(patch: &str, root: &Path) -> Result<Vec<FileChange>, String> {
+    let hunks = parse_unified_diff_checked(patch)?;
+    if hunks.is_empty() {
+        return Err("no hunks found in patch".to_string());
+    }
+
+    // Group hunks by file, keeping the order of the patch
+    let mut files: Vec<(&str, Vec<&DiffHunk>)> = Vec::new();
+    for hunk in &hunks {
+        match files.iter_mut().find(|(path, _)| *path == hunk.file_path) {
+            Some((_, file_hunks)) => file_hunks.push(hunk),
+            None => files.push((&hunk.file_path, vec![hunk])),
+        }
+    }
+
+    let mut changes = Vec::with_capacity(files.len());
+    for (display, file_hunks) in files {
+        if display.is_empty() || display == "/dev/null" {
+            return Err("hunk without a file header".to_string());
+        }
+        let path = sanitize_path(display, root)?;
+        let creates = file_hunks[0].old_start == 0 && file_hunks[0].old_count == 0;
+        let deletes = file_hunks[0].new_start == 0 && file_hunks[0].new_count == 0;
+
+        let old = if creates {
+            if path.exists() {
+                return Err(format!("{display} already exists"));
+            }
+            None
+        } else {
+            Some(read_existing(&path, display)?)
+        };
+        let new = apply_hunks(old.as_deref().unwrap_or(""), &file_hunks, display)?;
+
+        changes.push(FileChange {
+            display: display.to_string(),
+            path,
+            old,
+            new: if deletes && new.is_empty() {
+                None
+            } else {
+                Some(new)
+            },
+        });
+    }
+    Ok(changes)
+}
+
+fn plan_edits(edits: &[JsonValue], root: &Path) -> Result<Vec<FileChange>, String> {
+    if edits.is_empty() {
+        return Err("'edits' is empty".to_string());
+    }
+
+    let mut changes: Vec<FileChange> = Vec::new();
+    for (i, edit) in edits.iter().enumerate() {
+        let n = i + 1;
+        let display = edit["path"]
+            .as_str()
+            .ok_or_else(|| format!("edit {n}: 'path' is required"))?;
+        let old_string = match edit["old_string"].as_str() {
+            Some(s) if !s.is_empty() => s,
+            _ => return Err(format!("edit {n}: 'old_string' must be a non-empty string")),
+        };
+        let new_string = edit["new_string"]
+            .as_str()
+            .ok_or_else(|| format!("edit {n}: 'new_string' is required"))?;
+        let replace_all = edit["replace_all"].as_bool().unwrap_or(false);
+
+        let path = sanitize_path(display, root).map_err(|e| format!("edit {n}: {e}"))?;
+        let index = match changes.iter().position(|c| c.path == path) {
+            Some(index) => index,
+            None => {
+                let content =
+                    read_existing(&path, display).map_err(|e| format!("edit {n}: {e}"))?;
+                changes.push(FileChange {
+                    display: display.to_string(),
+                    path,
+                    old: Some(content.clone()),
+                    new: Some(content),
+                });
+                changes.len() - 1
+            }
+        };
+
+        let change = &mut changes[index];
+        let current = change.new.as_deref().unwrap_or("");
+        let replacement = replace_text(current, old_string, new_string, replace_all)
+            .map_err(|e| format!("edit {n} ({display}): {e}"))?;
+        change.new = Some(replacement.content);
+    }
+    Ok(changes)
+}
+
+/// Apply the hunks of one file in order. A hunk that no longer sits at its
+/// recorded line is looked for elsewhere, and the nearest match wins.
+fn apply_hunks(content: &str, hunks: &[&DiffHunk], display: &str) -> Result<String, String> {
+    let eol = if content.contains("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    };
+    let lines: Vec<&str> = content.lines().collect();
+    let mut out: Vec<&str> = Vec::with_capacity(lines.len());
+    let mut pos = 0;
+
+    for (i, hunk) in hunks.iter().enumerate() {
+        let old: Vec<&str> = hunk
+            .lines
+            .iter()
+            .filter(|l| matches!(l.line_type, DiffLineType::Context | DiffLineType::Removed))
+            .map(|l| l.content.as_str())
+            .collect();
+        let new = hunk
+            .lines
+            .iter()
+            .filter(|l| matches!(l.line_type, DiffLineType::Context | DiffLineType::Added))
+            .map(|l| l.content.as_str());
+
+        // A pure insertion's start is the line it goes after
+        let expected = if old.is_empty() {
+            hunk.old_start
+        } else {
+            hunk.old_start.saturating_sub(1)
+        };
+        let at = find_hunk(&lines, &old, expected, pos).ok_or_else(|| {
+            format!(
+                "hunk {} of {display} does not apply at line {}",
+                i + 1,
+                hunk.old_start
+            )
+        })?;
+
+        out.extend(&lines[pos..at]);
+        out.extend(new);
+        pos = at + old.len();
+    }
+    out.extend(&lines[pos..]);
+
+    let mut result = out.join(eol);
+    if !out.is_empty() && (content.is_empty() || content.ends_with('\n')) {
+        result.push_str(eol);
+    }
+    Ok(result)
+}
+
+/// Position of `old` in `lines` at or after `min`, nearest to `expected`.
+/// Trailing whitespace is ignored.
+fn find_hunk(lines: &[&str], old: &[&str], expected: usize, min: usize) -> Option<usize> {
+    if old.is_empty() {
+        return Some(expected.clamp(min, lines.len().max(min)));
+    }
+    if old.len() > lines.len() {
+        return None;
+    }
+
+    (min..=lines.len() - old.len())
+        .filter(|&start| {
+            old.iter()
+                .zip(&lines[start..])
+                .all(|(o, l)| o.trim_end() == l.trim_end())
+        })
+        .min_by_key(|&start| start.abs_diff(expected))
+}
+
+fn write_change(change: &FileChange) -> std::io::Result<()> {
+    match &change.new {
+        Some(content) => {
+            if let Some(parent) = change.path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(&change.path, content)
+        }
+        None => fs::remove_file(&change.path),
+    }
+}
+
+/// Undo the given changes; returns the files that could not be restored
+fn rollback(
+    backup_manager: &BackupManager,
+    changes: &[FileChange],
+    backups: &[Option<PathBuf>],
+) -> Vec<String> {
+    changes
+        .iter()
+        .zip(backups)
+        .filter(|(change, backup)| {
+            let restored = match backup {
+                Some(backup_path) => backup_manager
+                    .restore_backup(backup_path, &change.path)
+                    .is_ok(),
+                None => !change.path.exists() || fs::remove_file(&change.path).is_ok(),
+            };
+            !restored
+        })
+        .map(|(change, _)| change.display.clone())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_hunks_with_offset() {
+        let patch = "--- a/f.txt\n+++ b/f.txt\n@@ -2,3 +2,3 @@\n b\n-c\n+C\n d\n@@ -6,2 +6,3 @@\n f\n g\n+h\n";
+        let hunks = parse_unified_diff_checked(patch).unwrap();
+        let hunks: Vec<&DiffHunk> = hunks.iter().collect();
+        assert_eq!(hunks.len(), 2);
+
+        // Two lines were added at the top since the diff was made
+        let content = "x\ny\na\nb\nc\nd\ne\nf\ng\n";
+        assert_eq!(
+            apply_hunks(content, &hunks, "f.txt").unwrap(),
+            "x\ny\na\nb\nC\nd\ne\nf\ng\nh\n"
+        );
+
+        let err = apply_hunks("a\nb\nz\nd\n", &hunks, "f.txt").unwrap_err();
+        assert!(err.contains("hunk 1 of f.txt does not apply"));
+    }
+}
@@ -0,0 +1,297 @@
+//! Permission modes and remembered approvals
+//!
+//! The mode decides how much the agent may do without asking:
+//!
+//! - `plan`: nothing is changed; tools report what they would do
+//! - `ask`: every change and every command not allowed by the command policy
+//!   needs confirmation
+//! - `accept-edits`: file edits are applied without review, everything else
+//!   is asked as in `ask`
+//! - `bypass`: nothing is asked
+//!
+//! When asked, the user can approve once, for the rest of the session or for
+//! the project. An approved shell command is remembered as exactly that
+//! command, never a wider pattern. Project approvals are appended to the
+//! user's approvals file for the project (see
+//! [`Config::approvals_path`](crate::config::Config::approvals_path)), not
+//! to a file in the repository: tools as `[[permissions.always_allow]]`
+//! entries and shell commands as allow rules in `[[commands.rules]]`.
+
+use super::policy::{CommandPolicy, CommandRule, PolicyAction};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// How much the agent may do without asking
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum PermissionMode {
+    /// Change nothing, only describe the changes
+    Plan,
+    /// Ask before every change
+    #[default]
+    Ask,
+    /// Apply file edits without asking; ask for everything else
+    AcceptEdits,
+    /// Never ask
+    Bypass,
+}
+
+impl PermissionMode {
+    pub const ALL: [PermissionMode; 4] = [
+        PermissionMode::Plan,
+        PermissionMode::Ask,
+        PermissionMode::AcceptEdits,
+        PermissionMode::Bypass,
+    ];
+
+    /// One-line explanation for the user
+    pub fn description(self) -> &'static str {
+        match self {
+            PermissionMode::Plan => "plan only, nothing is changed",
+            PermissionMode::Ask => "ask before changes and commands",
+            PermissionMode::AcceptEdits => "apply file edits, ask before anything else",
+            PermissionMode::Bypass => "never ask",
+        }
+    }
+}
+
+impl fmt::Display for PermissionMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            PermissionMode::Plan => "plan",
+            PermissionMode::Ask => "ask",
+            PermissionMode::AcceptEdits => "accept-edits",
+            PermissionMode::Bypass => "bypass",
+        })
+    }
+}
+
+impl FromStr for PermissionMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        PermissionMode::ALL
+            .into_iter()
+            .find(|mode| mode.to_string() == s.trim())
+            .ok_or_else(|| {
+                format!(
+                    "unknown permission mode '{}' (expected plan, ask, accept-edits or bypass)",
+                    s.trim()
+                )
+            })
+    }
+}
+
+/// The user's answer to a confirmation prompt
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Approval {
+    Denied,
+    Once,
+    /// Don't ask again until the agent exits
+    Session,
+    /// Don't ask again in this project
+    Project,
+}
+
+impl Approval {
+    pub fn approved(self) -> bool {
+        self != Approval::Denied
+    }
+}
+
+/// A `[[permissions.always_allow]]` entry
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ToolGrant {
+    pub tool: String,
+}
+
+#[derive(Default)]
+struct State {
+    mode: PermissionMode,
+    tools: BTreeSet<String>,
+    rules: Vec<CommandRule>,
+    project_file: Option<PathBuf>,
+}
+
+/// Permission mode and approvals remembered by this session
+///
+/// Clones share the same state, so a mode change or an approval given while
+/// one tool runs applies to every later tool.
+#[derive(Clone, Default)]
+pub struct Permissions {
+    state: Arc<Mutex<State>>,
+}
+
+impl Permissions {
+    /// Permissions in `mode` with `tools` already allowed. Project approvals
+    /// are written to `project_file`; without one they last for the session.
+    pub fn new(mode: PermissionMode, tools: Vec<String>, project_file: Option<PathBuf>) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(State {
+                mode,
+                tools: tools.into_iter().collect(),
+                rules: Vec::new(),
+                project_file,
+            })),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn mode(&self) -> PermissionMode {
+        self.lock().mode
+    }
+
+    pub fn set_mode(&self, mode: PermissionMode) {
+        self.lock().mode = mode;
+    }
+
+    /// Whether the user already allowed every use of `tool`
+    pub fn tool_allowed(&self, tool: &str) -> bool {
+        self.lock().tools.contains(tool)
+    }
+
+    /// Whether every command in `command` was allowed by an earlier answer
+    pub fn command_allowed(&self, command: &str, project_root: &Path) -> bool {
+        let rules = self.lock().rules.clone();
+        !rules.is_empty()
+            && CommandPolicy::new(PolicyAction::Ask, rules)
+                .check(command, project_root)
+                .action
+                == PolicyAction::Allow
+    }
+
+    /// Remember that `tool` may run without asking
+    pub fn allow_tool(&self, tool: &str, approval: Approval) -> Result<(), String> {
+        if !matches!(approval, Approval::Session | Approval::Project) {
+            return Ok(());
+        }
+        let project_file = {
+            let mut state = self.lock();
+            state.tools.insert(tool.to_string());
+            state.project_file.clone()
+        };
+        match project_file {
+            Some(path) if approval == Approval::Project => append_table(
+                &path,
+                "permissions.always_allow",
+                &ToolGrant {
+                    tool: tool.to_string(),
+                },
+            ),
+            _ => Ok(()),
+        }
+    }
+
+    /// Remember that exactly the commands in `command` may run without asking
+    pub fn allow_command(&self, command: &str, approval: Approval) -> Result<(), String> {
+        if !matches!(approval, Approval::Session | Approval::Project) {
+            return Ok(());
+        }
+        let rules = CommandRule::allowing(command);
+        let project_file = {
+            let mut state = self.lock();
+            state.rules.extend(rules.iter().cloned());
+            state.project_file.clone()
+        };
+        match project_file {
+            Some(path) if approval == Approval::Project => rules
+                .iter()
+                .try_for_each(|rule| append_table(&path, "commands.rules", rule)),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Append `value` to the config file at `path` as a `[[key]]` entry. The file
+/// is left as it was if the result would not load.
+fn append_table(path: &Path, key: &str, value: &impl Serialize) -> Result<(), String> {
+    let body = toml::to_string(value).map_err(|e| format!("Could not save permission: {e}"))?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("Could not create {}: {e}", dir.display()))?;
+    }
+    let previous = match std::fs::read_to_string(path) {
+        Ok(content) => Some(content),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+        Err(e) => return Err(format!("Could not read {}: {e}", path.display())),
+    };
+
+    let mut content = previous.clone().unwrap_or_default();
+    if !content.is_empty() {
+        if !content.ends_with('\n') {
+            content.push('\n');
+        }
+        content.push('\n');
+    }
+    content.push_str(&format!("[[{key}]]\n{body}"));
+
+    std::fs::write(path, &content)
+        .map_err(|e| format!("Could not write {}: {e}", path.display()))?;
+    if let Err(e) = crate::config::PartialConfig::from_file(path) {
+        let _ = match previous {
+            Some(previous) => std::fs::write(path, previous),
+            None => std::fs::remove_file(path),
+        };
+        return Err(format!("Could not save permission: {e}"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::PartialConfig;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_remembered_approvals() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        let file = root.join("projects").join("app-1234.toml");
+        std::fs::create_dir(root.join("projects")).unwrap();
+        std::fs::write(&file, "# my settings\nmodel = \"grok-4\"").unwrap();
+        let permissions = Permissions::new(PermissionMode::Ask, Vec::new(), Some(file.clone()));
+
+        assert!(!permissions.command_allowed("cargo test", root));
+        permissions
+            .allow_command("cargo test --release", Approval::Session)
+            .unwrap();
+        assert!(permissions.command_allowed("cargo test --release", root));
+        assert!(!permissions.command_allowed("cargo test -p core", root));
+        assert!(!permissions.command_allowed("cargo test --release -p core", root));
+        assert!(!permissions.command_allowed("cargo publish", root));
+        assert!(!permissions.command_allowed("cargo test && make", root));
+
+        permissions
+            .allow_command("git status", Approval::Project)
+            .unwrap();
+        permissions
+            .allow_tool("create_commit", Approval::Project)
+            .unwrap();
+        permissions
+            .allow_tool("write_file", Approval::Once)
+            .unwrap();
+        assert!(permissions.tool_allowed("create_commit"));
+        assert!(!permissions.tool_allowed("write_file"));
+
+        let content = std::fs::read_to_string(&file).unwrap();
+        assert!(content.starts_with("# my settings\nmodel = \"grok-4\"\n\n[[commands.rules]]"));
+        let saved = PartialConfig::from_file(&file).unwrap();
+        assert_eq!(saved.model.as_deref(), Some("grok-4"));
+        assert_eq!(saved.commands.rules, CommandRule::allowing("git status"));
+        assert_eq!(
+            saved.permissions.always_allow,
+            [ToolGrant {
+                tool: "create_commit".to_string()
+            }]
+        );
+    }
+}
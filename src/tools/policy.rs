@@ -0,0 +1,957 @@
+//! Shell command policy
+//!
+//! A command line is split into simple commands, each parsed into an argv,
+//! and checked against allow / ask / deny [`CommandRule`]s. Rules come from
+//! the `[commands]` config section, project rules before user rules, followed
+//! by built-in rules that deny obviously destructive commands. The first rule
+//! that matches a command decides; when a line runs several commands the
+//! strictest decision wins.
+//!
+//! ```toml
+//! [commands]
+//! default = "ask"
+//!
+//! [[commands.rules]]
+//! action = "allow"
+//! command = "cargo"
+//! args = ["test"]
+//!
+//! [[commands.rules]]
+//! action = "deny"
+//! command = "git"
+//! args = ["push"]
+//! flags = ["--force|-f"]
+//! reason = "Force pushes need a human"
+//! ```
+
+use globset::{Glob, GlobBuilder, GlobMatcher};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::{Component, Path, PathBuf};
+use std::str::FromStr;
+
+/// What to do with a command
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum PolicyAction {
+    /// Run without asking
+    Allow,
+    /// Ask the user first (unless confirmations are turned off)
+    Ask,
+    /// Never run
+    Deny,
+}
+
+impl fmt::Display for PolicyAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PolicyAction::Allow => write!(f, "allow"),
+            PolicyAction::Ask => write!(f, "ask"),
+            PolicyAction::Deny => write!(f, "deny"),
+        }
+    }
+}
+
+impl FromStr for PolicyAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "allow" => Ok(PolicyAction::Allow),
+            "ask" => Ok(PolicyAction::Ask),
+            "deny" => Ok(PolicyAction::Deny),
+            other => Err(format!(
+                "unknown command policy action '{other}' (expected allow, ask or deny)"
+            )),
+        }
+    }
+}
+
+/// One `[[commands.rules]]` entry
+///
+/// Every field that is set must match for the rule to apply.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct CommandRule {
+    pub action: PolicyAction,
+    /// Glob for the program name (`cargo`, `python*`). `>` matches the
+    /// targets of output redirections. With `exact_args` it is matched
+    /// against the program as written, path included.
+    pub command: String,
+    /// Arguments that must follow the program exactly, in order and with
+    /// nothing else; remembered approvals use this
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exact_args: Option<Vec<String>>,
+    /// Globs that must each match one of the non-flag arguments
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub args: Vec<String>,
+    /// Flags that must all be present; `|` separates alternatives
+    /// (`-r|-R|--recursive`). Combined short flags like `-rf` are split.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub flags: Vec<String>,
+    /// Globs matched against path arguments after resolving them to absolute
+    /// paths. Relative patterns are relative to the project root, which is
+    /// also available as `{project}`; `~` is the home directory.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub paths: Vec<String>,
+    /// Shown to the user and the model when the rule decides
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+impl CommandRule {
+    fn builtin(
+        action: PolicyAction,
+        command: &str,
+        args: &[&str],
+        flags: &[&str],
+        paths: &[&str],
+        reason: &str,
+    ) -> Self {
+        let owned = |items: &[&str]| items.iter().map(|s| s.to_string()).collect();
+        Self {
+            action,
+            command: command.to_string(),
+            exact_args: None,
+            args: owned(args),
+            flags: owned(flags),
+            paths: owned(paths),
+            reason: Some(reason.to_string()),
+        }
+    }
+
+    /// Allow rules for the commands in `command`, each matching that exact
+    /// argv and nothing else (`cargo test` allows neither
+    /// `cargo test --release` nor `cargo publish`)
+    pub fn allowing(command: &str) -> Vec<Self> {
+        let mut rules: Vec<Self> = Vec::new();
+        for cmd in parse_command_line(command).unwrap_or_default() {
+            let Some((program, args)) = cmd.argv.split_first() else {
+                continue;
+            };
+            let rule = Self {
+                action: PolicyAction::Allow,
+                command: glob_escape(program),
+                exact_args: Some(args.to_vec()),
+                args: Vec::new(),
+                flags: Vec::new(),
+                paths: Vec::new(),
+                reason: None,
+            };
+            if !rules.contains(&rule) {
+                rules.push(rule);
+            }
+        }
+        rules
+    }
+
+    /// The rule in one line, as shown to the user
+    pub fn describe(&self) -> String {
+        let mut parts = vec![format!("command = {}", self.command)];
+        if let Some(exact_args) = &self.exact_args {
+            parts.push(format!("exact_args = {exact_args:?}"));
+        }
+        if !self.args.is_empty() {
+            parts.push(format!("args = {:?}", self.args));
+        }
+        if !self.flags.is_empty() {
+            parts.push(format!("flags = {:?}", self.flags));
+        }
+        if !self.paths.is_empty() {
+            parts.push(format!("paths = {:?}", self.paths));
+        }
+        format!("{} rule ({})", self.action, parts.join(", "))
+    }
+
+    fn matches(&self, cmd: &SimpleCommand, project_root: &Path) -> bool {
+        let Some((program, rest)) = cmd.argv.split_first() else {
+            return false;
+        };
+        if let Some(exact_args) = &self.exact_args {
+            return glob_matches(&self.command, program)
+                && rest == exact_args.as_slice()
+                && self.args.is_empty()
+                && self.flags.is_empty()
+                && self.paths.is_empty();
+        }
+        let name = Path::new(program)
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_else(|| program.clone());
+        if !glob_matches(&self.command, &name) {
+            return false;
+        }
+
+        let args = cmd.args();
+        if !self
+            .args
+            .iter()
+            .all(|pattern| args.iter().any(|arg| glob_matches(pattern, arg)))
+        {
+            return false;
+        }
+
+        let flags = cmd.flags();
+        if !self.flags.iter().all(|wanted| {
+            wanted
+                .split('|')
+                .any(|alt| flags.iter().any(|flag| flag == alt.trim()))
+        }) {
+            return false;
+        }
+
+        if self.paths.is_empty() {
+            return true;
+        }
+        let patterns: Vec<GlobMatcher> = self
+            .paths
+            .iter()
+            .filter_map(|p| path_glob(p, project_root))
+            .collect();
+        cmd.path_args(project_root).iter().any(|path| {
+            let path = path.to_string_lossy();
+            patterns.iter().any(|glob| glob.is_match(path.as_ref()))
+        })
+    }
+}
+
+/// The outcome of checking a command line
+#[derive(Debug, Clone, PartialEq)]
+pub struct Decision {
+    pub action: PolicyAction,
+    /// Why; names the rule that decided
+    pub reason: String,
+}
+
+/// Rules for shell commands run by tools and plugins
+#[derive(Debug, Clone)]
+pub struct CommandPolicy {
+    default: PolicyAction,
+    rules: Vec<CommandRule>,
+}
+
+impl Default for CommandPolicy {
+    /// Built-in rules only; anything else needs confirmation
+    fn default() -> Self {
+        Self::new(PolicyAction::Ask, Vec::new())
+    }
+}
+
+static FUNCTION_DEF: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"([A-Za-z_:][\w:]*)\s*\(\)\s*\{([^}]*)\}").unwrap());
+
+impl CommandPolicy {
+    /// A policy with `rules` checked before the built-in ones
+    pub fn new(default: PolicyAction, mut rules: Vec<CommandRule>) -> Self {
+        rules.extend(builtin_rules());
+        Self { default, rules }
+    }
+
+    /// Decide whether a shell command line may run in `project_root`
+    pub fn check(&self, command: &str, project_root: &Path) -> Decision {
+        if command.trim().is_empty() {
+            return Decision {
+                action: PolicyAction::Deny,
+                reason: "Command cannot be empty".to_string(),
+            };
+        }
+        if let Some(reason) = fork_bomb(command) {
+            return Decision {
+                action: PolicyAction::Deny,
+                reason,
+            };
+        }
+
+        let commands = match parse_command_line(command) {
+            Ok(commands) => commands,
+            Err(e) => {
+                return Decision {
+                    action: PolicyAction::Ask.max(self.default),
+                    reason: format!("could not parse command: {e}"),
+                }
+            }
+        };
+
+        commands
+            .iter()
+            .map(|cmd| self.check_simple(cmd, project_root))
+            .reduce(|strictest, next| {
+                if next.action > strictest.action {
+                    next
+                } else {
+                    strictest
+                }
+            })
+            .unwrap_or(Decision {
+                action: self.default,
+                reason: "command is empty".to_string(),
+            })
+    }
+
+    /// Decide whether a program may run with these exact arguments
+    pub fn check_argv(&self, argv: &[String], project_root: &Path) -> Decision {
+        let cmd = SimpleCommand {
+            argv: argv.to_vec(),
+        };
+        self.check_simple(&cmd, project_root)
+    }
+
+    fn check_simple(&self, cmd: &SimpleCommand, project_root: &Path) -> Decision {
+        match self
+            .rules
+            .iter()
+            .find(|rule| rule.matches(cmd, project_root))
+        {
+            Some(rule) => Decision {
+                action: rule.action,
+                reason: match &rule.reason {
+                    Some(reason) => format!("{reason} [{}]", rule.describe()),
+                    None => format!("matched {}", rule.describe()),
+                },
+            },
+            None => Decision {
+                action: self.default,
+                reason: format!(
+                    "no command rule matches '{}'",
+                    cmd.argv.first().map(String::as_str).unwrap_or_default()
+                ),
+            },
+        }
+    }
+}
+
+/// Rules that apply even without any configuration
+fn builtin_rules() -> Vec<CommandRule> {
+    use PolicyAction::Deny;
+    vec![
+        CommandRule::builtin(
+            Deny,
+            "rm",
+            &[],
+            &["-r|-R|--recursive"],
+            &["/", "/*", "~", "~/[*]", "{project}", "{project}/[*]"],
+            "Recursive delete of the filesystem root, home directory or whole project",
+        ),
+        CommandRule::builtin(
+            Deny,
+            "chmod",
+            &[],
+            &[],
+            &["/", "/*"],
+            "Changing permissions of the filesystem root or a top-level directory",
+        ),
+        CommandRule::builtin(
+            Deny,
+            "chown",
+            &[],
+            &["-R|--recursive"],
+            &["/", "/*"],
+            "Recursively changing the owner of system directories",
+        ),
+        CommandRule::builtin(
+            Deny,
+            "dd",
+            &["of=/dev/*"],
+            &[],
+            &[],
+            "Writing directly to a device",
+        ),
+        CommandRule::builtin(Deny, "mkfs*", &[], &[], &[], "Formatting a filesystem"),
+        CommandRule::builtin(
+            Deny,
+            "{nc,ncat,netcat}",
+            &[],
+            &["-l|--listen"],
+            &[],
+            "Opening a network listener (potential backdoor)",
+        ),
+        CommandRule::builtin(
+            Deny,
+            ">",
+            &[],
+            &[],
+            &[
+                "/etc/**",
+                "/boot/**",
+                "/proc/sys/**",
+                "/dev/sd*",
+                "/dev/nvme*",
+            ],
+            "Overwriting a system file or device",
+        ),
+    ]
+}
+
+/// A shell function that pipes into itself, like `:(){ :|:& };:`
+fn fork_bomb(command: &str) -> Option<String> {
+    FUNCTION_DEF.captures_iter(command).find_map(|caps| {
+        let name = &caps[1];
+        let body: String = caps[2].split_whitespace().collect();
+        body.contains(&format!("{name}|{name}"))
+            .then(|| format!("Fork bomb: function '{name}' pipes into itself"))
+    })
+}
+
+fn glob_matches(pattern: &str, text: &str) -> bool {
+    match Glob::new(pattern) {
+        Ok(glob) => glob.compile_matcher().is_match(text),
+        Err(_) => pattern == text,
+    }
+}
+
+/// A glob that matches `text` literally
+fn glob_escape(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '*' | '?' | '[' | ']' | '{' | '}' | '\\' => format!("[{c}]"),
+            c => c.to_string(),
+        })
+        .collect()
+}
+
+fn home_dir() -> PathBuf {
+    dirs::home_dir().unwrap_or_else(|| PathBuf::from("/"))
+}
+
+/// Compile a `paths` pattern into a glob over absolute paths
+fn path_glob(pattern: &str, project_root: &Path) -> Option<GlobMatcher> {
+    let project = project_root.to_string_lossy();
+    let pattern = pattern.replace("{project}", &project);
+    let pattern = if pattern == "~" {
+        home_dir().to_string_lossy().into_owned()
+    } else if let Some(rest) = pattern.strip_prefix("~/") {
+        format!(
+            "{}/{rest}",
+            home_dir().to_string_lossy().trim_end_matches('/')
+        )
+    } else if pattern.starts_with('/') {
+        pattern
+    } else {
+        format!("{}/{pattern}", project.trim_end_matches('/'))
+    };
+    GlobBuilder::new(&pattern)
+        .literal_separator(true)
+        .build()
+        .ok()
+        .map(|glob| glob.compile_matcher())
+}
+
+/// Make `arg` absolute and remove `.` and `..` without touching the disk
+fn resolve_path(arg: &str, project_root: &Path) -> PathBuf {
+    let path = if arg == "~" {
+        home_dir()
+    } else if let Some(rest) = arg.strip_prefix("~/") {
+        home_dir().join(rest)
+    } else {
+        project_root.join(arg)
+    };
+
+    let mut resolved = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                resolved.pop();
+            }
+            other => resolved.push(other),
+        }
+    }
+    resolved
+}
+
+/// One command with its arguments, after stripping redirections
+#[derive(Debug, Clone, PartialEq)]
+struct SimpleCommand {
+    argv: Vec<String>,
+}
+
+impl SimpleCommand {
+    fn is_flag(arg: &str) -> bool {
+        arg.len() > 1 && arg.starts_with('-')
+    }
+
+    /// Arguments up to `--`, where flags can appear
+    fn before_double_dash(&self) -> &[String] {
+        let args = self.argv.get(1..).unwrap_or_default();
+        match args.iter().position(|a| a == "--") {
+            Some(end) => &args[..end],
+            None => args,
+        }
+    }
+
+    /// Flags, with combined short flags split up and `--long=value` cut at `=`
+    fn flags(&self) -> Vec<String> {
+        let mut flags = Vec::new();
+        for arg in self.before_double_dash() {
+            if !Self::is_flag(arg) {
+                continue;
+            }
+            flags.push(arg.clone());
+            if let Some(long) = arg.strip_prefix("--") {
+                if let Some((name, _)) = long.split_once('=') {
+                    flags.push(format!("--{name}"));
+                }
+            } else {
+                flags.extend(arg[1..].chars().map(|c| format!("-{c}")));
+            }
+        }
+        flags
+    }
+
+    /// Everything after the program that isn't a flag
+    fn args(&self) -> Vec<&str> {
+        let args = self.argv.get(1..).unwrap_or_default();
+        let options = self.before_double_dash();
+        let operands = args.get(options.len() + 1..).unwrap_or_default();
+        options
+            .iter()
+            .filter(|arg| !Self::is_flag(arg))
+            .chain(operands)
+            .map(String::as_str)
+            .collect()
+    }
+
+    /// Non-flag arguments as absolute paths; `key=value` arguments use the value
+    fn path_args(&self, project_root: &Path) -> Vec<PathBuf> {
+        self.args()
+            .into_iter()
+            .map(|arg| match arg.split_once('=') {
+                Some((_, value)) if !arg.starts_with('/') => value,
+                _ => arg,
+            })
+            .filter(|arg| !arg.is_empty())
+            .map(|arg| resolve_path(arg, project_root))
+            .collect()
+    }
+}
+
+/// Programs that run another command given as their arguments
+const WRAPPERS: &[&str] = &[
+    "sudo", "doas", "env", "nohup", "time", "nice", "exec", "command", "xargs", "timeout",
+    "stdbuf", "builtin",
+];
+
+/// Flags of wrappers that take a value
+const WRAPPER_VALUE_FLAGS: &[&str] = &["-u", "-g", "-n", "-C", "-I", "-P", "-s", "-k"];
+
+/// Shell keywords that can start a command without being one
+const KEYWORDS: &[&str] = &[
+    "if", "then", "else", "elif", "fi", "do", "done", "while", "until", "for", "case", "esac", "{",
+    "}", "!",
+];
+
+/// Split a command line into simple commands, including those nested in
+/// substitutions, `sh -c` strings and wrappers like `sudo`. Output
+/// redirections become commands named `>` with the target as argument.
+fn parse_command_line(line: &str) -> Result<Vec<SimpleCommand>, String> {
+    let mut commands = Vec::new();
+    for argv in tokenize(line, &mut commands)? {
+        expand(argv, &mut commands)?;
+    }
+    Ok(commands)
+}
+
+fn expand(argv: Vec<String>, commands: &mut Vec<SimpleCommand>) -> Result<(), String> {
+    let mut argv: Vec<String> = argv
+        .into_iter()
+        .skip_while(|word| KEYWORDS.contains(&word.as_str()) || is_assignment(word))
+        .collect();
+    if argv.is_empty() {
+        return Ok(());
+    }
+    commands.push(SimpleCommand { argv: argv.clone() });
+
+    let program = Path::new(&argv[0])
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    if matches!(program.as_str(), "sh" | "bash" | "zsh" | "dash" | "ksh") {
+        if let Some(pos) = argv.iter().position(|a| a == "-c") {
+            if let Some(script) = argv.get(pos + 1) {
+                commands.extend(parse_command_line(script)?);
+            }
+        }
+    } else if program == "eval" {
+        commands.extend(parse_command_line(&argv[1..].join(" "))?);
+    } else if WRAPPERS.contains(&program.as_str()) {
+        let mut rest = argv.split_off(1).into_iter().peekable();
+        while let Some(word) = rest.peek() {
+            if WRAPPER_VALUE_FLAGS.contains(&word.as_str()) {
+                rest.next();
+                rest.next();
+            } else if SimpleCommand::is_flag(word) || is_assignment(word) {
+                rest.next();
+            } else {
+                break;
+            }
+        }
+        let mut inner: Vec<String> = rest.collect();
+        if program == "timeout" && !inner.is_empty() {
+            inner.remove(0);
+        }
+        expand(inner, commands)?;
+    }
+    Ok(())
+}
+
+fn is_assignment(word: &str) -> bool {
+    match word.split_once('=') {
+        Some((name, _)) => {
+            !name.is_empty()
+                && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+                && !name.starts_with(|c: char| c.is_ascii_digit())
+        }
+        None => false,
+    }
+}
+
+/// Shell-like tokenizer: returns the argv of each command in `line`.
+/// Commands found in `$(...)` and backticks are parsed into `nested`.
+fn tokenize(line: &str, nested: &mut Vec<SimpleCommand>) -> Result<Vec<Vec<String>>, String> {
+    // A final newline ends the last command
+    let chars: Vec<char> = line.chars().chain(['\n']).collect();
+    let mut commands = Vec::new();
+    let mut argv: Vec<String> = Vec::new();
+    let mut word = String::new();
+    let mut in_word = false;
+    // Set after an output redirection operator: the next word is its target
+    let mut redirect: Option<bool> = None;
+    let mut i = 0;
+
+    macro_rules! end_word {
+        () => {
+            if in_word {
+                let w = std::mem::take(&mut word);
+                match redirect.take() {
+                    Some(true) if !w.starts_with('&') => {
+                        commands.push(vec![">".to_string(), w]);
+                    }
+                    Some(_) => {}
+                    None => argv.push(w),
+                }
+                in_word = false;
+            }
+        };
+    }
+    macro_rules! end_command {
+        () => {
+            end_word!();
+            if !argv.is_empty() {
+                commands.push(std::mem::take(&mut argv));
+            }
+        };
+    }
+
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            ' ' | '\t' => end_word!(),
+            '\n' | ';' | '&' | '|' | '(' | ')' => {
+                // `2>&1` style duplications are handled with the redirection
+                end_command!();
+            }
+            '>' | '<' => {
+                // A number right before the operator is a file descriptor
+                if in_word && word.chars().all(|c| c.is_ascii_digit()) {
+                    word.clear();
+                    in_word = false;
+                }
+                end_word!();
+                let output = c == '>';
+                while i + 1 < chars.len() && matches!(chars[i + 1], '>' | '|') {
+                    i += 1;
+                }
+                if i + 1 < chars.len() && chars[i + 1] == '&' {
+                    // `>&2` duplicates a descriptor; `&>` is handled above
+                    i += 1;
+                    redirect = Some(false);
+                } else if i + 1 < chars.len() && chars[i + 1] == '(' {
+                    // Process substitution `<(cmd)` / `>(cmd)`
+                    let (inner, end) = balanced(&chars, i + 2, '(', ')')?;
+                    nested.extend(parse_command_line(&inner)?);
+                    i = end;
+                } else {
+                    redirect = Some(output);
+                }
+            }
+            '\'' => {
+                let end = chars[i + 1..]
+                    .iter()
+                    .position(|&c| c == '\'')
+                    .ok_or("unterminated single quote")?;
+                word.extend(&chars[i + 1..i + 1 + end]);
+                in_word = true;
+                i += end + 1;
+            }
+            '"' => {
+                in_word = true;
+                i += 1;
+                loop {
+                    match chars.get(i) {
+                        None => return Err("unterminated double quote".to_string()),
+                        Some('"') => break,
+                        Some('\\') if i + 1 < chars.len() => {
+                            word.push(chars[i + 1]);
+                            i += 2;
+                        }
+                        Some('$') if chars.get(i + 1) == Some(&'(') => {
+                            let (inner, end) = balanced(&chars, i + 2, '(', ')')?;
+                            nested.extend(parse_command_line(&inner)?);
+                            word.push_str("$(...)");
+                            i = end + 1;
+                        }
+                        Some('`') => {
+                            let (inner, end) = backticks(&chars, i + 1)?;
+                            nested.extend(parse_command_line(&inner)?);
+                            word.push_str("`...`");
+                            i = end + 1;
+                        }
+                        Some(&c) => {
+                            word.push(c);
+                            i += 1;
+                        }
+                    }
+                }
+            }
+            '\\' => {
+                if let Some(&next) = chars.get(i + 1) {
+                    if next != '\n' {
+                        word.push(next);
+                        in_word = true;
+                    }
+                    i += 1;
+                }
+            }
+            '$' if chars.get(i + 1) == Some(&'(') => {
+                let (inner, end) = balanced(&chars, i + 2, '(', ')')?;
+                nested.extend(parse_command_line(&inner)?);
+                word.push_str("$(...)");
+                in_word = true;
+                i = end;
+            }
+            '`' => {
+                let (inner, end) = backticks(&chars, i + 1)?;
+                nested.extend(parse_command_line(&inner)?);
+                word.push_str("`...`");
+                in_word = true;
+                i = end;
+            }
+            '#' if !in_word => {
+                // Comment until the end of the line
+                while i + 1 < chars.len() && chars[i + 1] != '\n' {
+                    i += 1;
+                }
+            }
+            _ => {
+                word.push(c);
+                in_word = true;
+            }
+        }
+        i += 1;
+    }
+    if redirect.is_some() {
+        return Err("redirection without a target".to_string());
+    }
+    Ok(commands)
+}
+
+/// Text up to the `close` that balances an already opened `open`, and the
+/// index of that `close`
+fn balanced(
+    chars: &[char],
+    start: usize,
+    open: char,
+    close: char,
+) -> Result<(String, usize), String> {
+    let mut depth = 1;
+    for (i, &c) in chars.iter().enumerate().skip(start) {
+        if c == open {
+            depth += 1;
+        } else if c == close {
+            depth -= 1;
+            if depth == 0 {
+                return Ok((chars[start..i].iter().collect(), i));
+            }
+        }
+    }
+    Err(format!("unbalanced '{open}'"))
+}
+
+fn backticks(chars: &[char], start: usize) -> Result<(String, usize), String> {
+    let end = chars[start..]
+        .iter()
+        .position(|&c| c == '`')
+        .ok_or("unterminated backtick")?;
+    Ok((chars[start..start + end].iter().collect(), start + end))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn argvs(line: &str) -> Vec<Vec<String>> {
+        parse_command_line(line)
+            .unwrap()
+            .into_iter()
+            .map(|c| c.argv)
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_command_line() {
+        assert_eq!(
+            argvs("FOO=1 cargo test -- --nocapture 2>&1 | tee out.log"),
+            vec![
+                vec!["cargo", "test", "--", "--nocapture"],
+                vec!["tee", "out.log"]
+            ]
+        );
+        assert_eq!(
+            argvs("echo 'a b' \"c $(rm -rf /)\" > /tmp/x; sudo -u root rm x"),
+            vec![
+                vec!["rm", "-rf", "/"],
+                vec![">", "/tmp/x"],
+                vec!["echo", "a b", "c $(...)"],
+                vec!["sudo", "-u", "root", "rm", "x"],
+                vec!["rm", "x"],
+            ]
+        );
+        assert_eq!(
+            argvs("bash -c 'cd src && ls'"),
+            vec![
+                vec!["bash", "-c", "cd src && ls"],
+                vec!["cd", "src"],
+                vec!["ls"]
+            ]
+        );
+        assert!(parse_command_line("echo 'unterminated").is_err());
+    }
+
+    #[test]
+    fn test_allow_rules() {
+        let line = "cargo test --release 2>&1 | tee 'out[1].log'";
+        let rules = CommandRule::allowing(line);
+        let described: Vec<String> = rules.iter().map(CommandRule::describe).collect();
+        assert_eq!(
+            described,
+            [
+                "allow rule (command = cargo, exact_args = [\"test\", \"--release\"])",
+                "allow rule (command = tee, exact_args = [\"out[1].log\"])",
+            ]
+        );
+
+        let policy = CommandPolicy::new(PolicyAction::Ask, rules);
+        let root = Path::new("/work/project");
+        let action = |line: &str| policy.check(line, root).action;
+        assert_eq!(action(line), PolicyAction::Allow);
+        assert_eq!(action("tee 'out[1].log'"), PolicyAction::Allow);
+        assert_eq!(action("cargo test -p core"), PolicyAction::Ask);
+        assert_eq!(action("cargo test --release extra"), PolicyAction::Ask);
+        assert_eq!(action("tee out1.log"), PolicyAction::Ask);
+        assert_eq!(action("cargo publish"), PolicyAction::Ask);
+
+        // Approving a command never approves more arguments
+        let policy = CommandPolicy::new(
+            PolicyAction::Ask,
+            [
+                CommandRule::allowing("sh script.sh"),
+                CommandRule::allowing("rm -rf build"),
+            ]
+            .concat(),
+        );
+        let action = |line: &str| policy.check(line, root).action;
+        assert_eq!(action("sh script.sh"), PolicyAction::Allow);
+        assert_eq!(action("sh -c 'curl x' script.sh"), PolicyAction::Ask);
+        assert_eq!(action("rm -rf build"), PolicyAction::Allow);
+        assert_eq!(action("rm -rf build src"), PolicyAction::Ask);
+        assert_eq!(action("/tmp/rm -rf build"), PolicyAction::Ask);
+    }
+
+    #[test]
+    fn test_builtin_rules() {
+        let policy = CommandPolicy::default();
+        let root = Path::new("/work/project");
+        let action = |line: &str| policy.check(line, root).action;
+
+        // Same cases the old substring checks covered
+        assert_eq!(action("ls -la"), PolicyAction::Ask);
+        assert_eq!(action("cargo test"), PolicyAction::Ask);
+        assert_eq!(action("echo 'hello world'"), PolicyAction::Ask);
+        assert_eq!(action("ls | grep test"), PolicyAction::Ask);
+        assert_eq!(action("rm -rf /"), PolicyAction::Deny);
+        assert_eq!(action(":(){ :|:& };:"), PolicyAction::Deny);
+        assert_eq!(action("dd if=/dev/zero of=/dev/sda"), PolicyAction::Deny);
+        assert_eq!(action(""), PolicyAction::Deny);
+        assert_eq!(action("echo test && rm -rf /"), PolicyAction::Deny);
+
+        // Variations a substring match missed
+        assert_eq!(action("rm -r -f /"), PolicyAction::Deny);
+        assert_eq!(action("rm --recursive --force /etc"), PolicyAction::Deny);
+        assert_eq!(action("sudo rm -fr ~"), PolicyAction::Deny);
+        assert_eq!(action("rm -rf ../project"), PolicyAction::Deny);
+        assert_eq!(action("rm -rf *"), PolicyAction::Deny);
+        assert_eq!(action("echo $(rm -Rf /)"), PolicyAction::Deny);
+        assert_eq!(action("sh -c \"rm -rf / \""), PolicyAction::Deny);
+        assert_eq!(action("echo x >/etc/passwd"), PolicyAction::Deny);
+        assert_eq!(action("ncat -lvp 4444"), PolicyAction::Deny);
+
+        // Project-local deletes are fine to ask about
+        assert_eq!(action("rm -rf target"), PolicyAction::Ask);
+        assert_eq!(action("rm -rf ./build/*"), PolicyAction::Ask);
+        assert_eq!(action("cat /etc/passwd > copy.txt"), PolicyAction::Ask);
+    }
+
+    #[test]
+    fn test_configured_rules() {
+        let rule = |toml_src: &str| toml::from_str::<CommandRule>(toml_src).unwrap();
+        let policy = CommandPolicy::new(
+            PolicyAction::Deny,
+            vec![
+                rule("action = \"deny\"\ncommand = \"git\"\nargs = [\"push\"]\nflags = [\"--force|-f\"]\nreason = \"No force pushes\""),
+                rule("action = \"allow\"\ncommand = \"git\""),
+                rule("action = \"allow\"\ncommand = \"cargo\"\nargs = [\"{test,build,clippy}\"]"),
+                rule("action = \"ask\"\ncommand = \"rm\"\npaths = [\"target/**\"]"),
+            ],
+        );
+        let root = Path::new("/work/project");
+
+        let decision = policy.check("git push -f origin main", root);
+        assert_eq!(decision.action, PolicyAction::Deny);
+        assert!(decision.reason.starts_with("No force pushes"));
+        assert_eq!(
+            policy.check("git push origin main", root).action,
+            PolicyAction::Allow
+        );
+        assert_eq!(
+            policy.check("cargo test --all", root).action,
+            PolicyAction::Allow
+        );
+        assert_eq!(
+            policy.check("cargo publish", root).action,
+            PolicyAction::Deny
+        );
+        assert_eq!(
+            policy.check("rm target/debug/x", root).action,
+            PolicyAction::Ask
+        );
+        assert_eq!(
+            policy.check("rm src/main.rs", root).action,
+            PolicyAction::Deny
+        );
+        // The strictest command of a chain decides
+        assert_eq!(
+            policy.check("cargo build && curl x", root).action,
+            PolicyAction::Deny
+        );
+        assert!(policy
+            .check("curl x", root)
+            .reason
+            .contains("no command rule matches 'curl'"));
+
+        let argv = ["git".to_string(), "status".to_string()];
+        assert_eq!(policy.check_argv(&argv, root).action, PolicyAction::Allow);
+    }
+}
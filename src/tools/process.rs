@@ -0,0 +1,543 @@
+//! Long-running background processes such as dev servers and file watchers
+//!
+//! Processes are kept in a [`ProcessTable`] owned by the agent. Their output
+//! is collected into a [`RingBuffer`] so it can be read later, and every
+//! process still running is killed when the table is dropped.
+
+use super::shell::{kill_process_group, sandbox_error, shell_command};
+use super::{Tool, ToolContext};
+use serde_json::{json, Value as JsonValue};
+use std::collections::{BTreeMap, VecDeque};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::process::{Child, ChildStdin, Stdio};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Output kept per process; older lines are dropped first
+pub const PROCESS_BUFFER_BYTES: usize = 256 * 1024;
+/// Most output returned by a single read
+pub const MAX_READ_OUTPUT_BYTES: usize = 30_000;
+/// Longest a tool waits for a process to produce output
+const MAX_WAIT_SECS: u64 = 30;
+
+/// Lines of output that keeps only the most recent `capacity` bytes
+pub struct RingBuffer {
+    capacity: usize,
+    lines: VecDeque<String>,
+    bytes: usize,
+    /// Sequence number of the first line still in the buffer
+    first_seq: u64,
+}
+
+impl RingBuffer {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            lines: VecDeque::new(),
+            bytes: 0,
+            first_seq: 0,
+        }
+    }
+
+    pub fn push(&mut self, line: String) {
+        self.bytes += line.len();
+        self.lines.push_back(line);
+        while self.bytes > self.capacity && self.lines.len() > 1 {
+            if let Some(dropped) = self.lines.pop_front() {
+                self.bytes -= dropped.len();
+                self.first_seq += 1;
+            }
+        }
+    }
+
+    /// Sequence number the next line will get
+    pub fn end_seq(&self) -> u64 {
+        self.first_seq + self.lines.len() as u64
+    }
+
+    /// Lines from sequence number `from` on, and how many of the lines
+    /// requested were already dropped from the buffer
+    pub fn read_from(&self, from: u64) -> (Vec<&str>, u64) {
+        let dropped = self.first_seq.saturating_sub(from);
+        let skip = from.saturating_sub(self.first_seq) as usize;
+        let lines = self.lines.iter().skip(skip).map(String::as_str).collect();
+        (lines, dropped)
+    }
+}
+
+struct BackgroundProcess {
+    command: String,
+    child: Child,
+    stdin: Option<ChildStdin>,
+    output: Arc<Mutex<RingBuffer>>,
+    /// Sequence number of the first line not returned by a read yet
+    read_seq: u64,
+    started: Instant,
+}
+
+impl BackgroundProcess {
+    fn status(&mut self) -> String {
+        match self.child.try_wait() {
+            Ok(None) => format!("running for {}s", self.started.elapsed().as_secs()),
+            Ok(Some(status)) => match status.code() {
+                Some(code) => format!("exited with code {code}"),
+                None => "killed by a signal".to_string(),
+            },
+            Err(e) => format!("unknown ({e})"),
+        }
+    }
+
+    fn is_running(&mut self) -> bool {
+        matches!(self.child.try_wait(), Ok(None))
+    }
+
+    /// Output produced since the last read, or everything still buffered
+    fn read_output(&mut self, all: bool) -> String {
+        let output = lock(&self.output);
+        let from = if all { 0 } else { self.read_seq };
+        let (lines, dropped) = output.read_from(from);
+        self.read_seq = output.end_seq();
+
+        let mut text = String::new();
+        let mut size = 0;
+        let mut omitted = dropped as usize;
+        let start = lines
+            .iter()
+            .rposition(|line| {
+                size += line.len();
+                size > MAX_READ_OUTPUT_BYTES
+            })
+            .map_or(0, |i| i + 1);
+        omitted += start;
+        if omitted > 0 {
+            text.push_str(&format!("[... {omitted} earlier lines not shown ...]\n"));
+        }
+        text.extend(lines[start..].iter().copied());
+        text
+    }
+}
+
+#[derive(Default)]
+struct Processes {
+    next_id: u32,
+    running: BTreeMap<u32, BackgroundProcess>,
+}
+
+impl Drop for Processes {
+    fn drop(&mut self) {
+        for process in self.running.values_mut() {
+            if process.is_running() {
+                kill_process_group(&mut process.child);
+            }
+        }
+    }
+}
+
+/// Background processes started by the agent, shared by all clones
+#[derive(Clone, Default)]
+pub struct ProcessTable {
+    inner: Arc<Mutex<Processes>>,
+}
+
+impl ProcessTable {
+    fn lock(&self) -> MutexGuard<'_, Processes> {
+        lock(&self.inner)
+    }
+
+    /// Kill every process and forget about it
+    pub fn kill_all(&self) {
+        let mut processes = self.lock();
+        for process in processes.running.values_mut() {
+            if process.is_running() {
+                kill_process_group(&mut process.child);
+            }
+        }
+        processes.running.clear();
+    }
+
+    /// Number of processes in the table, running or not
+    pub fn len(&self) -> usize {
+        self.lock().running.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Append a pipe to `output` line by line until it closes
+fn collect_output<R: Read + Send + 'static>(
+    pipe: R,
+    prefix: &'static str,
+    output: Arc<Mutex<RingBuffer>>,
+) {
+    thread::spawn(move || {
+        let mut reader = BufReader::new(pipe);
+        let mut buf = Vec::new();
+        loop {
+            buf.clear();
+            match reader.read_until(b'\n', &mut buf) {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {
+                    let mut line = format!("{prefix}{}", String::from_utf8_lossy(&buf));
+                    if !line.ends_with('\n') {
+                        line.push('\n');
+                    }
+                    lock(&output).push(line);
+                }
+            }
+        }
+    });
+}
+
+fn process_id(args: &JsonValue) -> Result<u32, String> {
+    args["id"]
+        .as_u64()
+        .and_then(|id| u32::try_from(id).ok())
+        .ok_or_else(|| "Error: 'id' parameter is required".to_string())
+}
+
+fn wait_secs(args: &JsonValue, default: u64) -> Duration {
+    Duration::from_secs(
+        args["wait_secs"]
+            .as_u64()
+            .unwrap_or(default)
+            .min(MAX_WAIT_SECS),
+    )
+}
+
+/// Give a process `wait` to produce output, returning early if it exits
+fn settle(table: &ProcessTable, id: u32, wait: Duration) {
+    let deadline = Instant::now() + wait;
+    while Instant::now() < deadline {
+        let running = table
+            .lock()
+            .running
+            .get_mut(&id)
+            .is_some_and(|process| process.is_running());
+        if !running {
+            break;
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+    // Let the reader threads catch up with anything written right before exit
+    thread::sleep(Duration::from_millis(50));
+}
+
+fn no_such_process(id: u32) -> String {
+    format!("Error: No background process with id {id}. Call read_process_output without an id to list them.")
+}
+
+/// Tool for starting a process that keeps running in the background
+pub struct StartBackgroundProcess;
+
+impl Tool for StartBackgroundProcess {
+    fn name(&self) -> &'static str {
+        "start_background_process"
+    }
+
+    fn description(&self) -> &'static str {
+        "Start a long-running shell command in the background, such as a dev server or watcher, and return its id with its first output. Use read_process_output, send_process_input and kill_process to interact with it later."
+    }
+
+    fn parameters(&self) -> JsonValue {
+        json!({
+            "type": "object",
+            "properties": {
+                "command": {"type": "string", "description": "The shell command to start."},
+                "wait_secs": {"type": "integer", "description": "Seconds to wait for initial output before returning (default 2, max 30)."}
+            },
+            "required": ["command"]
+        })
+    }
+
+    fn execute(&self, args: &JsonValue, context: &ToolContext<'_>) -> String {
+        let command = match args["command"].as_str() {
+            Some(cmd) => cmd.trim(),
+            None => return "Error: 'command' parameter is required".to_string(),
+        };
+        if let Err(message) =
+            context.approve_command(command, &format!("start background process '{command}'"))
+        {
+            return message;
+        }
+
+        if context.dry_run {
+            return format!("Dry-run: Would start background process: {command}");
+        }
+
+        let spawned =
+            shell_command(command, context).and_then(|mut cmd| cmd.stdin(Stdio::piped()).spawn());
+        let mut child = match spawned {
+            Ok(child) => child,
+            Err(e) if context.sandbox.is_some() => return sandbox_error(&e),
+            Err(e) => return format!("Error starting process: {e}"),
+        };
+
+        let output = Arc::new(Mutex::new(RingBuffer::new(PROCESS_BUFFER_BYTES)));
+        if let Some(stdout) = child.stdout.take() {
+            collect_output(stdout, "", output.clone());
+        }
+        if let Some(stderr) = child.stderr.take() {
+            collect_output(stderr, "[stderr] ", output.clone());
+        }
+
+        let pid = child.id();
+        let id = {
+            let mut processes = context.processes.lock();
+            processes.next_id += 1;
+            let id = processes.next_id;
+            processes.running.insert(
+                id,
+                BackgroundProcess {
+                    command: command.to_string(),
+                    stdin: child.stdin.take(),
+                    child,
+                    output,
+                    read_seq: 0,
+                    started: Instant::now(),
+                },
+            );
+            id
+        };
+
+        settle(&context.processes, id, wait_secs(args, 2));
+
+        let mut processes = context.processes.lock();
+        let Some(process) = processes.running.get_mut(&id) else {
+            return no_such_process(id);
+        };
+        let mut result = format!(
+            "Started background process {id} (pid {pid}): {command}\nStatus: {}\n",
+            process.status()
+        );
+        let output = process.read_output(false);
+        if !output.is_empty() {
+            result.push_str("Output:\n");
+            result.push_str(&output);
+        }
+        result.trim_end().to_string()
+    }
+}
+
+/// Tool for reading what a background process has printed
+pub struct ReadProcessOutput;
+
+impl Tool for ReadProcessOutput {
+    fn name(&self) -> &'static str {
+        "read_process_output"
+    }
+
+    fn description(&self) -> &'static str {
+        "Read the output a background process printed since the last read, and whether it is still running. Without an id, list all background processes."
+    }
+
+    fn parameters(&self) -> JsonValue {
+        json!({
+            "type": "object",
+            "properties": {
+                "id": {"type": "integer", "description": "Id returned by start_background_process. Omit to list processes."},
+                "wait_secs": {"type": "integer", "description": "Seconds to wait for more output first (default 0, max 30)."},
+                "all": {"type": "boolean", "description": "Return all buffered output instead of only new output (default false)."}
+            }
+        })
+    }
+
+    fn execute(&self, args: &JsonValue, context: &ToolContext<'_>) -> String {
+        if args["id"].is_null() {
+            let mut processes = context.processes.lock();
+            if processes.running.is_empty() {
+                return "No background processes.".to_string();
+            }
+            return processes
+                .running
+                .iter_mut()
+                .map(|(id, process)| {
+                    let status = process.status();
+                    format!("{id}: {} ({status})", process.command)
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+        }
+
+        let id = match process_id(args) {
+            Ok(id) => id,
+            Err(e) => return e,
+        };
+        settle(&context.processes, id, wait_secs(args, 0));
+
+        let mut processes = context.processes.lock();
+        let Some(process) = processes.running.get_mut(&id) else {
+            return no_such_process(id);
+        };
+        let status = process.status();
+        let output = process.read_output(args["all"].as_bool().unwrap_or(false));
+        if output.is_empty() {
+            format!("Process {id} ({status}): no new output")
+        } else {
+            format!("Process {id} ({status}):\n{}", output.trim_end())
+        }
+    }
+}
+
+/// Tool for writing to the standard input of a background process
+pub struct SendProcessInput;
+
+impl Tool for SendProcessInput {
+    fn name(&self) -> &'static str {
+        "send_process_input"
+    }
+
+    fn description(&self) -> &'static str {
+        "Write text to the standard input of a background process and return the output it prints in response."
+    }
+
+    fn parameters(&self) -> JsonValue {
+        json!({
+            "type": "object",
+            "properties": {
+                "id": {"type": "integer", "description": "Id returned by start_background_process."},
+                "input": {"type": "string", "description": "Text to send."},
+                "newline": {"type": "boolean", "description": "Append a newline to the input (default true)."},
+                "wait_secs": {"type": "integer", "description": "Seconds to wait for a response (default 1, max 30)."}
+            },
+            "required": ["id", "input"]
+        })
+    }
+
+    fn execute(&self, args: &JsonValue, context: &ToolContext<'_>) -> String {
+        let id = match process_id(args) {
+            Ok(id) => id,
+            Err(e) => return e,
+        };
+        let mut input = match args["input"].as_str() {
+            Some(input) => input.to_string(),
+            None => return "Error: 'input' parameter is required".to_string(),
+        };
+        if args["newline"].as_bool().unwrap_or(true) {
+            input.push('\n');
+        }
+
+        if context.dry_run {
+            return format!("Dry-run: Would send {} bytes to process {id}", input.len());
+        }
+
+        {
+            let mut processes = context.processes.lock();
+            let Some(process) = processes.running.get_mut(&id) else {
+                return no_such_process(id);
+            };
+            if !process.is_running() {
+                return format!("Error: Process {id} has {}", process.status());
+            }
+            let Some(stdin) = process.stdin.as_mut() else {
+                return format!("Error: Standard input of process {id} is closed");
+            };
+            if let Err(e) = stdin
+                .write_all(input.as_bytes())
+                .and_then(|_| stdin.flush())
+            {
+                return format!("Error writing to process {id}: {e}");
+            }
+        }
+
+        settle(&context.processes, id, wait_secs(args, 1));
+
+        let mut processes = context.processes.lock();
+        let Some(process) = processes.running.get_mut(&id) else {
+            return no_such_process(id);
+        };
+        let output = process.read_output(false);
+        if output.is_empty() {
+            format!("Sent input to process {id}; no output yet")
+        } else {
+            format!("Sent input to process {id}. Output:\n{}", output.trim_end())
+        }
+    }
+}
+
+/// Tool for stopping a background process
+pub struct KillProcess;
+
+impl Tool for KillProcess {
+    fn name(&self) -> &'static str {
+        "kill_process"
+    }
+
+    fn description(&self) -> &'static str {
+        "Stop a background process and everything it started, returning any output not read yet."
+    }
+
+    fn parameters(&self) -> JsonValue {
+        json!({
+            "type": "object",
+            "properties": {
+                "id": {"type": "integer", "description": "Id returned by start_background_process."}
+            },
+            "required": ["id"]
+        })
+    }
+
+    fn execute(&self, args: &JsonValue, context: &ToolContext<'_>) -> String {
+        let id = match process_id(args) {
+            Ok(id) => id,
+            Err(e) => return e,
+        };
+
+        if context.dry_run {
+            return format!("Dry-run: Would kill process {id}");
+        }
+
+        let Some(mut process) = context.processes.lock().running.remove(&id) else {
+            return no_such_process(id);
+        };
+        let status = if process.is_running() {
+            kill_process_group(&mut process.child);
+            "killed".to_string()
+        } else {
+            process.status()
+        };
+        // Let the reader threads pick up what was written before the kill
+        thread::sleep(Duration::from_millis(50));
+
+        let output = process.read_output(false);
+        if output.is_empty() {
+            format!("Process {id} {status}: {}", process.command)
+        } else {
+            format!(
+                "Process {id} {status}: {}\nUnread output:\n{}",
+                process.command,
+                output.trim_end()
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ring_buffer_drops_oldest_lines() {
+        let mut buffer = RingBuffer::new(20);
+        for i in 0..10 {
+            buffer.push(format!("line {i}\n"));
+        }
+        assert_eq!(buffer.end_seq(), 10);
+
+        let (lines, dropped) = buffer.read_from(0);
+        assert_eq!(lines, vec!["line 8\n", "line 9\n"]);
+        assert_eq!(dropped, 8);
+
+        let (lines, dropped) = buffer.read_from(9);
+        assert_eq!(lines, vec!["line 9\n"]);
+        assert_eq!(dropped, 0);
+
+        assert!(buffer.read_from(10).0.is_empty());
+    }
+}
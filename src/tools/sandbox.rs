@@ -0,0 +1,393 @@
+//! Optional sandbox for shell commands
+//!
+//! On Linux, commands started by `run_shell_command` and
+//! `start_background_process` can run inside their own user and mount
+//! namespaces, in the spirit of bubblewrap:
+//!
+//! - the whole filesystem is read-only except the project and a private `/tmp`
+//! - the project's `.git` stays read-only, so hooks and git config that run
+//!   outside the sandbox can't be planted
+//! - the network is cut off unless `network = true`
+//! - CPU time, address space and process count are capped with rlimits
+//!
+//! Commands keep no capabilities, so they cannot undo any of this: an
+//! ordinary user loses them on exec, and root is moved into a second user
+//! namespace with no id mapping. `no_new_privs` keeps setuid programs from
+//! getting them back. No external tools are needed, only unprivileged user
+//! namespaces.
+
+use std::path::Path;
+use std::process::{Command, ExitStatus};
+
+/// Sandbox settings for a session
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sandbox {
+    /// Keep the host network reachable
+    pub network: bool,
+    /// CPU seconds per process; 0 for no limit
+    pub cpu_secs: u64,
+    /// Address space per process in MiB; 0 for no limit
+    pub memory_mb: u64,
+    /// Processes the command may have running at once; 0 for no limit
+    pub max_processes: u64,
+}
+
+impl Default for Sandbox {
+    fn default() -> Self {
+        Self {
+            network: false,
+            cpu_secs: 300,
+            memory_mb: 4096,
+            max_processes: 256,
+        }
+    }
+}
+
+impl Sandbox {
+    /// Arrange for `cmd` to run inside the sandbox, with `project_root`
+    /// (except its `.git`) as the only writable directory
+    #[cfg(target_os = "linux")]
+    pub fn apply(&self, cmd: &mut Command, project_root: &Path) -> std::io::Result<()> {
+        linux::apply(self, cmd, project_root)
+    }
+
+    /// Sandboxing needs Linux namespaces
+    #[cfg(not(target_os = "linux"))]
+    pub fn apply(&self, _cmd: &mut Command, _project_root: &Path) -> std::io::Result<()> {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "the sandbox is only available on Linux",
+        ))
+    }
+
+    /// Short description for status output
+    pub fn summary(&self) -> String {
+        let limit = |value: u64, unit: &str| {
+            if value == 0 {
+                "unlimited".to_string()
+            } else {
+                format!("{value}{unit}")
+            }
+        };
+        format!(
+            "network {}, cpu {}, memory {}, processes {}",
+            if self.network { "on" } else { "off" },
+            limit(self.cpu_secs, "s"),
+            limit(self.memory_mb, " MiB"),
+            limit(self.max_processes, ""),
+        )
+    }
+
+    /// Explain how a sandboxed command ran into the sandbox, judging by how it
+    /// exited and what it printed
+    pub fn violations(&self, status: Option<ExitStatus>, output: &str) -> Vec<String> {
+        let mut found = Vec::new();
+
+        #[cfg(unix)]
+        if let Some(signal) = status.and_then(|s| std::os::unix::process::ExitStatusExt::signal(&s))
+        {
+            if signal == libc::SIGXCPU && self.cpu_secs > 0 {
+                found.push(format!(
+                    "CPU time limit of {}s exceeded; the command was killed",
+                    self.cpu_secs
+                ));
+            }
+        }
+        #[cfg(not(unix))]
+        let _ = status;
+
+        let output = output.to_lowercase();
+        let mentions = |needles: &[&str]| needles.iter().any(|n| output.contains(n));
+
+        if mentions(&["read-only file system"]) {
+            found.push(
+                "write outside the project or into .git blocked (only the project and /tmp are writable)"
+                    .to_string(),
+            );
+        }
+        if !self.network
+            && mentions(&[
+                "network is unreachable",
+                "temporary failure in name resolution",
+                "could not resolve host",
+                "name or service not known",
+                "failed to lookup address",
+                "dns error",
+            ])
+        {
+            found.push("network access blocked (sandbox network is off)".to_string());
+        }
+        if self.memory_mb > 0
+            && mentions(&[
+                "cannot allocate memory",
+                "out of memory",
+                "memory allocation of",
+                "std::bad_alloc",
+                "memoryerror",
+            ])
+        {
+            found.push(format!("memory limit of {} MiB reached", self.memory_mb));
+        }
+        if self.max_processes > 0
+            && mentions(&[
+                "fork: resource temporarily unavailable",
+                "can't fork",
+                "cannot fork",
+            ])
+        {
+            found.push(format!("process limit of {} reached", self.max_processes));
+        }
+
+        found
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use super::Sandbox;
+    use std::ffi::CString;
+    use std::io;
+    use std::os::unix::ffi::OsStrExt;
+    use std::os::unix::process::CommandExt;
+    use std::path::Path;
+    use std::process::Command;
+
+    #[cfg(target_env = "gnu")]
+    type Resource = libc::__rlimit_resource_t;
+    #[cfg(not(target_env = "gnu"))]
+    type Resource = libc::c_int;
+
+    /// CPU seconds between SIGXCPU and SIGKILL
+    const CPU_GRACE_SECS: u64 = 5;
+
+    const MOUNT_ATTR_RDONLY: u64 = 0x1;
+
+    /// `struct mount_attr` for `mount_setattr(2)`
+    #[repr(C)]
+    struct MountAttr {
+        attr_set: u64,
+        attr_clr: u64,
+        propagation: u64,
+        userns_fd: u64,
+    }
+
+    /// Everything the child needs, prepared before the fork so the setup
+    /// code in between fork and exec does not allocate
+    struct Setup {
+        network: bool,
+        limits: [(Resource, u64); 2],
+        max_processes: u64,
+        project: CString,
+        /// The project's `.git` directory or file, kept read-only
+        git: Option<CString>,
+        private_tmp: bool,
+        /// Running as root, which would keep its capabilities through exec
+        root: bool,
+        uid_map: Vec<u8>,
+        gid_map: Vec<u8>,
+    }
+
+    pub(super) fn apply(
+        sandbox: &Sandbox,
+        cmd: &mut Command,
+        project_root: &Path,
+    ) -> io::Result<()> {
+        let project = project_root.canonicalize()?;
+        let git = project.join(".git");
+        let git = match git.symlink_metadata() {
+            Ok(_) => Some(CString::new(git.as_os_str().as_bytes())?),
+            Err(_) => None,
+        };
+        let (uid, gid) = unsafe { (libc::getuid(), libc::getgid()) };
+        let setup = Setup {
+            network: sandbox.network,
+            limits: [
+                (libc::RLIMIT_CPU, sandbox.cpu_secs),
+                (
+                    libc::RLIMIT_AS,
+                    sandbox.memory_mb.saturating_mul(1024 * 1024),
+                ),
+            ],
+            max_processes: sandbox.max_processes,
+            // A tmpfs over /tmp would hide a project that lives there
+            private_tmp: !project.starts_with("/tmp"),
+            project: CString::new(project.as_os_str().as_bytes())?,
+            git,
+            root: uid == 0,
+            uid_map: format!("{uid} {uid} 1\n").into_bytes(),
+            gid_map: format!("{gid} {gid} 1\n").into_bytes(),
+        };
+
+        unsafe {
+            cmd.pre_exec(move || setup.enter());
+        }
+        Ok(())
+    }
+
+    fn check(result: libc::c_int) -> io::Result<()> {
+        if result == -1 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(())
+        }
+    }
+
+    fn write_file(path: &std::ffi::CStr, content: &[u8]) -> io::Result<()> {
+        unsafe {
+            let fd = libc::open(path.as_ptr(), libc::O_WRONLY | libc::O_CLOEXEC);
+            check(fd)?;
+            let written = libc::write(fd, content.as_ptr().cast(), content.len());
+            libc::close(fd);
+            if written != content.len() as isize {
+                return Err(io::Error::last_os_error());
+            }
+        }
+        Ok(())
+    }
+
+    fn set_readonly(path: &std::ffi::CStr, readonly: bool) -> io::Result<()> {
+        let attr = MountAttr {
+            attr_set: if readonly { MOUNT_ATTR_RDONLY } else { 0 },
+            attr_clr: if readonly { 0 } else { MOUNT_ATTR_RDONLY },
+            propagation: 0,
+            userns_fd: 0,
+        };
+        let result = unsafe {
+            libc::syscall(
+                libc::SYS_mount_setattr,
+                libc::AT_FDCWD,
+                path.as_ptr(),
+                libc::AT_RECURSIVE as libc::c_uint,
+                &attr as *const MountAttr,
+                std::mem::size_of::<MountAttr>(),
+            )
+        };
+        check(result as libc::c_int)
+    }
+
+    impl Setup {
+        /// Runs in the child between fork and exec
+        fn enter(&self) -> io::Result<()> {
+            for &(resource, value) in &self.limits {
+                if value > 0 {
+                    set_limit(resource, value)?;
+                }
+            }
+
+            let mut flags = libc::CLONE_NEWUSER | libc::CLONE_NEWNS | libc::CLONE_NEWIPC;
+            if !self.network {
+                flags |= libc::CLONE_NEWNET;
+            }
+            unsafe { check(libc::unshare(flags))? };
+            self.map_ids()?;
+
+            let none = std::ptr::null();
+            unsafe {
+                check(libc::mount(
+                    none,
+                    c"/".as_ptr(),
+                    none,
+                    libc::MS_REC | libc::MS_PRIVATE,
+                    std::ptr::null(),
+                ))?;
+            }
+            set_readonly(c"/", true)?;
+            unsafe {
+                check(libc::mount(
+                    self.project.as_ptr(),
+                    self.project.as_ptr(),
+                    none,
+                    libc::MS_BIND | libc::MS_REC,
+                    std::ptr::null(),
+                ))?;
+            }
+            set_readonly(&self.project, false)?;
+            if let Some(git) = &self.git {
+                unsafe {
+                    check(libc::mount(
+                        git.as_ptr(),
+                        git.as_ptr(),
+                        none,
+                        libc::MS_BIND | libc::MS_REC,
+                        std::ptr::null(),
+                    ))?;
+                }
+                set_readonly(git, true)?;
+            }
+            if self.private_tmp {
+                unsafe {
+                    check(libc::mount(
+                        c"tmpfs".as_ptr(),
+                        c"/tmp".as_ptr(),
+                        c"tmpfs".as_ptr(),
+                        libc::MS_NOSUID | libc::MS_NODEV,
+                        c"mode=1777".as_ptr().cast(),
+                    ))?;
+                }
+            }
+
+            if self.root {
+                // Root without a mapping is nobody, and exec drops its
+                // capabilities. The new mount namespace also locks the mounts.
+                unsafe { check(libc::unshare(libc::CLONE_NEWUSER | libc::CLONE_NEWNS))? };
+            }
+            unsafe {
+                check(libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0))?;
+                // The working directory still points into the old, read-only view
+                check(libc::chdir(self.project.as_ptr()))?;
+            }
+
+            if self.max_processes > 0 {
+                set_limit(libc::RLIMIT_NPROC, self.max_processes)?;
+            }
+            Ok(())
+        }
+
+        fn map_ids(&self) -> io::Result<()> {
+            write_file(c"/proc/self/setgroups", b"deny")?;
+            write_file(c"/proc/self/uid_map", &self.uid_map)?;
+            write_file(c"/proc/self/gid_map", &self.gid_map)
+        }
+    }
+
+    fn set_limit(resource: Resource, value: u64) -> io::Result<()> {
+        // SIGXCPU at the soft limit says why the command stopped; the hard
+        // limit a little later kills it if the signal is ignored
+        let hard = if resource == libc::RLIMIT_CPU {
+            value.saturating_add(CPU_GRACE_SECS)
+        } else {
+            value
+        };
+        let limit = libc::rlimit {
+            rlim_cur: value as libc::rlim_t,
+            rlim_max: hard as libc::rlim_t,
+        };
+        unsafe { check(libc::setrlimit(resource, &limit)) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_violations_from_output() {
+        let sandbox = Sandbox::default();
+        let found = sandbox.violations(
+            None,
+            "touch: cannot touch '/etc/x': Read-only file system\ncurl: (6) Could not resolve host: example.com",
+        );
+        assert_eq!(found.len(), 2);
+        assert!(found[0].contains("write outside the project"));
+        assert!(found[1].contains("network access blocked"));
+
+        let online = Sandbox {
+            network: true,
+            ..Sandbox::default()
+        };
+        assert!(online
+            .violations(None, "curl: (6) Could not resolve host: example.com")
+            .is_empty());
+        assert!(sandbox.violations(None, "all good").is_empty());
+    }
+}
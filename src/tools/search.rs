@@ -1,9 +1,26 @@
-use super::utils::sanitize_regex_pattern;
+use super::utils::{format_size, sanitize_path, sanitize_regex_pattern};
 use super::{Tool, ToolContext};
-use regex::Regex;
+use chrono::{DateTime, Local};
+use globset::GlobBuilder;
+use ignore::overrides::OverrideBuilder;
+use ignore::types::TypesBuilder;
+use ignore::{WalkBuilder, WalkState};
+use regex::{Regex, RegexBuilder};
 use serde_json::{json, Value as JsonValue};
 use std::fs;
-use walkdir::WalkDir;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+/// Matches returned when the caller doesn't set `max_results`
+const DEFAULT_MAX_RESULTS: usize = 100;
+/// Upper bound for `max_results`
+const MAX_RESULTS_LIMIT: usize = 1000;
+/// Upper bound for `context_lines`
+const MAX_CONTEXT_LINES: usize = 10;
+/// Longer lines are cut when shown
+const MAX_LINE_CHARS: usize = 300;
 
 /// Tool for searching the codebase
 pub struct SearchCodebase;
@@ -14,7 +31,7 @@ impl Tool for SearchCodebase {
     }
 
     fn description(&self) -> &'static str {
-        "Search for text in the codebase files, optionally using regex."
+        "Search file contents in the codebase, optionally using regex. Respects .gitignore and skips hidden and binary files. Returns matches as path:line:column: text, with optional context lines. Narrow the search with 'path', 'glob' or 'file_type'."
     }
 
     fn parameters(&self) -> JsonValue {
@@ -22,7 +39,17 @@ impl Tool for SearchCodebase {
             "type": "object",
             "properties": {
                 "query": {"type": "string", "description": "The search query or regex pattern."},
-                "is_regex": {"type": "boolean", "description": "Whether to treat query as regex.", "default": false}
+                "is_regex": {"type": "boolean", "description": "Whether to treat query as regex.", "default": false},
+                "case_insensitive": {"type": "boolean", "description": "Ignore case when matching.", "default": false},
+                "path": {"type": "string", "description": "Directory or file to search in, relative to the project root (default: the whole project)."},
+                "glob": {
+                    "type": "array",
+                    "items": {"type": "string"},
+                    "description": "Only search files matching these globs, e.g. [\"*.rs\"]. Prefix a glob with ! to exclude, e.g. [\"!tests/**\"]."
+                },
+                "file_type": {"type": "string", "description": "Only search files of this type, e.g. rust, py, js, ts, go, md."},
+                "context_lines": {"type": "number", "description": "Lines of context to show around each match (default: 0, max: 10).", "default": 0},
+                "max_results": {"type": "number", "description": "Maximum number of matches to return (default: 100, max: 1000).", "default": 100}
             },
             "required": ["query"]
         })
@@ -39,7 +66,15 @@ impl Tool for SearchCodebase {
         }
 
         let is_regex = args["is_regex"].as_bool().unwrap_or(false);
-        let mut results = String::new();
+        let case_insensitive = args["case_insensitive"].as_bool().unwrap_or(false);
+        let context_lines = args["context_lines"]
+            .as_u64()
+            .map_or(0, |n| (n as usize).min(MAX_CONTEXT_LINES));
+        let max_results = args["max_results"]
+            .as_u64()
+            .map_or(DEFAULT_MAX_RESULTS, |n| {
+                (n as usize).clamp(1, MAX_RESULTS_LIMIT)
+            });
 
         if std::env::var("DEBUG_API").is_ok() {
             use colored::*;
@@ -51,55 +86,405 @@ impl Tool for SearchCodebase {
             );
         }
 
-        if is_regex {
-            let validated_pattern = match sanitize_regex_pattern(query) {
+        let pattern = if is_regex {
+            match sanitize_regex_pattern(query) {
                 Ok(p) => p,
                 Err(e) => return format!("Error: {e}"),
-            };
+            }
+        } else {
+            regex::escape(query)
+        };
+        let re = match RegexBuilder::new(&pattern)
+            .case_insensitive(case_insensitive)
+            .build()
+        {
+            Ok(re) => re,
+            Err(e) => return format!("Invalid regex: {e}"),
+        };
 
-            let re = match Regex::new(&validated_pattern) {
-                Ok(re) => re,
-                Err(e) => return format!("Invalid regex: {e}"),
-            };
+        let search_root = match args["path"].as_str() {
+            Some(p) => match sanitize_path(p, &context.project_root) {
+                Ok(p) => p,
+                Err(e) => return format!("Error: {e}"),
+            },
+            None => context.project_root.clone(),
+        };
+        if !search_root.exists() {
+            return format!("Error: Path not found: {}", search_root.display());
+        }
 
-            for e in WalkDir::new(&context.project_root).into_iter().flatten() {
-                if e.file_type().is_file() {
-                    if let Ok(content) = fs::read_to_string(e.path()) {
-                        if re.is_match(&content) {
-                            results.push_str(&format!("Match in {}\n", e.path().display()));
-                        }
+        let mut walker = WalkBuilder::new(&search_root);
+        // Honour .gitignore even outside a git repository
+        walker.require_git(false);
+
+        let globs: Vec<&str> = match &args["glob"] {
+            JsonValue::String(glob) => vec![glob.as_str()],
+            JsonValue::Array(globs) => globs.iter().filter_map(|g| g.as_str()).collect(),
+            _ => Vec::new(),
+        };
+        if !globs.is_empty() {
+            let mut overrides = OverrideBuilder::new(&search_root);
+            for glob in globs {
+                if let Err(e) = overrides.add(glob) {
+                    return format!("Error: Invalid glob '{glob}': {e}");
+                }
+            }
+            match overrides.build() {
+                Ok(overrides) => {
+                    walker.overrides(overrides);
+                }
+                Err(e) => return format!("Error: Invalid glob: {e}"),
+            }
+        }
+
+        if let Some(file_type) = args["file_type"].as_str() {
+            let mut types = TypesBuilder::new();
+            types.add_defaults();
+            types.select(file_type);
+            match types.build() {
+                Ok(types) => {
+                    walker.types(types);
+                }
+                Err(e) => return format!("Error: {e}"),
+            }
+        }
+
+        let found = AtomicUsize::new(0);
+        let files: Mutex<Vec<FileMatches>> = Mutex::new(Vec::new());
+        walker.build_parallel().run(|| {
+            let re = &re;
+            let found = &found;
+            let files = &files;
+            let project_root = &context.project_root;
+            Box::new(move |entry| {
+                let Ok(entry) = entry else {
+                    return WalkState::Continue;
+                };
+                if !entry.file_type().is_some_and(|t| t.is_file()) {
+                    return WalkState::Continue;
+                }
+                if let Some(matches) = search_file(entry.path(), project_root, re, context_lines) {
+                    let count = matches.matches;
+                    files.lock().unwrap().push(matches);
+                    // Past the cap there is no point in searching further
+                    if found.fetch_add(count, Ordering::Relaxed) + count > max_results {
+                        return WalkState::Quit;
                     }
                 }
+                WalkState::Continue
+            })
+        });
+
+        let mut files = files.into_inner().unwrap();
+        if files.is_empty() {
+            return "No matches found.".to_string();
+        }
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+
+        let total = found.into_inner();
+        let mut results = String::new();
+        let mut shown = 0;
+        let mut shown_files = 0;
+        for file in &files {
+            if shown >= max_results {
+                break;
             }
-        } else {
-            for e in WalkDir::new(&context.project_root).into_iter().flatten() {
-                if e.file_type().is_file() {
-                    if let Ok(content) = fs::read_to_string(e.path()) {
-                        if content.contains(query) {
-                            results.push_str(&format!(
-                                "Found '{}' in {}\n",
-                                query,
-                                e.path().display()
-                            ));
+            for group in &file.groups {
+                if shown >= max_results {
+                    break;
+                }
+                if context_lines > 0 && !results.is_empty() {
+                    results.push_str("--\n");
+                }
+                for line in group {
+                    if line.column.is_some() {
+                        if shown >= max_results {
+                            break;
                         }
+                        shown += 1;
                     }
+                    results.push_str(&line.format(&file.path));
                 }
             }
+            shown_files += 1;
+        }
+
+        if std::env::var("DEBUG_API").is_ok() {
+            use colored::*;
+            eprintln!(
+                "{}: Found {} matches",
+                "DEBUG".blue().bold(),
+                total.to_string().green()
+            );
+        }
+
+        if total > shown {
+            results.push_str(&format!(
+                "\n[Showing the first {shown} of at least {total} matches. Narrow the search with 'path', 'glob' or 'file_type', or raise 'max_results'.]"
+            ));
+        } else {
+            results.push_str(&format!(
+                "\n[{shown} match{} in {shown_files} file{}]",
+                if shown == 1 { "" } else { "es" },
+                if shown_files == 1 { "" } else { "s" }
+            ));
+        }
+        results
+    }
+}
+
+/// Paths returned by `find_files` when the caller doesn't set `max_results`
+const DEFAULT_MAX_FILES: usize = 200;
+
+/// Tool for finding files by name or path glob
+pub struct FindFiles;
+
+impl Tool for FindFiles {
+    fn name(&self) -> &'static str {
+        "find_files"
+    }
+
+    fn description(&self) -> &'static str {
+        "Find files by glob pattern, e.g. 'src/**/*.rs' (matched against the path) or '*.toml' (matched against the file name at any depth). Respects .gitignore and skips hidden files. Each result shows its type, size and modification time."
+    }
+
+    fn parameters(&self) -> JsonValue {
+        json!({
+            "type": "object",
+            "properties": {
+                "pattern": {"type": "string", "description": "Glob pattern. Patterns containing '/' match the path relative to 'path'; others match the file name."},
+                "path": {"type": "string", "description": "Directory to search in (default: the project root)."},
+                "kind": {"type": "string", "enum": ["file", "dir", "any"], "description": "What to return (default: file)."},
+                "max_depth": {"type": "number", "description": "Maximum directory depth to descend."},
+                "sort": {"type": "string", "enum": ["path", "modified", "size"], "description": "Sort by path (default), newest first, or largest first."},
+                "max_results": {"type": "number", "description": "Maximum number of results (default: 200, max: 1000)."}
+            },
+            "required": ["pattern"]
+        })
+    }
+
+    fn execute(&self, args: &JsonValue, context: &ToolContext<'_>) -> String {
+        let pattern = match args["pattern"].as_str() {
+            Some(p) if !p.is_empty() => p,
+            _ => return "Error: 'pattern' parameter is required".to_string(),
+        };
+        let glob = match GlobBuilder::new(pattern).literal_separator(true).build() {
+            Ok(glob) => glob.compile_matcher(),
+            Err(e) => return format!("Error: Invalid glob '{pattern}': {e}"),
+        };
+        // "*.rs" should find files at any depth, like .gitignore patterns
+        let match_name = !pattern.contains('/');
+
+        let kind = args["kind"].as_str().unwrap_or("file");
+        if !matches!(kind, "file" | "dir" | "any") {
+            return format!("Error: Unknown kind '{kind}' (expected file, dir or any)");
+        }
+        let sort = args["sort"].as_str().unwrap_or("path");
+        if !matches!(sort, "path" | "modified" | "size") {
+            return format!("Error: Unknown sort '{sort}' (expected path, modified or size)");
+        }
+        let max_results = args["max_results"].as_u64().map_or(DEFAULT_MAX_FILES, |n| {
+            (n as usize).clamp(1, MAX_RESULTS_LIMIT)
+        });
+
+        let root = match args["path"].as_str() {
+            Some(p) => match sanitize_path(p, &context.project_root) {
+                Ok(p) => p,
+                Err(e) => return format!("Error: {e}"),
+            },
+            None => context.project_root.clone(),
+        };
+        if !root.is_dir() {
+            return format!("Error: Directory not found: {}", root.display());
+        }
+
+        let mut walker = WalkBuilder::new(&root);
+        walker.require_git(false);
+        if let Some(depth) = args["max_depth"].as_u64() {
+            walker.max_depth(Some(depth as usize));
+        }
+
+        let mut found = Vec::new();
+        for entry in walker.build().flatten().filter(|e| e.depth() > 0) {
+            let Some(file_type) = entry.file_type() else {
+                continue;
+            };
+            let is_dir = file_type.is_dir();
+            if (kind == "file" && is_dir) || (kind == "dir" && !is_dir) {
+                continue;
+            }
+            let relative = entry.path().strip_prefix(&root).unwrap_or(entry.path());
+            let matched = if match_name {
+                glob.is_match(entry.file_name())
+            } else {
+                glob.is_match(relative)
+            };
+            if !matched {
+                continue;
+            }
+
+            let metadata = entry.metadata().ok();
+            found.push(FoundFile {
+                path: entry
+                    .path()
+                    .strip_prefix(&context.project_root)
+                    .unwrap_or(relative)
+                    .display()
+                    .to_string(),
+                is_dir,
+                size: metadata.as_ref().map_or(0, |m| m.len()),
+                modified: metadata.and_then(|m| m.modified().ok()),
+            });
         }
 
-        if results.is_empty() {
-            "No matches found.".to_string()
+        if found.is_empty() {
+            return format!("No files matching '{pattern}' found.");
+        }
+        match sort {
+            "modified" => found.sort_by_key(|f| std::cmp::Reverse(f.modified)),
+            "size" => found.sort_by_key(|f| std::cmp::Reverse(f.size)),
+            _ => found.sort_by(|a, b| a.path.cmp(&b.path)),
+        }
+
+        let total = found.len();
+        let mut results: Vec<String> = found
+            .iter()
+            .take(max_results)
+            .map(FoundFile::describe)
+            .collect();
+        if total > max_results {
+            results.push(format!(
+                "[Showing {max_results} of {total} results. Use a more specific pattern or path.]"
+            ));
         } else {
-            if std::env::var("DEBUG_API").is_ok() {
-                use colored::*;
-                let match_count = results.lines().count();
-                eprintln!(
-                    "{}: Found {} matches",
-                    "DEBUG".blue().bold(),
-                    match_count.to_string().green()
-                );
+            results.push(format!(
+                "[{total} result{}]",
+                if total == 1 { "" } else { "s" }
+            ));
+        }
+        results.join("\n")
+    }
+}
+
+struct FoundFile {
+    path: String,
+    is_dir: bool,
+    size: u64,
+    modified: Option<SystemTime>,
+}
+
+impl FoundFile {
+    /// `src/main.rs (file, 12.3 KB, modified 2024-03-15 14:30)`
+    fn describe(&self) -> String {
+        let modified = self.modified.map_or(String::new(), |time| {
+            let time: DateTime<Local> = time.into();
+            format!(", modified {}", time.format("%Y-%m-%d %H:%M"))
+        });
+        if self.is_dir {
+            format!("{}/ (dir{modified})", self.path)
+        } else {
+            format!("{} (file, {}{modified})", self.path, format_size(self.size))
+        }
+    }
+}
+
+/// Matches in one file
+struct FileMatches {
+    path: String,
+    matches: usize,
+    /// Runs of adjacent lines; separated by `--` when context is shown
+    groups: Vec<Vec<OutputLine>>,
+}
+
+/// A matching line (with a column) or a context line
+struct OutputLine {
+    number: usize,
+    column: Option<usize>,
+    text: String,
+}
+
+impl OutputLine {
+    /// `path:line:col: text` for matches, `path-line- text` for context
+    fn format(&self, path: &str) -> String {
+        match self.column {
+            Some(column) => format!("{path}:{}:{column}: {}\n", self.number, self.text),
+            None => format!("{path}-{}- {}\n", self.number, self.text),
+        }
+    }
+}
+
+fn search_file(
+    path: &Path,
+    project_root: &Path,
+    re: &Regex,
+    context_lines: usize,
+) -> Option<FileMatches> {
+    let bytes = fs::read(path).ok()?;
+    // Skip binary files
+    if bytes[..bytes.len().min(8192)].contains(&0) {
+        return None;
+    }
+    let content = String::from_utf8_lossy(&bytes);
+    let lines: Vec<&str> = content.lines().collect();
+
+    let hits: Vec<(usize, usize)> = lines
+        .iter()
+        .enumerate()
+        .filter_map(|(i, line)| {
+            re.find(line)
+                .map(|m| (i, line[..m.start()].chars().count() + 1))
+        })
+        .collect();
+    if hits.is_empty() {
+        return None;
+    }
+
+    // Matches whose context touches or overlaps share a group
+    let mut groups: Vec<Vec<OutputLine>> = Vec::new();
+    let mut last_shown: Option<usize> = None;
+    for &(i, _) in &hits {
+        let start = i.saturating_sub(context_lines);
+        let end = (i + context_lines).min(lines.len() - 1);
+        let from = match last_shown {
+            Some(last) if start <= last + 1 => last + 1,
+            _ => {
+                groups.push(Vec::new());
+                start
             }
-            results
+        };
+        if from > end {
+            continue;
         }
+        let group = groups.last_mut().expect("a group was started above");
+        for (n, line) in lines.iter().enumerate().take(end + 1).skip(from) {
+            let column = hits
+                .binary_search_by_key(&n, |(hit, _)| *hit)
+                .ok()
+                .map(|k| hits[k].1);
+            group.push(OutputLine {
+                number: n + 1,
+                column,
+                text: truncate_line(line),
+            });
+        }
+        last_shown = Some(end);
+    }
+
+    let display = path
+        .strip_prefix(project_root)
+        .unwrap_or(path)
+        .display()
+        .to_string();
+    Some(FileMatches {
+        path: display,
+        matches: hits.len(),
+        groups,
+    })
+}
+
+fn truncate_line(line: &str) -> String {
+    match line.char_indices().nth(MAX_LINE_CHARS) {
+        Some((cut, _)) => format!("{}…", &line[..cut]),
+        None => line.to_string(),
     }
 }
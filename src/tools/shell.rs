@@ -1,7 +1,25 @@
-use super::utils::sanitize_shell_command;
-use super::{Tool, ToolContext};
+use super::utils::format_size;
+use super::{Sandbox, Tool, ToolContext};
 use serde_json::{json, Value as JsonValue};
-use std::process::Command;
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader, Read};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How long a command may run when the caller doesn't say
+pub const DEFAULT_TIMEOUT_SECS: u64 = 120;
+/// Upper bound for `timeout_secs`
+pub const MAX_TIMEOUT_SECS: u64 = 3600;
+/// Output kept per stream; the middle of anything longer is dropped
+pub const MAX_OUTPUT_BYTES: usize = 30_000;
+
+/// How often the running command is checked for exit, timeout and cancellation
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+/// How long to wait for output after the shell exits, in case something it
+/// started in the background still holds the pipes open
+const DRAIN_GRACE: Duration = Duration::from_millis(500);
 
 /// Tool for running shell commands
 pub struct RunShellCommand;
@@ -12,14 +30,15 @@ impl Tool for RunShellCommand {
     }
 
     fn description(&self) -> &'static str {
-        "Run a shell command and return output. Use for testing like 'cargo test'. Confirm for sensitive commands."
+        "Run a shell command and return output. Use for testing like 'cargo test'. Confirm for sensitive commands. The command and everything it starts is killed after timeout_secs (default 120). Long output keeps only its beginning and end."
     }
 
     fn parameters(&self) -> JsonValue {
         json!({
             "type": "object",
             "properties": {
-                "command": {"type": "string", "description": "The shell command to run."}
+                "command": {"type": "string", "description": "The shell command to run."},
+                "timeout_secs": {"type": "integer", "description": "Seconds before the command is killed (default 120, max 3600)."}
             },
             "required": ["command"]
         })
@@ -30,69 +49,341 @@ impl Tool for RunShellCommand {
             Some(cmd) => cmd.trim(),
             None => return "Error: 'command' parameter is required".to_string(),
         };
+        let timeout_secs = args["timeout_secs"]
+            .as_u64()
+            .unwrap_or(DEFAULT_TIMEOUT_SECS)
+            .clamp(1, MAX_TIMEOUT_SECS);
 
-        // Sanitize the command
-        let command = match sanitize_shell_command(command) {
-            Ok(cmd) => cmd,
-            Err(e) => return format!("Error: {e}"),
-        };
-
-        if !context.confirm_action(&format!("run command '{command}'")) {
-            return "Command not confirmed.".to_string();
+        if let Err(message) = context.approve_command(command, &format!("run command '{command}'"))
+        {
+            return message;
         }
 
         if context.dry_run {
             return format!("Dry-run: Would execute command: {command}");
         }
 
-        let output = Command::new("sh")
-            .arg("-c")
-            .arg(command)
-            .current_dir(&context.project_root)
-            .output();
+        match run_command(command, Duration::from_secs(timeout_secs), context) {
+            Ok(run) => run.report(timeout_secs, context.sandbox.as_ref()),
+            Err(e) if context.sandbox.is_some() => sandbox_error(&e),
+            Err(e) => match e.kind() {
+                std::io::ErrorKind::NotFound => {
+                    "Error: 'sh' command not found. Shell might not be available.".to_string()
+                }
+                std::io::ErrorKind::PermissionDenied => {
+                    "Error: Permission denied executing shell command".to_string()
+                }
+                _ => format!("Error running command: {e}"),
+            },
+        }
+    }
+}
+
+/// How a command finished
+enum Outcome {
+    Exited(ExitStatus),
+    TimedOut,
+    Cancelled,
+}
 
-        match output {
-            Ok(o) => {
-                let stdout = String::from_utf8_lossy(&o.stdout);
-                let stderr = String::from_utf8_lossy(&o.stderr);
+struct CommandRun {
+    outcome: Outcome,
+    stdout: OutputBuffer,
+    stderr: OutputBuffer,
+}
 
-                let mut result = String::new();
+impl CommandRun {
+    fn report(self, timeout_secs: u64, sandbox: Option<&Sandbox>) -> String {
+        let mut result = String::new();
 
-                if !o.status.success() {
-                    result.push_str(&format!(
-                        "Command failed with exit code: {}\n",
-                        o.status.code().unwrap_or(-1)
-                    ));
-                }
+        let status = match self.outcome {
+            Outcome::Exited(status) => Some(status),
+            _ => None,
+        };
+        match self.outcome {
+            Outcome::Exited(status) if !status.success() => {
+                result.push_str(&format!(
+                    "Command failed with exit code: {}\n",
+                    status.code().unwrap_or(-1)
+                ));
+            }
+            Outcome::Exited(_) => {}
+            Outcome::TimedOut => {
+                result.push_str(&format!(
+                    "Command timed out after {timeout_secs}s and was killed.\n"
+                ));
+            }
+            Outcome::Cancelled => result.push_str("Command cancelled by user.\n"),
+        }
+
+        let stdout = self.stdout.finish();
+        let stderr = self.stderr.finish();
+        if !stdout.is_empty() {
+            result.push_str("Output:\n");
+            result.push_str(&stdout);
+        }
 
-                if !stdout.is_empty() {
-                    result.push_str("Output:\n");
-                    result.push_str(&stdout);
+        if !stderr.is_empty() {
+            if !stdout.is_empty() {
+                result.push('\n');
+            }
+            result.push_str("Error output:\n");
+            result.push_str(&stderr);
+        }
+
+        if let Some(sandbox) = sandbox {
+            let violations = sandbox.violations(status, &format!("{stdout}\n{stderr}"));
+            if !violations.is_empty() {
+                if !result.is_empty() && !result.ends_with('\n') {
+                    result.push('\n');
+                }
+                result.push_str("Sandbox:\n");
+                for violation in violations {
+                    result.push_str(&format!("- {violation}\n"));
                 }
+            }
+        }
+
+        if result.is_empty() {
+            "Command executed successfully (no output)".to_string()
+        } else {
+            result.trim_end().to_string()
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Stream {
+    Stdout,
+    Stderr,
+}
+
+/// Run `command` in its own process group, streaming its output to the
+/// context's output sink until it exits, times out or is cancelled
+fn run_command(
+    command: &str,
+    timeout: Duration,
+    context: &ToolContext<'_>,
+) -> std::io::Result<CommandRun> {
+    let mut child = shell_command(command, context)?
+        .stdin(Stdio::null())
+        .spawn()?;
+
+    let (tx, rx) = mpsc::channel();
+    if let Some(stdout) = child.stdout.take() {
+        spawn_reader(stdout, Stream::Stdout, tx.clone());
+    }
+    if let Some(stderr) = child.stderr.take() {
+        spawn_reader(stderr, Stream::Stderr, tx);
+    }
+
+    let mut run = CommandRun {
+        outcome: Outcome::Cancelled,
+        stdout: OutputBuffer::new(MAX_OUTPUT_BYTES),
+        stderr: OutputBuffer::new(MAX_OUTPUT_BYTES),
+    };
+    let mut record = |stream: Stream, line: String| {
+        if let Some(output) = &context.output {
+            output(line.trim_end_matches(['\n', '\r']));
+        }
+        match stream {
+            Stream::Stdout => run.stdout.push(&line),
+            Stream::Stderr => run.stderr.push(&line),
+        }
+    };
+
+    let deadline = Instant::now() + timeout;
+    let outcome = loop {
+        match rx.recv_timeout(POLL_INTERVAL) {
+            Ok((stream, line)) => record(stream, line),
+            Err(mpsc::RecvTimeoutError::Timeout) => {}
+            Err(mpsc::RecvTimeoutError::Disconnected) => thread::sleep(POLL_INTERVAL),
+        }
+
+        if let Some(status) = child.try_wait()? {
+            break Outcome::Exited(status);
+        }
+        if context.cancel.is_cancelled() {
+            kill_process_group(&mut child);
+            break Outcome::Cancelled;
+        }
+        if Instant::now() >= deadline {
+            kill_process_group(&mut child);
+            break Outcome::TimedOut;
+        }
+    };
 
-                if !stderr.is_empty() {
-                    if !stdout.is_empty() {
-                        result.push('\n');
+    let drain_until = Instant::now() + DRAIN_GRACE;
+    while let Some(left) = drain_until.checked_duration_since(Instant::now()) {
+        match rx.recv_timeout(left) {
+            Ok((stream, line)) => record(stream, line),
+            Err(_) => break,
+        }
+    }
+
+    run.outcome = outcome;
+    Ok(run)
+}
+
+/// `sh -c command` in the project root with piped output, as the leader of a
+/// new process group so [`kill_process_group`] can stop everything it starts.
+/// Runs inside the context's sandbox, if any.
+pub(super) fn shell_command(command: &str, context: &ToolContext<'_>) -> std::io::Result<Command> {
+    let mut cmd = Command::new("sh");
+    cmd.arg("-c")
+        .arg(command)
+        .current_dir(&context.project_root)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        cmd.process_group(0);
+    }
+    if let Some(sandbox) = &context.sandbox {
+        sandbox.apply(&mut cmd, &context.project_root)?;
+    }
+    Ok(cmd)
+}
+
+/// Error for a command that could not be started inside the sandbox
+pub(super) fn sandbox_error(e: &std::io::Error) -> String {
+    format!(
+        "Error: Could not start the command in the sandbox: {e}. \
+         The sandbox needs Linux with unprivileged user namespaces enabled."
+    )
+}
+
+/// Forward a pipe to `tx` line by line, keeping line endings
+fn spawn_reader<R: Read + Send + 'static>(
+    pipe: R,
+    stream: Stream,
+    tx: mpsc::Sender<(Stream, String)>,
+) {
+    thread::spawn(move || {
+        let mut reader = BufReader::new(pipe);
+        let mut buf = Vec::new();
+        loop {
+            buf.clear();
+            match reader.read_until(b'\n', &mut buf) {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {
+                    let line = String::from_utf8_lossy(&buf).into_owned();
+                    if tx.send((stream, line)).is_err() {
+                        break;
                     }
-                    result.push_str("Error output:\n");
-                    result.push_str(&stderr);
                 }
+            }
+        }
+    });
+}
 
-                if result.is_empty() {
-                    "Command executed successfully (no output)".to_string()
-                } else {
-                    result.trim_end().to_string()
-                }
+/// Kill the command and everything it started
+#[cfg(unix)]
+pub(super) fn kill_process_group(child: &mut Child) {
+    // The shell was started as the leader of its own process group, so a
+    // negative pid reaches pipelines and background jobs as well
+    unsafe {
+        libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL);
+    }
+    let _ = child.wait();
+}
+
+/// Kill the command and everything it started
+#[cfg(not(unix))]
+pub(super) fn kill_process_group(child: &mut Child) {
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+/// Keeps the beginning and the end of a stream of output within a byte budget
+struct OutputBuffer {
+    limit: usize,
+    head: String,
+    tail: VecDeque<String>,
+    tail_bytes: usize,
+    omitted_lines: usize,
+    omitted_bytes: usize,
+}
+
+impl OutputBuffer {
+    fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            head: String::new(),
+            tail: VecDeque::new(),
+            tail_bytes: 0,
+            omitted_lines: 0,
+            omitted_bytes: 0,
+        }
+    }
+
+    fn push(&mut self, line: &str) {
+        let half = self.limit / 2;
+        let line = if line.len() > half {
+            let mut end = half;
+            while !line.is_char_boundary(end) {
+                end -= 1;
             }
-            Err(e) => match e.kind() {
-                std::io::ErrorKind::NotFound => {
-                    "Error: 'sh' command not found. Shell might not be available.".to_string()
-                }
-                std::io::ErrorKind::PermissionDenied => {
-                    "Error: Permission denied executing shell command".to_string()
-                }
-                _ => format!("Error running command: {e}"),
-            },
+            format!("{}…\n", &line[..end])
+        } else {
+            line.to_string()
+        };
+
+        if self.tail.is_empty() && self.head.len() + line.len() <= half {
+            self.head.push_str(&line);
+            return;
         }
+
+        self.tail_bytes += line.len();
+        self.tail.push_back(line);
+        while self.tail_bytes > half && self.tail.len() > 1 {
+            if let Some(dropped) = self.tail.pop_front() {
+                self.tail_bytes -= dropped.len();
+                self.omitted_lines += 1;
+                self.omitted_bytes += dropped.len();
+            }
+        }
+    }
+
+    fn finish(self) -> String {
+        let mut out = self.head;
+        if self.omitted_lines > 0 {
+            if !out.ends_with('\n') {
+                out.push('\n');
+            }
+            out.push_str(&format!(
+                "[... {} lines ({}) omitted ...]\n",
+                self.omitted_lines,
+                format_size(self.omitted_bytes as u64)
+            ));
+        }
+        out.extend(self.tail);
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_output_buffer_keeps_head_and_tail() {
+        let mut buffer = OutputBuffer::new(40);
+        for i in 1..=100 {
+            buffer.push(&format!("line {i}\n"));
+        }
+        let output = buffer.finish();
+
+        assert!(output.starts_with("line 1\nline 2\n"));
+        assert!(output.ends_with("line 99\nline 100\n"));
+        assert!(output.contains(" lines ("));
+        assert!(output.contains(") omitted ...]"));
+        assert!(!output.contains("line 50\n"));
+
+        let mut small = OutputBuffer::new(1000);
+        small.push("a\n");
+        small.push("b\n");
+        assert_eq!(small.finish(), "a\nb\n");
     }
 }
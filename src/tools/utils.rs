@@ -45,80 +45,6 @@ pub fn sanitize_path(path: &str, root: &Path) -> Result<PathBuf, String> {
     Ok(root.join(path))
 }
 
-/// Sanitize shell commands to prevent injection attacks
-pub fn sanitize_shell_command(command: &str) -> Result<String, String> {
-    let command = command.trim();
-
-    // Check for empty command
-    if command.is_empty() {
-        return Err("Command cannot be empty".to_string());
-    }
-
-    // List of dangerous patterns
-    let dangerous_patterns = vec![
-        // File system destruction
-        ("rm -rf /", "Deleting root filesystem"),
-        ("rm -rf /*", "Deleting everything in root"),
-        ("rm -rf ~", "Deleting home directory"),
-        ("rm -rf *", "Deleting everything in current directory"),
-        ("chmod -R 777 /", "Making entire filesystem world-writable"),
-        ("chmod 000 /", "Making root inaccessible"),
-        // Disk operations
-        ("dd if=/dev/zero of=/", "Overwriting disk with zeros"),
-        (
-            "dd if=/dev/random of=/",
-            "Overwriting disk with random data",
-        ),
-        ("mkfs.", "Formatting filesystem"),
-        // Fork bombs and resource exhaustion
-        (":(){ :|:& };:", "Fork bomb"),
-        (":(){:|:&};:", "Fork bomb (no spaces)"),
-        ("fork while fork", "Fork bomb variant"),
-        // Network attacks
-        ("nc -l", "Opening network listener (potential backdoor)"),
-        // System modification
-        ("> /etc/passwd", "Overwriting password file"),
-        ("> /etc/shadow", "Overwriting shadow password file"),
-        ("echo > /proc/sys", "Modifying kernel parameters"),
-    ];
-
-    for (pattern, description) in &dangerous_patterns {
-        if command.contains(pattern) {
-            return Err(format!(
-                "Dangerous command pattern detected: {pattern} - {description}"
-            ));
-        }
-    }
-
-    // Check for command chaining that might bypass checks
-    let chain_operators = vec!["&&", "||", ";", "|", "`", "$(", "<(", ">("];
-    let mut has_chaining = false;
-    for op in &chain_operators {
-        if command.contains(op) {
-            has_chaining = true;
-            break;
-        }
-    }
-
-    if has_chaining {
-        // If command has chaining, do more thorough checks
-        // Split by common operators and check each part
-        let parts: Vec<&str> = command.split([';', '&', '|']).collect();
-        for part in parts {
-            let part = part.trim();
-            for (pattern, description) in &dangerous_patterns {
-                if part.contains(pattern) {
-                    return Err(format!(
-                        "Dangerous pattern in command chain: {pattern} - {description}"
-                    ));
-                }
-            }
-        }
-    }
-
-    Ok(command.to_string())
-}
-
 /// Sanitize git branch names according to git's rules
 pub fn sanitize_git_branch_name(name: &str) -> Result<String, String> {
     let name = name.trim();
@@ -197,11 +123,99 @@ pub fn sanitize_regex_pattern(pattern: &str) -> Result<String, String> {
     }
 }
 
+/// Human-readable file size, e.g. `980 B`, `12.3 KB`, `4.0 MB`
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{size:.1} {}", UNITS[unit])
+}
+
+/// Unified diff between two versions of a file, labelled with `path`
+///
+/// Returns an empty string when the contents are identical.
+pub fn unified_diff(path: &str, old: &str, new: &str) -> String {
+    similar::TextDiff::from_lines(old, new)
+        .unified_diff()
+        .context_radius(3)
+        .header(&format!("a/{path}"), &format!("b/{path}"))
+        .to_string()
+}
+
+/// Apply only some of the hunks of the diff from `old` to `new`
+///
+/// Hunks are numbered as in [`unified_diff`]; `accepted[i]` says whether
+/// hunk `i` is taken from `new`. Missing entries count as rejected.
+pub fn select_hunks(old: &str, new: &str, accepted: &[bool]) -> String {
+    let diff = similar::TextDiff::from_lines(old, new);
+    let old_lines = diff.old_slices();
+    let new_lines = diff.new_slices();
+
+    let mut result = String::with_capacity(new.len());
+    let mut pos = 0;
+    for (i, group) in diff.grouped_ops(3).iter().enumerate() {
+        let (Some(first), Some(last)) = (group.first(), group.last()) else {
+            continue;
+        };
+        let old_range = first.old_range().start..last.old_range().end;
+        let new_range = first.new_range().start..last.new_range().end;
+
+        result.extend(old_lines[pos..old_range.start].iter().copied());
+        if accepted.get(i).copied().unwrap_or(false) {
+            result.extend(new_lines[new_range].iter().copied());
+        } else {
+            result.extend(old_lines[old_range.clone()].iter().copied());
+        }
+        pos = old_range.end;
+    }
+    result.extend(old_lines[pos..].iter().copied());
+    result
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use std::path::Path;
 
+    #[test]
+    fn test_unified_diff() {
+        let diff = unified_diff("src/lib.rs", "a\nb\nc\n", "a\nB\nc\n");
+        assert!(diff.starts_with("--- a/src/lib.rs\n+++ b/src/lib.rs\n"));
+        assert!(diff.contains("@@ -1,3 +1,3 @@"));
+        assert!(diff.contains("-b\n+B\n"));
+        assert_eq!(unified_diff("x", "same\n", "same\n"), "");
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(980), "980 B");
+        assert_eq!(format_size(12_595), "12.3 KB");
+        assert_eq!(format_size(4 * 1024 * 1024), "4.0 MB");
+    }
+
+    #[test]
+    fn test_select_hunks() {
+        let old: String = (1..=20).map(|n| format!("{n}\n")).collect();
+        let new = old
+            .replacen("2\n", "two\n", 1)
+            .replace("18\n", "eighteen\n");
+        assert_eq!(unified_diff("f", &old, &new).matches("@@ -").count(), 2);
+
+        assert_eq!(select_hunks(&old, &new, &[true, true]), new);
+        assert_eq!(select_hunks(&old, &new, &[false, false]), old);
+        assert_eq!(
+            select_hunks(&old, &new, &[false, true]),
+            old.replace("18\n", "eighteen\n")
+        );
+    }
+
     #[test]
     fn test_sanitize_path() {
         let root = Path::new("/project");
@@ -217,24 +231,6 @@ mod tests {
         assert!(sanitize_path("test\0file", root).is_err());
     }
 
-    #[test]
-    fn test_sanitize_shell_command() {
-        // Valid commands
-        assert!(sanitize_shell_command("ls -la").is_ok());
-        assert!(sanitize_shell_command("cargo test").is_ok());
-        assert!(sanitize_shell_command("echo 'hello world'").is_ok());
-
-        // Invalid commands
-        assert!(sanitize_shell_command("rm -rf /").is_err());
-        assert!(sanitize_shell_command(":(){ :|:& };:").is_err());
-        assert!(sanitize_shell_command("dd if=/dev/zero of=/dev/sda").is_err());
-        assert!(sanitize_shell_command("").is_err());
-
-        // Commands with chaining
-        assert!(sanitize_shell_command("echo test && rm -rf /").is_err());
-        assert!(sanitize_shell_command("ls | grep test").is_ok());
-    }
-
     #[test]
     fn test_sanitize_git_branch_name() {
         // Valid names
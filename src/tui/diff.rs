@@ -1,5 +1,6 @@
 //! Code diff visualization for the TUI
 
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 use ratatui::{
     layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
@@ -29,7 +30,7 @@ pub struct DiffLine {
 }
 
 /// Type of diff line
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum DiffLineType {
     Context,
     Added,
@@ -37,16 +38,114 @@ pub enum DiffLineType {
     Header,
 }
 
-/// A widget for displaying code diffs
+/// What the user decided in a [`DiffView`]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DiffViewAction {
+    /// Apply the accepted hunks
+    Submit,
+    /// Reject everything
+    Cancel,
+}
+
+/// A widget for reviewing code diffs hunk by hunk
 pub struct DiffView {
     hunks: Vec<DiffHunk>,
     scroll: usize,
+    /// Hunk the cursor is on
+    selected: usize,
+    /// Whether each hunk will be applied
+    accepted: Vec<bool>,
+    title: String,
 }
 
 impl DiffView {
-    /// Create a new diff view
+    /// Create a new diff view with every hunk accepted
     pub fn new(hunks: Vec<DiffHunk>) -> Self {
-        Self { hunks, scroll: 0 }
+        let accepted = vec![true; hunks.len()];
+        Self {
+            hunks,
+            scroll: 0,
+            selected: 0,
+            accepted,
+            title: "Code Diff View".to_string(),
+        }
+    }
+
+    /// Set the text shown in the header
+    pub fn with_title(mut self, title: impl Into<String>) -> Self {
+        self.title = title.into();
+        self
+    }
+
+    /// Which hunks are currently accepted, in order
+    pub fn accepted(&self) -> &[bool] {
+        &self.accepted
+    }
+
+    /// Handle a key press; returns the decision once the user has made one
+    pub fn handle_key(&mut self, key: KeyEvent) -> Option<DiffViewAction> {
+        match key.code {
+            KeyCode::Char('c') if key.modifiers == KeyModifiers::CONTROL => {
+                return Some(DiffViewAction::Cancel)
+            }
+            KeyCode::Enter => return Some(DiffViewAction::Submit),
+            KeyCode::Esc | KeyCode::Char('q') => return Some(DiffViewAction::Cancel),
+            KeyCode::Char('j') | KeyCode::Down => self.scroll_down(1),
+            KeyCode::Char('k') | KeyCode::Up => self.scroll_up(1),
+            KeyCode::PageDown => self.scroll_down(10),
+            KeyCode::PageUp => self.scroll_up(10),
+            KeyCode::Char('n') | KeyCode::Tab => self.select(self.selected + 1),
+            KeyCode::Char('p') | KeyCode::BackTab => self.select(self.selected.saturating_sub(1)),
+            KeyCode::Char(' ') => {
+                if let Some(accepted) = self.accepted.get_mut(self.selected) {
+                    *accepted = !*accepted;
+                }
+            }
+            KeyCode::Char('y') => self.set_selected(true),
+            KeyCode::Char('d') => self.set_selected(false),
+            KeyCode::Char('a') => self.accepted.fill(true),
+            KeyCode::Char('r') => self.accepted.fill(false),
+            _ => {}
+        }
+        None
+    }
+
+    /// Accept or reject the selected hunk and move to the next one
+    fn set_selected(&mut self, accepted: bool) {
+        if let Some(a) = self.accepted.get_mut(self.selected) {
+            *a = accepted;
+        }
+        self.select(self.selected + 1);
+    }
+
+    /// Move the cursor to a hunk and scroll it into view
+    fn select(&mut self, hunk: usize) {
+        if self.hunks.is_empty() {
+            return;
+        }
+        self.selected = hunk.min(self.hunks.len() - 1);
+        self.scroll = self.hunk_offset(self.selected);
+    }
+
+    /// Row at which a hunk starts in the rendered list
+    fn hunk_offset(&self, hunk: usize) -> usize {
+        let mut rows = 0;
+        for (i, h) in self.hunks.iter().enumerate() {
+            let new_file = i == 0 || self.hunks[i - 1].file_path != h.file_path;
+            if new_file {
+                rows += 1;
+            }
+            if i == hunk {
+                return rows;
+            }
+            // Hunk header, diff lines and a blank separator
+            rows += 2 + h
+                .lines
+                .iter()
+                .filter(|l| l.line_type != DiffLineType::Header)
+                .count();
+        }
+        rows
     }
 
     /// Render the diff view
@@ -60,9 +159,19 @@ impl DiffView {
             .split(area);
 
         // Render header
-        let header = Paragraph::new("Code Diff View - Use j/k to scroll, q to close")
-            .style(Style::default().fg(Color::White).bg(Color::Blue))
-            .block(Block::default().borders(Borders::ALL));
+        let accepted = self.accepted.iter().filter(|a| **a).count();
+        let header = Paragraph::new(vec![
+            Line::from(format!(
+                "{} - {accepted}/{} hunks accepted",
+                self.title,
+                self.hunks.len()
+            )),
+            Line::from(
+                "n/p: next/prev hunk  Space: toggle  y/d: accept/reject hunk  a/r: all  j/k: scroll  Enter: apply  Esc: reject all",
+            ),
+        ])
+        .style(Style::default().fg(Color::White).bg(Color::Blue))
+        .block(Block::default().borders(Borders::ALL));
         f.render_widget(header, chunks[0]);
 
         // Render diff content
@@ -73,32 +182,48 @@ impl DiffView {
     fn render_diff_content(&self, f: &mut Frame, area: Rect) {
         let mut items: Vec<ListItem> = Vec::new();
 
-        for hunk in &self.hunks {
+        for (i, hunk) in self.hunks.iter().enumerate() {
             // File header
-            items.push(ListItem::new(Line::from(vec![Span::styled(
-                format!("=== {} ===", hunk.file_path),
-                Style::default()
-                    .fg(Color::Cyan)
-                    .add_modifier(Modifier::BOLD),
-            )])));
-
-            // Hunk header
-            items.push(ListItem::new(Line::from(vec![Span::styled(
-                format!(
-                    "@@ -{},{} +{},{} @@",
-                    hunk.old_start, hunk.old_count, hunk.new_start, hunk.new_count
+            if i == 0 || self.hunks[i - 1].file_path != hunk.file_path {
+                items.push(ListItem::new(Line::from(vec![Span::styled(
+                    format!("=== {} ===", hunk.file_path),
+                    Style::default()
+                        .fg(Color::Cyan)
+                        .add_modifier(Modifier::BOLD),
+                )])));
+            }
+
+            // Hunk header with its approval state
+            let accepted = self.accepted[i];
+            let mut header_style = Style::default().fg(Color::Magenta);
+            if i == self.selected {
+                header_style = header_style.add_modifier(Modifier::REVERSED);
+            }
+            items.push(ListItem::new(Line::from(vec![
+                Span::styled(
+                    if accepted { "[✓] " } else { "[✗] " },
+                    Style::default().fg(if accepted { Color::Green } else { Color::Red }),
+                ),
+                Span::styled(
+                    format!(
+                        "@@ -{},{} +{},{} @@",
+                        hunk.old_start, hunk.old_count, hunk.new_start, hunk.new_count
+                    ),
+                    header_style,
                 ),
-                Style::default().fg(Color::Magenta),
-            )])));
+            ])));
 
             // Diff lines
             for line in &hunk.lines {
-                let (prefix, style) = match line.line_type {
+                let (prefix, mut style) = match line.line_type {
                     DiffLineType::Context => (" ", Style::default()),
                     DiffLineType::Added => ("+", Style::default().fg(Color::Green)),
                     DiffLineType::Removed => ("-", Style::default().fg(Color::Red)),
-                    DiffLineType::Header => ("", Style::default().fg(Color::Yellow)),
+                    DiffLineType::Header => continue,
                 };
+                if !accepted {
+                    style = style.fg(Color::DarkGray).add_modifier(Modifier::DIM);
+                }
 
                 let line_nums = format!(
                     "{:>4} {:>4} ",
@@ -116,7 +241,7 @@ impl DiffView {
             items.push(ListItem::new(""));
         }
 
-        let diff_list = List::new(items)
+        let diff_list = List::new(items.into_iter().skip(self.scroll).collect::<Vec<_>>())
             .block(
                 Block::default()
                     .borders(Borders::ALL)
@@ -134,67 +259,112 @@ impl DiffView {
 
     /// Scroll down
     pub fn scroll_down(&mut self, amount: usize) {
-        self.scroll = self.scroll.saturating_add(amount);
+        let rows = self.hunk_offset(self.hunks.len());
+        self.scroll = self
+            .scroll
+            .saturating_add(amount)
+            .min(rows.saturating_sub(1));
     }
 }
 
-/// Parse a unified diff into hunks
+/// Parse a unified diff into hunks, one per `@@` section
+///
+/// File paths come from the `+++` header (or `---` when the file is deleted),
+/// with any `a/`/`b/` prefix removed. A file created by the diff has a hunk
+/// with `old_start == 0`, a deleted file one with `new_start == 0`.
+///
+/// Lines beyond a hunk's `@@` counts are dropped; use
+/// [`parse_unified_diff_checked`] when the patch is going to be applied.
 pub fn parse_unified_diff(diff_text: &str) -> Vec<DiffHunk> {
+    parse_hunks(diff_text).0
+}
+
+/// Parse a unified diff like [`parse_unified_diff`], failing when a hunk's
+/// body doesn't have the number of lines its `@@` header counts
+pub fn parse_unified_diff_checked(diff_text: &str) -> Result<Vec<DiffHunk>, String> {
+    match parse_hunks(diff_text) {
+        (hunks, None) => Ok(hunks),
+        (_, Some(error)) => Err(error),
+    }
+}
+
+/// The hunks of a diff, and the first hunk whose body doesn't match its header
+fn parse_hunks(diff_text: &str) -> (Vec<DiffHunk>, Option<String>) {
     let mut hunks = Vec::new();
+    let mut error = None;
     let mut current_hunk: Option<DiffHunk> = None;
+    let mut file_path = String::new();
     let mut old_line = 0;
     let mut new_line = 0;
+    // Lines still expected by the current hunk, so that content such as
+    // "--- x" is not mistaken for a file header
+    let mut old_left = 0;
+    let mut new_left = 0;
 
     for line in diff_text.lines() {
-        if line.starts_with("diff --git") {
+        let in_hunk = current_hunk.is_some() && (old_left > 0 || new_left > 0);
+
+        if !in_hunk && line.starts_with("diff --git") {
             // New file
+            hunks.extend(current_hunk.take());
+            file_path.clear();
+        } else if !in_hunk && line.starts_with("--- ") {
+            hunks.extend(current_hunk.take());
+            file_path = header_path(&line[4..]);
+        } else if !in_hunk && line.starts_with("+++ ") {
+            let path = header_path(&line[4..]);
+            if path != "/dev/null" {
+                file_path = path;
+            }
+        } else if line.starts_with("@@") {
+            // Parse @@ -old_start,old_count +new_start,new_count @@
             if let Some(hunk) = current_hunk.take() {
+                if in_hunk {
+                    error.get_or_insert_with(|| short_hunk(&hunk));
+                }
                 hunks.push(hunk);
             }
-        } else if line.starts_with("---") || line.starts_with("+++") {
-            // File headers - extract filename
-            if line.starts_with("+++") {
-                if let Some(path) = line.strip_prefix("+++ b/") {
-                    if current_hunk.is_none() {
-                        current_hunk = Some(DiffHunk {
-                            file_path: path.to_string(),
-                            old_start: 0,
-                            old_count: 0,
-                            new_start: 0,
-                            new_count: 0,
-                            lines: Vec::new(),
-                        });
-                    }
+            let mut hunk = DiffHunk {
+                file_path: file_path.clone(),
+                old_start: 0,
+                old_count: 0,
+                new_start: 0,
+                new_count: 0,
+                lines: Vec::new(),
+            };
+
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            if parts.len() >= 3 {
+                if let Some(old_info) = parts[1].strip_prefix('-') {
+                    (hunk.old_start, hunk.old_count) = parse_range(old_info);
                 }
-            }
-        } else if line.starts_with("@@") {
-            // Hunk header
-            if let Some(ref mut hunk) = current_hunk {
-                // Parse @@ -old_start,old_count +new_start,new_count @@
-                let parts: Vec<&str> = line.split_whitespace().collect();
-                if parts.len() >= 3 {
-                    if let Some(old_info) = parts[1].strip_prefix('-') {
-                        let old_parts: Vec<&str> = old_info.split(',').collect();
-                        old_line = old_parts[0].parse().unwrap_or(1);
-                        hunk.old_start = old_line;
-                        hunk.old_count = old_parts.get(1).and_then(|s| s.parse().ok()).unwrap_or(1);
-                    }
-                    if let Some(new_info) = parts[2].strip_prefix('+') {
-                        let new_parts: Vec<&str> = new_info.split(',').collect();
-                        new_line = new_parts[0].parse().unwrap_or(1);
-                        hunk.new_start = new_line;
-                        hunk.new_count = new_parts.get(1).and_then(|s| s.parse().ok()).unwrap_or(1);
-                    }
+                if let Some(new_info) = parts[2].strip_prefix('+') {
+                    (hunk.new_start, hunk.new_count) = parse_range(new_info);
                 }
-
-                hunk.lines.push(DiffLine {
-                    line_type: DiffLineType::Header,
-                    content: line.to_string(),
-                    old_line_num: None,
-                    new_line_num: None,
-                });
             }
+            old_line = hunk.old_start;
+            new_line = hunk.new_start;
+            old_left = hunk.old_count;
+            new_left = hunk.new_count;
+
+            hunk.lines.push(DiffLine {
+                line_type: DiffLineType::Header,
+                content: line.to_string(),
+                old_line_num: None,
+                new_line_num: None,
+            });
+            current_hunk = Some(hunk);
+        } else if line.starts_with('\\') {
+            // "\ No newline at end of file"
         } else if let Some(ref mut hunk) = current_hunk {
+            if !in_hunk {
+                // The signature `git format-patch` ends with is not a diff line
+                if line.starts_with(['+', '-', ' ']) && line != "-- " {
+                    error.get_or_insert_with(|| long_hunk(hunk));
+                }
+                continue;
+            }
+
             // Diff content
             let (line_type, content) = if let Some(content) = line.strip_prefix('+') {
                 (DiffLineType::Added, content)
@@ -209,20 +379,33 @@ pub fn parse_unified_diff(diff_text: &str) -> Vec<DiffHunk> {
 
             let (old_num, new_num) = match line_type {
                 DiffLineType::Added => {
+                    if new_left == 0 {
+                        error.get_or_insert_with(|| long_hunk(hunk));
+                    }
                     let n = Some(new_line);
                     new_line += 1;
+                    new_left = new_left.saturating_sub(1);
                     (None, n)
                 }
                 DiffLineType::Removed => {
+                    if old_left == 0 {
+                        error.get_or_insert_with(|| long_hunk(hunk));
+                    }
                     let n = Some(old_line);
                     old_line += 1;
+                    old_left = old_left.saturating_sub(1);
                     (n, None)
                 }
                 DiffLineType::Context => {
+                    if old_left == 0 || new_left == 0 {
+                        error.get_or_insert_with(|| long_hunk(hunk));
+                    }
                     let o = Some(old_line);
                     let n = Some(new_line);
                     old_line += 1;
                     new_line += 1;
+                    old_left = old_left.saturating_sub(1);
+                    new_left = new_left.saturating_sub(1);
                     (o, n)
                 }
                 _ => (None, None),
@@ -238,8 +421,129 @@ pub fn parse_unified_diff(diff_text: &str) -> Vec<DiffHunk> {
     }
 
     if let Some(hunk) = current_hunk {
+        if old_left > 0 || new_left > 0 {
+            error.get_or_insert_with(|| short_hunk(&hunk));
+        }
         hunks.push(hunk);
     }
 
-    hunks
+    (hunks, error)
+}
+
+fn short_hunk(hunk: &DiffHunk) -> String {
+    format!(
+        "hunk '{}' of {} has fewer lines than its header counts",
+        hunk.lines[0].content, hunk.file_path
+    )
+}
+
+fn long_hunk(hunk: &DiffHunk) -> String {
+    format!(
+        "hunk '{}' of {} has more lines than its header counts",
+        hunk.lines[0].content, hunk.file_path
+    )
+}
+
+/// Path from a `---`/`+++` header, without the `a/`/`b/` prefix or timestamp
+fn header_path(header: &str) -> String {
+    let path = header.split('\t').next().unwrap_or(header).trim_end();
+    path.strip_prefix("a/")
+        .or_else(|| path.strip_prefix("b/"))
+        .unwrap_or(path)
+        .to_string()
+}
+
+/// Parse `start,count` from a hunk header; the count defaults to 1
+fn parse_range(range: &str) -> (usize, usize) {
+    let mut parts = range.split(',');
+    let start = parts.next().and_then(|s| s.parse().ok()).unwrap_or(1);
+    let count = parts.next().and_then(|s| s.parse().ok()).unwrap_or(1);
+    (start, count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    #[test]
+    fn test_parse_unified_diff_splits_hunks() {
+        let diff = "\
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -1,2 +1,2 @@
+--- not a header
++++ not a header either
+ keep
+@@ -10 +10 @@
+-old
++new
+--- /dev/null
++++ b/NEW.md
+@@ -0,0 +1 @@
++hello
+";
+        let hunks = parse_unified_diff(diff);
+        assert_eq!(hunks.len(), 3);
+        assert_eq!(hunks[0].file_path, "src/lib.rs");
+        assert_eq!(hunks[0].lines[1].line_type, DiffLineType::Removed);
+        assert_eq!(hunks[0].lines[1].content, "-- not a header");
+        assert_eq!(hunks[0].lines[2].line_type, DiffLineType::Added);
+        assert_eq!((hunks[1].old_start, hunks[1].old_count), (10, 1));
+        assert_eq!(hunks[2].file_path, "NEW.md");
+        assert_eq!((hunks[2].old_start, hunks[2].old_count), (0, 0));
+    }
+
+    #[test]
+    fn test_parse_unified_diff_checked_counts() {
+        let diff = "--- a/f.txt\n+++ b/f.txt\n@@ -1,2 +1,2 @@\n a\n-b\n+B\n";
+        assert_eq!(parse_unified_diff_checked(diff).unwrap().len(), 1);
+
+        // One line more than the header counts
+        let long = format!("{diff}+C\n");
+        assert_eq!(parse_unified_diff(&long)[0].lines.len(), 4);
+        let error = parse_unified_diff_checked(&long).err().unwrap();
+        assert!(
+            error.contains("more lines than its header counts"),
+            "{error}"
+        );
+
+        // One line fewer, then the next hunk
+        let short = "--- a/f.txt\n+++ b/f.txt\n@@ -1,3 +1,3 @@\n a\n-b\n+B\n@@ -9 +9 @@\n-x\n+y\n";
+        let error = parse_unified_diff_checked(short).err().unwrap();
+        assert_eq!(
+            error,
+            "hunk '@@ -1,3 +1,3 @@' of f.txt has fewer lines than its header counts"
+        );
+        let short = "--- a/f.txt\n+++ b/f.txt\n@@ -1,3 +1,3 @@\n a\n-b\n+B\n";
+        assert!(parse_unified_diff_checked(short).is_err());
+    }
+
+    #[test]
+    fn test_diff_view_toggles_hunks() {
+        let diff = "--- a/f\n+++ b/f\n@@ -1 +1 @@\n-a\n+b\n@@ -9 +9 @@\n-c\n+d\n";
+        let mut view = DiffView::new(parse_unified_diff(diff));
+        assert_eq!(view.accepted(), &[true, true]);
+
+        assert_eq!(view.handle_key(key(KeyCode::Char(' '))), None);
+        assert_eq!(view.accepted(), &[false, true]);
+
+        view.handle_key(key(KeyCode::Char('n')));
+        view.handle_key(key(KeyCode::Char('d')));
+        assert_eq!(view.accepted(), &[false, false]);
+
+        view.handle_key(key(KeyCode::Char('a')));
+        assert_eq!(view.accepted(), &[true, true]);
+        assert_eq!(
+            view.handle_key(key(KeyCode::Enter)),
+            Some(DiffViewAction::Submit)
+        );
+        assert_eq!(
+            view.handle_key(key(KeyCode::Esc)),
+            Some(DiffViewAction::Cancel)
+        );
+    }
 }
@@ -8,9 +8,12 @@
 //! - File tree navigation
 
 pub mod diff;
+pub mod prompt;
 
 use crate::agent::TuiUpdate;
 use crate::api::Message;
+use crate::tools::CancelToken;
+use crate::usage::SessionUsage;
 use crossterm::{
     event::{
         self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent, KeyModifiers,
@@ -47,8 +50,21 @@ pub struct TuiApp {
     is_processing: bool,
     /// Channel to receive updates from agent
     update_receiver: Option<mpsc::UnboundedReceiver<TuiUpdate>>,
+    /// Index of the assistant message currently being streamed
+    streaming_message: Option<usize>,
+    /// Token usage of the session, once the provider has reported any
+    usage: Option<SessionUsage>,
+    /// Index of the message showing output of the running tool
+    live_output: Option<usize>,
+    /// Stops the running tools when Ctrl-C is pressed during processing
+    cancel: Option<CancelToken>,
+    /// Prompts answered when the screen was last drawn
+    prompts_seen: usize,
 }
 
+/// Lines of live tool output kept on screen
+const LIVE_OUTPUT_LINES: usize = 12;
+
 /// UI representation of a message
 #[derive(Clone)]
 struct UiMessage {
@@ -78,6 +94,11 @@ impl TuiApp {
             status: "Ready. Press ? for help, Esc to toggle modes, Ctrl-C to quit.".to_string(),
             is_processing: false,
             update_receiver: None,
+            streaming_message: None,
+            usage: None,
+            live_output: None,
+            cancel: None,
+            prompts_seen: prompt::prompts_answered(),
         }
     }
 
@@ -86,6 +107,11 @@ impl TuiApp {
         self.update_receiver = Some(receiver);
     }
 
+    /// Set the token that Ctrl-C cancels while the agent is working
+    pub fn set_cancel_token(&mut self, cancel: CancelToken) {
+        self.cancel = Some(cancel);
+    }
+
     /// Process updates from the agent
     pub fn process_updates(&mut self) {
         // Take the receiver out temporarily to avoid borrow issues
@@ -96,6 +122,9 @@ impl TuiApp {
                     TuiUpdate::Message(msg) => {
                         self.add_message(&msg);
                     }
+                    TuiUpdate::StreamChunk { content } => {
+                        self.append_stream_chunk(&content);
+                    }
                     TuiUpdate::ToolStart { name, icon } => {
                         // Add tool start message
                         let tool_msg = UiMessage {
@@ -107,7 +136,11 @@ impl TuiApp {
                         self.messages.push(tool_msg);
                         self.scroll = self.messages.len().saturating_sub(1) as u16;
                     }
+                    TuiUpdate::ToolOutput { name: _, line } => {
+                        self.append_tool_output(&line);
+                    }
                     TuiUpdate::ToolResult { name, result } => {
+                        self.live_output = None;
                         // Add tool result message
                         let tool_msg = UiMessage {
                             role: "tool".to_string(),
@@ -118,6 +151,9 @@ impl TuiApp {
                         self.messages.push(tool_msg);
                         self.scroll = self.messages.len().saturating_sub(1) as u16;
                     }
+                    TuiUpdate::Usage(usage) => {
+                        self.usage = Some(usage);
+                    }
                     TuiUpdate::Processing { message } => {
                         // Update status with processing message
                         self.status = format!("⏳ {message}");
@@ -132,9 +168,11 @@ impl TuiApp {
                         };
                         self.messages.push(error_msg);
                         self.scroll = self.messages.len().saturating_sub(1) as u16;
+                        self.streaming_message = None;
                         self.set_processing(false);
                     }
                     TuiUpdate::Complete => {
+                        self.streaming_message = None;
                         self.set_processing(false);
                     }
                 }
@@ -161,17 +199,73 @@ impl TuiApp {
                 })
                 .unwrap_or_default(),
         };
-        self.messages.push(ui_msg);
+
+        // A finished assistant message replaces its streamed preview
+        match self.streaming_message.take() {
+            Some(idx) if message.role == "assistant" && idx < self.messages.len() => {
+                self.messages[idx] = ui_msg;
+            }
+            _ => self.messages.push(ui_msg),
+        }
 
         // Auto-scroll to bottom when new message arrives
         self.scroll = self.messages.len().saturating_sub(1) as u16;
     }
 
+    /// Append streamed assistant text, starting a new message if needed
+    pub fn append_stream_chunk(&mut self, chunk: &str) {
+        match self.streaming_message {
+            Some(idx) if idx < self.messages.len() => {
+                self.messages[idx].content.push_str(chunk);
+            }
+            _ => {
+                self.messages.push(UiMessage {
+                    role: "assistant".to_string(),
+                    content: chunk.to_string(),
+                    timestamp: chrono::Local::now().format("%H:%M:%S").to_string(),
+                    tool_calls: vec![],
+                });
+                self.streaming_message = Some(self.messages.len() - 1);
+            }
+        }
+        self.scroll = self.messages.len().saturating_sub(1) as u16;
+    }
+
+    /// Show a line of output from a running tool, keeping only the latest lines
+    fn append_tool_output(&mut self, line: &str) {
+        let idx = match self.live_output {
+            Some(idx) if idx < self.messages.len() => idx,
+            _ => {
+                self.messages.push(UiMessage {
+                    role: "output".to_string(),
+                    content: String::new(),
+                    timestamp: chrono::Local::now().format("%H:%M:%S").to_string(),
+                    tool_calls: vec![],
+                });
+                self.messages.len() - 1
+            }
+        };
+        self.live_output = Some(idx);
+
+        let content = &mut self.messages[idx].content;
+        content.push_str(line);
+        content.push('\n');
+        let lines = content.lines().count();
+        if lines > LIVE_OUTPUT_LINES {
+            *content = content
+                .lines()
+                .skip(lines - LIVE_OUTPUT_LINES)
+                .map(|l| format!("{l}\n"))
+                .collect();
+        }
+        self.scroll = self.messages.len().saturating_sub(1) as u16;
+    }
+
     /// Set processing state
     pub fn set_processing(&mut self, processing: bool) {
         self.is_processing = processing;
         if processing {
-            self.status = "🤔 AI is thinking... Please wait.".to_string();
+            self.status = "🤔 AI is thinking... Press Ctrl-C to cancel.".to_string();
         } else {
             self.status = "✅ Done! Press 'i' to send another message.".to_string();
         }
@@ -217,11 +311,42 @@ impl TuiApp {
         }
     }
 
+    /// Keep the screen live while the agent works: apply its updates, redraw
+    /// and handle keys. Call repeatedly until the agent is done.
+    pub fn tick<B: Backend>(&mut self, terminal: &mut Terminal<B>) -> Result<(), Box<dyn Error>> {
+        self.process_updates();
+
+        // An approval prompt owns the screen and the keyboard until answered
+        let Some(_screen) = prompt::lock_screen() else {
+            return Ok(());
+        };
+        let answered = prompt::prompts_answered();
+        if answered != self.prompts_seen {
+            self.prompts_seen = answered;
+            terminal.clear()?;
+        }
+
+        terminal.draw(|f| self.draw(f))?;
+
+        while event::poll(Duration::ZERO)? {
+            if let Event::Key(key) = event::read()? {
+                self.handle_key_event(key)?;
+            }
+        }
+        Ok(())
+    }
+
     /// Handle key events
     fn handle_key_event(&mut self, key: KeyEvent) -> Result<Option<String>, Box<dyn Error>> {
-        // Always allow Ctrl-C to quit
+        // Ctrl-C stops the running tools while processing, and quits otherwise
         if key.code == KeyCode::Char('c') && key.modifiers == KeyModifiers::CONTROL {
-            self.should_quit = true;
+            match &self.cancel {
+                Some(cancel) if self.is_processing => {
+                    cancel.cancel();
+                    self.status = "⛔ Cancelling...".to_string();
+                }
+                _ => self.should_quit = true,
+            }
             return Ok(None);
         }
 
@@ -240,12 +365,10 @@ impl TuiApp {
     /// Handle keys in normal mode
     fn handle_normal_mode(&mut self, key: KeyEvent) -> Result<Option<String>, Box<dyn Error>> {
         match (key.code, key.modifiers) {
-            (KeyCode::Char('i'), _) => {
-                if !self.is_processing {
-                    self.mode = AppMode::Input;
-                    self.status = "📝 Input mode - Type your message, Enter to send, Esc to cancel"
-                        .to_string();
-                }
+            (KeyCode::Char('i'), _) if !self.is_processing => {
+                self.mode = AppMode::Input;
+                self.status =
+                    "📝 Input mode - Type your message, Enter to send, Esc to cancel".to_string();
             }
             (KeyCode::Char('s'), _) => {
                 self.mode = AppMode::ScrollingMessages;
@@ -263,14 +386,12 @@ impl TuiApp {
     /// Handle keys in input mode
     fn handle_input_mode(&mut self, key: KeyEvent) -> Result<Option<String>, Box<dyn Error>> {
         match key.code {
-            KeyCode::Enter => {
-                if !self.input.is_empty() {
-                    let input = self.input.clone();
-                    self.clear_input();
-                    self.mode = AppMode::Normal;
-                    self.set_processing(true);
-                    return Ok(Some(input));
-                }
+            KeyCode::Enter if !self.input.is_empty() => {
+                let input = self.input.clone();
+                self.clear_input();
+                self.mode = AppMode::Normal;
+                self.set_processing(true);
+                return Ok(Some(input));
             }
             KeyCode::Char(c) => {
                 self.input.push(c);
@@ -324,7 +445,8 @@ impl TuiApp {
     /// Show help message
     fn show_help(&mut self) {
         self.status =
-            "📚 Help: i=input s=scroll j/k=up/down Enter=send Esc=mode Ctrl-C=quit".to_string();
+            "📚 Help: i=input s=scroll j/k=up/down Enter=send Esc=mode Ctrl-C=cancel/quit"
+                .to_string();
     }
 
     /// Draw the UI
@@ -370,6 +492,7 @@ impl TuiApp {
                 "tool" => Style::default()
                     .fg(Color::Magenta)
                     .add_modifier(Modifier::BOLD),
+                "output" => Style::default().fg(Color::DarkGray),
                 _ => Style::default().fg(Color::Gray),
             };
 
@@ -378,6 +501,7 @@ impl TuiApp {
                 "assistant" => "🤖",
                 "system" => "⚙️",
                 "tool" => "🔧",
+                "output" => "📤",
                 _ => "❓",
             };
 
@@ -498,7 +622,27 @@ impl TuiApp {
             .alignment(Alignment::Left)
             .wrap(Wrap { trim: true });
 
-        f.render_widget(status, area);
+        // Token usage goes on the right-hand side
+        let Some(usage) = &self.usage else {
+            f.render_widget(status, area);
+            return;
+        };
+        let usage_text = format!(" {} ", usage.compact());
+        let chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([
+                Constraint::Min(0),
+                Constraint::Length(usage_text.chars().count() as u16),
+            ])
+            .split(area);
+
+        f.render_widget(status, chunks[0]);
+        f.render_widget(
+            Paragraph::new(usage_text)
+                .style(Style::default().fg(Color::Cyan).bg(Color::Black))
+                .alignment(Alignment::Right),
+            chunks[1],
+        );
     }
 }
 
@@ -0,0 +1,179 @@
+//! Modal prompts shown while the agent is working
+//!
+//! Tools run on their own threads while the main TUI loop keeps drawing, so
+//! these prompts take the screen over: they hold a lock that pauses the main
+//! loop, draw over the screen themselves and block until the user answers.
+//! The main loop repaints everything once a prompt has been answered.
+
+use super::diff::{DiffHunk, DiffView, DiffViewAction};
+use crate::tools::Approval;
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use once_cell::sync::Lazy;
+use ratatui::{
+    backend::CrosstermBackend,
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::Line,
+    widgets::{Block, Borders, Clear, Paragraph, Wrap},
+    Terminal,
+};
+use std::io::{self, IsTerminal};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, MutexGuard, TryLockError};
+
+// Tools may run in parallel; only one prompt can own the screen
+static PROMPT_MUTEX: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+// Number of prompts answered so far, so the main loop knows when to repaint
+static PROMPTS_ANSWERED: AtomicUsize = AtomicUsize::new(0);
+
+/// Claim the screen for the main loop. Returns `None` while a prompt is shown.
+pub fn lock_screen() -> Option<MutexGuard<'static, ()>> {
+    match PROMPT_MUTEX.try_lock() {
+        Ok(guard) => Some(guard),
+        Err(TryLockError::Poisoned(e)) => Some(e.into_inner()),
+        Err(TryLockError::WouldBlock) => None,
+    }
+}
+
+/// How many prompts have been answered; changes whenever the screen was drawn over
+pub fn prompts_answered() -> usize {
+    PROMPTS_ANSWERED.load(Ordering::SeqCst)
+}
+
+fn interactive() -> bool {
+    io::stdin().is_terminal() && io::stdout().is_terminal()
+}
+
+fn terminal() -> io::Result<Terminal<CrosstermBackend<io::Stdout>>> {
+    let mut terminal = Terminal::new(CrosstermBackend::new(io::stdout()))?;
+    // Start from a blank screen so nothing of the chat view shows through
+    terminal.clear()?;
+    Ok(terminal)
+}
+
+/// Don't leave a prompt on screen once it has been answered
+fn clear_screen() {
+    let _ = terminal();
+    PROMPTS_ANSWERED.fetch_add(1, Ordering::SeqCst);
+}
+
+/// Ask whether to go ahead, offering to remember the answer for the
+/// session or the project; `always` says what that would allow.
+/// Non-interactive sessions are auto-confirmed once.
+pub fn ask(action: &str, always: &str) -> Approval {
+    if !interactive() {
+        return Approval::Once;
+    }
+    let _lock = PROMPT_MUTEX.lock().unwrap_or_else(|e| e.into_inner());
+    let approval = run_ask(action, always).unwrap_or(Approval::Denied);
+    clear_screen();
+    approval
+}
+
+fn run_ask(action: &str, always: &str) -> io::Result<Approval> {
+    let mut terminal = terminal()?;
+    loop {
+        terminal.draw(|f| {
+            let area = centered(f.area(), 64, 12);
+            let text = vec![
+                Line::from(format!("Confirm {action}?")),
+                Line::from(""),
+                Line::from("y / Enter: yes, once    n / Esc: no"),
+                Line::from("a: always this session    p: always in this project"),
+                Line::from(""),
+                Line::from(format!("\"Always\" allows {always}"))
+                    .style(Style::default().fg(Color::DarkGray)),
+            ];
+            f.render_widget(Clear, area);
+            f.render_widget(
+                Paragraph::new(text)
+                    .alignment(Alignment::Center)
+                    .wrap(Wrap { trim: true })
+                    .block(
+                        Block::default()
+                            .borders(Borders::ALL)
+                            .title(" ❓ Confirm ")
+                            .border_style(
+                                Style::default()
+                                    .fg(Color::Yellow)
+                                    .add_modifier(Modifier::BOLD),
+                            ),
+                    ),
+                area,
+            );
+        })?;
+
+        if let Event::Key(key) = event::read()? {
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            match key.code {
+                KeyCode::Char('y') | KeyCode::Char('Y') | KeyCode::Enter => {
+                    return Ok(Approval::Once)
+                }
+                KeyCode::Char('a') | KeyCode::Char('A') => return Ok(Approval::Session),
+                KeyCode::Char('p') | KeyCode::Char('P') => return Ok(Approval::Project),
+                KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => {
+                    return Ok(Approval::Denied)
+                }
+                KeyCode::Char('c') if key.modifiers == KeyModifiers::CONTROL => {
+                    return Ok(Approval::Denied)
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Show proposed changes in a [`DiffView`] and let the user accept or reject
+/// each hunk. Returns which hunks were accepted, or `None` if the user
+/// rejected the whole change. Non-interactive sessions accept everything.
+pub fn review_diff(action: &str, hunks: Vec<DiffHunk>) -> Option<Vec<bool>> {
+    if !interactive() {
+        return Some(vec![true; hunks.len()]);
+    }
+    let _lock = PROMPT_MUTEX.lock().unwrap_or_else(|e| e.into_inner());
+    let accepted = run_review(action, hunks).unwrap_or(None);
+    clear_screen();
+    accepted
+}
+
+fn run_review(action: &str, hunks: Vec<DiffHunk>) -> io::Result<Option<Vec<bool>>> {
+    let mut terminal = terminal()?;
+    let mut view = DiffView::new(hunks).with_title(format!("Review: {action}"));
+    loop {
+        terminal.draw(|f| view.render(f, f.area()))?;
+
+        if let Event::Key(key) = event::read()? {
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            match view.handle_key(key) {
+                Some(DiffViewAction::Submit) => return Ok(Some(view.accepted().to_vec())),
+                Some(DiffViewAction::Cancel) => return Ok(None),
+                None => {}
+            }
+        }
+    }
+}
+
+/// A `width` x `height` rectangle in the middle of `area`
+fn centered(area: Rect, width: u16, height: u16) -> Rect {
+    let vertical = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(0),
+            Constraint::Length(height.min(area.height)),
+            Constraint::Min(0),
+        ])
+        .split(area);
+    Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Min(0),
+            Constraint::Length(width.min(area.width)),
+            Constraint::Min(0),
+        ])
+        .split(vertical[1])[1]
+}
@@ -0,0 +1,177 @@
+//! Token usage and cost accounting
+//!
+//! Every API response may report how many tokens it used. The agent adds them
+//! up per session in a [`SessionUsage`], and when the configuration has a
+//! price for the model it also keeps a running cost.
+
+use crate::api::Usage;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+
+/// Price of a model in USD per million tokens
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ModelPrice {
+    /// Prompt (input) tokens
+    pub input: f64,
+    /// Completion (output) tokens
+    pub output: f64,
+}
+
+impl ModelPrice {
+    /// Cost of a single request in USD
+    pub fn cost(&self, usage: &Usage) -> f64 {
+        (usage.prompt_tokens as f64 * self.input + usage.completion_tokens as f64 * self.output)
+            / 1_000_000.0
+    }
+}
+
+/// Find the price for a model: an exact match, else the longest matching prefix
+pub fn price_for<'a>(
+    prices: &'a HashMap<String, ModelPrice>,
+    model: &str,
+) -> Option<&'a ModelPrice> {
+    prices.get(model).or_else(|| {
+        prices
+            .iter()
+            .filter(|(prefix, _)| model.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(_, price)| price)
+    })
+}
+
+/// Running totals for one session
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SessionUsage {
+    /// API requests that reported usage
+    pub requests: u32,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    /// Total cost in USD, when the model has a configured price
+    pub cost: Option<f64>,
+}
+
+impl SessionUsage {
+    /// Add the usage of one response
+    pub fn record(&mut self, usage: &Usage, price: Option<&ModelPrice>) {
+        self.requests += 1;
+        self.prompt_tokens += u64::from(usage.prompt_tokens);
+        self.completion_tokens += u64::from(usage.completion_tokens);
+        if let Some(price) = price {
+            self.cost = Some(self.cost.unwrap_or(0.0) + price.cost(usage));
+        }
+    }
+
+    pub fn total_tokens(&self) -> u64 {
+        self.prompt_tokens + self.completion_tokens
+    }
+
+    /// Short form for the TUI status bar, e.g. `12.3k in · 850 out · $0.0421`
+    pub fn compact(&self) -> String {
+        let mut text = format!(
+            "{} in · {} out",
+            format_count(self.prompt_tokens),
+            format_count(self.completion_tokens)
+        );
+        if let Some(cost) = self.cost {
+            text.push_str(&format!(" · ${cost:.4}"));
+        }
+        text
+    }
+}
+
+fn format_count(count: u64) -> String {
+    match count {
+        0..=999 => count.to_string(),
+        1_000..=999_999 => format!("{:.1}k", count as f64 / 1_000.0),
+        _ => format!("{:.1}M", count as f64 / 1_000_000.0),
+    }
+}
+
+impl fmt::Display for SessionUsage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} tokens ({} in / {} out, {} request{})",
+            self.total_tokens(),
+            self.prompt_tokens,
+            self.completion_tokens,
+            self.requests,
+            if self.requests == 1 { "" } else { "s" }
+        )?;
+        if let Some(cost) = self.cost {
+            write!(f, " · ${cost:.4}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usage(prompt_tokens: u32, completion_tokens: u32) -> Usage {
+        Usage {
+            prompt_tokens,
+            completion_tokens,
+        }
+    }
+
+    #[test]
+    fn test_price_lookup_prefers_longest_prefix() {
+        let prices = HashMap::from([
+            (
+                "gpt-4".to_string(),
+                ModelPrice {
+                    input: 30.0,
+                    output: 60.0,
+                },
+            ),
+            (
+                "gpt-4o".to_string(),
+                ModelPrice {
+                    input: 2.5,
+                    output: 10.0,
+                },
+            ),
+            (
+                "gpt-4o-mini".to_string(),
+                ModelPrice {
+                    input: 0.15,
+                    output: 0.6,
+                },
+            ),
+        ]);
+
+        assert_eq!(price_for(&prices, "gpt-4o-mini").unwrap().input, 0.15);
+        assert_eq!(price_for(&prices, "gpt-4o-2024-08-06").unwrap().input, 2.5);
+        assert_eq!(price_for(&prices, "gpt-4-0613").unwrap().input, 30.0);
+        assert!(price_for(&prices, "grok-4").is_none());
+    }
+
+    #[test]
+    fn test_session_usage_accumulates_cost() {
+        let price = ModelPrice {
+            input: 3.0,
+            output: 15.0,
+        };
+        let mut session = SessionUsage::default();
+        session.record(&usage(1_000_000, 0), Some(&price));
+        session.record(&usage(0, 100_000), Some(&price));
+
+        assert_eq!(session.requests, 2);
+        assert_eq!(session.total_tokens(), 1_100_000);
+        assert!((session.cost.unwrap() - 4.5).abs() < 1e-9);
+        assert_eq!(
+            session.to_string(),
+            "1100000 tokens (1000000 in / 100000 out, 2 requests) · $4.5000"
+        );
+        assert_eq!(session.compact(), "1.0M in · 100.0k out · $4.5000");
+
+        let mut unpriced = SessionUsage::default();
+        unpriced.record(&usage(10, 5), None);
+        assert_eq!(unpriced.cost, None);
+        assert_eq!(unpriced.to_string(), "15 tokens (10 in / 5 out, 1 request)");
+    }
+}
@@ -1,14 +1,27 @@
-use grok_code::tools::{ToolContext, ToolRegistry};
+use grok_code::tools::file_ops::MAX_READ_BYTES;
+use grok_code::tools::forge::ForgeKind;
+use grok_code::tools::{
+    CancelToken, CommandPolicy, ForgeSettings, PolicyAction, ProcessTable, Sandbox, ToolContext,
+    ToolRegistry, ToolSettings,
+};
 use std::fs;
 use tempfile::TempDir;
 
-fn create_test_context(temp_dir: &TempDir) -> ToolContext {
+fn create_test_context(temp_dir: &TempDir) -> ToolContext<'_> {
     ToolContext {
         project_root: temp_dir.path().to_path_buf(),
         dry_run: false,
         no_confirm: true,
         git_repo: None,
         tui_mode: false,
+        cancel: CancelToken::default(),
+        output: None,
+        processes: ProcessTable::default(),
+        policy: Default::default(),
+        sandbox: None,
+        tool_name: String::new(),
+        permissions: Default::default(),
+        settings: Default::default(),
     }
 }
 
@@ -23,13 +36,27 @@ fn test_tool_registry_creation() {
     assert!(tool_names.contains(&"read_file".to_string()));
     assert!(tool_names.contains(&"write_file".to_string()));
     assert!(tool_names.contains(&"edit_file".to_string()));
+    assert!(tool_names.contains(&"replace_in_file".to_string()));
+    assert!(tool_names.contains(&"apply_patch".to_string()));
     assert!(tool_names.contains(&"list_files".to_string()));
     assert!(tool_names.contains(&"run_shell_command".to_string()));
+    assert!(tool_names.contains(&"start_background_process".to_string()));
+    assert!(tool_names.contains(&"read_process_output".to_string()));
+    assert!(tool_names.contains(&"send_process_input".to_string()));
+    assert!(tool_names.contains(&"kill_process".to_string()));
     assert!(tool_names.contains(&"search_codebase".to_string()));
+    assert!(tool_names.contains(&"find_files".to_string()));
     assert!(tool_names.contains(&"debug_code".to_string()));
     assert!(tool_names.contains(&"run_lint".to_string()));
     assert!(tool_names.contains(&"create_commit".to_string()));
+    assert!(tool_names.contains(&"git_status".to_string()));
+    assert!(tool_names.contains(&"git_diff".to_string()));
+    assert!(tool_names.contains(&"git_log".to_string()));
+    assert!(tool_names.contains(&"git_blame".to_string()));
+    assert!(tool_names.contains(&"git_show".to_string()));
     assert!(tool_names.contains(&"submit_pr".to_string()));
+    assert!(tool_names.contains(&"list_pull_requests".to_string()));
+    assert!(tool_names.contains(&"get_review_comments".to_string()));
     assert!(tool_names.contains(&"resolve_merge_conflict".to_string()));
     assert!(tool_names.contains(&"analyze_log".to_string()));
     assert!(tool_names.contains(&"web_search".to_string()));
@@ -63,6 +90,83 @@ fn test_file_operations_integration() {
     assert!(list_result.contains("test.txt"));
 }
 
+#[test]
+fn test_read_file_ranges_and_line_numbers() {
+    let temp_dir = TempDir::new().unwrap();
+    let context = create_test_context(&temp_dir);
+    let registry = ToolRegistry::new();
+
+    let content: String = (1..=10).map(|n| format!("line {n}\n")).collect();
+    fs::write(temp_dir.path().join("ten.txt"), &content).unwrap();
+
+    let args = r#"{"path": "ten.txt", "offset": 3, "limit": 2, "line_numbers": true}"#;
+    let result = registry.execute_tool("read_file", args, &context);
+    assert_eq!(
+        result,
+        "     3\tline 3\n     4\tline 4\n[Showing lines 3-4 of 10. Use offset=5 to read more.]"
+    );
+
+    // Reading to the end needs no notice
+    let args = r#"{"path": "ten.txt", "offset": 9}"#;
+    let result = registry.execute_tool("read_file", args, &context);
+    assert_eq!(result, "line 9\nline 10\n");
+
+    // A limit larger than any file reads to the end
+    let args = serde_json::json!({"path": "ten.txt", "offset": 9, "limit": u64::MAX}).to_string();
+    let result = registry.execute_tool("read_file", &args, &context);
+    assert_eq!(result, "line 9\nline 10\n");
+
+    let args = r#"{"path": "ten.txt", "offset": 11}"#;
+    let result = registry.execute_tool("read_file", args, &context);
+    assert!(
+        result.starts_with("Error: offset 11 is past the end"),
+        "{result}"
+    );
+}
+
+#[test]
+fn test_read_file_caps_size_and_skips_binary() {
+    let temp_dir = TempDir::new().unwrap();
+    let context = create_test_context(&temp_dir);
+    let registry = ToolRegistry::new();
+
+    let line = format!("{}\n", "x".repeat(99));
+    let lines = MAX_READ_BYTES / line.len() + 100;
+    fs::write(temp_dir.path().join("big.txt"), line.repeat(lines)).unwrap();
+
+    let result = registry.execute_tool("read_file", r#"{"path": "big.txt"}"#, &context);
+    assert!(result.len() < MAX_READ_BYTES + 200);
+    let shown = MAX_READ_BYTES / line.len();
+    assert!(
+        result.ends_with(&format!(
+            "[Output truncated at {MAX_READ_BYTES} bytes. Showing lines 1-{shown} of {lines}. Use offset={} to read more.]",
+            shown + 1
+        )),
+        "{}",
+        &result[result.len() - 200..]
+    );
+
+    fs::write(
+        temp_dir.path().join("image.png"),
+        [0x89, b'P', b'N', b'G', 0, 0, 1],
+    )
+    .unwrap();
+    let result = registry.execute_tool("read_file", r#"{"path": "image.png"}"#, &context);
+    assert!(result.starts_with("Binary file not shown"), "{result}");
+    assert!(result.ends_with("(7 bytes)"), "{result}");
+
+    fs::write(temp_dir.path().join("latin1.txt"), b"caf\xe9\n").unwrap();
+    let result = registry.execute_tool("read_file", r#"{"path": "latin1.txt"}"#, &context);
+    assert!(
+        result.starts_with("File is not valid UTF-8 text"),
+        "{result}"
+    );
+    assert!(
+        result.contains("first invalid byte at offset 3"),
+        "{result}"
+    );
+}
+
 #[test]
 fn test_edit_file_integration() {
     let temp_dir = TempDir::new().unwrap();
@@ -85,6 +189,280 @@ fn test_edit_file_integration() {
     assert!(content.contains("Line 3"));
 }
 
+#[test]
+fn test_replace_in_file_integration() {
+    let temp_dir = TempDir::new().unwrap();
+    let context = create_test_context(&temp_dir);
+    let registry = ToolRegistry::new();
+
+    let file_path = temp_dir.path().join("lib.rs");
+    fs::write(&file_path, "fn a() {\n    1\n}\n\nfn b() {\n    1\n}\n").unwrap();
+
+    // Ambiguous matches are rejected and the file is left alone
+    let args = r#"{"path": "lib.rs", "old_string": "    1\n", "new_string": "    2\n"}"#;
+    let result = registry.execute_tool("replace_in_file", args, &context);
+    assert!(result.contains("matches 2 places"), "{result}");
+    assert!(fs::read_to_string(&file_path)
+        .unwrap()
+        .contains("fn a() {\n    1"));
+
+    // More context makes the match unique; the result carries a diff
+    let args =
+        r#"{"path": "lib.rs", "old_string": "fn b() {\n    1", "new_string": "fn b() {\n    2"}"#;
+    let result = registry.execute_tool("replace_in_file", args, &context);
+    assert!(
+        result.contains("File edited successfully: 1 replacement"),
+        "{result}"
+    );
+    assert!(result.contains("-    1\n+    2\n"), "{result}");
+    assert_eq!(
+        fs::read_to_string(&file_path).unwrap(),
+        "fn a() {\n    1\n}\n\nfn b() {\n    2\n}\n"
+    );
+
+    // replace_all touches every occurrence
+    let args =
+        r#"{"path": "lib.rs", "old_string": "fn ", "new_string": "pub fn ", "replace_all": true}"#;
+    let result = registry.execute_tool("replace_in_file", args, &context);
+    assert!(result.contains("2 replacements"), "{result}");
+    assert_eq!(
+        fs::read_to_string(&file_path)
+            .unwrap()
+            .matches("pub fn ")
+            .count(),
+        2
+    );
+
+    let args = r#"{"path": "lib.rs", "old_string": "missing", "new_string": "x"}"#;
+    let result = registry.execute_tool("replace_in_file", args, &context);
+    assert!(
+        result.starts_with("Error: old_string not found"),
+        "{result}"
+    );
+}
+
+#[test]
+fn test_replace_in_file_ignores_indentation() {
+    let temp_dir = TempDir::new().unwrap();
+    let context = create_test_context(&temp_dir);
+    let registry = ToolRegistry::new();
+
+    let file_path = temp_dir.path().join("main.rs");
+    fs::write(
+        &file_path,
+        "fn main() {\n    if ok {\n        run();  \n    }\n}\n",
+    )
+    .unwrap();
+
+    // The snippet is unindented and lacks the trailing spaces
+    let args = r#"{"path": "main.rs", "old_string": "if ok {\n    run();\n}", "new_string": "if ok {\n    run();\n    done();\n}"}"#;
+    let result = registry.execute_tool("replace_in_file", args, &context);
+    assert!(result.contains("ignoring whitespace"), "{result}");
+    assert_eq!(
+        fs::read_to_string(&file_path).unwrap(),
+        "fn main() {\n    if ok {\n        run();\n        done();\n    }\n}\n"
+    );
+}
+
+#[test]
+fn test_replace_in_file_dry_run() {
+    let temp_dir = TempDir::new().unwrap();
+    let mut context = create_test_context(&temp_dir);
+    context.dry_run = true;
+    let registry = ToolRegistry::new();
+
+    let file_path = temp_dir.path().join("notes.txt");
+    fs::write(&file_path, "alpha\nbeta\n").unwrap();
+
+    let args = r#"{"path": "notes.txt", "old_string": "beta", "new_string": "gamma"}"#;
+    let result = registry.execute_tool("replace_in_file", args, &context);
+    assert!(
+        result.starts_with("Dry-run: Would make 1 replacement"),
+        "{result}"
+    );
+    assert!(result.contains("+gamma"), "{result}");
+    assert_eq!(fs::read_to_string(&file_path).unwrap(), "alpha\nbeta\n");
+}
+
+#[test]
+fn test_apply_patch_multiple_files() {
+    let temp_dir = TempDir::new().unwrap();
+    let context = create_test_context(&temp_dir);
+    let registry = ToolRegistry::new();
+
+    fs::write(temp_dir.path().join("a.txt"), "one\ntwo\nthree\n").unwrap();
+    fs::write(temp_dir.path().join("old.txt"), "bye\n").unwrap();
+
+    let patch = "\
+--- a/a.txt
++++ b/a.txt
+@@ -1,3 +1,3 @@
+ one
+-two
++TWO
+ three
+--- /dev/null
++++ b/src/new.txt
+@@ -0,0 +1,2 @@
++hello
++world
+--- a/old.txt
++++ /dev/null
+@@ -1 +0,0 @@
+-bye
+";
+    let args = serde_json::json!({ "patch": patch }).to_string();
+    let result = registry.execute_tool("apply_patch", &args, &context);
+    assert!(
+        result.contains("Patch applied to 3 files (a.txt, src/new.txt, old.txt)"),
+        "{result}"
+    );
+    assert_eq!(
+        fs::read_to_string(temp_dir.path().join("a.txt")).unwrap(),
+        "one\nTWO\nthree\n"
+    );
+    assert_eq!(
+        fs::read_to_string(temp_dir.path().join("src/new.txt")).unwrap(),
+        "hello\nworld\n"
+    );
+    assert!(!temp_dir.path().join("old.txt").exists());
+}
+
+#[test]
+fn test_apply_patch_is_all_or_nothing() {
+    let temp_dir = TempDir::new().unwrap();
+    let context = create_test_context(&temp_dir);
+    let registry = ToolRegistry::new();
+
+    fs::write(temp_dir.path().join("a.txt"), "one\ntwo\n").unwrap();
+    fs::write(temp_dir.path().join("b.txt"), "three\nfour\n").unwrap();
+
+    // The second hunk does not match, so the first must not be applied either
+    let patch = "\
+--- a/a.txt
++++ b/a.txt
+@@ -1,2 +1,2 @@
+-one
++ONE
+ two
+--- a/b.txt
++++ b/b.txt
+@@ -1,2 +1,2 @@
+-five
++FIVE
+ four
+";
+    let args = serde_json::json!({ "patch": patch }).to_string();
+    let result = registry.execute_tool("apply_patch", &args, &context);
+    assert!(
+        result.contains("hunk 1 of b.txt does not apply"),
+        "{result}"
+    );
+    assert!(result.contains("No files were changed"), "{result}");
+    assert_eq!(
+        fs::read_to_string(temp_dir.path().join("a.txt")).unwrap(),
+        "one\ntwo\n"
+    );
+
+    // Lines past the hunk's counts are an error, not dropped
+    let patch = "\
+--- a/a.txt
++++ b/a.txt
+@@ -1 +1 @@
+-one
++ONE
+-two
++TWO
+";
+    let args = serde_json::json!({ "patch": patch }).to_string();
+    let result = registry.execute_tool("apply_patch", &args, &context);
+    assert!(
+        result.contains("hunk '@@ -1 +1 @@' of a.txt has more lines than its header counts"),
+        "{result}"
+    );
+    assert_eq!(
+        fs::read_to_string(temp_dir.path().join("a.txt")).unwrap(),
+        "one\ntwo\n"
+    );
+
+    // A write that fails midway rolls back the files already written
+    fs::write(temp_dir.path().join("blocker"), "not a directory").unwrap();
+    let args = serde_json::json!({
+        "patch": "\
+--- a/a.txt
++++ b/a.txt
+@@ -1,2 +1,2 @@
+-one
++ONE
+ two
+--- /dev/null
++++ b/blocker/new.txt
+@@ -0,0 +1 @@
++hello
+"
+    })
+    .to_string();
+    let result = registry.execute_tool("apply_patch", &args, &context);
+    assert!(result.contains("All changes were rolled back"), "{result}");
+    assert_eq!(
+        fs::read_to_string(temp_dir.path().join("a.txt")).unwrap(),
+        "one\ntwo\n"
+    );
+}
+
+#[test]
+fn test_apply_patch_edits() {
+    let temp_dir = TempDir::new().unwrap();
+    let mut context = create_test_context(&temp_dir);
+    let registry = ToolRegistry::new();
+
+    fs::write(temp_dir.path().join("a.rs"), "fn old_name() {}\n").unwrap();
+    fs::write(temp_dir.path().join("b.rs"), "old_name();\nold_name();\n").unwrap();
+
+    let args = serde_json::json!({
+        "edits": [
+            {"path": "a.rs", "old_string": "old_name", "new_string": "new_name"},
+            {"path": "b.rs", "old_string": "old_name", "new_string": "new_name", "replace_all": true}
+        ]
+    })
+    .to_string();
+
+    context.dry_run = true;
+    let result = registry.execute_tool("apply_patch", &args, &context);
+    assert!(
+        result.starts_with("Dry-run: Would apply patch to 2 files"),
+        "{result}"
+    );
+    assert!(result.contains("+new_name();"), "{result}");
+    assert_eq!(
+        fs::read_to_string(temp_dir.path().join("b.rs")).unwrap(),
+        "old_name();\nold_name();\n"
+    );
+
+    context.dry_run = false;
+    let result = registry.execute_tool("apply_patch", &args, &context);
+    assert!(result.contains("Patch applied to 2 files"), "{result}");
+    assert_eq!(
+        fs::read_to_string(temp_dir.path().join("b.rs")).unwrap(),
+        "new_name();\nnew_name();\n"
+    );
+
+    // An edit that cannot be made stops the whole batch
+    let args = serde_json::json!({
+        "edits": [
+            {"path": "a.rs", "old_string": "new_name", "new_string": "newer_name"},
+            {"path": "b.rs", "old_string": "missing", "new_string": "x"}
+        ]
+    })
+    .to_string();
+    let result = registry.execute_tool("apply_patch", &args, &context);
+    assert!(result.starts_with("Error: edit 2 (b.rs)"), "{result}");
+    assert_eq!(
+        fs::read_to_string(temp_dir.path().join("a.rs")).unwrap(),
+        "fn new_name() {}\n"
+    );
+}
+
 #[test]
 fn test_search_codebase_integration() {
     let temp_dir = TempDir::new().unwrap();
@@ -108,10 +486,118 @@ fn test_search_codebase_integration() {
     let search_args = r#"{"query": "fn"}"#;
     let search_result = registry.execute_tool("search_codebase", search_args, &context);
 
-    assert!(search_result.contains("test1.rs"));
-    assert!(search_result.contains("test2.rs"));
-    // The tool only reports file names, not content
-    assert!(search_result.contains("Found 'fn'"));
+    assert!(search_result.contains("test1.rs:1:1: fn main() {"));
+    assert!(search_result.contains("test2.rs:1:1: fn helper() {"));
+    assert!(search_result.ends_with("[2 matches in 2 files]"));
+}
+
+#[test]
+fn test_search_codebase_filters() {
+    let temp_dir = TempDir::new().unwrap();
+    let context = create_test_context(&temp_dir);
+    let registry = ToolRegistry::new();
+
+    fs::create_dir_all(temp_dir.path().join("src")).unwrap();
+    fs::create_dir_all(temp_dir.path().join("target")).unwrap();
+    fs::write(temp_dir.path().join(".gitignore"), "target/\n").unwrap();
+    fs::write(
+        temp_dir.path().join("src/lib.rs"),
+        "// one\nlet Needle = 1;\n// three\n// four\n// five\n// six\nneedle();\n",
+    )
+    .unwrap();
+    fs::write(temp_dir.path().join("src/notes.md"), "a needle here\n").unwrap();
+    fs::write(temp_dir.path().join("target/out.rs"), "needle\n").unwrap();
+    fs::write(temp_dir.path().join("blob.bin"), b"needle\0\x01").unwrap();
+
+    // Ignored and binary files are skipped; matching is case-sensitive
+    let result = registry.execute_tool("search_codebase", r#"{"query": "needle"}"#, &context);
+    assert_eq!(
+        result,
+        "src/lib.rs:7:1: needle();\nsrc/notes.md:1:3: a needle here\n\n[2 matches in 2 files]"
+    );
+
+    // Context lines, merged when they overlap
+    let args =
+        r#"{"query": "needle", "case_insensitive": true, "file_type": "rust", "context_lines": 1}"#;
+    let result = registry.execute_tool("search_codebase", args, &context);
+    assert_eq!(
+        result,
+        "src/lib.rs-1- // one\nsrc/lib.rs:2:5: let Needle = 1;\nsrc/lib.rs-3- // three\n--\nsrc/lib.rs-6- // six\nsrc/lib.rs:7:1: needle();\n\n[2 matches in 1 file]"
+    );
+
+    let args = r#"{"query": "needle", "glob": ["*.md"]}"#;
+    let result = registry.execute_tool("search_codebase", args, &context);
+    assert!(result.starts_with("src/notes.md:1:3:"), "{result}");
+    assert!(!result.contains("lib.rs"), "{result}");
+
+    let args = r#"{"query": "needle", "case_insensitive": true, "max_results": 1}"#;
+    let result = registry.execute_tool("search_codebase", args, &context);
+    assert!(
+        result.contains("[Showing the first 1 of at least"),
+        "{result}"
+    );
+    assert_eq!(result.lines().filter(|l| l.contains(": ")).count(), 1);
+}
+
+fn create_tree(temp_dir: &TempDir) {
+    let root = temp_dir.path();
+    fs::create_dir_all(root.join("src/tools")).unwrap();
+    fs::create_dir_all(root.join("target/debug")).unwrap();
+    fs::write(root.join(".gitignore"), "target/\n").unwrap();
+    fs::write(root.join("Cargo.toml"), "[package]\n").unwrap();
+    fs::write(root.join("src/main.rs"), "fn main() {}\n").unwrap();
+    fs::write(root.join("src/tools/mod.rs"), "x".repeat(2048)).unwrap();
+    fs::write(root.join("target/debug/app.rs"), "").unwrap();
+}
+
+#[test]
+fn test_find_files_integration() {
+    let temp_dir = TempDir::new().unwrap();
+    let context = create_test_context(&temp_dir);
+    let registry = ToolRegistry::new();
+    create_tree(&temp_dir);
+
+    // Path globs; ignored directories are skipped
+    let result = registry.execute_tool("find_files", r#"{"pattern": "src/**/*.rs"}"#, &context);
+    let paths: Vec<&str> = result
+        .lines()
+        .map(|l| l.split(' ').next().unwrap())
+        .collect();
+    assert_eq!(paths, ["src/main.rs", "src/tools/mod.rs", "[2"], "{result}");
+    assert!(
+        result.contains("src/tools/mod.rs (file, 2.0 KB, modified "),
+        "{result}"
+    );
+
+    // Name globs match at any depth; sort by size
+    let args = r#"{"pattern": "*.rs", "sort": "size"}"#;
+    let result = registry.execute_tool("find_files", args, &context);
+    assert!(result.starts_with("src/tools/mod.rs "), "{result}");
+    assert!(!result.contains("target"), "{result}");
+
+    let args = r#"{"pattern": "*", "kind": "dir"}"#;
+    let result = registry.execute_tool("find_files", args, &context);
+    assert!(result.contains("src/tools/ (dir, modified "), "{result}");
+    assert!(!result.contains("main.rs"), "{result}");
+
+    let args = r#"{"pattern": "*.rs", "max_depth": 1}"#;
+    let result = registry.execute_tool("find_files", args, &context);
+    assert_eq!(result, "No files matching '*.rs' found.");
+}
+
+#[test]
+fn test_list_files_recursive() {
+    let temp_dir = TempDir::new().unwrap();
+    let context = create_test_context(&temp_dir);
+    let registry = ToolRegistry::new();
+    create_tree(&temp_dir);
+
+    let result = registry.execute_tool("list_files", r#"{"recursive": true}"#, &context);
+    assert_eq!(result, "Cargo.toml\nsrc/\n  main.rs\n  tools/\n    mod.rs");
+
+    let args = r#"{"path": ".", "recursive": true, "max_depth": 1}"#;
+    let result = registry.execute_tool("list_files", args, &context);
+    assert_eq!(result, "Cargo.toml\nsrc/");
 }
 
 #[test]
@@ -131,6 +617,193 @@ fn test_shell_command_integration() {
     assert!(result.contains("Test with quotes"));
 }
 
+#[test]
+fn test_shell_command_timeout_kills_process_group() {
+    let temp_dir = TempDir::new().unwrap();
+    let context = create_test_context(&temp_dir);
+    let registry = ToolRegistry::new();
+
+    // The background job must die with the shell, or it writes the marker
+    let args =
+        r#"{"command": "(sleep 2; touch marker) & echo started; sleep 30", "timeout_secs": 1}"#;
+    let start = std::time::Instant::now();
+    let result = registry.execute_tool("run_shell_command", args, &context);
+    assert!(start.elapsed() < std::time::Duration::from_secs(10));
+    assert!(result.starts_with("Command timed out after 1s and was killed."));
+    assert!(result.contains("started"));
+
+    std::thread::sleep(std::time::Duration::from_secs(2));
+    assert!(!temp_dir.path().join("marker").exists());
+}
+
+#[test]
+fn test_shell_command_truncates_long_output() {
+    let temp_dir = TempDir::new().unwrap();
+    let context = create_test_context(&temp_dir);
+    let registry = ToolRegistry::new();
+
+    let args = r#"{"command": "seq 1 100000"}"#;
+    let result = registry.execute_tool("run_shell_command", args, &context);
+    assert!(result.starts_with("Output:\n1\n2\n3\n"));
+    assert!(result.ends_with("99999\n100000"));
+    assert!(result.contains("lines ("));
+    assert!(result.contains(") omitted ...]"));
+    assert!(!result.contains("\n50000\n"));
+}
+
+#[test]
+fn test_shell_command_streams_output_and_cancels() {
+    let temp_dir = TempDir::new().unwrap();
+    let mut context = create_test_context(&temp_dir);
+    let registry = ToolRegistry::new();
+
+    let lines = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let sink = lines.clone();
+    context.output = Some(std::sync::Arc::new(move |line: &str| {
+        sink.lock().unwrap().push(line.to_string());
+    }));
+    let args = r#"{"command": "echo one; echo two >&2"}"#;
+    let result = registry.execute_tool("run_shell_command", args, &context);
+    assert!(result.contains("Output:\none"));
+    assert!(result.contains("Error output:\ntwo"));
+    let mut streamed = lines.lock().unwrap().clone();
+    streamed.sort();
+    assert_eq!(streamed, vec!["one", "two"]);
+
+    let cancel = CancelToken::default();
+    context.cancel = cancel.clone();
+    let canceller = std::thread::spawn(move || {
+        std::thread::sleep(std::time::Duration::from_millis(300));
+        cancel.cancel();
+    });
+    let start = std::time::Instant::now();
+    let args = r#"{"command": "echo waiting; sleep 30"}"#;
+    let result = registry.execute_tool("run_shell_command", args, &context);
+    canceller.join().unwrap();
+    assert!(start.elapsed() < std::time::Duration::from_secs(10));
+    assert!(result.starts_with("Command cancelled by user."));
+    assert!(result.contains("waiting"));
+}
+
+#[test]
+fn test_shell_command_policy() {
+    let temp_dir = TempDir::new().unwrap();
+    let mut context = create_test_context(&temp_dir);
+    let registry = ToolRegistry::new();
+
+    let args = r#"{"command": "echo ok && rm -r -f /"}"#;
+    let result = registry.execute_tool("run_shell_command", args, &context);
+    assert!(result.starts_with("Error: Command blocked by policy: Recursive delete"));
+
+    let rule =
+        toml::from_str("action = \"deny\"\ncommand = \"curl\"\nreason = \"No network\"").unwrap();
+    context.policy = std::sync::Arc::new(CommandPolicy::new(PolicyAction::Allow, vec![rule]));
+    let args = r#"{"command": "echo $(curl example.com)"}"#;
+    let result = registry.execute_tool("run_shell_command", args, &context);
+    assert!(result.starts_with("Error: Command blocked by policy: No network"));
+
+    let args = r#"{"command": "echo allowed"}"#;
+    let result = registry.execute_tool("run_shell_command", args, &context);
+    assert_eq!(result, "Output:\nallowed");
+}
+
+#[test]
+fn test_sandboxed_shell_command() {
+    let temp_dir = TempDir::new().unwrap();
+    let outside = TempDir::new().unwrap();
+    let mut context = create_test_context(&temp_dir);
+    context.sandbox = Some(Sandbox::default());
+    let registry = ToolRegistry::new();
+
+    let args = r#"{"command": "echo inside > inside.txt && cat inside.txt"}"#;
+    let result = registry.execute_tool("run_shell_command", args, &context);
+    if result.starts_with("Error: Could not start the command in the sandbox") {
+        // No unprivileged user namespaces on this machine
+        eprintln!("skipping: {result}");
+        return;
+    }
+    assert_eq!(result, "Output:\ninside");
+    assert!(temp_dir.path().join("inside.txt").exists());
+
+    let target = outside.path().join("escaped.txt");
+    let args =
+        serde_json::json!({"command": format!("echo no > {}", target.display())}).to_string();
+    let result = registry.execute_tool("run_shell_command", &args, &context);
+    assert!(result.contains("Read-only file system"), "{result}");
+    assert!(result.contains("Sandbox:\n- write outside the project or into .git blocked"));
+    assert!(!target.exists());
+
+    // Hooks and git config would run outside the sandbox
+    fs::create_dir_all(temp_dir.path().join(".git/hooks")).unwrap();
+    let args = r#"{"command": "echo evil > .git/hooks/pre-commit"}"#;
+    let result = registry.execute_tool("run_shell_command", args, &context);
+    assert!(result.contains("Read-only file system"), "{result}");
+    assert!(!temp_dir.path().join(".git/hooks/pre-commit").exists());
+    let args = r#"{"command": "echo still > inside.txt && cat inside.txt"}"#;
+    let result = registry.execute_tool("run_shell_command", args, &context);
+    assert_eq!(result, "Output:\nstill");
+
+    // Only the loopback interface exists
+    let args = r#"{"command": "tail -n +3 /proc/net/dev | cut -d: -f1"}"#;
+    let result = registry.execute_tool("run_shell_command", args, &context);
+    assert_eq!(
+        result.split_whitespace().collect::<Vec<_>>(),
+        ["Output:", "lo"]
+    );
+}
+
+#[test]
+fn test_background_process_lifecycle() {
+    let temp_dir = TempDir::new().unwrap();
+    let context = create_test_context(&temp_dir);
+    let registry = ToolRegistry::new();
+
+    let args = r#"{"command": "echo ready; while read line; do echo \"got $line\"; done", "wait_secs": 1}"#;
+    let result = registry.execute_tool("start_background_process", args, &context);
+    assert!(
+        result.starts_with("Started background process 1"),
+        "{result}"
+    );
+    assert!(result.contains("running"));
+    assert!(result.contains("ready"));
+
+    let args = r#"{"id": 1, "input": "hello"}"#;
+    let result = registry.execute_tool("send_process_input", args, &context);
+    assert!(result.contains("got hello"), "{result}");
+
+    // Output already returned is not repeated unless asked for
+    let result = registry.execute_tool("read_process_output", r#"{"id": 1}"#, &context);
+    assert!(result.contains("no new output"), "{result}");
+    let result =
+        registry.execute_tool("read_process_output", r#"{"id": 1, "all": true}"#, &context);
+    assert!(result.contains("ready") && result.contains("got hello"));
+
+    let result = registry.execute_tool("read_process_output", "{}", &context);
+    assert!(result.starts_with("1: echo ready"));
+
+    let result = registry.execute_tool("kill_process", r#"{"id": 1}"#, &context);
+    assert!(result.starts_with("Process 1 killed"), "{result}");
+    assert!(context.processes.is_empty());
+
+    let result = registry.execute_tool("read_process_output", r#"{"id": 1}"#, &context);
+    assert!(result.starts_with("Error: No background process with id 1"));
+}
+
+#[test]
+fn test_background_processes_die_with_table() {
+    let temp_dir = TempDir::new().unwrap();
+    let context = create_test_context(&temp_dir);
+    let registry = ToolRegistry::new();
+
+    let args = r#"{"command": "sleep 1; touch marker", "wait_secs": 0}"#;
+    let result = registry.execute_tool("start_background_process", args, &context);
+    assert!(result.contains("running"), "{result}");
+
+    drop(context);
+    std::thread::sleep(std::time::Duration::from_millis(1500));
+    assert!(!temp_dir.path().join("marker").exists());
+}
+
 #[test]
 fn test_tool_error_handling() {
     let temp_dir = TempDir::new().unwrap();
@@ -224,3 +897,279 @@ fn test_git_operations() {
     let result = registry.execute_tool("resolve_merge_conflict", args, &context);
     assert!(result.contains("Successfully resolved") || result.contains("No merge conflicts"));
 }
+
+#[test]
+fn test_submit_pr_pushes_and_opens_pull_request() {
+    let temp_dir = TempDir::new().unwrap();
+    let project = temp_dir.path().join("project");
+    let origin = temp_dir.path().join("origin.git");
+    fs::create_dir(&project).unwrap();
+    let git = |dir: &std::path::Path, args: &[&str]| {
+        let output = std::process::Command::new("git")
+            .args(args)
+            .current_dir(dir)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "git {args:?} failed");
+    };
+    git(temp_dir.path(), &["init", "--bare", "origin.git"]);
+    git(&project, &["init"]);
+    git(&project, &["config", "user.email", "test@example.com"]);
+    git(&project, &["config", "user.name", "Test User"]);
+    git(
+        &project,
+        &["remote", "add", "origin", origin.to_str().unwrap()],
+    );
+    git(&project, &["checkout", "-b", "feature"]);
+    fs::create_dir(project.join(".github")).unwrap();
+    fs::write(
+        project.join(".github/pull_request_template.md"),
+        "## Summary\n\n## Testing\n",
+    )
+    .unwrap();
+    git(&project, &["add", "."]);
+    git(&project, &["commit", "-m", "Add template"]);
+
+    let mut server = mockito::Server::new();
+    let created = server
+        .mock("POST", "/repos/octo/app/pulls")
+        .match_body(mockito::Matcher::PartialJson(serde_json::json!({
+            "title": "Add template",
+            "body": "## Summary\n\n## Testing\n",
+            "head": "feature",
+            "base": "main",
+            "draft": true
+        })))
+        .with_status(201)
+        .with_body(r#"{"number": 7, "html_url": "https://github.com/octo/app/pull/7"}"#)
+        .create();
+    let reviewers = server
+        .mock("POST", "/repos/octo/app/pulls/7/requested_reviewers")
+        .match_body(mockito::Matcher::PartialJson(serde_json::json!({
+            "reviewers": ["alice"],
+            "team_reviewers": ["core"]
+        })))
+        .with_status(201)
+        .with_body("{}")
+        .create();
+    let labels = server
+        .mock("POST", "/repos/octo/app/issues/7/labels")
+        .with_status(200)
+        .with_body("[]")
+        .create();
+
+    let repo = git2::Repository::open(&project).unwrap();
+    let mut context = create_test_context(&temp_dir);
+    context.project_root = project.clone();
+    context.git_repo = Some(&repo);
+    context.settings = std::sync::Arc::new(ToolSettings {
+        forge: ForgeSettings {
+            kind: Some(ForgeKind::GitHub),
+            api_url: Some(server.url()),
+            token: Some("secret".to_string()),
+            github_repo: Some("octo/app".to_string()),
+        },
+        ..Default::default()
+    });
+    let registry = ToolRegistry::new();
+
+    let args = r#"{"title": "Add template", "draft": true, "reviewers": ["alice", "octo/core"], "labels": ["docs"]}"#;
+    let result = registry.execute_tool("submit_pr", args, &context);
+    assert!(
+        result.contains("Pushed branch 'feature' to origin."),
+        "{result}"
+    );
+    assert!(
+        result.contains("Created draft pull request #7: https://github.com/octo/app/pull/7"),
+        "{result}"
+    );
+    assert!(result.contains("Requested reviews from: alice, octo/core"));
+    assert!(result.contains("Labels: docs"));
+    created.assert();
+    reviewers.assert();
+    labels.assert();
+
+    let pushed = git2::Repository::open_bare(&origin).unwrap();
+    assert_eq!(
+        pushed.refname_to_id("refs/heads/feature").unwrap(),
+        repo.refname_to_id("refs/heads/feature").unwrap()
+    );
+
+    // Already up to date, so nothing is pushed the second time
+    let result = registry.execute_tool("submit_pr", args, &context);
+    assert!(!result.contains("Pushed branch"), "{result}");
+}
+
+#[test]
+fn test_create_commit_stages_paths_and_uses_git_identity() {
+    let temp_dir = TempDir::new().unwrap();
+    let project = temp_dir.path();
+    let git = |args: &[&str]| {
+        let output = std::process::Command::new("git")
+            .args(args)
+            .current_dir(project)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "git {args:?} failed");
+    };
+    git(&["init"]);
+    git(&["config", "user.email", "test@example.com"]);
+    git(&["config", "user.name", "Test User"]);
+    fs::write(project.join("a.txt"), "hello\n").unwrap();
+    fs::write(project.join("b.txt"), "world\n").unwrap();
+    fs::write(project.join("a.txt.20240101_000000.bak"), "old\n").unwrap();
+
+    let repo = git2::Repository::open(project).unwrap();
+    let mut context = create_test_context(&temp_dir);
+    context.git_repo = Some(&repo);
+    let registry = ToolRegistry::new();
+    let files = |repo: &git2::Repository| {
+        let tree = repo.head().unwrap().peel_to_tree().unwrap();
+        tree.iter()
+            .map(|entry| entry.name().unwrap().to_string())
+            .collect::<Vec<_>>()
+    };
+
+    // The dry run previews the diff without staging anything
+    context.dry_run = true;
+    let args = r#"{"message": "Add a", "paths": ["a.txt"]}"#;
+    let result = registry.execute_tool("create_commit", args, &context);
+    assert!(
+        result.starts_with("Dry-run: Would create git commit with these changes:"),
+        "{result}"
+    );
+    assert!(result.contains("+hello"), "{result}");
+    assert!(!result.contains("b.txt"), "{result}");
+    assert!(repo.index().unwrap().is_empty());
+    context.dry_run = false;
+
+    // The first commit in a repository without HEAD
+    let result = registry.execute_tool("create_commit", args, &context);
+    assert!(
+        result.starts_with("Commit successful (initial commit): "),
+        "{result}"
+    );
+    assert!(result.ends_with(" Add a"), "{result}");
+    assert_eq!(files(&repo), ["a.txt"]);
+    let head = repo.head().unwrap().peel_to_commit().unwrap();
+    assert_eq!(head.author().name(), Some("Test User"));
+    assert_eq!(head.author().email(), Some("test@example.com"));
+
+    let args = r#"{"amend": true, "paths": ["b.txt"]}"#;
+    let result = registry.execute_tool("create_commit", args, &context);
+    assert!(
+        result.starts_with("Commit successful (amended): "),
+        "{result}"
+    );
+    assert_eq!(files(&repo), ["a.txt", "b.txt"]);
+    let head = repo.head().unwrap().peel_to_commit().unwrap();
+    assert_eq!(head.parent_count(), 0);
+    assert_eq!(head.message(), Some("Add a"));
+
+    // Without paths every change is committed, except backups
+    context.settings = std::sync::Arc::new(ToolSettings {
+        co_authors: vec!["Ada Lovelace <ada@example.com>".to_string()],
+        ..Default::default()
+    });
+    fs::write(project.join("c.txt"), "more\n").unwrap();
+    let args = r#"{"message": "Add c"}"#;
+    let result = registry.execute_tool("create_commit", args, &context);
+    assert!(result.starts_with("Commit successful: "), "{result}");
+    assert_eq!(files(&repo), ["a.txt", "b.txt", "c.txt"]);
+    let head = repo.head().unwrap().peel_to_commit().unwrap();
+    assert_eq!(
+        head.message(),
+        Some("Add c\n\nCo-authored-by: Ada Lovelace <ada@example.com>")
+    );
+
+    let result = registry.execute_tool("create_commit", args, &context);
+    assert_eq!(result, "Nothing to commit.");
+}
+
+#[test]
+fn test_read_only_git_tools() {
+    let temp_dir = TempDir::new().unwrap();
+    let project = temp_dir.path();
+    let git = |args: &[&str]| {
+        let output = std::process::Command::new("git")
+            .args(args)
+            .current_dir(project)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "git {args:?} failed");
+    };
+    git(&["init"]);
+    git(&["config", "user.email", "test@example.com"]);
+    git(&["config", "user.name", "Test User"]);
+    fs::write(project.join("a.txt"), "one\ntwo\n").unwrap();
+    git(&["add", "."]);
+    git(&["commit", "-m", "Add a"]);
+    fs::create_dir(project.join("sub")).unwrap();
+    fs::write(project.join("sub/d.txt"), "d\n").unwrap();
+    git(&["add", "."]);
+    git(&["commit", "-m", "Add d"]);
+    fs::write(project.join("a.txt"), "one\nTWO\n").unwrap();
+    fs::write(project.join("b.txt"), "b\n").unwrap();
+    git(&["add", "b.txt"]);
+    fs::write(project.join("c.txt"), "c\n").unwrap();
+
+    // Like the parallel tool path: no repository handle, and never asking
+    let mut context = create_test_context(&temp_dir);
+    context.no_confirm = false;
+    let registry = ToolRegistry::new();
+    let run = |name: &str, args: &str| registry.execute_tool(name, args, &context);
+
+    let result = run("git_status", "{}");
+    assert!(result.starts_with("On branch "), "{result}");
+    assert!(result.contains("\n M a.txt"), "{result}");
+    assert!(result.contains("\nA  b.txt"), "{result}");
+    assert!(result.contains("\n?? c.txt"), "{result}");
+
+    let result = run("git_diff", "{}");
+    assert!(result.starts_with("1 file changed"), "{result}");
+    assert!(result.contains("-two\n+TWO"), "{result}");
+    assert!(!result.contains("b.txt"), "{result}");
+
+    let result = run("git_diff", r#"{"staged": true}"#);
+    assert!(result.contains("+b"), "{result}");
+    assert!(!result.contains("a.txt"), "{result}");
+
+    let result = run("git_diff", r#"{"from": "HEAD~1", "to": "HEAD"}"#);
+    assert!(result.contains("+++ b/sub/d.txt"), "{result}");
+    assert_eq!(run("git_diff", r#"{"paths": ["sub"]}"#), "No changes.");
+
+    let result = run("git_log", "{}");
+    let lines: Vec<&str> = result.lines().collect();
+    assert_eq!(lines.len(), 2, "{result}");
+    assert!(lines[0].ends_with(" Test User Add d"), "{result}");
+    let result = run("git_log", r#"{"path": "a.txt"}"#);
+    assert_eq!(result.lines().count(), 1, "{result}");
+    assert!(result.ends_with(" Add a"), "{result}");
+    assert_eq!(run("git_log", r#"{"limit": 1}"#).lines().count(), 1);
+    assert_eq!(
+        run("git_log", r#"{"rev": "nope"}"#),
+        "Error: unknown revision 'nope'"
+    );
+
+    let result = run("git_blame", r#"{"path": "a.txt"}"#);
+    let lines: Vec<&str> = result.lines().collect();
+    assert!(lines[0].contains("(Test User "), "{result}");
+    assert!(lines[0].ends_with(" 1: one"), "{result}");
+    assert!(lines[1].contains("(Not committed yet)"), "{result}");
+    assert!(lines[1].ends_with(" 2: TWO"), "{result}");
+    let result = run("git_blame", r#"{"path": "a.txt", "start_line": 2}"#);
+    assert_eq!(result.lines().count(), 1, "{result}");
+
+    let result = run("git_show", "{}");
+    assert!(result.starts_with("commit "), "{result}");
+    assert!(
+        result.contains("Author: Test User <test@example.com>"),
+        "{result}"
+    );
+    assert!(result.contains("\n    Add d\n"), "{result}");
+    assert!(result.contains("+d"), "{result}");
+    assert_eq!(
+        run("git_show", r#"{"rev": "HEAD~1", "path": "a.txt"}"#),
+        "one\ntwo"
+    );
+}
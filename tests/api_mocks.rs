@@ -33,6 +33,7 @@ impl ApiClient for MockApiClient {
         // Since we can't modify self, we'll just return the first response
         Ok(ChatCompletionResponse {
             choices: self.responses[0].choices.clone(),
+            usage: self.responses[0].usage,
         })
     }
 }
@@ -56,6 +57,7 @@ async fn test_mock_api_simple_response() {
                 tool_call_id: None,
             },
         }],
+        usage: None,
     };
 
     let client = MockApiClient::new(config, vec![mock_response]);
@@ -111,6 +113,7 @@ async fn test_mock_api_tool_calls() {
                 tool_call_id: None,
             },
         }],
+        usage: None,
     };
 
     let client = MockApiClient::new(config, vec![mock_response]);
@@ -1,5 +1,5 @@
 use grok_code::agent::GrokAgent;
-use grok_code::api::{create_client, ApiConfig};
+use grok_code::api::{create_client, ApiConfig, PROVIDERS};
 use grok_code::error::GrokError;
 use std::env;
 use std::fs;
@@ -277,3 +277,95 @@ async fn test_rate_limit_env_var() {
 
     env::remove_var("XAI_API_KEY");
 }
+
+#[tokio::test]
+async fn test_every_provider_creates_client() {
+    for info in PROVIDERS {
+        let config = ApiConfig {
+            api_key: "test_key".to_string(),
+            base_url: info.default_base_url.to_string(),
+            model: info.default_model.to_string(),
+            timeout_secs: info.default_timeout_secs,
+            max_retries: 3,
+        };
+
+        let client = create_client(info.name, config).unwrap();
+        assert_eq!(client.config().model, info.default_model);
+    }
+
+    let config = ApiConfig {
+        api_key: "test_key".to_string(),
+        base_url: "api.x.ai/v1".to_string(),
+        model: "grok-4-0709".to_string(),
+        timeout_secs: 60,
+        max_retries: 3,
+    };
+    assert!(matches!(
+        create_client("xai", config),
+        Err(GrokError::Config(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_reply_with_text_and_tool_calls_is_recorded_once() {
+    let temp_dir = TempDir::new().unwrap();
+    fs::write(temp_dir.path().join("a.txt"), "hello\n").unwrap();
+
+    // Text and a tool call first, then a plain answer once the tool ran
+    let mut server = mockito::Server::new_async().await;
+    let api = server
+        .mock("POST", "/chat/completions")
+        .with_body_from_request(|request| {
+            let body = request.utf8_lossy_body().unwrap();
+            let message = if body.contains("\"role\":\"tool\"") {
+                serde_json::json!({"role": "assistant", "content": "It says hello."})
+            } else {
+                serde_json::json!({
+                    "role": "assistant",
+                    "content": "Let me read it.",
+                    "tool_calls": [{
+                        "id": "call_1",
+                        "type": "function",
+                        "function": {"name": "read_file", "arguments": "{\"path\": \"a.txt\"}"}
+                    }]
+                })
+            };
+            serde_json::json!({"choices": [{"message": message}]})
+                .to_string()
+                .into_bytes()
+        })
+        .expect(2)
+        .create_async()
+        .await;
+
+    let config = ApiConfig {
+        api_key: "test_key".to_string(),
+        base_url: server.url(),
+        model: "gpt-4o".to_string(),
+        timeout_secs: 60,
+        max_retries: 0,
+    };
+    let mut agent = GrokAgent::new(
+        "openai",
+        config,
+        temp_dir.path().to_path_buf(),
+        false,
+        1,
+        true,
+    )
+    .unwrap();
+    agent.set_streaming(false);
+    agent.process_prompt("What does a.txt say?", false).await;
+    api.assert_async().await;
+
+    let roles: Vec<&str> = agent
+        .get_messages_since(1)
+        .iter()
+        .map(|m| m.role.as_str())
+        .collect();
+    assert_eq!(roles, ["user", "assistant", "tool", "assistant"]);
+    let messages = agent.get_messages_since(1);
+    assert_eq!(messages[1].content.as_deref(), Some("Let me read it."));
+    assert_eq!(messages[2].tool_call_id.as_deref(), Some("call_1"));
+    assert_eq!(messages[3].content.as_deref(), Some("It says hello."));
+}
@@ -1,6 +1,6 @@
 #[cfg(test)]
 mod tests {
-    use grok_code::tools::{Tool, ToolContext, RunShellCommand, ReadFile};
+    use grok_code::tools::{CancelToken, ProcessTable, Tool, ToolContext, RunShellCommand, ReadFile};
     use serde_json::json;
     use std::path::PathBuf;
     use tempfile::TempDir;
@@ -12,6 +12,14 @@ mod tests {
             no_confirm: true, // Auto-confirm for tests
             git_repo: None,
             tui_mode: false,
+            cancel: CancelToken::default(),
+            output: None,
+            processes: ProcessTable::default(),
+            policy: Default::default(),
+            sandbox: None,
+            tool_name: String::new(),
+            permissions: Default::default(),
+            settings: Default::default(),
         }
     }
     